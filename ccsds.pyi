@@ -1,11 +1,19 @@
 import enum
+import os
+import types
 import typing
 
+StrPath = typing.Union[str, os.PathLike[str]]
+
+# asyncio variants of the decode functions returning async iterators
+aio: types.ModuleType
+
 class RSState(enum.Enum):
     OK = 0
     Corrected = 1
     Uncorrected = 2
     NotPerformed = 3
+    Detected = 4
 
 class VCDUHeader:
     version: int
@@ -15,11 +23,20 @@ class VCDUHeader:
     replay: bool
     cycle: bool
     counter_cycle: int
+    extended_counter: int
 
 class Frame:
     header: VCDUHeader
     rsstate: RSState
     data: bytes
+    offset: int | None
+    time: int | None
+    izone_crc: bool | None
+    asm_errors: int | None
+
+    def __bytes__(self) -> bytes: ...
+    def hexdump(self, width: int = 16) -> str: ...
+    def describe(self, max_bytes: int = 64) -> str: ...
 
 class PrimaryHeader:
     version: int
@@ -36,28 +53,1005 @@ class PrimaryHeader:
 class Packet:
     header: PrimaryHeader
     data: bytes
+    truncated: bool
+    samples: list[int] | None
+    offset: int | None
+    secondary_header_len: int | None
+    secondary_header_bytes: bytes
 
     @classmethod
     def decode(cls, dat: bytes) -> Packet: ...
+    def u(self, bit_offset: int, bit_len: int) -> int: ...
+    def i(self, bit_offset: int, bit_len: int) -> int: ...
+    def f32(self, bit_offset: int, mil1750a: bool = False) -> float: ...
+    def f64(self, bit_offset: int, mil1750a: bool = False) -> float: ...
+    def user_data(self, secondary_header_len: int | None = None) -> bytes: ...
+    def encode(self) -> bytes: ...
+    def __bytes__(self) -> bytes: ...
+    def hexdump(self, width: int = 16) -> str: ...
+    def describe(self, max_bytes: int = 64) -> str: ...
 
 class DecodedPacket:
     scid: int
     vcid: int
     packet: Packet
 
-def decode_packets(source: str) -> typing.Iterable[Packet]: ...
+    def __init__(self, scid: int, vcid: int, packet: Packet) -> None: ...
+    def to_dict(self) -> dict[str, typing.Any]: ...
+
+class PacketFilter:
+    expr: str
+
+    def __init__(self, expr: str) -> None: ...
+    def matches(self, packet: Packet | DecodedPacket) -> bool: ...
+
+class Summary:
+    complete: bool
+    cancelled: bool
+    items: int
+    bytes_read: int
+    errors: int
+    missing: int
+    skipped_bytes: int
+    rs_ok: int
+    rs_corrected: int
+    rs_uncorrectable: int
+    rs_not_performed: int
+    rs_detected: int
+
+class Anomaly:
+    kind: str
+    severity: str
+    offset: int | None
+    scid: int | None
+    vcid: int | None
+    apid: int | None
+    description: str
+
+class AnomalyCollector:
+    def __init__(self) -> None: ...
+    def drain(self) -> list[Anomaly]: ...
+    def __len__(self) -> int: ...
+
+class PacketIterator(typing.Iterator[Packet]):
+    def poll(self) -> Packet | None: ...
+    def peek(self) -> Packet | None: ...
+    def summary(self) -> Summary: ...
+    def cancel(self) -> None: ...
+    def skipped(self) -> list[tuple[int, int]]: ...
+    def secondary_header_violations(self) -> list[tuple[int, int | None]]: ...
+    def length_violations(self) -> list[tuple[int, int, int | None]]: ...
+    def fill_violations(self) -> list[tuple[int, int, float, int | None]]: ...
+    def sequence_flag_violations(self) -> list[tuple[int, str, int | None]]: ...
+    def clock_violations(self) -> list[tuple[int, str, int, int, int | None]]: ...
+    def quicklook(self) -> dict[int, QuickLook]: ...
+    def progress(self) -> float | None: ...
+    def __length_hint__(self) -> int: ...
+
+class FrameIterator(typing.Iterator[Frame]):
+    asm: list[int]
+    interleave: int | None
+
+    def poll(self) -> Frame | None: ...
+    def peek(self) -> Frame | None: ...
+    def summary(self) -> Summary: ...
+    def gaps(self) -> list[FrameGap]: ...
+    def cancel(self) -> None: ...
+    def progress(self) -> float | None: ...
+    def __length_hint__(self) -> int: ...
+    def cached_frame(
+        self, vcid: int, counter: int, scid: int | None = None
+    ) -> Frame | None: ...
+    def cached_frames(self) -> list[Frame]: ...
+
+class VcidFrameIterator(typing.Iterator[Frame]):
+    vcid: int
+
+class BitstreamData:
+    scid: int
+    vcid: int
+    counter: int
+    data: bytes
+
+class DecodedPacketIterator(typing.Iterator[DecodedPacket | BitstreamData]):
+    interleave: int | None
+
+    def poll(self) -> DecodedPacket | BitstreamData | None: ...
+    def peek(self) -> DecodedPacket | BitstreamData | None: ...
+    def cancel(self) -> None: ...
+    def secondary_header_violations(self) -> list[tuple[int, int | None]]: ...
+    def length_violations(self) -> list[tuple[int, int, int | None]]: ...
+    def fill_violations(self) -> list[tuple[int, int, float, int | None]]: ...
+    def sequence_flag_violations(self) -> list[tuple[int, str, int | None]]: ...
+    def clock_violations(self) -> list[tuple[int, str, int, int, int | None]]: ...
+    def quicklook(self) -> dict[int, QuickLook]: ...
+    def cached_frame(
+        self, vcid: int, counter: int, scid: int | None = None
+    ) -> Frame | None: ...
+    def cached_frames(self) -> list[Frame]: ...
+
+def decode_packets(
+    source: StrPath,
+    prefetch: int | None = None,
+    timeout: float | None = None,
+    resync: bool = False,
+    max_packet_len: int | None = None,
+    versions: typing.Iterable[int] | None = None,
+    apids: typing.Iterable[int] | None = None,
+    truncated: str = "drop",
+    rice: dict[int, RiceConfig] | None = None,
+    filter: str | None = None,
+    secondary_headers: dict[int, bool] | None = None,
+    secondary_header_policy: str = "flag",
+    secondary_header_lens: dict[int, int] | None = None,
+    apid_lengths: dict[int, tuple[int | None, int | None]] | None = None,
+    length_policy: str = "flag",
+    fill: FillDetector | None = None,
+    fill_policy: str = "flag",
+    sample: int | None = None,
+    probability: float | None = None,
+    seed: int | None = None,
+    quicklook: dict[int, QuickLookConfig] | None = None,
+    anomalies: AnomalyCollector | None = None,
+    headers_only: bool = False,
+    check_sequence_flags: bool = False,
+    clock: ClockCheck | None = None,
+) -> PacketIterator: ...
 def decode_frames(
-    source: str, frame_len: int, interleave: int
-) -> typing.Iterable[Frame]: ...
+    source: StrPath,
+    frame_len: int,
+    interleave: int | str | None = None,
+    prefetch: int | None = None,
+    timeout: float | None = None,
+    asm: bytes | str | None = None,
+    rs_check_only: bool = False,
+    uncorrectable_dest: StrPath | None = None,
+    corrected_dest: StrPath | None = None,
+    izone_timecodes: dict[int, str] | None = None,
+    izone_crcs: dict[int, tuple[str, int]] | None = None,
+    izone_crc_policy: str = "flag",
+    sample: int | None = None,
+    probability: float | None = None,
+    seed: int | None = None,
+    anomalies: AnomalyCollector | None = None,
+    frame_cache: int | None = None,
+    headers_only: bool = False,
+    rs_threads: int | None = None,
+    report: PassReporter | None = None,
+    asm_errors: int = 0,
+    packet_timecodes: dict[int, tuple[str | TimecodeRegistry, int]] | None = None,
+) -> FrameIterator: ...
+def demux_frames(
+    source: StrPath,
+    frame_len: int,
+    vcids: typing.Sequence[int],
+    interleave: int | str | None = None,
+    asm: bytes | str | None = None,
+    timeout: float | None = None,
+    rs_threads: int | None = None,
+    report: PassReporter | None = None,
+    asm_errors: int = 0,
+) -> dict[int, VcidFrameIterator]: ...
 def decode_framed_packets(
-    source: str,
+    source: StrPath,
+    scid: int | None = None,
+    cadu_len: int | None = None,
+    izone_len: int | None = None,
+    trailer_len: int | None = None,
+    interleave: int | str | None = None,
+    prefetch: int | None = None,
+    timeout: float | None = None,
+    asm: bytes | str | None = None,
+    services: dict[int, str] | None = None,
+    filter: str | None = None,
+    secondary_headers: dict[int, bool] | None = None,
+    secondary_header_policy: str = "flag",
+    secondary_header_lens: dict[int, int] | None = None,
+    apid_lengths: dict[int, tuple[int | None, int | None]] | None = None,
+    length_policy: str = "flag",
+    fill: FillDetector | None = None,
+    fill_policy: str = "flag",
+    sample: int | None = None,
+    probability: float | None = None,
+    seed: int | None = None,
+    quicklook: dict[int, QuickLookConfig] | None = None,
+    anomalies: AnomalyCollector | None = None,
+    frame_cache: int | None = None,
+    report: PassReporter | None = None,
+    asm_errors: int = 0,
+    check_sequence_flags: bool = False,
+    mission: str | None = None,
+    clock: ClockCheck | None = None,
+) -> DecodedPacketIterator: ...
+
+class Pipeline:
+    options: dict[str, typing.Any]
+
+    def __init__(self, **options: typing.Any) -> None: ...
+    def add_stage(
+        self,
+        func: typing.Callable[[list[typing.Any]], typing.Iterable[typing.Any] | None],
+        batch_size: int = 256,
+    ) -> None: ...
+    def decode_packets(self, source: StrPath) -> PacketIterator | BatchStage: ...
+    def decode_frames(self, source: StrPath) -> FrameIterator | BatchStage: ...
+    def decode_framed_packets(
+        self, source: StrPath
+    ) -> DecodedPacketIterator | BatchStage: ...
+
+class FileSummary:
+    path: str
+    summary: Summary
+    dest: str | None
+    error: str | None
+
+class BatchSummary:
+    total: Summary
+    failed: int
+    files: list[FileSummary]
+
+    def to_json(self, indent: bool = True) -> str: ...
+
+def process_files(
+    paths: typing.Sequence[StrPath],
+    pipeline: Pipeline,
+    input_format: str = "cadu",
+    dest_dir: StrPath | None = None,
+    jobs: int | None = None,
+) -> BatchSummary: ...
+
+class BatchStage:
+    def __iter__(self) -> BatchStage: ...
+    def __next__(self) -> typing.Any: ...
+
+def map_batches(
+    items: typing.Iterable[typing.Any],
+    func: typing.Callable[[list[typing.Any]], typing.Iterable[typing.Any] | None],
+    batch_size: int = 256,
+) -> BatchStage: ...
+
+def encode_cadu(
+    frame: Frame | bytes,
+    interleave: int | None = None,
+    randomize: bool = True,
+    asm: bytes | None = None,
+) -> bytes: ...
+class PacketWriter:
+    def __init__(
+        self,
+        dest: StrPath | typing.BinaryIO,
+        type_flag: int = 0,
+        max_data_len: int = 65536,
+        compression: str | None = None,
+    ) -> None: ...
+    def write(
+        self, apid: int, data: bytes, secondary_header: bytes | None = None
+    ) -> int: ...
+    def sequence_counters(self) -> dict[int, int]: ...
+    def flush(self) -> None: ...
+    def close(self) -> None: ...
+    def __enter__(self) -> PacketWriter: ...
+    def __exit__(self, *args: typing.Any) -> None: ...
+
+class RoundtripReport:
+    packets: int
+    frames: int
+    packet_zone_len: int
+    fill_bytes: int
+
+def roundtrip(
+    packets: typing.Iterable[Packet | DecodedPacket | bytes],
     scid: int,
-    frame_len: int,
+    cadu_len: int,
+    vcid: int = 0,
     izone_len: int = 0,
     trailer_len: int = 0,
     interleave: int | None = None,
-) -> typing.Iterable[DecodedPacket]: ...
-def decode_cdc_timecode(dat: bytes) -> int: ...
+    asm: bytes | None = None,
+) -> RoundtripReport: ...
+
+class PushDecoder:
+    fed: int
+    errors: int
+    buffered: int
+
+    def __init__(
+        self,
+        frame_len: int | None = None,
+        interleave: int | None = None,
+        asm: bytes | str | None = None,
+    ) -> None: ...
+    def feed(self, chunk: bytes) -> None: ...
+    def take(self) -> list[Packet] | list[Frame]: ...
+
+class PacketArchiveWriter:
+    def __init__(
+        self,
+        dest: StrPath,
+        chunk_size: int = 1048576,
+        timecode: str = "cds",
+    ) -> None: ...
+    def write(self, packet: Packet | DecodedPacket | bytes) -> None: ...
+    def write_all(
+        self, packets: typing.Iterable[Packet | DecodedPacket | bytes]
+    ) -> int: ...
+    def close(self) -> None: ...
+    def __enter__(self) -> PacketArchiveWriter: ...
+    def __exit__(self, *args: typing.Any) -> None: ...
+
+class ArchiveChunk:
+    offset: int
+    compressed_len: int
+    len: int
+    packets: int
+    apids: list[int]
+    start: int | None
+    end: int | None
+
+class PacketArchive:
+    timecode: str
+    chunks: list[ArchiveChunk]
+
+    def __init__(self, path: StrPath) -> None: ...
+    def read(
+        self,
+        apids: typing.Iterable[int] | None = None,
+        start: int | None = None,
+        end: int | None = None,
+    ) -> list[Packet]: ...
+    def __len__(self) -> int: ...
+
+def split_cadus_by_vcid(
+    source: StrPath,
+    out_dir: StrPath,
+    cadu_len: int,
+    randomized: bool = True,
+    asm: bytes | str | None = None,
+    vcids: typing.Iterable[int] | None = None,
+    compression: str | None = None,
+) -> dict[int, int]: ...
+def decode_turbo(
+    source: StrPath,
+    dest: StrPath,
+    frame_len: int,
+    rate: str,
+    iterations: int = 8,
+    randomized: bool = True,
+) -> int: ...
+def encode_turbo(frame: bytes, rate: str, randomize: bool = True) -> bytes: ...
+
+class Dvbs2Summary:
+    bbframes: int
+    skipped_bytes: int
+    pdus: int
+    filtered: int
+    dropped: int
+
+def extract_dvbs2(
+    source: StrPath,
+    dest: StrPath,
+    mode: str = "gse",
+    frame_len: int | None = None,
+    protocol_type: int | None = None,
+) -> Dvbs2Summary: ...
+
+# entry points of the ccsds-info, ccsds-filter and ccsds-merge commands
+def info_main(args: list[str] | None = None) -> None: ...
+def filter_main(args: list[str] | None = None) -> None: ...
+def merge_main(args: list[str] | None = None) -> None: ...
+
+class RiceConfig:
+    bits_per_sample: int
+    block_size: int
+    rsi: int
+    preprocess: bool
+    signed: bool
+    pad_rsi: bool
+    offset: int
+
+    def __init__(
+        self,
+        bits_per_sample: int,
+        block_size: int = 16,
+        rsi: int = 128,
+        preprocess: bool = True,
+        signed: bool = False,
+        pad_rsi: bool = False,
+        offset: int = 0,
+    ) -> None: ...
+    def decompress(self, data: bytes, samples: int | None = None) -> list[int]: ...
+
+def rice_decompress(
+    data: bytes,
+    bits_per_sample: int,
+    block_size: int = 16,
+    rsi: int = 128,
+    preprocess: bool = True,
+    signed: bool = False,
+    pad_rsi: bool = False,
+    samples: int | None = None,
+) -> list[int]: ...
+
+class FillDetector:
+    patterns: list[int]
+    min_run: int
+    threshold: float
+    offset: int
+    apids: set[int] | None
+
+    def __init__(
+        self,
+        patterns: typing.Iterable[int] = (0xAA, 0x55, 0x00),
+        min_run: int = 16,
+        threshold: float = 0.9,
+        offset: int = 0,
+        apids: typing.Iterable[int] | None = None,
+    ) -> None: ...
+
+class ClockCheck:
+    max_regression: int
+    max_jump: int | None
+    apids: set[int] | None
+
+    def __init__(
+        self,
+        timecode: str | TimecodeRegistry | None = None,
+        max_regression: int = 0,
+        max_jump: int | None = None,
+        apids: typing.Iterable[int] | None = None,
+    ) -> None: ...
+
+class QuickLookConfig:
+    offset: int
+    width: int
+    bit_depth: int
+    decimate: int
+
+    def __init__(
+        self, offset: int, width: int, bit_depth: int, decimate: int = 1
+    ) -> None: ...
+
+class QuickLook:
+    apid: int
+    packets: int
+    width: int
+    height: int
+    lines: list[list[int]]
+
+class TimecodeRegistry:
+    def __init__(
+        self,
+        apids: dict[int, str | tuple[str, int]] | None = None,
+        default: str | tuple[str, int] | None = None,
+    ) -> None: ...
+    @staticmethod
+    def from_spacecraftsdb(scid: int, path: StrPath | None = None) -> TimecodeRegistry: ...
+    def timecode(self, apid: int) -> tuple[str, int] | None: ...
+    def decode(self, packet: Packet | DecodedPacket) -> int | None: ...
+    def decode_ns(self, packet: Packet | DecodedPacket) -> int | None: ...
+
+def decode_cds_timecode(dat: bytes) -> int: ...
+def decode_cds_timecode_ns(dat: bytes) -> int: ...
 def decode_eoscuc_timecode(dat: bytes) -> int: ...
+def decode_eoscuc_timecode_ns(dat: bytes) -> int: ...
+def decode_cuc_timecode(dat: bytes, coarse: int = 4, fine: int = 2, epoch: str = "tai1958") -> int: ...
+def decode_cuc_timecode_ns(dat: bytes, coarse: int = 4, fine: int = 2, epoch: str = "tai1958") -> int: ...
+def register_epoch(name: str, time: int, timescale: str = "utc") -> None: ...
+def epochs() -> list[str]: ...
 def missing_packets(cur: int, last: int) -> int: ...
-def missing_frames(cur: int, last: int) -> int: ...
+def missing_frames(cur: int, last: int, counter_bits: int = 24) -> int: ...
+
+class PnConfig: ...
+
+class RSConfig:
+    interleave: int
+    virtual_fill_length: int
+    num_correctable: int
+
+class FramingConfig:
+    length: int
+    insert_zone_length: int
+    trailer_length: int
+    pseudo_noise: PnConfig | None
+    reed_solomon: RSConfig | None
+
+    def codeblock_len(self) -> int: ...
+
+def framing_config(scid: int, path: StrPath | None = None) -> FramingConfig | None: ...
+
+class MissionPreset:
+    name: str
+    scid: int
+    cadu_len: int
+    interleave: int | None
+    izone_len: int
+    trailer_len: int
+    timecode: TimecodeRegistry
+    services: dict[int, str]
+    framing_config: FramingConfig
+
+def mission_preset(name: str, path: StrPath | None = None) -> MissionPreset: ...
+def missions(path: StrPath | None = None) -> list[str]: ...
+
+class EpsProduct:
+    mphr: dict[str, str]
+    sphr: dict[str, str]
+    mdrs: int
+    dummy_mdrs: int
+    def to_json(self, indent: bool = True) -> str: ...
+
+def read_eps_product(path: StrPath) -> EpsProduct: ...
+
+class GrbPayload:
+    channel: str
+    vcid: int
+    apid: int
+    sequence_id: int
+    time: int | None
+    version: int | None
+    payload_variant: int | None
+    assembler_id: int | None
+    system_environment: int | None
+    packets: int
+    complete: bool
+    data: bytes
+
+class GrbPayloadIterator:
+    def __iter__(self) -> GrbPayloadIterator: ...
+    def __next__(self) -> GrbPayload: ...
+
+def decode_grb(
+    scid: int,
+    rhcp: StrPath | None = None,
+    lhcp: StrPath | None = None,
+    cadu_len: int = 2048,
+    vcids: typing.Iterable[int] | None = None,
+    interleave: int | None = None,
+    asm: bytes | None = None,
+) -> GrbPayloadIterator: ...
+
+class Gap:
+    apid: int
+    start_sequence_id: int
+    end_sequence_id: int
+    count: int
+    start_offset: int
+    end_offset: int
+    start_time: int | None
+    end_time: int | None
+
+class FrameGap:
+    scid: int
+    vcid: int
+    start_counter: int
+    end_counter: int
+    count: int
+    start_offset: int | None
+    end_offset: int | None
+
+def gap_report(source: StrPath, timecode: str | TimecodeRegistry | None = None) -> list[Gap]: ...
+def write_gap_report(
+    source: StrPath,
+    dest: StrPath,
+    format: str = "csv",
+    timecode: str | TimecodeRegistry | None = None,
+    compression: str | None = None,
+) -> int: ...
+
+class PacketRef:
+    apid: int
+    sequence_id: int
+    time: int | None
+    offset: int
+
+class PacketComparison:
+    only_a: list[PacketRef]
+    only_b: list[PacketRef]
+    mismatched: list[tuple[PacketRef, PacketRef]]
+    matched: int
+
+    def identical(self) -> bool: ...
+
+def compare_packets(a: StrPath, b: StrPath, timecode: str | TimecodeRegistry | None = None) -> PacketComparison: ...
+
+class ValidationProfile:
+    apids: set[int] | None
+    lengths: dict[int, tuple[int, int]]
+    monotonic: bool
+    max_gap: int | None
+
+    def __init__(
+        self,
+        apids: typing.Iterable[int] | None = None,
+        lengths: dict[int, tuple[int, int]] | None = None,
+        secondary_header: bool | dict[int, bool] | None = None,
+        timecode: str | TimecodeRegistry | None = None,
+        monotonic: bool = False,
+        max_gap: int | None = None,
+    ) -> None: ...
+
+class ValidationViolation:
+    check: str
+    apid: int
+    sequence_id: int
+    offset: int
+    message: str
+
+class ValidationReport:
+    passed: bool
+    packets: int
+    counts: dict[str, int]
+    violations: list[ValidationViolation]
+
+    def to_json(self, indent: bool = True) -> str: ...
+
+def validate(source: StrPath, profile: ValidationProfile, max_violations: int = 1000) -> ValidationReport: ...
+def dedup_packets(
+    source: StrPath,
+    dest: StrPath,
+    window: int = 10000,
+    timecode: str | TimecodeRegistry | None = None,
+    compression: str | None = None,
+) -> tuple[int, int]: ...
+def rewrite_packets(
+    source: StrPath,
+    dest: StrPath,
+    apid_map: dict[int, int] | None = None,
+    compression: str | None = None,
+) -> tuple[int, int]: ...
+def subset(
+    source: StrPath,
+    dest: StrPath,
+    apids: typing.Iterable[int] | None = None,
+    start: int | None = None,
+    end: int | None = None,
+    timecode: str | TimecodeRegistry | None = None,
+    vcids: typing.Iterable[int] | None = None,
+    filter: str | None = None,
+    scid: int | None = None,
+    cadu_len: int | None = None,
+    izone_len: int = 0,
+    trailer_len: int = 0,
+    interleave: int | str | None = None,
+    compression: str | None = None,
+) -> int: ...
+class MergeSummary:
+    realtime: int
+    playback: int
+    duplicates: int
+    untimed: int
+
+def merge_realtime_playback(
+    realtime: StrPath,
+    playback: StrPath,
+    dest: StrPath,
+    scid: int,
+    cadu_len: int,
+    izone_len: int = 0,
+    trailer_len: int = 0,
+    interleave: int | str | None = None,
+    timecode: str | TimecodeRegistry | None = None,
+    compression: str | None = None,
+) -> MergeSummary: ...
+def convert(
+    source: StrPath,
+    dest: StrPath,
+    input_format: str = "cadu",
+    output_format: str = "packets",
+    compression: str | None = None,
+    **options: typing.Any,
+) -> int: ...
+
+class IdleAccounting:
+    frames: int
+    fill_frames: int
+    packets: int
+    fill_packets: int
+    total_bytes: int
+    packet_bytes: int
+    fill_packet_bytes: int
+    fill_frame_bytes: int
+    pad_bytes: int
+    overhead_bytes: int
+    unknown_bytes: int
+    efficiency: float
+
+def idle_accounting(
+    source: StrPath,
+    cadu_len: int,
+    izone_len: int = 0,
+    trailer_len: int = 0,
+    interleave: int | str | None = None,
+    asm: bytes | str | None = None,
+) -> IdleAccounting: ...
+
+class VcidReport:
+    vcid: int
+    frames: int
+    missing: int
+    gaps: int
+    rs_ok: int
+    rs_corrected: int
+    rs_uncorrectable: int
+    rs_not_performed: int
+
+class ApidReport:
+    apid: int
+    packets: int
+    bytes: int
+    missing: int
+    gaps: int
+    start_time: int | None
+    end_time: int | None
+
+class PassReport:
+    scid: int
+    cadus: int
+    lock_offset: int | None
+    lock_losses: int
+    unlocked_bytes: int
+    frames: int
+    fill_frames: int
+    packets: int
+    fill_packets: int
+    start_time: int | None
+    end_time: int | None
+    vcids: list[VcidReport]
+    apids: list[ApidReport]
+    fill_frame_percent: float
+    fill_packet_percent: float
+
+    def to_json(self, indent: bool = True) -> str: ...
+
+class PassReporter:
+    def __init__(self, timecode: str | TimecodeRegistry | None = None) -> None: ...
+    def report(self) -> PassReport: ...
+
+def pass_report(
+    source: StrPath,
+    scid: int,
+    cadu_len: int,
+    izone_len: int = 0,
+    trailer_len: int = 0,
+    interleave: int | str | None = None,
+    asm: bytes | str | None = None,
+    timecode: str | TimecodeRegistry | None = None,
+) -> PassReport: ...
+
+class RsTrendRecord:
+    start_frame: int
+    frames: int
+    start_offset: int
+    end_offset: int
+    corrected: list[int]
+    uncorrectable: list[int]
+
+def rs_trend(
+    source: StrPath,
+    cadu_len: int,
+    interleave: int,
+    cadence: int = 1000,
+    asm: bytes | str | None = None,
+    dest: StrPath | None = None,
+    compression: str | None = None,
+) -> list[RsTrendRecord]: ...
+
+class ApidManifest:
+    apid: int
+    packets: int
+    bytes: int
+    digest: str
+
+class Manifest:
+    algorithm: str
+    digest: str
+    bytes: int
+    packets: int
+    apids: list[ApidManifest]
+
+    def to_json(self, indent: bool = True) -> str: ...
+
+def manifest(source: StrPath, algorithm: str = "sha256") -> Manifest: ...
+
+class MessageFrameIterator:
+    errors: int
+
+    def __iter__(self) -> MessageFrameIterator: ...
+    def __next__(self) -> Frame: ...
+
+def decode_kafka_frames(
+    consumer: typing.Any,
+    interleave: int | None = None,
+    asm: bytes | None = None,
+    poll_timeout: float = 1.0,
+) -> MessageFrameIterator: ...
+def publish_kafka_packets(
+    packets: typing.Iterable[Packet | DecodedPacket],
+    producer: typing.Any,
+    topic: str,
+    serialization: str = "raw",
+    flush: bool = True,
+    rate: float | str | None = None,
+) -> int: ...
+def publish_redis_packets(
+    packets: typing.Iterable[Packet | DecodedPacket],
+    client: typing.Any,
+    key_prefix: str = "ccsds:apid:",
+    apids: typing.Iterable[int] | None = None,
+    serialization: str = "raw",
+    maxlen: int | None = None,
+    rate: float | str | None = None,
+) -> int: ...
+def serve_packets(
+    source: StrPath,
+    bind: str = "0.0.0.0:8765",
+    serialization: str = "json",
+    apids: typing.Iterable[int] | None = None,
+    rate: float | str | None = None,
+) -> int: ...
+
+class AsmDetection:
+    name: str
+    asm: list[int]
+    count: int
+    spacing: int | None
+    bit_offset: int
+    locked: bool
+
+def detect_asm(
+    source: StrPath, candidates: list[bytes] | None = None, scan_len: int = 4194304
+) -> list[AsmDetection]: ...
+
+class FramingDetection:
+    asm_name: str
+    asm: list[int]
+    cadu_len: int
+    frame_len: int
+    count: int
+    bit_offset: int
+    randomized: bool
+    scid: int | None
+    interleave: int | None
+
+def detect_framing(
+    source: StrPath, asm: bytes | str | None = None, scan_len: int = 4194304
+) -> FramingDetection: ...
+
+class InterleaveDetection:
+    interleave: int
+    frames: int
+    ok: int
+    corrected: int
+    uncorrectable: int
+
+def detect_interleave(
+    source: StrPath,
+    cadu_len: int,
+    asm: bytes | str | None = None,
+    randomized: bool = True,
+    frames: int = 16,
+) -> InterleaveDetection | None: ...
+
+class Calibration:
+    @staticmethod
+    def polynomial(coefficients: list[float]) -> Calibration: ...
+    @staticmethod
+    def point_pair(points: list[tuple[float, float]]) -> Calibration: ...
+    def apply(self, raw: float) -> float: ...
+
+class AlarmLimits:
+    red_low: float | None
+    yellow_low: float | None
+    yellow_high: float | None
+    red_high: float | None
+
+    def __init__(
+        self,
+        red_low: float | None = None,
+        yellow_low: float | None = None,
+        yellow_high: float | None = None,
+        red_high: float | None = None,
+    ) -> None: ...
+
+class Violation:
+    field: str
+    value: float
+    level: str
+    bound: str
+    limit: float
+    apid: int
+    sequence_id: int
+    time: int | None
+
+class Field:
+    name: str
+    bit_offset: int
+    bit_len: int
+    calibration: Calibration | None
+    states: dict[int, str] | None
+    limits: AlarmLimits | None
+    count: int | None
+    stride: int
+    byte_order: str
+    fraction_bits: int
+
+    def __init__(
+        self,
+        name: str,
+        bit_offset: int,
+        bit_len: int,
+        kind: str = "uint",
+        calibration: Calibration | None = None,
+        states: dict[int, str] | None = None,
+        limits: AlarmLimits | None = None,
+        count: int | None = None,
+        stride: int | None = None,
+        byte_order: str = "big",
+        fraction_bits: int = 0,
+    ) -> None: ...
+    def pack(
+        self, data: bytes, value: int | float | str | list[typing.Any], raw: bool = False
+    ) -> bytes: ...
+
+class Derived:
+    name: str
+    expr: str
+
+    def __init__(self, name: str, expr: str) -> None: ...
+
+class Layout:
+    fields: list[Field]
+    derived: list[Derived]
+
+    def __init__(
+        self, fields: list[Field], derived: list[Derived] | None = None
+    ) -> None: ...
+    def extract(self, packet: Packet) -> dict[str, int | float | str | None]: ...
+    def pack(
+        self, values: dict[str, typing.Any], data: bytes | None = None, raw: bool = False
+    ) -> bytes: ...
+    def extract_all(
+        self, packets: typing.Iterable[Packet], categorical: bool = False
+    ) -> dict[str, typing.Any]: ...
+    def check(self, packet: Packet, timecode: str | TimecodeRegistry | None = None) -> list[Violation]: ...
+    def check_all(
+        self, packets: typing.Iterable[Packet], timecode: str | TimecodeRegistry | None = None
+    ) -> list[Violation]: ...
+
+def packets_to_dataframe(
+    source: StrPath,
+    fields: Layout | None = None,
+    timecode: str | TimecodeRegistry | None = None,
+    apids: typing.Iterable[int] | None = None,
+    categorical: bool = True,
+) -> typing.Any: ...
+def packets_to_dataset(
+    source: StrPath,
+    fields: Layout,
+    timecode: str | TimecodeRegistry | None = None,
+    apids: typing.Iterable[int] | None = None,
+) -> typing.Any: ...
+
+class MibPacket:
+    spid: int
+    apid: int
+    service_type: int
+    service_subtype: int
+    pi1_val: int
+    pi2_val: int
+    description: str
+    layout: Layout
+    min_len: int
+
+def load_mib(path: str | os.PathLike) -> list[MibPacket]: ...
+
+class PacketGroups(typing.Iterator[list[Packet] | list[DecodedPacket]]): ...
+
+def group_packets_by_apid(
+    packets: typing.Iterable[Packet] | typing.Iterable[DecodedPacket],
+) -> PacketGroups: ...
+def window_packets_by_time(
+    packets: typing.Iterable[Packet] | typing.Iterable[DecodedPacket],
+    seconds: float,
+    timecode: str | TimecodeRegistry | None = None,
+) -> PacketGroups: ...
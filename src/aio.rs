@@ -0,0 +1,249 @@
+//! asyncio support.
+//!
+//! Sources that are paths or URLs are decoded on a background thread, with each
+//! `__anext__` completed on the event loop's default executor. asyncio streams are
+//! read on the event loop itself and the packets assembled as data arrives.
+use std::sync::{Condvar, Mutex};
+
+use pyo3::{
+    exceptions::{PyStopAsyncIteration, PyStopIteration},
+    prelude::*,
+    types::{PyDict, PyString, PyTuple},
+};
+
+use crate::{packets::PacketAssembler, prefetch::DEFAULT_PREFETCH, Packet};
+
+/// Number of bytes requested from an asyncio stream per read.
+const STREAM_READ_SIZE: usize = 65536;
+
+fn running_loop(py: Python<'_>) -> PyResult<&PyAny> {
+    py.import("asyncio")?.call_method0("get_running_loop")
+}
+
+/// Async iterator adapting one of the blocking decode iterators.
+///
+/// Concurrent `__anext__` calls, e.g., from `asyncio.gather`, each receive the next
+/// item in turn.
+#[pyclass]
+pub struct AsyncIterator {
+    inner: PyObject,
+    /// Set while an executor thread advances `inner`, which cannot be borrowed by more
+    /// than one thread at once.
+    busy: Mutex<bool>,
+    idle: Condvar,
+}
+
+#[pymethods]
+impl AsyncIterator {
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __anext__(slf: &PyCell<Self>) -> PyResult<Option<PyObject>> {
+        let py = slf.py();
+        let next = slf.getattr("_next")?;
+        let fut = running_loop(py)?.call_method1("run_in_executor", (py.None(), next))?;
+        Ok(Some(fut.into()))
+    }
+
+    /// Blocking next, run on an executor thread.
+    fn _next(&self, py: Python) -> PyResult<PyObject> {
+        // wait without the GIL so the busy thread can finish its item
+        py.allow_threads(|| {
+            let mut busy = self.busy.lock().unwrap();
+            while *busy {
+                busy = self.idle.wait(busy).unwrap();
+            }
+            *busy = true;
+        });
+        let next = self.inner.call_method0(py, "__next__");
+        *self.busy.lock().unwrap() = false;
+        self.idle.notify_one();
+        match next {
+            Err(err) if err.is_instance_of::<PyStopIteration>(py) => {
+                Err(PyStopAsyncIteration::new_err(()))
+            }
+            result => result,
+        }
+    }
+
+    /// Stop decoding.
+    fn cancel(&self, py: Python) -> PyResult<()> {
+        self.inner.call_method0(py, "cancel").map(|_| ())
+    }
+}
+
+fn wrap(py: Python, inner: &PyAny) -> PyResult<PyObject> {
+    Ok(Py::new(
+        py,
+        AsyncIterator {
+            inner: inner.into(),
+            busy: Mutex::default(),
+            idle: Condvar::new(),
+        },
+    )?
+    .into_py(py))
+}
+
+/// Async iterator assembling packets from an asyncio stream.
+///
+/// Each `__anext__` must be awaited before the next, as asyncio streams do not support
+/// concurrent reads.
+#[pyclass]
+pub struct StreamPacketIterator {
+    reader: PyObject,
+    assembler: PacketAssembler,
+    eof: bool,
+}
+
+impl StreamPacketIterator {
+    /// Complete `fut` with the next packet, reading from the stream as necessary.
+    fn fill(slf: &PyCell<Self>, fut: &PyAny) -> PyResult<()> {
+        let py = slf.py();
+        let read = {
+            let mut this = slf.borrow_mut();
+            if let Some(packet) = this.assembler.next_packet() {
+                fut.call_method1("set_result", (Py::new(py, Packet::from(packet))?,))?;
+                return Ok(());
+            }
+            if this.eof {
+                let err = PyStopAsyncIteration::new_err(());
+                fut.call_method1("set_exception", (err.value(py),))?;
+                return Ok(());
+            }
+            this.reader.call_method1(py, "read", (STREAM_READ_SIZE,))?
+        };
+        let task = py
+            .import("asyncio")?
+            .call_method1("ensure_future", (read,))?;
+        let callback = ReadDone {
+            iter: slf.into(),
+            fut: fut.into(),
+        };
+        task.call_method1("add_done_callback", (Py::new(py, callback)?,))?;
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl StreamPacketIterator {
+    fn __aiter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __anext__(slf: &PyCell<Self>) -> PyResult<Option<PyObject>> {
+        let fut = running_loop(slf.py())?.call_method0("create_future")?;
+        Self::fill(slf, fut)?;
+        Ok(Some(fut.into()))
+    }
+}
+
+/// Callback invoked when a stream read completes.
+#[pyclass]
+struct ReadDone {
+    iter: Py<StreamPacketIterator>,
+    fut: PyObject,
+}
+
+#[pymethods]
+impl ReadDone {
+    fn __call__(&self, py: Python, task: &PyAny) -> PyResult<()> {
+        let fut = self.fut.as_ref(py);
+        if fut.call_method0("done")?.is_true()? {
+            return Ok(()); // cancelled by the consumer
+        }
+        match task.call_method0("result") {
+            Ok(chunk) => {
+                let chunk: &[u8] = chunk.extract()?;
+                {
+                    let mut iter = self.iter.borrow_mut(py);
+                    if chunk.is_empty() {
+                        iter.eof = true;
+                    } else {
+                        iter.assembler.push(chunk);
+                    }
+                }
+                StreamPacketIterator::fill(self.iter.as_ref(py), fut)
+            }
+            Err(err) => fut
+                .call_method1("set_exception", (err.value(py),))
+                .map(|_| ()),
+        }
+    }
+}
+
+/// Decode space packets asynchronously.
+///
+/// Parameters
+/// ----------
+/// source : str, os.PathLike or asyncio.StreamReader
+///     Either any source supported by `ccsds.decode_packets`, which is decoded on a
+///     background thread, or an asyncio stream providing space packets.
+///
+/// prefetch : int, optional
+///     Decode queue size used for non-stream sources.
+///
+/// Any other keyword arguments are passed to `ccsds.decode_packets` for non-stream
+/// sources.
+///
+/// Returns
+/// -------
+///     Async iterator of Packets
+#[pyfunction(signature=(source, prefetch=None, **kwargs))]
+fn decode_packets(
+    py: Python,
+    source: &PyAny,
+    prefetch: Option<usize>,
+    kwargs: Option<&PyDict>,
+) -> PyResult<PyObject> {
+    let is_path = source.is_instance_of::<PyString>() || source.hasattr("__fspath__")?;
+    if !is_path && source.hasattr("read")? {
+        let iter = StreamPacketIterator {
+            reader: source.into(),
+            assembler: PacketAssembler::default(),
+            eof: false,
+        };
+        return Ok(Py::new(py, iter)?.into_py(py));
+    }
+    let kwargs = match kwargs {
+        Some(kwargs) => kwargs.copy()?,
+        None => PyDict::new(py),
+    };
+    kwargs.set_item("prefetch", prefetch.unwrap_or(DEFAULT_PREFETCH))?;
+    let func = py.import("ccsds")?.getattr("decode_packets")?;
+    wrap(py, func.call((source,), Some(kwargs))?)
+}
+
+/// Decode frames asynchronously. Accepts the same arguments as `ccsds.decode_frames`.
+#[pyfunction(signature=(*args, **kwargs))]
+fn decode_frames(py: Python, args: &PyTuple, kwargs: Option<&PyDict>) -> PyResult<PyObject> {
+    let func = py.import("ccsds")?.getattr("decode_frames")?;
+    wrap(py, func.call(args, kwargs)?)
+}
+
+/// Decode framed space packets asynchronously. Accepts the same arguments as
+/// `ccsds.decode_framed_packets`.
+#[pyfunction(signature=(*args, **kwargs))]
+fn decode_framed_packets(
+    py: Python,
+    args: &PyTuple,
+    kwargs: Option<&PyDict>,
+) -> PyResult<PyObject> {
+    let func = py.import("ccsds")?.getattr("decode_framed_packets")?;
+    wrap(py, func.call(args, kwargs)?)
+}
+
+/// Create the `ccsds.aio` submodule.
+pub fn module(py: Python<'_>) -> PyResult<&PyModule> {
+    let m = PyModule::new(py, "aio")?;
+    m.add_function(wrap_pyfunction!(decode_packets, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_frames, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_framed_packets, m)?)?;
+    m.add_class::<AsyncIterator>()?;
+    m.add_class::<StreamPacketIterator>()?;
+    // allow `import ccsds.aio`
+    py.import("sys")?
+        .getattr("modules")?
+        .set_item("ccsds.aio", m)?;
+    Ok(m)
+}
@@ -0,0 +1,176 @@
+//! Limit checking of extracted field values.
+
+// pyo3 0.19 generates the constructor text signature as an impl nested in a function
+#![allow(non_local_definitions)]
+use std::fmt;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::repr::{repr, PyRepr};
+
+/// Severity of a limit violation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Yellow,
+    Red,
+}
+
+impl Level {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Yellow => "yellow",
+            Self::Red => "red",
+        }
+    }
+}
+
+/// A limit exceeded by a value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Exceeded {
+    pub level: Level,
+    /// True for a high limit, false for a low limit.
+    pub high: bool,
+    pub limit: f64,
+}
+
+/// Red and yellow low/high limits for a field value. A value is in violation if it is
+/// strictly below a low limit or strictly above a high limit; red takes precedence.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default)]
+pub struct AlarmLimits {
+    #[pyo3(get)]
+    pub red_low: Option<f64>,
+    #[pyo3(get)]
+    pub yellow_low: Option<f64>,
+    #[pyo3(get)]
+    pub yellow_high: Option<f64>,
+    #[pyo3(get)]
+    pub red_high: Option<f64>,
+}
+
+#[pymethods]
+impl AlarmLimits {
+    #[new]
+    #[pyo3(signature=(red_low=None, yellow_low=None, yellow_high=None, red_high=None))]
+    pub fn py_new(
+        red_low: Option<f64>,
+        yellow_low: Option<f64>,
+        yellow_high: Option<f64>,
+        red_high: Option<f64>,
+    ) -> PyResult<Self> {
+        let ordered: Vec<f64> = [red_low, yellow_low, yellow_high, red_high]
+            .into_iter()
+            .flatten()
+            .collect();
+        if ordered.windows(2).any(|w| w[0] > w[1]) {
+            return Err(PyValueError::new_err(
+                "limits must satisfy red_low <= yellow_low <= yellow_high <= red_high",
+            ));
+        }
+        Ok(Self {
+            red_low,
+            yellow_low,
+            yellow_high,
+            red_high,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    pub fn __str__(&self) -> String {
+        format!(
+            "AlarmLimits(red_low={}, yellow_low={}, yellow_high={}, red_high={})",
+            repr(&self.red_low),
+            repr(&self.yellow_low),
+            repr(&self.yellow_high),
+            repr(&self.red_high),
+        )
+    }
+}
+
+impl AlarmLimits {
+    /// Check `value`, returning the limit it exceeds, if any.
+    pub fn check(&self, value: f64) -> Option<Exceeded> {
+        let checks = [
+            (Level::Red, false, self.red_low),
+            (Level::Red, true, self.red_high),
+            (Level::Yellow, false, self.yellow_low),
+            (Level::Yellow, true, self.yellow_high),
+        ];
+        checks.into_iter().find_map(|(level, high, limit)| {
+            let limit = limit?;
+            let exceeded = if high { value > limit } else { value < limit };
+            exceeded.then_some(Exceeded { level, high, limit })
+        })
+    }
+}
+
+/// A field value outside of its limits.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct Violation {
+    /// Name of the field.
+    #[pyo3(get)]
+    pub field: String,
+    /// The (calibrated) value of the field.
+    #[pyo3(get)]
+    pub value: f64,
+    /// Either "yellow" or "red".
+    #[pyo3(get)]
+    pub level: &'static str,
+    /// Either "low" or "high".
+    #[pyo3(get)]
+    pub bound: &'static str,
+    /// The limit that was exceeded.
+    #[pyo3(get)]
+    pub limit: f64,
+    #[pyo3(get)]
+    pub apid: u16,
+    #[pyo3(get)]
+    pub sequence_id: u16,
+    /// Packet time in UTC milliseconds, if a timecode was provided and could be decoded.
+    #[pyo3(get)]
+    pub time: Option<i64>,
+}
+
+impl PyRepr for AlarmLimits {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.__str__())
+    }
+}
+
+#[pymethods]
+impl Violation {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "Violation(field={}, value={}, level={}, bound={}, limit={}, apid={}, sequence_id={}, time={})",
+            repr(&self.field), repr(&self.value), repr(self.level), repr(self.bound), repr(&self.limit), self.apid, self.sequence_id, repr(&self.time),
+        )
+    }
+}
+
+impl Violation {
+    pub fn new(
+        field: &str,
+        value: f64,
+        exceeded: Exceeded,
+        apid: u16,
+        sequence_id: u16,
+        time: Option<i64>,
+    ) -> Self {
+        Self {
+            field: field.to_string(),
+            value,
+            level: exceeded.level.name(),
+            bound: if exceeded.high { "high" } else { "low" },
+            limit: exceeded.limit,
+            apid,
+            sequence_id,
+            time,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Structured anomaly events emitted by the decode stages for alerting.
+use std::sync::{Arc, Mutex};
+
+use pyo3::prelude::*;
+
+use crate::repr::repr;
+
+/// How serious an anomaly is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// Expected in normal operations, e.g., a resync of the frame stream.
+    Info,
+    /// Data may be missing or malformed.
+    Warning,
+    /// Data was lost.
+    Error,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// An anomaly detected while decoding, e.g., a sync loss, uncorrectable frame, packet
+/// length error or continuity break.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct Anomaly {
+    /// Kind of anomaly: one of sync_loss, rs_uncorrectable, frame_error, frame_gap,
+    /// packet_error, packet_gap, resync, secondary_header, length, fill,
+    /// sequence_flags or clock.
+    #[pyo3(get)]
+    pub kind: &'static str,
+    /// One of info, warning or error.
+    #[pyo3(get)]
+    pub severity: &'static str,
+    /// Byte offset in the source, if known.
+    #[pyo3(get)]
+    pub offset: Option<u64>,
+    #[pyo3(get)]
+    pub scid: Option<u16>,
+    #[pyo3(get)]
+    pub vcid: Option<u16>,
+    #[pyo3(get)]
+    pub apid: Option<u16>,
+    #[pyo3(get)]
+    pub description: String,
+}
+
+#[pymethods]
+impl Anomaly {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "Anomaly(kind={}, severity={}, offset={}, scid={}, vcid={}, apid={}, description={})",
+            repr(self.kind),
+            repr(self.severity),
+            repr(&self.offset),
+            repr(&self.scid),
+            repr(&self.vcid),
+            repr(&self.apid),
+            repr(&self.description),
+        )
+    }
+}
+
+impl Anomaly {
+    pub fn new(kind: &'static str, severity: Severity, description: String) -> Self {
+        Self {
+            kind,
+            severity: severity.name(),
+            offset: None,
+            scid: None,
+            vcid: None,
+            apid: None,
+            description,
+        }
+    }
+
+    pub fn offset(self, offset: Option<u64>) -> Self {
+        Self { offset, ..self }
+    }
+
+    pub fn frame(self, scid: u16, vcid: u16) -> Self {
+        Self {
+            scid: Some(scid),
+            vcid: Some(vcid),
+            ..self
+        }
+    }
+
+    pub fn apid(self, apid: u16) -> Self {
+        Self {
+            apid: Some(apid),
+            ..self
+        }
+    }
+}
+
+/// Collects the anomalies emitted by any number of decode functions, including from
+/// their background decoding threads, as a single integration point for alerting.
+///
+/// Pass the collector as the `anomalies` argument of the decode functions and `drain`
+/// it periodically.
+#[pyclass]
+#[derive(Clone, Debug, Default)]
+pub struct AnomalyCollector {
+    anomalies: Arc<Mutex<Vec<Anomaly>>>,
+}
+
+#[pymethods]
+impl AnomalyCollector {
+    #[new]
+    fn py_new() -> Self {
+        Self::default()
+    }
+
+    /// Remove and return the anomalies collected so far, in the order emitted.
+    fn drain(&self) -> Vec<Anomaly> {
+        std::mem::take(&mut *self.anomalies.lock().unwrap())
+    }
+
+    fn __len__(&self) -> usize {
+        self.anomalies.lock().unwrap().len()
+    }
+}
+
+impl AnomalyCollector {
+    pub fn emit(&self, anomaly: Anomaly) {
+        self.anomalies.lock().unwrap().push(anomaly);
+    }
+}
+
+/// Emit the anomaly built by `anomaly` if there is a collector.
+pub fn emit(collector: &Option<AnomalyCollector>, anomaly: impl FnOnce() -> Anomaly) {
+    if let Some(collector) = collector {
+        collector.emit(anomaly());
+    }
+}
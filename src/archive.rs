@@ -0,0 +1,415 @@
+//! Zstandard-compressed packet archives with an index of the APIDs and times of each
+//! compressed chunk, so archives may be read selectively by APID and time.
+//!
+//! An archive is the magic `CCSDSZA1`, followed by chunks of packets each compressed
+//! as an independent zstd frame, followed by the JSON index, the little-endian 64-bit
+//! length of the index and the magic `CCSDSIDX`.
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs::File,
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{message::with_packet, packets::PacketReader, repr::repr, timecode::Timecode, Packet};
+
+const MAGIC: &[u8; 8] = b"CCSDSZA1";
+const INDEX_MAGIC: &[u8; 8] = b"CCSDSIDX";
+const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// A compressed chunk of packets in an archive.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArchiveChunk {
+    /// Byte offset of the compressed chunk in the archive.
+    #[pyo3(get)]
+    offset: u64,
+    /// Number of compressed bytes.
+    #[pyo3(get)]
+    compressed_len: u64,
+    /// Number of packet bytes.
+    #[pyo3(get)]
+    len: u64,
+    /// Number of packets.
+    #[pyo3(get)]
+    packets: u64,
+    /// The APIDs of the packets, sorted.
+    #[pyo3(get)]
+    apids: Vec<u16>,
+    /// Earliest packet time in UTC milliseconds, if any packets have a time.
+    #[pyo3(get)]
+    start: Option<i64>,
+    /// Latest packet time in UTC milliseconds, if any packets have a time.
+    #[pyo3(get)]
+    end: Option<i64>,
+}
+
+#[pymethods]
+impl ArchiveChunk {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "ArchiveChunk(offset={}, compressed_len={}, len={}, packets={}, apids={}, start={}, end={})",
+            self.offset, self.compressed_len, self.len, self.packets, repr(&self.apids), repr(&self.start), repr(&self.end),
+        )
+    }
+}
+
+impl ArchiveChunk {
+    /// Whether the chunk may contain packets matching the selection.
+    fn selected(&self, apids: &Option<HashSet<u16>>, start: Option<i64>, end: Option<i64>) -> bool {
+        if apids
+            .as_ref()
+            .is_some_and(|apids| !self.apids.iter().any(|a| apids.contains(a)))
+        {
+            return false;
+        }
+        if start.is_none() && end.is_none() {
+            return true;
+        }
+        let (Some(first), Some(last)) = (self.start, self.end) else {
+            return false;
+        };
+        start.is_none_or(|start| last >= start) && end.is_none_or(|end| first < end)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Index {
+    timecode: String,
+    chunks: Vec<ArchiveChunk>,
+}
+
+/// Writes packets to a zstd-compressed archive indexed by APID and time.
+///
+/// Packets are buffered until `chunk_size` bytes have been written and then compressed
+/// as a chunk. The index is written when the writer is closed, so the archive is not
+/// readable until then.
+///
+/// Parameters
+/// ----------
+/// dest : str or os.PathLike
+///     Path of the archive to create or truncate.
+///
+/// chunk_size : int
+///     Number of packet bytes compressed together. Larger chunks compress better but
+///     are read in full when any of their packets are selected.
+///
+/// timecode : str
+///     Timecode at the start of the secondary header used to index packets by time,
+///     either "cds" or "eoscuc".
+#[pyclass]
+pub struct PacketArchiveWriter {
+    file: Option<BufWriter<File>>,
+    chunk_size: usize,
+    timecode: Timecode,
+    timecode_name: String,
+    offset: u64,
+    buf: Vec<u8>,
+    packets: u64,
+    apids: BTreeSet<u16>,
+    start: Option<i64>,
+    end: Option<i64>,
+    chunks: Vec<ArchiveChunk>,
+}
+
+#[pymethods]
+impl PacketArchiveWriter {
+    #[new]
+    #[pyo3(signature=(dest, chunk_size=DEFAULT_CHUNK_SIZE, timecode="cds"))]
+    fn py_new(dest: PathBuf, chunk_size: usize, timecode: &str) -> PyResult<Self> {
+        if chunk_size == 0 || chunk_size > u32::MAX as usize {
+            return Err(PyValueError::new_err(format!(
+                "invalid chunk_size; expected 1..={}, got {chunk_size}",
+                u32::MAX
+            )));
+        }
+        let timecode_name = timecode.to_lowercase();
+        let timecode = Timecode::from_name(timecode)?;
+        let mut file = BufWriter::new(File::create(dest)?);
+        file.write_all(MAGIC)?;
+        Ok(Self {
+            file: Some(file),
+            chunk_size,
+            timecode,
+            timecode_name,
+            offset: MAGIC.len() as u64,
+            buf: Vec::with_capacity(chunk_size),
+            packets: 0,
+            apids: BTreeSet::default(),
+            start: None,
+            end: None,
+            chunks: Vec::default(),
+        })
+    }
+
+    /// Write a packet.
+    ///
+    /// Parameters
+    /// ----------
+    /// packet : Packet, DecodedPacket or bytes
+    ///     The packet, or the bytes of a single packet.
+    fn write(&mut self, py: Python, packet: &PyAny) -> PyResult<()> {
+        if self.file.is_none() {
+            return Err(PyValueError::new_err("write to closed PacketArchiveWriter"));
+        }
+        let timecode = self.timecode;
+        let fields = |packet: &Packet| {
+            let time = packet
+                .header
+                .has_secondary_header
+                .then(|| timecode.decode(packet.payload()))
+                .flatten();
+            (packet.header.apid, time, packet.data.to_vec())
+        };
+        let (apid, time, dat) = if let Ok(dat) = packet.extract::<&[u8]>() {
+            let Some(packet) = ccsds::Packet::decode(dat) else {
+                return Err(PyValueError::new_err("bytes do not contain a packet"));
+            };
+            fields(&Packet::new(packet))
+        } else {
+            with_packet(packet, |p| fields(p.packet))?
+        };
+        self.buf.extend_from_slice(&dat);
+        self.packets += 1;
+        self.apids.insert(apid);
+        if let Some(time) = time {
+            self.start = Some(self.start.map_or(time, |start| start.min(time)));
+            self.end = Some(self.end.map_or(time, |end| end.max(time)));
+        }
+        if self.buf.len() >= self.chunk_size {
+            self.write_chunk(py)?;
+        }
+        Ok(())
+    }
+
+    /// Write each of an iterable of packets, returning the number written.
+    fn write_all(&mut self, py: Python, packets: &PyAny) -> PyResult<usize> {
+        let mut count = 0;
+        for packet in packets.iter()? {
+            self.write(py, packet?)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Compress any buffered packets and write the index. The archive is complete once
+    /// closed.
+    fn close(&mut self, py: Python) -> PyResult<()> {
+        if self.file.is_none() {
+            return Ok(());
+        }
+        self.write_chunk(py)?;
+        let mut file = self.file.take().unwrap();
+        let index = Index {
+            timecode: self.timecode_name.clone(),
+            chunks: std::mem::take(&mut self.chunks),
+        };
+        let index = serde_json::to_vec(&index).expect("index is serializable");
+        file.write_all(&index)?;
+        file.write_all(&(index.len() as u64).to_le_bytes())?;
+        file.write_all(INDEX_MAGIC)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        py: Python,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+    ) -> PyResult<()> {
+        self.close(py)
+    }
+}
+
+impl PacketArchiveWriter {
+    /// Compress and write the buffered packets, if any.
+    fn write_chunk(&mut self, py: Python) -> PyResult<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        let buf = &self.buf;
+        let compressed =
+            py.allow_threads(|| zstd::bulk::compress(buf, zstd::DEFAULT_COMPRESSION_LEVEL))?;
+        file.write_all(&compressed)?;
+        self.chunks.push(ArchiveChunk {
+            offset: self.offset,
+            compressed_len: compressed.len() as u64,
+            len: self.buf.len() as u64,
+            packets: self.packets,
+            apids: std::mem::take(&mut self.apids).into_iter().collect(),
+            start: self.start.take(),
+            end: self.end.take(),
+        });
+        self.offset += compressed.len() as u64;
+        self.buf.clear();
+        self.packets = 0;
+        Ok(())
+    }
+}
+
+/// A zstd-compressed packet archive written by `PacketArchiveWriter`.
+///
+/// Only the index is read when opened; chunks are read and decompressed when they may
+/// contain selected packets.
+///
+/// Parameters
+/// ----------
+/// path : str or os.PathLike
+///     Path of the archive.
+#[pyclass]
+pub struct PacketArchive {
+    path: PathBuf,
+    timecode: Timecode,
+    /// Timecode used to index the packets by time.
+    #[pyo3(get, name = "timecode")]
+    timecode_name: String,
+    /// The compressed chunks, in the order written.
+    #[pyo3(get)]
+    chunks: Vec<ArchiveChunk>,
+}
+
+#[pymethods]
+impl PacketArchive {
+    #[new]
+    fn py_new(path: PathBuf) -> PyResult<Self> {
+        let mut file = File::open(&path)?;
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(PyValueError::new_err(format!(
+                "{} is not a packet archive",
+                path.display()
+            )));
+        }
+        let mut trailer = [0u8; 16];
+        let size = file.seek(SeekFrom::End(0))?;
+        if size < (MAGIC.len() + trailer.len()) as u64 {
+            return Err(PyValueError::new_err(format!(
+                "{} is truncated; was the writer closed?",
+                path.display()
+            )));
+        }
+        file.seek(SeekFrom::End(-(trailer.len() as i64)))?;
+        file.read_exact(&mut trailer)?;
+        if &trailer[8..] != INDEX_MAGIC {
+            return Err(PyValueError::new_err(format!(
+                "{} has no index; was the writer closed?",
+                path.display()
+            )));
+        }
+        let index_len = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        if index_len > size - (MAGIC.len() + trailer.len()) as u64 {
+            return Err(PyValueError::new_err(format!(
+                "invalid index length {index_len} in {}",
+                path.display()
+            )));
+        }
+        file.seek(SeekFrom::End(-((trailer.len() as u64 + index_len) as i64)))?;
+        let mut index = vec![0u8; index_len as usize];
+        file.read_exact(&mut index)?;
+        let index: Index = serde_json::from_slice(&index)
+            .map_err(|err| PyValueError::new_err(format!("invalid archive index: {err}")))?;
+        Ok(Self {
+            path,
+            timecode: Timecode::from_name(&index.timecode)?,
+            timecode_name: index.timecode,
+            chunks: index.chunks,
+        })
+    }
+
+    /// Read the packets matching the given criteria, in the order written.
+    ///
+    /// Parameters
+    /// ----------
+    /// apids : Iterable[int], optional
+    ///     Only include packets with these APIDs.
+    ///
+    /// start : int, optional
+    ///     Only include packets with a time at or after this time, in UTC milliseconds.
+    ///
+    /// end : int, optional
+    ///     Only include packets with a time before this time, in UTC milliseconds.
+    ///     Packets without a time are excluded if `start` or `end` is provided.
+    ///
+    /// Returns
+    /// -------
+    /// list[Packet]
+    #[pyo3(signature=(apids=None, start=None, end=None))]
+    fn read(
+        &self,
+        py: Python,
+        apids: Option<&PyAny>,
+        start: Option<i64>,
+        end: Option<i64>,
+    ) -> PyResult<Vec<Packet>> {
+        let apids: Option<HashSet<u16>> = crate::set_arg(apids)?;
+        py.allow_threads(|| {
+            let mut file = File::open(&self.path)?;
+            let mut packets = Vec::default();
+            for chunk in &self.chunks {
+                if !chunk.selected(&apids, start, end) {
+                    continue;
+                }
+                let mut compressed = vec![0u8; chunk.compressed_len as usize];
+                file.seek(SeekFrom::Start(chunk.offset))?;
+                file.read_exact(&mut compressed)?;
+                let dat = zstd::decode_all(&compressed[..])?;
+                for packet in PacketReader::new(&dat[..]) {
+                    let packet = Packet::from(packet?);
+                    let hdr = &packet.header;
+                    if apids.as_ref().is_some_and(|a| !a.contains(&hdr.apid)) {
+                        continue;
+                    }
+                    if start.is_some() || end.is_some() {
+                        let time = hdr
+                            .has_secondary_header
+                            .then(|| self.timecode.decode(packet.payload()))
+                            .flatten();
+                        let Some(time) = time else {
+                            continue;
+                        };
+                        if start.is_some_and(|start| time < start)
+                            || end.is_some_and(|end| time >= end)
+                        {
+                            continue;
+                        }
+                    }
+                    packets.push(packet);
+                }
+            }
+            Ok(packets)
+        })
+    }
+
+    /// Number of packets in the archive.
+    fn __len__(&self) -> usize {
+        self.chunks.iter().map(|c| c.packets as usize).sum()
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "PacketArchive(path={}, chunks={}, packets={})",
+            repr(&*self.path.to_string_lossy()),
+            self.chunks.len(),
+            self.__len__(),
+        )
+    }
+}
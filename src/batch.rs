@@ -0,0 +1,484 @@
+//! Parallel decoding of many files with the options of a `Pipeline`.
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+use serde::Serialize;
+
+use crate::{
+    filter::{self, PacketFilter},
+    frameconfig::FrameDecodeConfig,
+    gaps, limits_arg,
+    packets::{Limits, PacketReader},
+    repr::repr,
+    session::DecodePipeline,
+    source::open_source,
+    summary::{CountingReader, Summary},
+    sync, Packet,
+};
+
+/// Pipeline options that do not apply to batch processing and are ignored.
+const IGNORED_OPTIONS: &[&str] = &["prefetch", "timeout"];
+/// Pipeline options applied when processing files of space packets.
+const PACKET_OPTIONS: &[&str] = &["resync", "max_packet_len", "versions", "apids", "filter"];
+/// Pipeline options applied when processing files of CADUs.
+const CADU_OPTIONS: &[&str] = &[
+    "scid",
+    "cadu_len",
+    "izone_len",
+    "trailer_len",
+    "interleave",
+    "asm",
+    "filter",
+];
+
+/// Results of processing one file with `process_files`.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Serialize)]
+pub struct FileSummary {
+    #[pyo3(get)]
+    pub path: String,
+    /// Totals for the file, where the items are the packets that pass any filter.
+    #[pyo3(get)]
+    pub summary: Summary,
+    /// Path of the packets written for the file, if any.
+    #[pyo3(get)]
+    pub dest: Option<String>,
+    /// Error that ended processing of the file, if any.
+    #[pyo3(get)]
+    pub error: Option<String>,
+}
+
+#[pymethods]
+impl FileSummary {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "FileSummary(path={}, items={}, errors={}, missing={}, error={})",
+            repr(&self.path),
+            self.summary.items,
+            self.summary.errors,
+            self.summary.missing,
+            repr(&self.error),
+        )
+    }
+}
+
+/// Aggregated results of `process_files`.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchSummary {
+    /// Totals over all the files.
+    #[pyo3(get)]
+    pub total: Summary,
+    /// Number of files whose processing ended with an error.
+    #[pyo3(get)]
+    pub failed: u64,
+    /// Per-file results, in the order of the paths.
+    #[pyo3(get)]
+    pub files: Vec<FileSummary>,
+}
+
+#[pymethods]
+impl BatchSummary {
+    /// The summary as a JSON document.
+    #[pyo3(signature=(indent=true))]
+    fn to_json(&self, indent: bool) -> PyResult<String> {
+        let result = if indent {
+            serde_json::to_string_pretty(self)
+        } else {
+            serde_json::to_string(self)
+        };
+        result.map_err(|err| PyValueError::new_err(format!("{err}")))
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "BatchSummary(files={}, failed={}, items={}, errors={}, missing={})",
+            self.files.len(),
+            self.failed,
+            self.total.items,
+            self.total.errors,
+            self.total.missing,
+        )
+    }
+}
+
+/// How to decode each file, resolved from the pipeline options.
+enum Config {
+    Packets {
+        resync: bool,
+        limits: Option<Limits>,
+    },
+    Cadu {
+        scid: u16,
+        block_len: usize,
+        izone_len: usize,
+        trailer_len: usize,
+        interleave: Option<u8>,
+        asm: Vec<u8>,
+    },
+}
+
+/// Extract option `key`, or the default if it is not set or None.
+fn option<'py, T: FromPyObject<'py> + Default>(options: &'py PyDict, key: &str) -> PyResult<T> {
+    match options.get_item(key) {
+        Some(value) if !value.is_none() => value.extract(),
+        _ => Ok(T::default()),
+    }
+}
+
+impl Config {
+    fn new(input_format: &str, options: &PyDict, first: &Path) -> PyResult<Self> {
+        let (config, accepted) = match input_format.to_lowercase().as_str() {
+            "packets" => {
+                let config = Self::Packets {
+                    resync: option(options, "resync")?,
+                    limits: limits_arg(
+                        option(options, "max_packet_len")?,
+                        option(options, "versions")?,
+                        option(options, "apids")?,
+                    )?,
+                };
+                (config, PACKET_OPTIONS)
+            }
+            "cadu" => (Self::cadu(options, first)?, CADU_OPTIONS),
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "unsupported input_format; expected one of cadu, packets, got {input_format}"
+                )))
+            }
+        };
+        for key in options.keys() {
+            let key: &str = key.extract()?;
+            if !accepted.contains(&key) && !IGNORED_OPTIONS.contains(&key) {
+                return Err(PyTypeError::new_err(format!(
+                    "option {key} is not supported by process_files for input_format {input_format}"
+                )));
+            }
+        }
+        Ok(config)
+    }
+
+    fn cadu(options: &PyDict, first: &Path) -> PyResult<Self> {
+        let (Some(scid), Some(cadu_len)) = (
+            option::<Option<u16>>(options, "scid")?,
+            option::<Option<usize>>(options, "cadu_len")?,
+        ) else {
+            return Err(PyValueError::new_err(
+                "input_format cadu requires the scid and cadu_len options",
+            ));
+        };
+        let izone_len: usize = option(options, "izone_len")?;
+        let trailer_len: usize = option(options, "trailer_len")?;
+        if izone_len >= 16 || trailer_len >= 16 {
+            return Err(PyValueError::new_err(format!(
+                "invalid izone_len or trailer_len; expected 0..16, got {izone_len} and {trailer_len}"
+            )));
+        }
+        let asm = options.get_item("asm").filter(|v| !v.is_none());
+        let interleave = options.get_item("interleave").filter(|v| !v.is_none());
+        let config = FrameDecodeConfig::from_cadu_len(first, cadu_len as i64, interleave, asm)?;
+        config.check_layout(izone_len, trailer_len)?;
+        let FrameDecodeConfig {
+            asm,
+            frame_len: block_len,
+            interleave,
+        } = config;
+        Ok(Self::Cadu {
+            scid,
+            block_len,
+            izone_len,
+            trailer_len,
+            interleave,
+            asm,
+        })
+    }
+
+    /// Decode the packets of `path`, calling `yielded` with each, and returning the
+    /// totals.
+    fn process(
+        &self,
+        path: &Path,
+        mut yielded: impl FnMut(&Packet, Option<(u16, u16)>) -> io::Result<bool>,
+    ) -> io::Result<Summary> {
+        let mut summary = Summary::default();
+        let reader = CountingReader::new(BufReader::new(open_source(path)?));
+        let bytes_read = reader.counter();
+        match self {
+            Self::Packets { resync, limits } => {
+                let mut gaps = gaps::GapTracker::default();
+                let reader = PacketReader::new(reader)
+                    .resync(*resync)
+                    .limits(limits.clone());
+                for packet in reader {
+                    let packet = match packet {
+                        Ok(packet) => packet,
+                        Err(_) => {
+                            summary.errors += 1;
+                            continue;
+                        }
+                    };
+                    if let Some((_, len)) = packet.skipped {
+                        summary.skipped_bytes += len;
+                    }
+                    if let Some(gap) = gaps.add(&packet, None) {
+                        summary.missing += u64::from(gap.count);
+                    }
+                    if yielded(&Packet::from(packet), None)? {
+                        summary.items += 1;
+                    }
+                }
+            }
+            Self::Cadu {
+                scid,
+                block_len,
+                izone_len,
+                trailer_len,
+                interleave,
+                asm,
+            } => {
+                let frame_stats: Arc<Mutex<Summary>> = Arc::default();
+                let blocks = {
+                    let stats = frame_stats.clone();
+                    sync::Synchronizer::new(reader, asm, *block_len).filter_map(move |block| {
+                        block
+                            .map(|(_, block)| block)
+                            .map_err(|_| stats.lock().unwrap().errors += 1)
+                            .ok()
+                    })
+                };
+                let mut builder = ccsds::FrameDecoderBuilder::default();
+                if let Some(interleave) = interleave {
+                    builder = builder.reed_solomon(*interleave);
+                }
+                let frames = {
+                    let stats = frame_stats.clone();
+                    builder.build().start(blocks).filter_map(move |frame| {
+                        let mut stats = stats.lock().unwrap();
+                        match &frame {
+                            Ok(frame) => match frame.rsstate {
+                                ccsds::RSState::Ok => stats.rs_ok += 1,
+                                ccsds::RSState::Corrected(_) => stats.rs_corrected += 1,
+                                ccsds::RSState::Uncorrectable(_) => stats.rs_uncorrectable += 1,
+                                ccsds::RSState::NotPerformed => stats.rs_not_performed += 1,
+                            },
+                            Err(_) => stats.errors += 1,
+                        }
+                        frame.ok()
+                    })
+                };
+                let mut last: HashMap<u16, u16> = HashMap::default();
+                for decoded in ccsds::decode_framed_packets(*scid, frames, *izone_len, *trailer_len)
+                {
+                    let ids = Some((decoded.scid, decoded.vcid));
+                    let packet = Packet::new(decoded.packet);
+                    let hdr = &packet.header;
+                    if let Some(prev) = last.insert(hdr.apid, hdr.sequence_id) {
+                        summary.missing += u64::from(ccsds::missing_packets(hdr.sequence_id, prev));
+                    }
+                    if yielded(&packet, ids)? {
+                        summary.items += 1;
+                    }
+                }
+                let stats = frame_stats.lock().unwrap();
+                summary.errors += stats.errors;
+                summary.rs_ok = stats.rs_ok;
+                summary.rs_corrected = stats.rs_corrected;
+                summary.rs_uncorrectable = stats.rs_uncorrectable;
+                summary.rs_not_performed = stats.rs_not_performed;
+            }
+        }
+        summary.bytes_read = bytes_read.load(Ordering::Relaxed);
+        summary.complete = true;
+        Ok(summary)
+    }
+}
+
+/// Process one file, writing the packets that pass `filter` to `dest`, if any.
+fn process_file(
+    config: &Config,
+    filter: Option<&PacketFilter>,
+    path: &Path,
+    dest: Option<PathBuf>,
+) -> FileSummary {
+    let result = (|| -> io::Result<Summary> {
+        let mut writer = dest
+            .as_ref()
+            .map(|dest| File::create(dest).map(BufWriter::new))
+            .transpose()?;
+        let summary = config.process(path, |packet, ids| {
+            if let Some(filter) = filter {
+                let values = filter::Values {
+                    header: &packet.header,
+                    data_len: packet.payload().len(),
+                    ids,
+                };
+                if !filter.eval(&values) {
+                    return Ok(false);
+                }
+            }
+            if let Some(writer) = writer.as_mut() {
+                writer.write_all(&packet.data)?;
+            }
+            Ok(true)
+        })?;
+        if let Some(writer) = writer.as_mut() {
+            writer.flush()?;
+        }
+        Ok(summary)
+    })();
+    let (summary, error) = match result {
+        Ok(summary) => (summary, None),
+        Err(err) => (Summary::default(), Some(err.to_string())),
+    };
+    FileSummary {
+        path: path.display().to_string(),
+        summary,
+        dest: dest.map(|dest| dest.display().to_string()),
+        error,
+    }
+}
+
+/// Decode many files in parallel on a pool of threads using the decode options of a
+/// `Pipeline`, e.g., for nightly reprocessing of pass files, aggregating the totals
+/// of each file.
+///
+/// Decoding runs entirely without the GIL. Files of space packets use the `resync`,
+/// `max_packet_len`, `versions`, `apids` and `filter` options, and files of CADUs the
+/// `scid`, `cadu_len`, `izone_len`, `trailer_len`, `interleave`, `asm` and `filter`
+/// options. If `asm` or `interleave` is `auto` they are detected from the first file
+/// and used for all files. A pipeline with processing stages, or with other options,
+/// is not supported.
+///
+/// An error processing a file, e.g., because it cannot be read, is recorded in its
+/// summary and does not stop the other files.
+///
+/// Parameters
+/// ----------
+/// paths : list of str or os.PathLike
+///     Files to process.
+///
+/// pipeline : Pipeline
+///     The decode options to use for every file.
+///
+/// input_format : str
+///     Format of the files, either "cadu" or "packets".
+///
+/// dest_dir : str or os.PathLike, optional
+///     If provided, the packets that pass `filter` from each file are written to a file
+///     in this directory named after the source file with a `.packets` suffix.
+///
+/// jobs : int, optional
+///     Number of files to process at once. Defaults to the number of CPUs.
+///
+/// Returns
+/// -------
+/// BatchSummary
+#[pyfunction(signature=(paths, pipeline, input_format="cadu", dest_dir=None, jobs=None))]
+pub fn process_files(
+    py: Python,
+    paths: Vec<PathBuf>,
+    pipeline: PyRef<DecodePipeline>,
+    input_format: &str,
+    dest_dir: Option<PathBuf>,
+    jobs: Option<usize>,
+) -> PyResult<BatchSummary> {
+    if pipeline.has_stages() {
+        return Err(PyValueError::new_err(
+            "process_files does not support pipelines with processing stages",
+        ));
+    }
+    if jobs == Some(0) {
+        return Err(PyValueError::new_err("invalid jobs; expected > 0, got 0"));
+    }
+    let Some(first) = paths.first() else {
+        return Ok(BatchSummary {
+            total: Summary::default(),
+            failed: 0,
+            files: Vec::default(),
+        });
+    };
+    let options = pipeline.options_dict(py);
+    let config = Config::new(input_format, options, first)?;
+    let filter = filter::filter_arg(option(options, "filter")?)?;
+    let jobs = jobs
+        .or_else(|| thread::available_parallelism().ok().map(usize::from))
+        .unwrap_or(1)
+        .min(paths.len());
+    let dest = |path: &Path| {
+        dest_dir.as_ref().map(|dir| {
+            let mut name = path.file_name().unwrap_or_default().to_os_string();
+            name.push(".packets");
+            dir.join(name)
+        })
+    };
+
+    let files = py.allow_threads(|| {
+        let next = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<FileSummary>>> = Mutex::new(vec![None; paths.len()]);
+        thread::scope(|scope| {
+            for _ in 0..jobs {
+                scope.spawn(|| loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(idx) else {
+                        break;
+                    };
+                    let file = process_file(&config, filter.as_ref(), path, dest(path));
+                    results.lock().unwrap()[idx] = Some(file);
+                });
+            }
+        });
+        results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+    });
+
+    let mut total = Summary {
+        complete: true,
+        ..Summary::default()
+    };
+    let mut failed = 0;
+    for file in &files {
+        let summary = &file.summary;
+        total.items += summary.items;
+        total.bytes_read += summary.bytes_read;
+        total.errors += summary.errors;
+        total.missing += summary.missing;
+        total.skipped_bytes += summary.skipped_bytes;
+        total.rs_ok += summary.rs_ok;
+        total.rs_corrected += summary.rs_corrected;
+        total.rs_uncorrectable += summary.rs_uncorrectable;
+        total.rs_not_performed += summary.rs_not_performed;
+        if file.error.is_some() {
+            failed += 1;
+            total.complete = false;
+        }
+    }
+    Ok(BatchSummary {
+        total,
+        failed,
+        files,
+    })
+}
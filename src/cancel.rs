@@ -0,0 +1,44 @@
+use std::{
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Shared flag used to stop a decode pipeline, including any stages running on
+/// background threads.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Reader wrapper that fails all reads once its token is cancelled, causing the
+/// pipeline reading from it to wind down.
+pub struct CancellableReader<R> {
+    inner: R,
+    token: CancelToken,
+}
+
+impl<R: Read> CancellableReader<R> {
+    pub fn new(inner: R, token: CancelToken) -> Self {
+        Self { inner, token }
+    }
+}
+
+impl<R: Read> Read for CancellableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.token.is_cancelled() {
+            return Err(io::Error::other("cancelled"));
+        }
+        self.inner.read(buf)
+    }
+}
@@ -0,0 +1,287 @@
+//! Entry points of the command-line tools installed with the package, thin wrappers
+//! over the library functions using Python's argparse for argument handling.
+use std::path::PathBuf;
+
+use pyo3::{
+    exceptions::{PySystemExit, PyValueError},
+    prelude::*,
+    types::{PyDict, PyLong},
+};
+
+use crate::{manifest, merge, report, subset};
+
+/// How a command-line argument is parsed.
+enum Kind {
+    /// A required positional argument.
+    Positional,
+    /// An optional string.
+    Str,
+    /// An optional integer.
+    Int,
+    /// An integer that may be repeated.
+    Ints,
+}
+
+/// An argparse parser with the values parsed from the command line.
+struct Command<'py> {
+    py: Python<'py>,
+    parser: &'py PyAny,
+    args: Option<&'py PyAny>,
+}
+
+impl<'py> Command<'py> {
+    fn new(py: Python<'py>, prog: &str, description: &str) -> PyResult<Self> {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("prog", prog)?;
+        kwargs.set_item("description", description)?;
+        let parser = py
+            .import("argparse")?
+            .getattr("ArgumentParser")?
+            .call((), Some(kwargs))?;
+        Ok(Self {
+            py,
+            parser,
+            args: None,
+        })
+    }
+
+    fn arg(self, name: &str, kind: Kind, help: &str) -> PyResult<Self> {
+        let kwargs = PyDict::new(self.py);
+        kwargs.set_item("help", help)?;
+        match kind {
+            Kind::Positional | Kind::Str => (),
+            Kind::Int => kwargs.set_item("type", self.py.get_type::<PyLong>())?,
+            Kind::Ints => {
+                kwargs.set_item("type", self.py.get_type::<PyLong>())?;
+                kwargs.set_item("action", "append")?;
+            }
+        }
+        let name = match kind {
+            Kind::Positional => name.to_string(),
+            _ => format!("--{}", name.replace('_', "-")),
+        };
+        self.parser
+            .call_method("add_argument", (name,), Some(kwargs))?;
+        Ok(self)
+    }
+
+    /// Parse `args`, or the process arguments if not provided. Exits with usage on
+    /// invalid arguments.
+    fn parse(mut self, args: Option<Vec<String>>) -> PyResult<Self> {
+        self.args = Some(self.parser.call_method1("parse_args", (args,))?);
+        Ok(self)
+    }
+
+    fn get<T: FromPyObject<'py>>(&self, name: &str) -> PyResult<T> {
+        self.args
+            .expect("arguments are parsed")
+            .getattr(name)?
+            .extract()
+    }
+
+    /// The interleave argument, either a number or a name such as `auto`.
+    fn interleave(&self) -> PyResult<Option<PyObject>> {
+        let value: Option<String> = self.get("interleave")?;
+        Ok(value.map(|value| match value.parse::<u8>() {
+            Ok(n) => n.into_py(self.py),
+            Err(_) => value.into_py(self.py),
+        }))
+    }
+
+    /// Exit with a usage error for an invalid combination of arguments.
+    fn usage_error(&self, msg: &str) -> PyErr {
+        match self.parser.call_method1("error", (msg,)) {
+            Err(err) => err,
+            Ok(_) => PySystemExit::new_err(2),
+        }
+    }
+
+    /// Print `text` to standard output.
+    fn print(&self, text: String) -> PyResult<()> {
+        self.py
+            .import("builtins")?
+            .getattr("print")?
+            .call1((text,))?;
+        Ok(())
+    }
+
+    /// Exit with status 1 and the message of `err` unless it is already an exit.
+    fn fail(&self, err: PyErr) -> PyErr {
+        if err.is_instance_of::<PySystemExit>(self.py) {
+            return err;
+        }
+        let prog: String = self
+            .parser
+            .getattr("prog")
+            .and_then(|p| p.extract())
+            .unwrap_or_default();
+        let msg = format!("{prog}: error: {}\n", err.value(self.py));
+        match self.parser.call_method1("exit", (1, msg)) {
+            Err(err) => err,
+            Ok(_) => PySystemExit::new_err(1),
+        }
+    }
+}
+
+/// Entry point of `ccsds-info`, which prints a JSON summary of a packet file, or a
+/// pass report of a CADU file if `--cadu-len` is given.
+#[pyfunction(signature=(args=None))]
+pub fn info_main(py: Python, args: Option<Vec<String>>) -> PyResult<()> {
+    let cmd = Command::new(py, "ccsds-info", "Summarize a packet or CADU file.")?
+        .arg("source", Kind::Positional, "packet or CADU file")?
+        .arg("scid", Kind::Int, "spacecraft id of the CADUs")?
+        .arg("cadu_len", Kind::Int, "CADU length, if the source is CADUs")?
+        .arg("izone_len", Kind::Int, "frame insert zone length")?
+        .arg("trailer_len", Kind::Int, "frame trailer length")?
+        .arg("interleave", Kind::Str, "Reed-Solomon interleave or auto")?
+        .arg(
+            "timecode",
+            Kind::Str,
+            "secondary header timecode, cds or eoscuc",
+        )?
+        .parse(args)?;
+    let source: PathBuf = cmd.get("source")?;
+    let json = match cmd.get::<Option<usize>>("cadu_len")? {
+        Some(cadu_len) => {
+            let Some(scid) = cmd.get::<Option<u16>>("scid")? else {
+                return Err(cmd.usage_error("--cadu-len requires --scid"));
+            };
+            let interleave = cmd.interleave()?;
+            report::pass_report(
+                source,
+                scid,
+                cadu_len,
+                cmd.get::<Option<usize>>("izone_len")?.unwrap_or(0),
+                cmd.get::<Option<usize>>("trailer_len")?.unwrap_or(0),
+                interleave.as_ref().map(|i| i.as_ref(py)),
+                None,
+                cmd.get("timecode")?,
+            )
+            .and_then(|report| to_json(&report))
+        }
+        None => manifest::manifest(py, source, "sha256").and_then(|m| to_json(&m)),
+    }
+    .map_err(|err| cmd.fail(err))?;
+    cmd.print(json)
+}
+
+/// Entry point of `ccsds-filter`, which writes the packets of a packet or CADU file
+/// selected by APID, VCID, time or filter expression to a packet file.
+#[pyfunction(signature=(args=None))]
+pub fn filter_main(py: Python, args: Option<Vec<String>>) -> PyResult<()> {
+    let cmd = Command::new(
+        py,
+        "ccsds-filter",
+        "Select packets from a packet or CADU file.",
+    )?
+    .arg("source", Kind::Positional, "packet or CADU file")?
+    .arg("dest", Kind::Positional, "packet file to write")?
+    .arg("apid", Kind::Ints, "include packets with this APID")?
+    .arg(
+        "vcid",
+        Kind::Ints,
+        "include packets from frames with this VCID",
+    )?
+    .arg(
+        "start",
+        Kind::Int,
+        "include packets at or after this UTC ms",
+    )?
+    .arg("end", Kind::Int, "include packets before this UTC ms")?
+    .arg(
+        "timecode",
+        Kind::Str,
+        "secondary header timecode, cds or eoscuc",
+    )?
+    .arg("filter", Kind::Str, "packet filter expression")?
+    .arg("scid", Kind::Int, "spacecraft id of the CADUs")?
+    .arg("cadu_len", Kind::Int, "CADU length, if the source is CADUs")?
+    .arg("izone_len", Kind::Int, "frame insert zone length")?
+    .arg("trailer_len", Kind::Int, "frame trailer length")?
+    .arg("interleave", Kind::Str, "Reed-Solomon interleave or auto")?
+    .arg(
+        "compression",
+        Kind::Str,
+        "output compression, gzip, zstd or none",
+    )?
+    .parse(args)?;
+    let interleave = cmd.interleave()?;
+    let filter: Option<String> = cmd.get("filter")?;
+    let compression: Option<String> = cmd.get("compression")?;
+    let count = subset::subset(
+        py,
+        cmd.get("source")?,
+        cmd.get("dest")?,
+        cmd.get("apid")?,
+        cmd.get("start")?,
+        cmd.get("end")?,
+        cmd.get("timecode")?,
+        cmd.get("vcid")?,
+        filter.as_deref(),
+        cmd.get("scid")?,
+        cmd.get("cadu_len")?,
+        cmd.get::<Option<usize>>("izone_len")?.unwrap_or(0),
+        cmd.get::<Option<usize>>("trailer_len")?.unwrap_or(0),
+        interleave.as_ref().map(|i| i.as_ref(py)),
+        compression.as_deref(),
+    )
+    .map_err(|err| cmd.fail(err))?;
+    cmd.print(format!("wrote {count} packets"))
+}
+
+/// Entry point of `ccsds-merge`, which merges the packets of realtime and playback CADU
+/// files into a single time-ordered packet file.
+#[pyfunction(signature=(args=None))]
+pub fn merge_main(py: Python, args: Option<Vec<String>>) -> PyResult<()> {
+    let cmd = Command::new(
+        py,
+        "ccsds-merge",
+        "Merge realtime and playback CADU files into a packet file.",
+    )?
+    .arg("realtime", Kind::Positional, "realtime CADU file")?
+    .arg("playback", Kind::Positional, "playback CADU file")?
+    .arg("dest", Kind::Positional, "packet file to write")?
+    .arg("scid", Kind::Int, "spacecraft id of the CADUs")?
+    .arg("cadu_len", Kind::Int, "CADU length")?
+    .arg("izone_len", Kind::Int, "frame insert zone length")?
+    .arg("trailer_len", Kind::Int, "frame trailer length")?
+    .arg("interleave", Kind::Str, "Reed-Solomon interleave or auto")?
+    .arg(
+        "timecode",
+        Kind::Str,
+        "secondary header timecode, cds or eoscuc",
+    )?
+    .arg(
+        "compression",
+        Kind::Str,
+        "output compression, gzip, zstd or none",
+    )?
+    .parse(args)?;
+    let (Some(scid), Some(cadu_len)) = (cmd.get("scid")?, cmd.get("cadu_len")?) else {
+        return Err(cmd.usage_error("--scid and --cadu-len are required"));
+    };
+    let interleave = cmd.interleave()?;
+    let compression: Option<String> = cmd.get("compression")?;
+    let summary = merge::merge_realtime_playback(
+        py,
+        cmd.get("realtime")?,
+        cmd.get("playback")?,
+        cmd.get("dest")?,
+        scid,
+        cadu_len,
+        cmd.get::<Option<usize>>("izone_len")?.unwrap_or(0),
+        cmd.get::<Option<usize>>("trailer_len")?.unwrap_or(0),
+        interleave.as_ref().map(|i| i.as_ref(py)),
+        cmd.get("timecode")?,
+        compression.as_deref(),
+    )
+    .map_err(|err| cmd.fail(err))?;
+    cmd.print(format!(
+        "wrote {} realtime and {} playback packets; dropped {} duplicate and {} untimed packets",
+        summary.realtime, summary.playback, summary.duplicates, summary.untimed
+    ))
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> PyResult<String> {
+    serde_json::to_string_pretty(value).map_err(|err| PyValueError::new_err(format!("{err}")))
+}
@@ -0,0 +1,146 @@
+//! Detection of packet time regressions and jumps, e.g., from an onboard clock reset,
+//! which would otherwise silently scramble time-ordered processing downstream.
+
+// pyo3 0.19 generates the constructor text signature as an impl nested in a function
+#![allow(non_local_definitions)]
+use std::collections::{HashMap, HashSet};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    anomaly::{self, Anomaly, AnomalyCollector, Severity},
+    repr::repr,
+    timecode::{registry_or_cds, TimecodeRegistry},
+    PrimaryHeader,
+};
+
+/// Thresholds for flagging packets whose time is implausible given the time of the
+/// previous packet with the same APID.
+///
+/// Parameters
+/// ----------
+/// timecode : str or TimecodeRegistry, optional
+///     Format of the timecode at the start of the secondary header, one of `cds` or
+///     `eoscuc`, or the timecode of each APID. Defaults to `cds`.
+///
+/// max_regression : int
+///     Largest step backwards in time, in milliseconds, that is not flagged as a
+///     `regression`. Defaults to 0, flagging any decrease.
+///
+/// max_jump : int, optional
+///     Largest step forwards in time, in milliseconds, that is not flagged as a `jump`.
+///     Forward steps are not checked if not provided.
+///
+/// apids : Iterable[int], optional
+///     Only check packets with these APIDs. All packets are checked if not provided.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct ClockCheck {
+    timecode: TimecodeRegistry,
+    #[pyo3(get)]
+    max_regression: i64,
+    #[pyo3(get)]
+    max_jump: Option<i64>,
+    #[pyo3(get)]
+    apids: Option<HashSet<u16>>,
+}
+
+#[pymethods]
+impl ClockCheck {
+    #[new]
+    #[pyo3(signature=(timecode=None, max_regression=0, max_jump=None, apids=None))]
+    fn py_new(
+        timecode: Option<&PyAny>,
+        max_regression: i64,
+        max_jump: Option<i64>,
+        apids: Option<&PyAny>,
+    ) -> PyResult<Self> {
+        let apids: Option<HashSet<u16>> = crate::set_arg(apids)?;
+        if max_regression < 0 {
+            return Err(PyValueError::new_err(format!(
+                "invalid max_regression; expected >= 0, got {max_regression}"
+            )));
+        }
+        if let Some(max_jump) = max_jump.filter(|max_jump| *max_jump <= 0) {
+            return Err(PyValueError::new_err(format!(
+                "invalid max_jump; expected > 0, got {max_jump}"
+            )));
+        }
+        Ok(Self {
+            timecode: registry_or_cds(timecode)?,
+            max_regression,
+            max_jump,
+            apids,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "ClockCheck(max_regression={}, max_jump={}, apids={})",
+            self.max_regression,
+            repr(&self.max_jump),
+            repr(&self.apids),
+        )
+    }
+}
+
+/// Flags packets whose time regresses or jumps according to a [ClockCheck].
+#[derive(Clone, Debug, Default)]
+pub struct ClockMonitor {
+    pub check: Option<ClockCheck>,
+    /// Time of the previous packet of each APID with a time.
+    last: HashMap<u16, i64>,
+    /// APID, kind of violation, previous and current packet time, and source offset, if
+    /// known, of each packet flagged.
+    pub violations: Vec<(u16, &'static str, i64, i64, Option<u64>)>,
+    pub anomalies: Option<AnomalyCollector>,
+}
+
+impl ClockMonitor {
+    pub fn new(check: Option<ClockCheck>) -> Self {
+        Self {
+            check,
+            ..Self::default()
+        }
+    }
+
+    /// Check the time of a packet against the previous packet of its APID. Packets
+    /// without a secondary header or whose time cannot be decoded are not checked.
+    pub fn check(&mut self, header: &PrimaryHeader, data: &[u8], offset: Option<u64>) {
+        let Some(check) = &self.check else {
+            return;
+        };
+        let apid = header.apid;
+        if !header.has_secondary_header || check.apids.as_ref().is_some_and(|a| !a.contains(&apid))
+        {
+            return;
+        }
+        let Some(time) = check.timecode.decode(apid, data) else {
+            return;
+        };
+        let Some(last) = self.last.insert(apid, time) else {
+            return;
+        };
+        let step = time - last;
+        let kind = if step < -check.max_regression {
+            "regression"
+        } else if check.max_jump.is_some_and(|max_jump| step > max_jump) {
+            "jump"
+        } else {
+            return;
+        };
+        self.violations.push((apid, kind, last, time, offset));
+        anomaly::emit(&self.anomalies, || {
+            let description = match kind {
+                "regression" => format!("time went back {} ms from {last} to {time}", -step),
+                _ => format!("time jumped {step} ms from {last} to {time}"),
+            };
+            Anomaly::new("clock", Severity::Warning, description)
+                .apid(apid)
+                .offset(offset)
+        });
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use pyo3::prelude::*;
+
+use crate::{
+    digest::Sha256,
+    gaps::packet_time,
+    packets::PacketReader,
+    repr::repr,
+    source::open_source,
+    timecode::{optional_registry_arg, TimecodeRegistry},
+};
+
+/// Identifies a packet in one of the files being compared.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct PacketRef {
+    #[pyo3(get)]
+    pub apid: u16,
+    #[pyo3(get)]
+    pub sequence_id: u16,
+    /// Packet time in UTC milliseconds, if a timecode was provided.
+    #[pyo3(get)]
+    pub time: Option<i64>,
+    /// Byte offset of the packet in its file.
+    #[pyo3(get)]
+    pub offset: u64,
+}
+
+#[pymethods]
+impl PacketRef {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "PacketRef(apid={}, sequence_id={}, time={}, offset={})",
+            self.apid,
+            self.sequence_id,
+            repr(&self.time),
+            self.offset
+        )
+    }
+}
+
+/// Result of comparing two packet files.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default)]
+pub struct PacketComparison {
+    /// Packets only found in the first file.
+    #[pyo3(get)]
+    pub only_a: Vec<PacketRef>,
+    /// Packets only found in the second file.
+    #[pyo3(get)]
+    pub only_b: Vec<PacketRef>,
+    /// Packets found in both files whose bytes differ, including those of the primary
+    /// header, as (a, b) pairs.
+    #[pyo3(get)]
+    pub mismatched: Vec<(PacketRef, PacketRef)>,
+    /// Number of packets found in both files with identical bytes.
+    #[pyo3(get)]
+    pub matched: usize,
+}
+
+#[pymethods]
+impl PacketComparison {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "PacketComparison(only_a={}, only_b={}, mismatched={}, matched={})",
+            self.only_a.len(),
+            self.only_b.len(),
+            self.mismatched.len(),
+            self.matched
+        )
+    }
+
+    /// True if both files contain exactly the same packets.
+    fn identical(&self) -> bool {
+        self.only_a.is_empty() && self.only_b.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+type Key = (u16, u16, Option<i64>);
+
+struct Entry {
+    packet: PacketRef,
+    /// SHA-256 of the packet bytes, including the primary header.
+    digest: [u8; 32],
+}
+
+fn read_entries(
+    source: &Path,
+    timecode: Option<&TimecodeRegistry>,
+) -> PyResult<HashMap<Key, VecDeque<Entry>>> {
+    let reader = PacketReader::new(BufReader::new(open_source(source)?));
+    let mut entries: HashMap<Key, VecDeque<Entry>> = HashMap::default();
+    for packet in reader {
+        let packet = packet?;
+        let time = packet_time(&packet, timecode);
+        let hdr = &packet.header;
+        let mut hasher = Sha256::default();
+        hasher.update(&hdr.encode());
+        hasher.update(&packet.data);
+        entries
+            .entry((hdr.apid, hdr.sequence_id, time))
+            .or_default()
+            .push_back(Entry {
+                packet: PacketRef {
+                    apid: hdr.apid,
+                    sequence_id: hdr.sequence_id,
+                    time,
+                    offset: packet.offset,
+                },
+                digest: hasher.finish(),
+            });
+    }
+    Ok(entries)
+}
+
+/// Compare the packets in two files.
+///
+/// Packets are matched on APID, sequence id and, if `timecode` is provided, packet time.
+/// Without a timecode packets are matched in file order amongst those with the same
+/// APID and sequence id, which is only reliable for files that do not wrap the sequence
+/// counter. Matched packets are compared including their primary headers, so packets
+/// differing only in e.g. their sequence flags are reported as mismatched.
+///
+/// Parameters
+/// ----------
+/// a : str or os.PathLike
+///     Source providing the first stream of space packets.
+///
+/// b : str or os.PathLike
+///     Source providing the second stream of space packets.
+///
+/// timecode : str or TimecodeRegistry, optional
+///     Format of the timecode at the start of the secondary header, one of `cds` or
+///     `eoscuc`, or the timecode of each APID.
+///
+/// Returns
+/// -------
+/// PacketComparison
+#[pyfunction(signature=(a, b, timecode=None))]
+pub fn compare_packets(
+    a: PathBuf,
+    b: PathBuf,
+    timecode: Option<&PyAny>,
+) -> PyResult<PacketComparison> {
+    let timecode = optional_registry_arg(timecode)?;
+    let a = read_entries(&a, timecode.as_ref())?;
+    let mut b = read_entries(&b, timecode.as_ref())?;
+
+    let mut cmp = PacketComparison::default();
+    for (key, a_entries) in a {
+        let mut b_entries = b.remove(&key).unwrap_or_default();
+        for a_entry in a_entries {
+            match b_entries.pop_front() {
+                Some(b_entry) if b_entry.digest == a_entry.digest => cmp.matched += 1,
+                Some(b_entry) => cmp.mismatched.push((a_entry.packet, b_entry.packet)),
+                None => cmp.only_a.push(a_entry.packet),
+            }
+        }
+        cmp.only_b.extend(b_entries.into_iter().map(|e| e.packet));
+    }
+    cmp.only_b.extend(
+        b.into_values()
+            .flat_map(|v| v.into_iter().map(|e| e.packet)),
+    );
+
+    cmp.only_a.sort_by_key(|p| p.offset);
+    cmp.only_b.sort_by_key(|p| p.offset);
+    cmp.mismatched.sort_by_key(|(a, _)| a.offset);
+
+    Ok(cmp)
+}
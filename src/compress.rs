@@ -0,0 +1,150 @@
+//! Compressed output for the functions that write files, since Level-0 deliveries are
+//! usually stored compressed and compressing them afterwards doubles the I/O.
+//!
+//! Output is compressed in chunks, each written as a complete gzip member or zstd frame,
+//! which standard decoders read back as a single stream, so memory use is bounded
+//! however much is written.
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::write::GzEncoder;
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// Amount of output compressed at a time.
+const CHUNK_LEN: usize = 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Resolve the `compression` argument of the writer functions, one of `gzip`,
+    /// `zstd` or `none`, inferring it from the extension of `path`, `.gz` or `.zst`, if
+    /// not provided.
+    pub fn from_arg(name: Option<&str>, path: Option<&Path>) -> PyResult<Option<Self>> {
+        let Some(name) = name else {
+            let ext = path.and_then(Path::extension).and_then(|ext| ext.to_str());
+            return Ok(match ext {
+                Some("gz") => Some(Self::Gzip),
+                Some("zst") => Some(Self::Zstd),
+                _ => None,
+            });
+        };
+        match name.to_lowercase().as_str() {
+            "gzip" => Ok(Some(Self::Gzip)),
+            "zstd" => Ok(Some(Self::Zstd)),
+            "none" => Ok(None),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported compression; expected one of gzip, zstd, none, got {name}"
+            ))),
+        }
+    }
+
+    /// Extension conventionally added to the names of files compressed this way.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+
+    fn compress(self, dat: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::default(), flate2::Compression::default());
+                encoder.write_all(dat)?;
+                encoder.finish()
+            }
+            Self::Zstd => zstd::bulk::compress(dat, zstd::DEFAULT_COMPRESSION_LEVEL),
+        }
+    }
+}
+
+/// `path` with the extension of `compression` appended, if any.
+pub fn with_extension(path: PathBuf, compression: Option<Compression>) -> PathBuf {
+    match compression {
+        Some(compression) => {
+            let mut path = path.into_os_string();
+            path.push(".");
+            path.push(compression.extension());
+            path.into()
+        }
+        None => path,
+    }
+}
+
+/// A file writer that optionally compresses what is written.
+///
+/// Flushing compresses the output buffered so far, ending the current gzip member or
+/// zstd frame, so is best left until the end. Output is flushed when the writer is
+/// dropped, ignoring any error, so call `flush` to handle errors.
+pub struct Writer {
+    file: BufWriter<File>,
+    compression: Option<Compression>,
+    /// Output not yet compressed.
+    buf: Vec<u8>,
+    /// True once a compressed chunk has been written, so an empty output is still a
+    /// valid compressed stream.
+    started: bool,
+}
+
+impl Writer {
+    /// Create or truncate the file at `path`.
+    pub fn create(path: &Path, compression: Option<Compression>) -> io::Result<Self> {
+        Ok(Self {
+            file: BufWriter::new(File::create(path)?),
+            compression,
+            buf: Vec::default(),
+            started: false,
+        })
+    }
+
+    fn write_chunk(&mut self) -> io::Result<()> {
+        let Some(compression) = self.compression else {
+            return Ok(());
+        };
+        if self.buf.is_empty() && self.started {
+            return Ok(());
+        }
+        self.file.write_all(&compression.compress(&self.buf)?)?;
+        self.buf.clear();
+        self.started = true;
+        Ok(())
+    }
+}
+
+impl Write for Writer {
+    fn write(&mut self, dat: &[u8]) -> io::Result<usize> {
+        if self.compression.is_none() {
+            return self.file.write(dat);
+        }
+        self.buf.extend_from_slice(dat);
+        if self.buf.len() >= CHUNK_LEN {
+            self.write_chunk()?;
+        }
+        Ok(dat.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_chunk()?;
+        self.file.flush()
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Create the file at `path` for a writer function, compressed according to its
+/// `compression` argument.
+pub fn create(path: &Path, compression: Option<&str>) -> PyResult<Writer> {
+    let compression = Compression::from_arg(compression, Some(path))?;
+    Ok(Writer::create(path, compression)?)
+}
@@ -0,0 +1,214 @@
+//! One-shot conversion of a source to an output format.
+use std::{io::Write, path::PathBuf};
+
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+
+use crate::{
+    compress,
+    dataframe::dataframe,
+    layout::Layout,
+    session::{accepted_options, FRAMED_PACKET_OPTIONS, FRAME_OPTIONS, PACKET_OPTIONS},
+    timecode::registry_arg,
+    DecodedPacket, Frame, Packet,
+};
+
+/// Options used by the table output formats, other than those used for decoding.
+const TABLE_OPTIONS: &[&str] = &["fields", "timecode", "categorical"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum InputFormat {
+    Cadu,
+    Packets,
+}
+
+impl InputFormat {
+    /// Lookup an input format by the name used in the Python API.
+    fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_lowercase().as_str() {
+            "cadu" => Ok(Self::Cadu),
+            "packets" => Ok(Self::Packets),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported input_format; expected one of cadu, packets, got {name}"
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    Packets,
+    Frames,
+    Parquet,
+    Hdf5,
+}
+
+impl OutputFormat {
+    /// Lookup an output format by the name used in the Python API.
+    fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_lowercase().as_str() {
+            "packets" => Ok(Self::Packets),
+            "frames" => Ok(Self::Frames),
+            "parquet" => Ok(Self::Parquet),
+            "hdf5" => Ok(Self::Hdf5),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported output_format; expected one of packets, frames, parquet, hdf5, got {name}"
+            ))),
+        }
+    }
+
+    fn is_table(&self) -> bool {
+        matches!(self, Self::Parquet | Self::Hdf5)
+    }
+}
+
+/// The packet of a decoded item, or `None` for items that are not packets, i.e.,
+/// `BitstreamData`.
+fn item_packet(item: &PyAny) -> PyResult<Option<Packet>> {
+    if let Ok(packet) = item.extract::<Packet>() {
+        return Ok(Some(packet));
+    }
+    if let Ok(decoded) = item.extract::<PyRef<DecodedPacket>>() {
+        return Ok(Some(decoded.packet.clone()));
+    }
+    Ok(None)
+}
+
+/// Convert a source to another format in a single call, e.g., CADUs to a file of space
+/// packets or to a Parquet table.
+///
+/// Sources are decoded with `decode_packets` if `input_format` is "packets", or with
+/// `decode_framed_packets`, or `decode_frames` for "frames" output, if it is "cadu".
+/// Tables are built as by `packets_to_dataframe`.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source to convert.
+///
+/// dest : str or os.PathLike
+///     Path of the file to write.
+///
+/// input_format : str
+///     Format of the source, either "cadu" or "packets".
+///
+/// output_format : str
+///     Format to write, one of:
+///     - "packets": a stream of space packets
+///     - "frames": a stream of frames, without ASM or Reed-Solomon parity; requires
+///       "cadu" input
+///     - "parquet": a Parquet table with a row per packet; requires pyarrow
+///     - "hdf5": an HDF5 table with a row per packet under the key `packets`;
+///       requires PyTables
+///
+/// compression : str, optional
+///     Compress packet or frame output, either `gzip`, `zstd` or `none`. Defaults to
+///     `gzip` or `zstd` if `dest` ends with `.gz` or `.zst`, otherwise `none`. Tables
+///     are compressed by their own format.
+///
+/// **options
+///     Keyword arguments of the decode function used for `input_format`, e.g., `scid`,
+///     `cadu_len` and `filter`, and, for table output, the `fields`, `timecode` and
+///     `categorical` arguments of `packets_to_dataframe`.
+///
+/// Returns
+/// -------
+/// int
+///     The number of packets, frames or rows written.
+///
+/// Raises
+/// ------
+/// TypeError
+///     If an option is not used by the input and output formats.
+///
+/// ValueError
+///     If `compression` is provided for table output.
+#[pyfunction(signature=(source, dest, input_format="cadu", output_format="packets", compression=None, **options))]
+pub fn convert(
+    py: Python,
+    source: &PyAny,
+    dest: PathBuf,
+    input_format: &str,
+    output_format: &str,
+    compression: Option<&str>,
+    options: Option<&PyDict>,
+) -> PyResult<usize> {
+    let input = InputFormat::from_name(input_format)?;
+    let output = OutputFormat::from_name(output_format)?;
+    let (name, accepted) = match (input, output) {
+        (InputFormat::Packets, OutputFormat::Frames) => {
+            return Err(PyValueError::new_err(
+                "output_format frames requires input_format cadu",
+            ));
+        }
+        (InputFormat::Packets, _) => ("decode_packets", PACKET_OPTIONS),
+        (InputFormat::Cadu, OutputFormat::Frames) => ("decode_frames", FRAME_OPTIONS),
+        (InputFormat::Cadu, _) => ("decode_framed_packets", FRAMED_PACKET_OPTIONS),
+    };
+    if output.is_table() && compression.is_some() {
+        return Err(PyValueError::new_err(format!(
+            "compression is not supported for output_format {output_format}"
+        )));
+    }
+    let options = options.unwrap_or_else(|| PyDict::new(py));
+    for key in options.keys() {
+        let key: &str = key.extract()?;
+        let known = accepted.contains(&key) || output.is_table() && TABLE_OPTIONS.contains(&key);
+        if !known {
+            return Err(PyTypeError::new_err(format!(
+                "unexpected option {key} for input_format {input_format} and output_format {output_format}"
+            )));
+        }
+    }
+    let kwargs = accepted_options(py, options, accepted)?;
+    let items = py
+        .import("ccsds")?
+        .getattr(name)?
+        .call((source,), Some(kwargs))?
+        .iter()?;
+
+    if output.is_table() {
+        let fields: Option<Layout> = match options.get_item("fields") {
+            Some(fields) if !fields.is_none() => Some(fields.extract()?),
+            _ => None,
+        };
+        let timecode = match options.get_item("timecode") {
+            Some(timecode) if !timecode.is_none() => Some(registry_arg(timecode)?),
+            _ => None,
+        };
+        let categorical = match options.get_item("categorical") {
+            Some(categorical) => categorical.extract()?,
+            None => true,
+        };
+        let packets = items.filter_map(|item| item.and_then(item_packet).transpose());
+        let df = dataframe(py, packets, fields.as_ref(), timecode.as_ref(), categorical)?;
+        if output == OutputFormat::Parquet {
+            df.call_method1("to_parquet", (dest,))?;
+        } else {
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("key", "packets")?;
+            kwargs.set_item("mode", "w")?;
+            df.call_method("to_hdf", (dest,), Some(kwargs))?;
+        }
+        return df.len();
+    }
+
+    let mut writer = compress::create(&dest, compression)?;
+    let mut written = 0;
+    for item in items {
+        let item = item?;
+        if output == OutputFormat::Frames {
+            writer.write_all(&item.extract::<PyRef<Frame>>()?.data)?;
+        } else if let Some(packet) = item_packet(item)? {
+            writer.write_all(&packet.data)?;
+        } else {
+            continue;
+        }
+        written += 1;
+    }
+    writer.flush()?;
+    Ok(written)
+}
@@ -0,0 +1,224 @@
+//! pandas DataFrame and xarray Dataset construction from packet streams.
+use std::{collections::HashSet, io::BufReader, path::PathBuf};
+
+use pyo3::{prelude::*, types::PyDict};
+
+use crate::{
+    layout::Layout,
+    packets::PacketReader,
+    source::open_source,
+    timecode::{optional_registry_arg, registry_or_cds, TimecodeRegistry},
+    Packet,
+};
+
+/// Primary header columns, accumulated while reading packets.
+#[derive(Default)]
+struct HeaderColumns {
+    apid: Vec<u16>,
+    sequence_flags: Vec<u8>,
+    sequence_id: Vec<u16>,
+    has_secondary_header: Vec<bool>,
+    len_minus1: Vec<u16>,
+    offset: Vec<Option<u64>>,
+    /// Packet times in UTC nanoseconds.
+    time: Vec<Option<i64>>,
+}
+
+/// Decode the packets in a source into a pandas DataFrame with a row per packet.
+///
+/// Columns are decoded in Rust and handed to pandas once all packets are read, avoiding
+/// the creation of a Packet object per packet.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of space packets, as accepted by `decode_packets`.
+///
+/// fields : Layout, optional
+///     Fields to extract from each packet into a column per field, following the
+///     header columns.
+///
+/// timecode : str or TimecodeRegistry, optional
+///     Timecode at the start of the secondary header, either "cds" or "eoscuc", or the
+///     timecode of each APID. If provided, a `time` column of packet times is included,
+///     with any sub-millisecond precision of the timecode, NaT for packets without a
+///     secondary header or whose time cannot be decoded.
+///
+/// apids : Iterable[int], optional
+///     Only include packets with these APIDs. This should usually be set when
+///     extracting fields, as a layout describes the packets of specific APIDs.
+///
+/// categorical : bool
+///     If True, columns for enumerated fields are pandas.Categorical.
+///
+/// Returns
+/// -------
+/// pandas.DataFrame
+///     The `apid`, `sequence_flags`, `sequence_id`, `has_secondary_header`,
+///     `len_minus1` and `offset` header columns, the optional `time` column, and the
+///     field columns.
+#[pyfunction(signature=(source, fields=None, timecode=None, apids=None, categorical=true))]
+pub fn packets_to_dataframe(
+    py: Python,
+    source: PathBuf,
+    fields: Option<Layout>,
+    timecode: Option<&PyAny>,
+    apids: Option<&PyAny>,
+    categorical: bool,
+) -> PyResult<PyObject> {
+    let apids: Option<HashSet<u16>> = crate::set_arg(apids)?;
+    let timecode = optional_registry_arg(timecode)?;
+    let reader = PacketReader::new(BufReader::new(open_source(&source)?));
+    let packets = reader
+        .map(|packet| packet.map(Packet::from).map_err(PyErr::from))
+        .filter(|packet| {
+            packet.as_ref().map_or(true, |packet| {
+                apids
+                    .as_ref()
+                    .is_none_or(|apids| apids.contains(&packet.header.apid))
+            })
+        });
+    Ok(dataframe(py, packets, fields.as_ref(), timecode.as_ref(), categorical)?.into())
+}
+
+/// Build a pandas DataFrame with a row per packet, as returned by
+/// `packets_to_dataframe`. The `offset` column is None for packets without an offset,
+/// i.e., those decoded from frames.
+pub(crate) fn dataframe<'py>(
+    py: Python<'py>,
+    packets: impl Iterator<Item = PyResult<Packet>>,
+    fields: Option<&Layout>,
+    timecode: Option<&TimecodeRegistry>,
+    categorical: bool,
+) -> PyResult<&'py PyAny> {
+    let pandas = py.import("pandas")?;
+    let mut header = HeaderColumns::default();
+    let field_columns = fields.map(|layout| layout.empty_columns(py));
+    for packet in packets {
+        let packet = packet?;
+        let hdr = &packet.header;
+        header.apid.push(hdr.apid);
+        header.sequence_flags.push(hdr.sequence_flags);
+        header.sequence_id.push(hdr.sequence_id);
+        header.has_secondary_header.push(hdr.has_secondary_header);
+        header.len_minus1.push(hdr.len_minus1);
+        header.offset.push(packet.offset);
+        if let Some(tc) = timecode {
+            let time = hdr
+                .has_secondary_header
+                .then(|| tc.decode_ns(hdr.apid, packet.payload()));
+            header.time.push(time.flatten());
+        }
+        if let (Some(layout), Some(columns)) = (fields, &field_columns) {
+            layout.extract_row(py, packet.payload(), columns)?;
+        }
+    }
+
+    let columns = PyDict::new(py);
+    columns.set_item("apid", header.apid)?;
+    columns.set_item("sequence_flags", header.sequence_flags)?;
+    columns.set_item("sequence_id", header.sequence_id)?;
+    columns.set_item("has_secondary_header", header.has_secondary_header)?;
+    columns.set_item("len_minus1", header.len_minus1)?;
+    columns.set_item("offset", header.offset)?;
+    if timecode.is_some() {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("unit", "ns")?;
+        let time = pandas.call_method("to_datetime", (header.time,), Some(kwargs))?;
+        columns.set_item("time", time)?;
+    }
+    if let (Some(layout), Some(field_columns)) = (fields, field_columns) {
+        layout.set_columns(py, field_columns, categorical, columns)?;
+    }
+    pandas.call_method1("DataFrame", (columns,))
+}
+
+/// Decode the packets in a source into an xarray Dataset of the extracted fields along
+/// a `time` dimension.
+///
+/// Scalar fields are variables with the `time` dimension. Array-valued fields, i.e.,
+/// fields with a `count`, are variables with dimensions `time` and `<name>_index`.
+/// Packets whose time cannot be decoded are skipped.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of space packets, as accepted by `decode_packets`.
+///
+/// fields : Layout
+///     Fields to extract from each packet.
+///
+/// timecode : str or TimecodeRegistry
+///     Timecode at the start of the secondary header, either "cds" or "eoscuc", or the
+///     timecode of each APID. Defaults to "cds".
+///
+/// apids : Iterable[int], optional
+///     Only include packets with these APIDs.
+///
+/// Returns
+/// -------
+/// xarray.Dataset
+///     The field variables, and `apid` and `sequence_id` variables, with a `time`
+///     coordinate of packet times, with any sub-millisecond precision of the timecode.
+#[pyfunction(signature=(source, fields, timecode=None, apids=None))]
+pub fn packets_to_dataset(
+    py: Python,
+    source: PathBuf,
+    fields: Layout,
+    timecode: Option<&PyAny>,
+    apids: Option<&PyAny>,
+) -> PyResult<PyObject> {
+    let apids: Option<HashSet<u16>> = crate::set_arg(apids)?;
+    let timecode = registry_or_cds(timecode)?;
+    let xarray = py.import("xarray")?;
+    let pandas = py.import("pandas")?;
+    let reader = PacketReader::new(BufReader::new(open_source(&source)?));
+
+    let mut header = HeaderColumns::default();
+    let columns = fields.empty_columns(py);
+    for packet in reader {
+        let packet = packet?;
+        let hdr = &packet.header;
+        if apids
+            .as_ref()
+            .is_some_and(|apids| !apids.contains(&hdr.apid))
+        {
+            continue;
+        }
+        let time = hdr
+            .has_secondary_header
+            .then(|| timecode.decode_ns(hdr.apid, &packet.data));
+        let Some(time) = time.flatten() else {
+            continue;
+        };
+        header.time.push(Some(time));
+        header.apid.push(hdr.apid);
+        header.sequence_id.push(hdr.sequence_id);
+        fields.extract_row(py, &packet.data, &columns)?;
+    }
+
+    let data_vars = PyDict::new(py);
+    data_vars.set_item("apid", (("time",), header.apid))?;
+    data_vars.set_item("sequence_id", (("time",), header.sequence_id))?;
+    let mut columns = columns.into_iter();
+    for (field, column) in fields.fields.iter().zip(columns.by_ref()) {
+        let mut dims = vec!["time".to_string()];
+        if field.count.is_some() {
+            dims.push(format!("{}_index", field.name));
+        }
+        data_vars.set_item(&field.name, (dims, column))?;
+    }
+    for (param, column) in fields.derived.iter().zip(columns) {
+        data_vars.set_item(&param.name, (("time",), column))?;
+    }
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("unit", "ns")?;
+    let time = pandas.call_method("to_datetime", (header.time,), Some(kwargs))?;
+    let coords = PyDict::new(py);
+    coords.set_item("time", time)?;
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("coords", coords)?;
+    Ok(xarray
+        .call_method("Dataset", (data_vars,), Some(kwargs))?
+        .into())
+}
@@ -0,0 +1,135 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    hash::{Hash, Hasher},
+    io::{BufReader, Write},
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    compress,
+    gaps::packet_time,
+    packets::{PacketReader, RawPacket},
+    source::open_source,
+    timecode::{optional_registry_arg, TimecodeRegistry},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Discriminator {
+    Time(i64),
+    Digest(u64),
+}
+
+type Key = (u16, u16, Discriminator);
+
+/// Remembers the keys of the last `window` packets seen.
+struct Window {
+    size: usize,
+    order: VecDeque<Key>,
+    counts: HashMap<Key, usize>,
+}
+
+impl Window {
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            order: VecDeque::with_capacity(size),
+            counts: HashMap::default(),
+        }
+    }
+
+    /// Add `key` to the window, returning true if it was already present.
+    fn insert(&mut self, key: Key) -> bool {
+        let dup = self.counts.contains_key(&key);
+        self.order.push_back(key);
+        *self.counts.entry(key).or_default() += 1;
+        if self.order.len() > self.size {
+            let old = self.order.pop_front().unwrap();
+            if let Some(count) = self.counts.get_mut(&old) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(&old);
+                }
+            }
+        }
+        dup
+    }
+}
+
+fn packet_key(packet: &RawPacket, timecode: Option<&TimecodeRegistry>) -> Key {
+    let hdr = &packet.header;
+    let disc = match packet_time(packet, timecode) {
+        Some(time) => Discriminator::Time(time),
+        None => {
+            let mut hasher = DefaultHasher::new();
+            packet.header.encode().hash(&mut hasher);
+            packet.data.hash(&mut hasher);
+            Discriminator::Digest(hasher.finish())
+        }
+    };
+    (hdr.apid, hdr.sequence_id, disc)
+}
+
+/// Remove duplicate packets from a stream of packets, writing the remaining packets to
+/// `dest`.
+///
+/// A packet is a duplicate if a packet with the same APID, sequence id and time was seen
+/// within the last `window` packets. If `timecode` is not provided, or a packet does not
+/// have a secondary header, the packet data is compared instead of the time, so only
+/// exact duplicates are removed.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing stream of space packets.
+///
+/// dest : str or os.PathLike
+///     Path of the file to write de-duplicated packets to.
+///
+/// window : int
+///     Number of most recent packets duplicates are checked against. This must be large
+///     enough to cover the overlap between merged streams.
+///
+/// timecode : str or TimecodeRegistry, optional
+///     Format of the timecode at the start of the secondary header, one of `cds` or
+///     `eoscuc`, or the timecode of each APID.
+///
+/// compression : str, optional
+///     Compress the output, either `gzip`, `zstd` or `none`. Defaults to `gzip` or
+///     `zstd` if `dest` ends with `.gz` or `.zst`, otherwise `none`.
+///
+/// Returns
+/// -------
+/// (int, int)
+///     The number of packets written and the number of duplicates removed.
+#[pyfunction(signature=(source, dest, window=10000, timecode=None, compression=None))]
+pub fn dedup_packets(
+    source: PathBuf,
+    dest: PathBuf,
+    window: usize,
+    timecode: Option<&PyAny>,
+    compression: Option<&str>,
+) -> PyResult<(usize, usize)> {
+    if window == 0 {
+        return Err(PyValueError::new_err("window must be > 0"));
+    }
+    let timecode = optional_registry_arg(timecode)?;
+    let reader = PacketReader::new(BufReader::new(open_source(&source)?));
+    let mut writer = compress::create(&dest, compression)?;
+    let mut seen = Window::new(window);
+
+    let (mut written, mut removed) = (0, 0);
+    for packet in reader {
+        let packet = packet?;
+        if seen.insert(packet_key(&packet, timecode.as_ref())) {
+            removed += 1;
+            continue;
+        }
+        packet.write_to(&mut writer)?;
+        written += 1;
+    }
+    writer.flush()?;
+
+    Ok((written, removed))
+}
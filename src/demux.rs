@@ -0,0 +1,162 @@
+//! Demultiplexing of a frame stream by VCID, so applications consuming several VCIDs
+//! concurrently share a single read and Reed-Solomon decode of the source.
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::mpsc::{channel, Sender},
+    thread,
+    time::Duration,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    next_or_timeout,
+    prefetch::{Next, Pipeline},
+    report, timeout_arg, Frame,
+};
+
+/// Iterator of the frames of a single VCID demultiplexed by `demux_frames`.
+#[pyclass]
+pub struct VcidFrameIterator {
+    /// The VCID of the frames.
+    #[pyo3(get)]
+    vcid: u16,
+    frames: Pipeline<Frame>,
+    timeout: Option<Duration>,
+}
+
+#[pymethods]
+impl VcidFrameIterator {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!("VcidFrameIterator(vcid={})", self.vcid)
+    }
+
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<Frame>> {
+        let py = slf.py();
+        let timeout = slf.timeout;
+        let next = slf.frames.wait(py, timeout)?;
+        next_or_timeout(next)
+    }
+}
+
+/// Decode frames from the byte stream provided by source, demultiplexing them into an
+/// iterator for each VCID.
+///
+/// The source is read and decoded once, on a background thread that feeds a queue for
+/// each VCID, so the iterators may be consumed concurrently, e.g., on separate threads,
+/// without each re-reading and re-decoding the source. Frames of VCIDs not in `vcids`
+/// are dropped. The queues are unbounded, so an iterator that is not consumed holds its
+/// frames in memory until it is dropped; decoding stops once all iterators are dropped.
+///
+/// Parameters
+/// ----------
+/// source: str or os.PathLike
+///     Source of stream containing CADUs, as accepted by `decode_frames`.
+///
+/// frame_len : int
+///     Length of each frame, as accepted by `decode_frames`.
+///
+/// vcids : list of int
+///     The VCIDs to provide iterators for.
+///
+/// interleave : int or str, optional
+///     The Reed-Solomon interleave, as accepted by `decode_frames`.
+///
+/// asm : bytes or str, optional
+///     Attached sync marker to synchronize on, as accepted by `decode_frames`.
+///
+/// timeout : float, optional
+///     Number of seconds each iterator waits for its next frame before raising a
+///     TimeoutError, e.g., for live sources.
+///
+/// rs_threads : int, optional
+///     Number of threads to decode the Reed-Solomon codewords on, as accepted by
+///     `decode_frames`.
+///
+/// report : PassReporter, optional
+///     Reporter to add the lock statistics and all decoded frames to, as for
+///     `decode_frames`.
+///
+/// asm_errors : int
+///     Number of bits of the attached sync marker, 0 to 3, that may be in error for it
+///     to be accepted, as for `decode_frames`.
+///
+/// Returns
+/// -------
+/// dict of int to VcidFrameIterator
+///     An iterator of the decoded Frames of each VCID.
+#[pyfunction(signature=(source, frame_len, vcids, interleave=None, asm=None, timeout=None, rs_threads=None, report=None, asm_errors=0))]
+#[allow(clippy::too_many_arguments)]
+pub fn demux_frames(
+    source: PathBuf,
+    frame_len: i32,
+    vcids: Vec<u16>,
+    interleave: Option<&PyAny>,
+    asm: Option<&PyAny>,
+    timeout: Option<f64>,
+    rs_threads: Option<usize>,
+    report: Option<report::PassReporter>,
+    asm_errors: u32,
+) -> PyResult<HashMap<u16, VcidFrameIterator>> {
+    if vcids.is_empty() {
+        return Err(PyValueError::new_err(
+            "invalid vcids; expected at least one VCID",
+        ));
+    }
+    if let Some(vcid) = vcids.iter().find(|vcid| **vcid >= 64) {
+        return Err(PyValueError::new_err(format!(
+            "invalid vcid; expected 0..64, got {vcid}"
+        )));
+    }
+    let timeout = timeout_arg(timeout)?;
+    let iter = crate::decode_frames(
+        source, frame_len, interleave, None, None, asm, false, None, None, None, None, "flag",
+        None, None, None, None, None, false, rs_threads, report, asm_errors, None,
+    )?;
+    let mut frames = iter.frames;
+
+    let mut senders: HashMap<u16, Sender<Frame>> = HashMap::default();
+    let mut iterators = HashMap::default();
+    for vcid in vcids {
+        let (tx, rx) = channel();
+        senders.insert(vcid, tx);
+        iterators.insert(
+            vcid,
+            VcidFrameIterator {
+                vcid,
+                frames: Pipeline::queued(rx),
+                timeout,
+            },
+        );
+    }
+    thread::Builder::new()
+        .name("ccsds-demux".to_string())
+        .spawn(move || {
+            while let Next::Item(frame) = frames.next(None) {
+                let Some(frame) = frame else {
+                    continue;
+                };
+                let vcid = frame.header.vcid;
+                let Some(tx) = senders.get(&vcid) else {
+                    continue;
+                };
+                if tx.send(frame).is_err() {
+                    // the iterator was dropped
+                    senders.remove(&vcid);
+                    if senders.is_empty() {
+                        break;
+                    }
+                }
+            }
+        })
+        .expect("failed to spawn demux thread");
+    Ok(iterators)
+}
@@ -0,0 +1,647 @@
+//! Derived parameters, compiled expressions over the fields of a layout evaluated in
+//! Rust per packet, e.g., `raw * 0.0025 + 1.2`.
+//!
+//! The grammar, lowest precedence first, is:
+//!
+//! ```text
+//! expr   := and ("or" and)*
+//! and    := not ("and" not)*
+//! not    := "not" not | cmp
+//! cmp    := bitor [("==" | "!=" | "<" | "<=" | ">" | ">=") bitor]
+//! bitor  := bitxor ("|" bitxor)*
+//! bitxor := bitand ("^" bitand)*
+//! bitand := shift ("&" shift)*
+//! shift  := sum (("<<" | ">>") sum)*
+//! sum    := prod (("+" | "-") prod)*
+//! prod   := unary (("*" | "/" | "//" | "%") unary)*
+//! unary  := ("-" | "~") unary | power
+//! power  := atom ["**" unary]
+//! atom   := number | name | name "(" expr ("," expr)* ")" | "(" expr ")"
+//! ```
+// pyo3 0.19 generates the constructor text signature as an impl nested in a function
+#![allow(non_local_definitions)]
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::repr::repr;
+
+/// A value computed by an expression. As in Python, integer arithmetic stays integer
+/// except for `/`, and comparisons and logical operators produce 0 or 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Num {
+    Int(i64),
+    Float(f64),
+}
+
+impl Num {
+    fn as_f64(self) -> f64 {
+        match self {
+            Self::Int(v) => v as f64,
+            Self::Float(v) => v,
+        }
+    }
+
+    fn truthy(self) -> bool {
+        match self {
+            Self::Int(v) => v != 0,
+            Self::Float(v) => v != 0.0,
+        }
+    }
+
+    /// The integer value for bitwise operators, or `None` for non-integral floats.
+    fn as_int(self) -> Option<i64> {
+        match self {
+            Self::Int(v) => Some(v),
+            Self::Float(v) if v.fract() == 0.0 && v.abs() < 9.2e18 => Some(v as i64),
+            Self::Float(_) => None,
+        }
+    }
+}
+
+impl From<bool> for Num {
+    fn from(v: bool) -> Self {
+        Self::Int(v.into())
+    }
+}
+
+impl IntoPy<PyObject> for Num {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        match self {
+            Self::Int(v) => v.into_py(py),
+            Self::Float(v) => v.into_py(py),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    FloorDiv,
+    Mod,
+    Pow,
+}
+
+impl BinOp {
+    /// Apply to operands, or `None` if the result is undefined, e.g., for a division
+    /// by zero.
+    fn apply(self, a: Num, b: Num) -> Option<Num> {
+        use Num::{Float, Int};
+        if matches!(self, Self::Div | Self::FloorDiv | Self::Mod) && b.as_f64() == 0.0 {
+            return None;
+        }
+        Some(match self {
+            Self::Or | Self::And => unreachable!("logical operators short-circuit"),
+            Self::Eq => (a.as_f64() == b.as_f64()).into(),
+            Self::Ne => (a.as_f64() != b.as_f64()).into(),
+            Self::Lt => (a.as_f64() < b.as_f64()).into(),
+            Self::Le => (a.as_f64() <= b.as_f64()).into(),
+            Self::Gt => (a.as_f64() > b.as_f64()).into(),
+            Self::Ge => (a.as_f64() >= b.as_f64()).into(),
+            Self::BitOr => Int(a.as_int()? | b.as_int()?),
+            Self::BitXor => Int(a.as_int()? ^ b.as_int()?),
+            Self::BitAnd => Int(a.as_int()? & b.as_int()?),
+            Self::Shl => Int(a.as_int()?.checked_shl(u32::try_from(b.as_int()?).ok()?)?),
+            Self::Shr => Int(a.as_int()?.checked_shr(u32::try_from(b.as_int()?).ok()?)?),
+            Self::Add => match (a, b) {
+                (Int(a), Int(b)) => Int(a.checked_add(b)?),
+                _ => Float(a.as_f64() + b.as_f64()),
+            },
+            Self::Sub => match (a, b) {
+                (Int(a), Int(b)) => Int(a.checked_sub(b)?),
+                _ => Float(a.as_f64() - b.as_f64()),
+            },
+            Self::Mul => match (a, b) {
+                (Int(a), Int(b)) => Int(a.checked_mul(b)?),
+                _ => Float(a.as_f64() * b.as_f64()),
+            },
+            Self::Div => Float(a.as_f64() / b.as_f64()),
+            Self::FloorDiv => match (a, b) {
+                (Int(a), Int(b)) => {
+                    let q = a.checked_div(b)?;
+                    Int(if a % b != 0 && (a < 0) != (b < 0) {
+                        q - 1
+                    } else {
+                        q
+                    })
+                }
+                _ => Float((a.as_f64() / b.as_f64()).floor()),
+            },
+            Self::Mod => match (a, b) {
+                (Int(a), Int(b)) => {
+                    let r = a.checked_rem(b)?;
+                    Int(if r != 0 && (r < 0) != (b < 0) {
+                        r + b
+                    } else {
+                        r
+                    })
+                }
+                _ => {
+                    let r = a.as_f64() % b.as_f64();
+                    Float(if r != 0.0 && (r < 0.0) != (b.as_f64() < 0.0) {
+                        r + b.as_f64()
+                    } else {
+                        r
+                    })
+                }
+            },
+            Self::Pow => match (a, b) {
+                (Int(a), Int(b)) if b >= 0 => Int(a.checked_pow(u32::try_from(b).ok()?)?),
+                _ => Float(a.as_f64().powf(b.as_f64())),
+            },
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Func {
+    Abs,
+    Min,
+    Max,
+    Sqrt,
+    Bit,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<(Self, usize)> {
+        Some(match name {
+            "abs" => (Self::Abs, 1),
+            "min" => (Self::Min, 2),
+            "max" => (Self::Max, 2),
+            "sqrt" => (Self::Sqrt, 1),
+            "bit" => (Self::Bit, 2),
+            _ => return None,
+        })
+    }
+
+    fn apply(self, args: &[Num]) -> Option<Num> {
+        Some(match (self, args) {
+            (Self::Abs, [Num::Int(v)]) => Num::Int(v.checked_abs()?),
+            (Self::Abs, [v]) => Num::Float(v.as_f64().abs()),
+            (Self::Min, [a, b]) => {
+                if b.as_f64() < a.as_f64() {
+                    *b
+                } else {
+                    *a
+                }
+            }
+            (Self::Max, [a, b]) => {
+                if b.as_f64() > a.as_f64() {
+                    *b
+                } else {
+                    *a
+                }
+            }
+            (Self::Sqrt, [v]) => Num::Float(v.as_f64().sqrt()),
+            (Self::Bit, [v, n]) => {
+                let n = u32::try_from(n.as_int()?).ok()?;
+                Num::Int(v.as_int()?.checked_shr(n)? & 1)
+            }
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Const(Num),
+    /// A field or earlier derived parameter, by name until resolved.
+    Name(String),
+    /// A field or earlier derived parameter, by index in the values of a packet.
+    Slot(usize),
+    Neg(Box<Expr>),
+    BitNot(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+impl Expr {
+    /// Evaluate against the values of a packet's fields and derived parameters, or
+    /// `None` if the result is undefined, e.g., because a referenced value is.
+    fn eval(&self, values: &[Option<Num>]) -> Option<Num> {
+        match self {
+            Self::Const(v) => Some(*v),
+            Self::Name(_) => None,
+            Self::Slot(i) => values[*i],
+            Self::Neg(a) => match a.eval(values)? {
+                Num::Int(v) => v.checked_neg().map(Num::Int),
+                Num::Float(v) => Some(Num::Float(-v)),
+            },
+            Self::BitNot(a) => Some(Num::Int(!a.eval(values)?.as_int()?)),
+            Self::Not(a) => Some((!a.eval(values)?.truthy()).into()),
+            Self::Binary(BinOp::And, a, b) => {
+                let a = a.eval(values)?.truthy();
+                Some((a && b.eval(values)?.truthy()).into())
+            }
+            Self::Binary(BinOp::Or, a, b) => {
+                let a = a.eval(values)?.truthy();
+                Some((a || b.eval(values)?.truthy()).into())
+            }
+            Self::Binary(op, a, b) => op.apply(a.eval(values)?, b.eval(values)?),
+            Self::Call(func, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| a.eval(values))
+                    .collect::<Option<Vec<_>>>()?;
+                func.apply(&args)
+            }
+        }
+    }
+
+    /// Replace names with slots using `slot`, or fail with the first unknown name.
+    fn resolve(&self, slot: &impl Fn(&str) -> Option<usize>) -> Result<Self, String> {
+        let boxed = |e: &Expr| e.resolve(slot).map(Box::new);
+        Ok(match self {
+            Self::Const(_) | Self::Slot(_) => self.clone(),
+            Self::Name(name) => Self::Slot(slot(name).ok_or_else(|| name.clone())?),
+            Self::Neg(a) => Self::Neg(boxed(a)?),
+            Self::BitNot(a) => Self::BitNot(boxed(a)?),
+            Self::Not(a) => Self::Not(boxed(a)?),
+            Self::Binary(op, a, b) => Self::Binary(*op, boxed(a)?, boxed(b)?),
+            Self::Call(func, args) => Self::Call(
+                *func,
+                args.iter()
+                    .map(|a| a.resolve(slot))
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Num(Num),
+    Sym(&'static str),
+}
+
+/// Symbols, longest first so that, e.g., `**` is not read as two `*`.
+const SYMBOLS: [&str; 22] = [
+    "**", "//", "<<", ">>", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "&", "|",
+    "^", "~", "(", ")", ",",
+];
+
+fn tokenize(expr: &str) -> PyResult<Vec<(usize, Token)>> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = Vec::default();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = if c.is_ascii_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            match word.as_str() {
+                "true" | "True" => Token::Num(Num::Int(1)),
+                "false" | "False" => Token::Num(Num::Int(0)),
+                _ => Token::Ident(word),
+            }
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            let hex = c == '0' && matches!(chars.get(i + 1), Some('x' | 'X'));
+            i += 1;
+            while i < chars.len() {
+                let c = chars[i];
+                let exponent_sign =
+                    !hex && matches!(c, '+' | '-') && matches!(chars[i - 1], 'e' | 'E');
+                if !(c.is_ascii_alphanumeric() || c == '.' || exponent_sign) {
+                    break;
+                }
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let value = if hex {
+                i64::from_str_radix(&literal[2..], 16).ok().map(Num::Int)
+            } else if literal.contains(['.', 'e', 'E']) {
+                literal.parse().ok().map(Num::Float)
+            } else {
+                literal.parse().ok().map(Num::Int)
+            };
+            match value {
+                Some(value) => Token::Num(value),
+                None => return Err(syntax_error(start, &format!("invalid number {literal}"))),
+            }
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let Some(sym) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) else {
+                return Err(syntax_error(start, &format!("unexpected character {c:?}")));
+            };
+            i += sym.len();
+            Token::Sym(sym)
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+fn syntax_error(pos: usize, msg: &str) -> PyErr {
+    PyValueError::new_err(format!("invalid expression at position {pos}: {msg}"))
+}
+
+/// Deepest nesting of an expression accepted, counting each operator of a chain,
+/// unary operator, parenthesis and function call, so parsing and evaluating it cannot
+/// exhaust the stack.
+const MAX_DEPTH: usize = 256;
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// Length of the expression, the position reported for errors at its end.
+    len: usize,
+    /// Nesting depth of the expression being parsed.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn error(&self, msg: &str) -> PyErr {
+        let pos = self.tokens.get(self.pos).map_or(self.len, |(p, _)| *p);
+        syntax_error(pos, msg)
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(w)) if w == word) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    /// The operator for the next token if it is one of `ops`, consuming it.
+    fn op(&mut self, ops: &[(&str, BinOp)]) -> Option<BinOp> {
+        let Some(Token::Sym(sym)) = self.peek() else {
+            return None;
+        };
+        let (_, op) = ops.iter().find(|(s, _)| s == sym)?;
+        self.pos += 1;
+        Some(*op)
+    }
+
+    fn expect(&mut self, sym: &str) -> PyResult<()> {
+        if self.peek() == Some(&Token::Sym(SYMBOLS.iter().find(|s| **s == sym).unwrap())) {
+            self.pos += 1;
+            return Ok(());
+        }
+        Err(self.error(&format!("expected '{sym}'")))
+    }
+
+    /// Descend a level into the expression, failing if it is nested too deeply.
+    fn descend(&mut self) -> PyResult<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(&format!("expression nested deeper than {MAX_DEPTH} levels")));
+        }
+        Ok(())
+    }
+
+    /// Parse with `parse` a level deeper into the expression.
+    fn nested(&mut self, parse: fn(&mut Self) -> PyResult<Expr>) -> PyResult<Expr> {
+        self.descend()?;
+        let expr = parse(self)?;
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> PyResult<Expr> {
+        let depth = self.depth;
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            self.descend()?;
+            expr = Expr::Binary(BinOp::Or, Box::new(expr), Box::new(self.and()?));
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> PyResult<Expr> {
+        let depth = self.depth;
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            self.descend()?;
+            expr = Expr::Binary(BinOp::And, Box::new(expr), Box::new(self.not()?));
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> PyResult<Expr> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.nested(Self::not)?)));
+        }
+        let expr = self.binary(0)?;
+        let ops = [
+            ("==", BinOp::Eq),
+            ("!=", BinOp::Ne),
+            ("<", BinOp::Lt),
+            ("<=", BinOp::Le),
+            (">", BinOp::Gt),
+            (">=", BinOp::Ge),
+        ];
+        match self.op(&ops) {
+            Some(op) => Ok(Expr::Binary(op, Box::new(expr), Box::new(self.binary(0)?))),
+            None => Ok(expr),
+        }
+    }
+
+    /// Left-associative binary operators from `level` of [Self::LEVELS] down.
+    fn binary(&mut self, level: usize) -> PyResult<Expr> {
+        let Some(ops) = Self::LEVELS.get(level) else {
+            return self.unary();
+        };
+        let depth = self.depth;
+        let mut expr = self.binary(level + 1)?;
+        while let Some(op) = self.op(ops) {
+            self.descend()?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.binary(level + 1)?));
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    const LEVELS: [&'static [(&'static str, BinOp)]; 6] = [
+        &[("|", BinOp::BitOr)],
+        &[("^", BinOp::BitXor)],
+        &[("&", BinOp::BitAnd)],
+        &[("<<", BinOp::Shl), (">>", BinOp::Shr)],
+        &[("+", BinOp::Add), ("-", BinOp::Sub)],
+        &[
+            ("*", BinOp::Mul),
+            ("/", BinOp::Div),
+            ("//", BinOp::FloorDiv),
+            ("%", BinOp::Mod),
+        ],
+    ];
+
+    fn unary(&mut self) -> PyResult<Expr> {
+        match self.peek() {
+            Some(Token::Sym("-")) => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.nested(Self::unary)?)))
+            }
+            Some(Token::Sym("~")) => {
+                self.pos += 1;
+                Ok(Expr::BitNot(Box::new(self.nested(Self::unary)?)))
+            }
+            _ => {
+                let base = self.atom()?;
+                if self.peek() == Some(&Token::Sym("**")) {
+                    self.pos += 1;
+                    let exp = self.nested(Self::unary)?;
+                    return Ok(Expr::Binary(BinOp::Pow, Box::new(base), Box::new(exp)));
+                }
+                Ok(base)
+            }
+        }
+    }
+
+    fn atom(&mut self) -> PyResult<Expr> {
+        match self.peek().cloned() {
+            Some(Token::Num(v)) => {
+                self.pos += 1;
+                Ok(Expr::Const(v))
+            }
+            Some(Token::Sym("(")) => {
+                self.pos += 1;
+                let expr = self.nested(Self::or)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) if !["and", "or", "not"].contains(&name.as_str()) => {
+                self.pos += 1;
+                if self.peek() != Some(&Token::Sym("(")) {
+                    return Ok(Expr::Name(name));
+                }
+                let (func, arity) = Func::from_name(&name).ok_or_else(|| {
+                    self.pos -= 1;
+                    self.error(&format!("unknown function {name}"))
+                })?;
+                self.pos += 1;
+                let mut args = vec![self.nested(Self::or)?];
+                while self.peek() == Some(&Token::Sym(",")) {
+                    self.pos += 1;
+                    args.push(self.nested(Self::or)?);
+                }
+                if args.len() != arity {
+                    return Err(self.error(&format!(
+                        "{name} expects {arity} argument(s), got {}",
+                        args.len()
+                    )));
+                }
+                self.expect(")")?;
+                Ok(Expr::Call(func, args))
+            }
+            _ => Err(self.error("expected a number, name or '('")),
+        }
+    }
+}
+
+/// A parameter derived from the fields of a layout, and earlier derived parameters, by
+/// an expression evaluated per packet, e.g., `Derived("volts", "raw * 0.0025 + 1.2")`.
+///
+/// Expressions reference fields by name, using calibrated values for fields with a
+/// calibration and raw values otherwise, and support the arithmetic operators `+`, `-`,
+/// `*`, `/`, `//`, `%` and `**`, the bitwise operators `&`, `|`, `^`, `~`, `<<` and
+/// `>>`, comparisons, `and`, `or`, `not`, parentheses and the functions `abs`, `min`,
+/// `max`, `sqrt` and `bit(value, n)`, which tests bit `n` counting from the least
+/// significant bit. As in Python, integer arithmetic stays integer except for `/`, and
+/// comparisons produce 0 or 1. The value is None if undefined, e.g., for a division by
+/// zero. Expressions may nest up to 256 levels deep.
+///
+/// Parameters
+/// ----------
+/// name : str
+///     Name of the parameter, which must differ from the names of the fields.
+///
+/// expr : str
+///     The expression. A ValueError is raised if it is not valid.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct Derived {
+    #[pyo3(get)]
+    pub name: String,
+    /// The source expression.
+    #[pyo3(get)]
+    expr: String,
+    compiled: Expr,
+}
+
+#[pymethods]
+impl Derived {
+    #[new]
+    fn py_new(name: String, expr: &str) -> PyResult<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(expr)?,
+            pos: 0,
+            len: expr.chars().count(),
+            depth: 0,
+        };
+        let compiled = parser.or()?;
+        if parser.peek().is_some() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+        Ok(Self {
+            name,
+            expr: expr.to_string(),
+            compiled,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "Derived(name={}, expr={})",
+            repr(&self.name),
+            repr(&self.expr)
+        )
+    }
+}
+
+impl Derived {
+    /// A copy with names resolved to slots using `slot`, which returns `None` for
+    /// names that are not available to this parameter.
+    pub fn resolve(&self, slot: impl Fn(&str) -> Option<usize>) -> PyResult<Self> {
+        let compiled = self.compiled.resolve(&slot).map_err(|name| {
+            PyValueError::new_err(format!(
+                "unknown name {name} in expression for {}; expected a field or earlier derived parameter",
+                self.name
+            ))
+        })?;
+        Ok(Self {
+            compiled,
+            ..self.clone()
+        })
+    }
+
+    /// Evaluate against the values of a packet's fields and earlier derived
+    /// parameters, by slot.
+    pub fn eval(&self, values: &[Option<Num>]) -> Option<Num> {
+        self.compiled.eval(values)
+    }
+}
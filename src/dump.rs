@@ -0,0 +1,52 @@
+//! Human readable renderings of packets and frames for interactive debugging.
+use std::fmt::Write;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// Default number of data bytes included by `describe`.
+pub const DESCRIBE_LEN: usize = 64;
+
+/// Render `dat` as lines of `width` bytes, each with the offset, the bytes in hex, and
+/// the printable ASCII characters.
+pub fn hexdump(dat: &[u8], width: usize) -> PyResult<String> {
+    if width == 0 {
+        return Err(PyValueError::new_err("width must be > 0"));
+    }
+    let mut out = String::with_capacity(dat.len().div_ceil(width) * (width * 4 + 14));
+    for (i, line) in dat.chunks(width).enumerate() {
+        let _ = write!(out, "{:08x} ", i * width);
+        for byte in line {
+            let _ = write!(out, " {byte:02x}");
+        }
+        out.push_str(&"   ".repeat(width - line.len()));
+        out.push_str("  |");
+        out.extend(line.iter().map(|b| {
+            if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            }
+        }));
+        out.push_str("|\n");
+    }
+    Ok(out)
+}
+
+/// Render a titled block of `fields` followed by a hexdump of up to `max_bytes` of
+/// `dat`.
+pub fn describe(title: &str, fields: &[(&str, String)], dat: &[u8], max_bytes: usize) -> String {
+    let mut out = format!("{title}\n");
+    let name_len = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, value) in fields {
+        let _ = writeln!(out, "  {name:<name_len$}  {value}");
+    }
+    let shown = &dat[..dat.len().min(max_bytes)];
+    if !shown.is_empty() {
+        out.push_str("data:\n");
+        out.push_str(&hexdump(shown, 16).expect("width is non-zero"));
+    }
+    if dat.len() > shown.len() {
+        let _ = writeln!(out, "... {} more bytes", dat.len() - shown.len());
+    }
+    out
+}
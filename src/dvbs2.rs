@@ -0,0 +1,331 @@
+//! Extraction of CCSDS data carried in DVB-S2 baseband frames (ETSI EN 302 307), either
+//! directly as a continuous stream, e.g., of CADUs, or encapsulated with the Generic
+//! Stream Encapsulation protocol (GSE, ETSI TS 102 606).
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{packets::read_full, source::open_source};
+
+/// Length of the baseband frame header.
+const BBHEADER_LEN: usize = 10;
+
+/// Largest baseband frame data field in bits, that of a normal frame with the highest
+/// code rate.
+const MAX_DFL: usize = 58112;
+
+/// Number of bytes read from the source at a time.
+const READ_LEN: usize = 64 * 1024;
+
+/// Length of the CRC-32 ending the last fragment of a fragmented GSE PDU.
+const GSE_CRC_LEN: usize = 4;
+
+/// How the baseband frame data fields carry the CCSDS data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    /// The data fields form a continuous stream.
+    Continuous,
+    /// The data fields carry GSE packets.
+    Gse,
+}
+
+impl Mode {
+    fn from_name(name: &str) -> PyResult<Self> {
+        match name {
+            "continuous" => Ok(Self::Continuous),
+            "gse" => Ok(Self::Gse),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported DVB-S2 mode; expected one of continuous, gse, got {name}"
+            ))),
+        }
+    }
+}
+
+/// CRC-8 of the baseband header, polynomial 0xD5.
+fn crc8(dat: &[u8]) -> u8 {
+    let mut crc: u8 = 0;
+    for byte in dat {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                crc << 1 ^ 0xD5
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// CRC-32/MPEG-2 used by GSE to protect fragmented PDUs.
+fn crc32(dat: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+    for byte in dat {
+        crc ^= u32::from(*byte) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                crc << 1 ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Length in bytes of the data field of the baseband frame with header `hdr`, or `None`
+/// if the header is not valid. The CRC is XORed with the mode, 0 for normal mode or 1
+/// for high efficiency mode. Empty data fields are rejected, since zero padding would
+/// otherwise pass as a valid header.
+fn data_field_len(hdr: &[u8]) -> Option<usize> {
+    let crc = crc8(&hdr[..BBHEADER_LEN - 1]);
+    if hdr[BBHEADER_LEN - 1] ^ crc > 1 {
+        return None;
+    }
+    let dfl = usize::from(u16::from_be_bytes([hdr[4], hdr[5]]));
+    (dfl > 0 && dfl <= MAX_DFL && dfl.is_multiple_of(8)).then_some(dfl / 8)
+}
+
+/// Counts from extracting the data of a DVB-S2 recording with `extract_dvbs2`.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default)]
+pub struct Dvbs2Summary {
+    /// Number of baseband frames with a valid header.
+    #[pyo3(get)]
+    pub bbframes: u64,
+    /// Number of bytes skipped searching for a valid baseband header.
+    #[pyo3(get)]
+    pub skipped_bytes: u64,
+    /// Number of GSE PDUs written, or data fields in continuous mode.
+    #[pyo3(get)]
+    pub pdus: u64,
+    /// Number of GSE PDUs not written because their protocol type did not match.
+    #[pyo3(get)]
+    pub filtered: u64,
+    /// Number of GSE PDUs dropped because they were malformed, or a fragment was missing
+    /// or failed the CRC.
+    #[pyo3(get)]
+    pub dropped: u64,
+}
+
+#[pymethods]
+impl Dvbs2Summary {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "Dvbs2Summary(bbframes={}, skipped_bytes={}, pdus={}, filtered={}, dropped={})",
+            self.bbframes, self.skipped_bytes, self.pdus, self.filtered, self.dropped,
+        )
+    }
+}
+
+/// Label length of a GSE packet with label type `lt`.
+fn label_len(lt: u8) -> usize {
+    match lt {
+        0 => 6,
+        1 => 3,
+        _ => 0,
+    }
+}
+
+/// A GSE PDU being reassembled from fragments.
+struct Fragment {
+    /// Label type of the first fragment.
+    lt: u8,
+    /// Bytes covered by the CRC: the total length, protocol type, label and PDU.
+    buf: Vec<u8>,
+}
+
+/// Reassembles GSE PDUs from the data fields of consecutive baseband frames.
+#[derive(Default)]
+struct GseDecoder {
+    /// Incomplete PDUs by fragment id.
+    fragments: HashMap<u8, Fragment>,
+}
+
+impl GseDecoder {
+    /// Decode the GSE packets of a data field, passing the protocol type and data of
+    /// each complete PDU to `emit`.
+    fn data_field<F>(&mut self, dat: &[u8], summary: &mut Dvbs2Summary, mut emit: F)
+    where
+        F: FnMut(u16, &[u8], &mut Dvbs2Summary),
+    {
+        let mut pos = 0;
+        while pos + 2 <= dat.len() {
+            let start = dat[pos] & 0x80 != 0;
+            let end = dat[pos] & 0x40 != 0;
+            let lt = dat[pos] >> 4 & 0x3;
+            if !start && !end && lt == 0 {
+                // the rest of the data field is padding
+                break;
+            }
+            let len = usize::from(u16::from_be_bytes([dat[pos] & 0xf, dat[pos + 1]]));
+            let Some(body) = dat.get(pos + 2..pos + 2 + len) else {
+                // a malformed length leaves no way to find the next packet
+                break;
+            };
+            pos += 2 + len;
+            match (start, end) {
+                (true, true) => match body.get(2 + label_len(lt)..) {
+                    Some(pdu) => emit(u16::from_be_bytes([body[0], body[1]]), pdu, summary),
+                    None => summary.dropped += 1,
+                },
+                (true, false) if body.len() > 3 => {
+                    let fragment = Fragment {
+                        lt,
+                        buf: body[1..].to_vec(),
+                    };
+                    if self.fragments.insert(body[0], fragment).is_some() {
+                        summary.dropped += 1;
+                    }
+                }
+                (false, false) if !body.is_empty() => match self.fragments.get_mut(&body[0]) {
+                    Some(fragment) => fragment.buf.extend_from_slice(&body[1..]),
+                    None => summary.dropped += 1,
+                },
+                (false, true) if body.len() > GSE_CRC_LEN => {
+                    let Some(mut fragment) = self.fragments.remove(&body[0]) else {
+                        summary.dropped += 1;
+                        continue;
+                    };
+                    let (data, crc) = body[1..].split_at(body.len() - 1 - GSE_CRC_LEN);
+                    fragment.buf.extend_from_slice(data);
+                    let buf = &fragment.buf;
+                    let total_len = usize::from(u16::from_be_bytes([buf[0], buf[1]]));
+                    let pdu = buf.get(4 + label_len(fragment.lt)..);
+                    match pdu {
+                        Some(pdu)
+                            if total_len == buf.len() - 2 && crc32(buf).to_be_bytes() == crc =>
+                        {
+                            emit(u16::from_be_bytes([buf[2], buf[3]]), pdu, summary)
+                        }
+                        _ => summary.dropped += 1,
+                    }
+                }
+                _ => summary.dropped += 1,
+            }
+        }
+    }
+}
+
+/// Extract CCSDS data from a recording of DVB-S2 baseband frames.
+///
+/// Each baseband frame is located by its header CRC, scanning forward for the next valid
+/// header if a frame is corrupt. In `continuous` mode the data fields are written as is,
+/// e.g., CADUs for `decode_frames`. In `gse` mode the PDUs encapsulated with GSE are
+/// written, reassembling fragmented PDUs and checking their CRC, e.g., space packets for
+/// `decode_packets` or CADUs.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of baseband frames, each a baseband header followed by
+///     its data field.
+///
+/// dest : str or os.PathLike
+///     Path of the file to write the data to. It is created or truncated.
+///
+/// mode : str
+///     How the data is carried, either `gse` or `continuous`.
+///
+/// frame_len : int, optional
+///     Length in bytes of each baseband frame in the recording, including the header and
+///     any padding following the data field, i.e., Kbch / 8. If not provided each frame
+///     is expected to end with its data field.
+///
+/// protocol_type : int, optional
+///     Only write GSE PDUs with this protocol type.
+///
+/// Returns
+/// -------
+/// Dvbs2Summary
+#[pyfunction(signature=(source, dest, mode="gse", frame_len=None, protocol_type=None))]
+pub fn extract_dvbs2(
+    source: PathBuf,
+    dest: PathBuf,
+    mode: &str,
+    frame_len: Option<usize>,
+    protocol_type: Option<u16>,
+) -> PyResult<Dvbs2Summary> {
+    let mode = Mode::from_name(mode)?;
+    if let Some(frame_len) = frame_len.filter(|len| *len <= BBHEADER_LEN) {
+        return Err(PyValueError::new_err(format!(
+            "invalid frame_len; expected > {BBHEADER_LEN}, got {frame_len}"
+        )));
+    }
+    let mut reader = open_source(&source)?;
+    let mut writer = BufWriter::new(File::create(dest)?);
+    let mut summary = Dvbs2Summary::default();
+    let mut gse = GseDecoder::default();
+    let mut buf: Vec<u8> = Vec::default();
+    let mut start = 0;
+    loop {
+        if start > READ_LEN {
+            buf.drain(..start);
+            start = 0;
+        }
+        if !fill(&mut reader, &mut buf, start + BBHEADER_LEN)? {
+            summary.skipped_bytes += (buf.len() - start) as u64;
+            break;
+        }
+        let dfl = data_field_len(&buf[start..start + BBHEADER_LEN])
+            .filter(|dfl| frame_len.is_none_or(|len| BBHEADER_LEN + dfl <= len));
+        let Some(dfl) = dfl else {
+            start += 1;
+            summary.skipped_bytes += 1;
+            continue;
+        };
+        let total = frame_len.unwrap_or(BBHEADER_LEN + dfl);
+        if !fill(&mut reader, &mut buf, start + BBHEADER_LEN + dfl)? {
+            summary.skipped_bytes += (buf.len() - start) as u64;
+            break;
+        }
+        summary.bbframes += 1;
+        let data = &buf[start + BBHEADER_LEN..start + BBHEADER_LEN + dfl];
+        let mut result = Ok(());
+        match mode {
+            Mode::Continuous => {
+                summary.pdus += 1;
+                result = writer.write_all(data);
+            }
+            Mode::Gse => gse.data_field(data, &mut summary, |proto, pdu, summary| {
+                if protocol_type.is_some_and(|p| p != proto) {
+                    summary.filtered += 1;
+                    return;
+                }
+                summary.pdus += 1;
+                if result.is_ok() {
+                    result = writer.write_all(pdu);
+                }
+            }),
+        }
+        result?;
+        // padding following the data field may be missing from the end of the stream
+        fill(&mut reader, &mut buf, start + total)?;
+        start = buf.len().min(start + total);
+    }
+    writer.flush()?;
+    Ok(summary)
+}
+
+/// Read into `buf` until it holds at least `len` bytes, returning false if the source
+/// ended first.
+fn fill<R: Read>(reader: &mut R, buf: &mut Vec<u8>, len: usize) -> PyResult<bool> {
+    while buf.len() < len {
+        let filled = buf.len();
+        buf.resize(filled + READ_LEN.max(len - filled), 0);
+        let n = read_full(reader, &mut buf[filled..])?;
+        buf.truncate(filled + n);
+        if n == 0 {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,283 @@
+// pyo3 0.19 generates the constructor text signature as an impl nested in a function
+#![allow(non_local_definitions)]
+use std::{collections::HashMap, io::Write, path::PathBuf};
+
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+
+use crate::{
+    compress::{self, Compression},
+    packets::HEADER_LEN,
+    pn, rs, Frame, PrimaryHeader,
+};
+
+/// Largest packet data field, i.e., the data following the primary header.
+const MAX_DATA_LEN: usize = 65536;
+/// Sequence counters are 14 bits.
+const SEQ_MODULUS: u16 = 1 << 14;
+
+/// Sequence flags for a packet in a group of segmented packets.
+const SEQ_CONTINUATION: u8 = 0b00;
+const SEQ_FIRST: u8 = 0b01;
+const SEQ_LAST: u8 = 0b10;
+const SEQ_UNSEGMENTED: u8 = 0b11;
+
+/// Reconstruct the CADU for a frame, i.e., the attached sync marker followed by the
+/// frame and its Reed-Solomon parity, pseudo-randomized.
+///
+/// Parameters
+/// ----------
+/// frame : Frame or bytes
+///     The frame to encode, either a Frame or the raw frame bytes, possibly edited.
+///
+/// interleave : int, optional
+///     The Reed-Solomon interleave. If provided, the frame must be 223 * interleave
+///     bytes, or 255 * interleave bytes in which case any existing parity is replaced.
+///     If not provided, no parity is added.
+///
+/// randomize : bool
+///     Apply the CCSDS pseudo-noise sequence to the frame and parity.
+///
+/// asm : bytes, optional
+///     Attached sync marker. Defaults to the standard CCSDS ASM.
+///
+/// Returns
+/// -------
+/// bytes
+///     The CADU.
+#[pyfunction(signature=(frame, interleave=None, randomize=true, asm=None))]
+pub fn encode_cadu<'py>(
+    py: Python<'py>,
+    frame: &PyAny,
+    interleave: Option<usize>,
+    randomize: bool,
+    asm: Option<Vec<u8>>,
+) -> PyResult<&'py PyBytes> {
+    let mut data = match frame.extract::<PyRef<Frame>>() {
+        Ok(frame) => frame.data.clone(),
+        Err(_) => frame.extract::<Vec<u8>>()?,
+    };
+
+    if let Some(interleave) = interleave {
+        if !(1..=8).contains(&interleave) {
+            return Err(PyValueError::new_err(format!(
+                "invalid interleave value; expected 1..=8, got {interleave}"
+            )));
+        }
+        if data.len() == rs::N * interleave {
+            data.truncate(rs::K * interleave);
+        }
+        if data.len() != rs::K * interleave {
+            return Err(PyValueError::new_err(format!(
+                "invalid frame length for interleave {interleave}; expected {} or {}, got {}",
+                rs::K * interleave,
+                rs::N * interleave,
+                data.len()
+            )));
+        }
+        data = rs::encode_interleaved(&data, interleave);
+    }
+    if randomize {
+        pn::apply(&mut data);
+    }
+
+    let mut cadu = asm.unwrap_or_else(|| ccsds::ASM.to_vec());
+    cadu.extend_from_slice(&data);
+    Ok(PyBytes::new(py, &cadu))
+}
+
+enum Sink {
+    File(compress::Writer),
+    /// A Python binary file-like object.
+    Py(PyObject),
+}
+
+/// Writes space packets, assigning sequence counters per APID and segmenting user data
+/// that does not fit in a single packet.
+///
+/// Parameters
+/// ----------
+/// dest : str, os.PathLike or binary file-like
+///     Where to write the packets. Paths are created or truncated; file-like objects
+///     must have a `write` method accepting bytes.
+///
+/// type_flag : int
+///     Packet type, 0 for telemetry or 1 for telecommand.
+///
+/// max_data_len : int
+///     Largest packet data field in bytes, including any secondary header. Defaults to
+///     the maximum of 65536.
+///
+/// compression : str, optional
+///     Compress the file at a `dest` path, either `gzip`, `zstd` or `none`. Defaults to
+///     `gzip` or `zstd` if `dest` ends with `.gz` or `.zst`, otherwise `none`. Not
+///     supported for file-like objects.
+#[pyclass]
+pub struct PacketWriter {
+    sink: Option<Sink>,
+    type_flag: u8,
+    max_data_len: usize,
+    counters: HashMap<u16, u16>,
+}
+
+#[pymethods]
+impl PacketWriter {
+    #[new]
+    #[pyo3(signature=(dest, type_flag=0, max_data_len=MAX_DATA_LEN, compression=None))]
+    fn py_new(
+        dest: &PyAny,
+        type_flag: u8,
+        max_data_len: usize,
+        compression: Option<&str>,
+    ) -> PyResult<Self> {
+        if type_flag > 1 {
+            return Err(PyValueError::new_err(format!(
+                "invalid type_flag; expected 0 or 1, got {type_flag}"
+            )));
+        }
+        if !(1..=MAX_DATA_LEN).contains(&max_data_len) {
+            return Err(PyValueError::new_err(format!(
+                "invalid max_data_len; expected 1..={MAX_DATA_LEN}, got {max_data_len}"
+            )));
+        }
+        let sink = if dest.hasattr("write")? {
+            if Compression::from_arg(compression, None)?.is_some() {
+                return Err(PyValueError::new_err(
+                    "compression is not supported for file-like destinations",
+                ));
+            }
+            Sink::Py(dest.into())
+        } else {
+            let path: PathBuf = dest.extract()?;
+            Sink::File(compress::create(&path, compression)?)
+        };
+        Ok(Self {
+            sink: Some(sink),
+            type_flag,
+            max_data_len,
+            counters: HashMap::default(),
+        })
+    }
+
+    /// Write user data for `apid` as one packet, or as a group of segmented packets if
+    /// it does not fit in a single packet.
+    ///
+    /// Parameters
+    /// ----------
+    /// apid : int
+    ///     Application process id.
+    ///
+    /// data : bytes
+    ///     The user data.
+    ///
+    /// secondary_header : bytes, optional
+    ///     Secondary header written at the start of the data field of each packet, with
+    ///     the secondary header flag set.
+    ///
+    /// Returns
+    /// -------
+    /// int
+    ///     Number of packets written.
+    #[pyo3(signature=(apid, data, secondary_header=None))]
+    fn write(
+        &mut self,
+        py: Python,
+        apid: u16,
+        data: &[u8],
+        secondary_header: Option<&[u8]>,
+    ) -> PyResult<usize> {
+        if apid > 0x7ff {
+            return Err(PyValueError::new_err(format!(
+                "invalid apid; expected 0..=2047, got {apid}"
+            )));
+        }
+        let shdr = secondary_header.unwrap_or_default();
+        if shdr.len() >= self.max_data_len {
+            return Err(PyValueError::new_err(format!(
+                "secondary header of {} bytes leaves no room for data in max_data_len {}",
+                shdr.len(),
+                self.max_data_len
+            )));
+        }
+        if data.is_empty() && shdr.is_empty() {
+            return Err(PyValueError::new_err(
+                "packets must contain at least one byte of data",
+            ));
+        }
+
+        let chunks: Vec<&[u8]> = if data.is_empty() {
+            vec![data]
+        } else {
+            data.chunks(self.max_data_len - shdr.len()).collect()
+        };
+        let mut buf = Vec::with_capacity(data.len() + chunks.len() * (HEADER_LEN + shdr.len()));
+        for (i, chunk) in chunks.iter().enumerate() {
+            let sequence_flags = match (i == 0, i == chunks.len() - 1) {
+                (true, true) => SEQ_UNSEGMENTED,
+                (true, false) => SEQ_FIRST,
+                (false, true) => SEQ_LAST,
+                (false, false) => SEQ_CONTINUATION,
+            };
+            let counter = self.counters.entry(apid).or_default();
+            let header = PrimaryHeader {
+                version: 0,
+                type_flag: self.type_flag,
+                has_secondary_header: secondary_header.is_some(),
+                apid,
+                sequence_flags,
+                sequence_id: *counter,
+                len_minus1: (shdr.len() + chunk.len() - 1) as u16,
+            };
+            *counter = (*counter + 1) % SEQ_MODULUS;
+            buf.extend_from_slice(&header.encode());
+            buf.extend_from_slice(shdr);
+            buf.extend_from_slice(chunk);
+        }
+        match &mut self.sink {
+            Some(Sink::File(file)) => file.write_all(&buf)?,
+            Some(Sink::Py(obj)) => {
+                obj.call_method1(py, "write", (PyBytes::new(py, &buf),))?;
+            }
+            None => return Err(PyValueError::new_err("write to closed PacketWriter")),
+        }
+        Ok(chunks.len())
+    }
+
+    /// Next sequence counter for each APID written so far.
+    fn sequence_counters(&self) -> HashMap<u16, u16> {
+        self.counters.clone()
+    }
+
+    /// Flush buffered packets to the destination.
+    fn flush(&mut self, py: Python) -> PyResult<()> {
+        match &mut self.sink {
+            Some(Sink::File(file)) => file.flush()?,
+            Some(Sink::Py(obj)) if obj.as_ref(py).hasattr("flush")? => {
+                obj.call_method0(py, "flush")?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Flush and close the destination file. File-like objects are flushed but not
+    /// closed.
+    fn close(&mut self, py: Python) -> PyResult<()> {
+        self.flush(py)?;
+        self.sink = None;
+        Ok(())
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        py: Python,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+    ) -> PyResult<()> {
+        self.close(py)
+    }
+}
@@ -0,0 +1,206 @@
+//! Named epochs of the CCSDS Unsegmented timecodes, so the time of a mission whose
+//! clock counts from e.g. the GPS or J2000 epoch is decoded to UTC directly rather than
+//! each mission post-adjusting the values returned.
+use std::{collections::BTreeMap, sync::Mutex};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
+/// Start in Unix seconds of each step in TAI-UTC since 1972, and TAI-UTC in seconds
+/// from then on.
+const LEAP_SECONDS: [(i64, i64); 28] = [
+    (63072000, 10),   // 1972-01-01
+    (78796800, 11),   // 1972-07-01
+    (94694400, 12),   // 1973-01-01
+    (126230400, 13),  // 1974-01-01
+    (157766400, 14),  // 1975-01-01
+    (189302400, 15),  // 1976-01-01
+    (220924800, 16),  // 1977-01-01
+    (252460800, 17),  // 1978-01-01
+    (283996800, 18),  // 1979-01-01
+    (315532800, 19),  // 1980-01-01
+    (362793600, 20),  // 1981-07-01
+    (394329600, 21),  // 1982-07-01
+    (425865600, 22),  // 1983-07-01
+    (489024000, 23),  // 1985-07-01
+    (567993600, 24),  // 1988-01-01
+    (631152000, 25),  // 1990-01-01
+    (662688000, 26),  // 1991-01-01
+    (709948800, 27),  // 1992-07-01
+    (741484800, 28),  // 1993-07-01
+    (773020800, 29),  // 1994-07-01
+    (820454400, 30),  // 1996-01-01
+    (867715200, 31),  // 1997-07-01
+    (915148800, 32),  // 1999-01-01
+    (1136073600, 33), // 2006-01-01
+    (1230768000, 34), // 2009-01-01
+    (1341100800, 35), // 2012-07-01
+    (1435708800, 36), // 2015-07-01
+    (1483228800, 37), // 2017-01-01
+];
+
+/// TAI-UTC in seconds at `tai_ns`, nanoseconds of TAI since the TAI instant
+/// 1970-01-01T00:00:00. Before 1972, when UTC was not offset by whole seconds, the
+/// initial 10 seconds are used.
+fn tai_minus_utc(tai_ns: i64) -> i64 {
+    LEAP_SECONDS
+        .iter()
+        .rev()
+        .find(|(start, offset)| (start + offset) * NANOS_PER_SEC <= tai_ns)
+        .map_or(10, |(_, offset)| *offset)
+}
+
+/// The start of a timecode's count of seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Epoch {
+    pub name: &'static str,
+    /// Nanoseconds since 1970-01-01T00:00:00 on the timescale of the epoch, i.e.,
+    /// including leap seconds for TAI epochs.
+    start_ns: i64,
+    /// True if the count does not stop for leap seconds, i.e., is on the TAI timescale,
+    /// otherwise the count is of UTC seconds.
+    tai: bool,
+}
+
+const BUILTIN: [Epoch; 5] = [
+    Epoch {
+        name: "tai1958",
+        start_ns: -378_691_200 * NANOS_PER_SEC,
+        tai: true,
+    },
+    // synonym of tai1958, the CCSDS recommended epoch
+    Epoch {
+        name: "ccsds",
+        start_ns: -378_691_200 * NANOS_PER_SEC,
+        tai: true,
+    },
+    // 1980-01-06T00:00:00 UTC, when TAI-UTC was 19 seconds
+    Epoch {
+        name: "gps",
+        start_ns: 315_964_819 * NANOS_PER_SEC,
+        tai: true,
+    },
+    Epoch {
+        name: "unix",
+        start_ns: 0,
+        tai: false,
+    },
+    // 2000-01-01T12:00:00 TT, i.e., 11:59:27.816 TAI
+    Epoch {
+        name: "j2000",
+        start_ns: 946_727_967_816 * 1_000_000,
+        tai: true,
+    },
+];
+
+/// Epochs registered with `register_epoch`.
+static REGISTERED: Mutex<BTreeMap<String, Epoch>> = Mutex::new(BTreeMap::new());
+
+impl Epoch {
+    /// Lookup a built-in or registered epoch by name.
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        let key = name.to_lowercase();
+        if let Some(epoch) = BUILTIN.iter().find(|epoch| epoch.name == key) {
+            return Ok(*epoch);
+        }
+        let registered = REGISTERED.lock().unwrap().get(&key).copied();
+        registered.ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "unknown epoch; expected one of {}, got {name}",
+                names().join(", ")
+            ))
+        })
+    }
+
+    /// UTC nanoseconds of the time `count_ns` nanoseconds after the epoch.
+    pub fn to_utc_ns(self, count_ns: i64) -> i64 {
+        let ns = self.start_ns + count_ns;
+        if self.tai {
+            ns - tai_minus_utc(ns) * NANOS_PER_SEC
+        } else {
+            ns
+        }
+    }
+}
+
+/// Names of the built-in and registered epochs.
+fn names() -> Vec<String> {
+    BUILTIN
+        .iter()
+        .map(|epoch| epoch.name.to_string())
+        .chain(REGISTERED.lock().unwrap().keys().cloned())
+        .collect()
+}
+
+/// Register a mission-specific epoch, making it available by name wherever a CUC
+/// timecode is selected, e.g., `cuc:<name>`.
+///
+/// Timecodes already parsed, e.g., by a `TimecodeRegistry`, keep the epoch they were
+/// created with, and a `PacketArchive` written with the epoch needs it registered to be
+/// read.
+///
+/// Parameters
+/// ----------
+/// name : str
+///     The case-insensitive name of the epoch, which may not be that of a built-in
+///     epoch. Registering a name again replaces the epoch.
+///
+/// time : int
+///     The epoch in UTC milliseconds.
+///
+/// timescale : str
+///     Either `utc`, if the timecode counts UTC seconds, or `tai`, if it counts atomic
+///     seconds, i.e., including leap seconds after the epoch, like GPS time.
+#[pyfunction(signature=(name, time, timescale="utc"))]
+pub fn register_epoch(name: &str, time: i64, timescale: &str) -> PyResult<()> {
+    let key = name.to_lowercase();
+    if key.is_empty() || key.contains(':') {
+        return Err(PyValueError::new_err(format!(
+            "invalid epoch name; expected a non-empty name without ':', got {name:?}"
+        )));
+    }
+    if BUILTIN.iter().any(|epoch| epoch.name == key) {
+        return Err(PyValueError::new_err(format!(
+            "cannot replace the built-in epoch {key}"
+        )));
+    }
+    let utc_ns = time * 1_000_000;
+    let (start_ns, tai) = match timescale.to_lowercase().as_str() {
+        "utc" => (utc_ns, false),
+        "tai" => {
+            // TAI-UTC at the epoch, looked up on the TAI side of the step it is after
+            let offset = LEAP_SECONDS
+                .iter()
+                .rev()
+                .find(|(start, _)| start * NANOS_PER_SEC <= utc_ns)
+                .map_or(10, |(_, offset)| *offset);
+            (utc_ns + offset * NANOS_PER_SEC, true)
+        }
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "invalid timescale; expected one of utc, tai, got {timescale}"
+            )))
+        }
+    };
+    let mut registered = REGISTERED.lock().unwrap();
+    // timecodes are Copy so refer to the name statically; re-registering a name is rare
+    // enough that leaking the previous one does not matter
+    let name: &'static str = Box::leak(key.clone().into_boxed_str());
+    registered.insert(
+        key,
+        Epoch {
+            name,
+            start_ns,
+            tai,
+        },
+    );
+    Ok(())
+}
+
+/// The names of the built-in epochs, `tai1958` (or `ccsds`), `gps`, `unix` and
+/// `j2000`, followed by those of any registered epochs.
+#[pyfunction]
+pub fn epochs() -> Vec<String> {
+    names()
+}
@@ -0,0 +1,202 @@
+//! Reading of EUMETSAT Polar System (EPS) Level 0 products, the format MetOp
+//! instrument source packets are delivered in, whose records each wrap one packet in a
+//! generic record header, with the product described by ASCII main and secondary
+//! product header records.
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, ErrorKind, Read},
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::Serialize;
+
+use crate::{packets::read_full, repr::repr};
+
+/// Length of the generic record header (GRH) starting every record.
+const GRH_LEN: usize = 20;
+
+const CLASS_MPHR: u8 = 1;
+const CLASS_SPHR: u8 = 2;
+const CLASS_MDR: u8 = 8;
+
+/// Instrument group of the dummy MDRs that stand in for missing data.
+const GROUP_DUMMY: u8 = 13;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid EPS product; {msg}"),
+    )
+}
+
+/// A record of an EPS product.
+struct Record {
+    class: u8,
+    instrument_group: u8,
+    /// The record following the generic record header.
+    body: Vec<u8>,
+}
+
+/// Read the next record, or `None` at the end of the product.
+fn read_record<R: Read>(reader: &mut R) -> io::Result<Option<Record>> {
+    let mut grh = [0u8; GRH_LEN];
+    match read_full(reader, &mut grh)? {
+        0 => return Ok(None),
+        GRH_LEN => {}
+        _ => return Err(invalid("truncated record header")),
+    }
+    let size = u32::from_be_bytes(grh[4..8].try_into().unwrap()) as usize;
+    let Some(body_len) = size.checked_sub(GRH_LEN) else {
+        return Err(invalid(&format!("bad record size {size}")));
+    };
+    let mut body = vec![0u8; body_len];
+    if read_full(reader, &mut body)? != body_len {
+        return Err(invalid("truncated record"));
+    }
+    Ok(Some(Record {
+        class: grh[0],
+        instrument_group: grh[1],
+        body,
+    }))
+}
+
+/// A reader of the source packets in the measurement data records (MDRs) of an EPS
+/// Level 0 product, as a contiguous packet stream.
+///
+/// Header, auxiliary and dummy records are skipped.
+pub struct EpsReader<R> {
+    reader: R,
+    payload: Vec<u8>,
+    /// Position in `payload` of the next byte to read.
+    pos: usize,
+}
+
+impl<R: Read> EpsReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            payload: Vec::default(),
+            pos: 0,
+        }
+    }
+
+    /// Load the packet of the next MDR, returning false at the end of the product.
+    fn next_payload(&mut self) -> io::Result<bool> {
+        while let Some(record) = read_record(&mut self.reader)? {
+            if record.class == CLASS_MDR && record.instrument_group != GROUP_DUMMY {
+                self.payload = record.body;
+                self.pos = 0;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl<R: Read> Read for EpsReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.payload.len() {
+            if !self.next_payload()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.payload.len() - self.pos);
+        buf[..n].copy_from_slice(&self.payload[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Parse the `NAME = value` lines of an ASCII product header record.
+fn header_fields(body: &[u8]) -> BTreeMap<String, String> {
+    String::from_utf8_lossy(body)
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// The headers and record counts of an EPS Level 0 product, as read by
+/// `read_eps_product`.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EpsProduct {
+    /// Fields of the main product header record (MPHR), e.g., `PRODUCT_NAME`,
+    /// `SPACECRAFT_ID` and `SENSING_START`.
+    #[pyo3(get)]
+    pub mphr: BTreeMap<String, String>,
+    /// Fields of the secondary product header record (SPHR), if any.
+    #[pyo3(get)]
+    pub sphr: BTreeMap<String, String>,
+    /// Number of measurement data records, i.e., packets.
+    #[pyo3(get)]
+    pub mdrs: u64,
+    /// Number of dummy measurement data records, each marking missing data.
+    #[pyo3(get)]
+    pub dummy_mdrs: u64,
+}
+
+#[pymethods]
+impl EpsProduct {
+    /// The product headers and counts as a JSON document.
+    #[pyo3(signature=(indent=true))]
+    fn to_json(&self, indent: bool) -> PyResult<String> {
+        let result = if indent {
+            serde_json::to_string_pretty(self)
+        } else {
+            serde_json::to_string(self)
+        };
+        result.map_err(|err| PyValueError::new_err(format!("{err}")))
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "EpsProduct(product_name={}, mdrs={}, dummy_mdrs={})",
+            repr(&self.mphr.get("PRODUCT_NAME")),
+            self.mdrs,
+            self.dummy_mdrs,
+        )
+    }
+}
+
+/// Read the main and secondary product headers of an EUMETSAT Polar System (EPS)
+/// Level 0 product, e.g., of MetOp AVHRR or IASI data, and count its measurement data
+/// records.
+///
+/// The source packets of the product are decoded with `decode_packets` using an
+/// `eps://path` source. MetOp packets carry an 8-byte CDS timecode at the start of
+/// their secondary header, so decode with the `cds` timecode.
+///
+/// Parameters
+/// ----------
+/// path : str or os.PathLike
+///     Path to the product file.
+///
+/// Returns
+/// -------
+/// EpsProduct
+#[pyfunction(signature=(path))]
+pub fn read_eps_product(py: Python, path: PathBuf) -> PyResult<EpsProduct> {
+    let mut reader = BufReader::new(File::open(&path)?);
+    py.allow_threads(|| {
+        let mut product = EpsProduct::default();
+        while let Some(record) = read_record(&mut reader)? {
+            match record.class {
+                CLASS_MPHR => product.mphr = header_fields(&record.body),
+                CLASS_SPHR => product.sphr = header_fields(&record.body),
+                CLASS_MDR if record.instrument_group == GROUP_DUMMY => product.dummy_mdrs += 1,
+                CLASS_MDR => product.mdrs += 1,
+                _ => {}
+            }
+        }
+        if product.mphr.is_empty() {
+            return Err(invalid("no main product header record").into());
+        }
+        Ok(product)
+    })
+}
@@ -0,0 +1,155 @@
+//! Extraction and encoding of fields at arbitrary bit offsets in packet data.
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// Check that a field of `bit_len` bits at `bit_offset` is within `dat_len` bytes,
+/// returning the end of the field in bits.
+fn check_field(dat_len: usize, bit_offset: usize, bit_len: u32) -> PyResult<usize> {
+    if !(1..=64).contains(&bit_len) {
+        return Err(PyValueError::new_err(format!(
+            "invalid bit_len; expected 1..=64, got {bit_len}"
+        )));
+    }
+    match bit_offset.checked_add(bit_len as usize) {
+        Some(end) if end <= dat_len * 8 => Ok(end),
+        _ => Err(PyValueError::new_err(format!(
+            "field at bit {bit_offset} of {bit_len} bits exceeds data length of {} bits",
+            dat_len * 8
+        ))),
+    }
+}
+
+/// Read `bit_len` bits, most significant first, starting `bit_offset` bits into `dat`.
+pub fn get_bits(dat: &[u8], bit_offset: usize, bit_len: u32) -> PyResult<u64> {
+    let end = check_field(dat.len(), bit_offset, bit_len)?;
+    let mut val: u128 = 0;
+    for byte in &dat[bit_offset / 8..end.div_ceil(8)] {
+        val = val << 8 | u128::from(*byte);
+    }
+    let trailing = end.div_ceil(8) * 8 - end;
+    val >>= trailing;
+    Ok((val & ((1u128 << bit_len) - 1)) as u64)
+}
+
+/// Read a two's complement signed integer field.
+pub fn get_signed(dat: &[u8], bit_offset: usize, bit_len: u32) -> PyResult<i64> {
+    let val = get_bits(dat, bit_offset, bit_len)?;
+    Ok(sign_extend(val, bit_len))
+}
+
+/// Write the `bit_len` bits of `val`, most significant first, starting `bit_offset` bits
+/// into `dat`, leaving the surrounding bits unchanged.
+pub fn set_bits(dat: &mut [u8], bit_offset: usize, bit_len: u32, val: u64) -> PyResult<()> {
+    check_field(dat.len(), bit_offset, bit_len)?;
+    if bit_len < 64 && val >> bit_len != 0 {
+        return Err(PyValueError::new_err(format!(
+            "value {val} does not fit in {bit_len} bits"
+        )));
+    }
+    for i in 0..bit_len as usize {
+        let bit = (val >> (bit_len as usize - 1 - i)) as u8 & 1;
+        let pos = bit_offset + i;
+        let shift = 7 - pos % 8;
+        dat[pos / 8] = dat[pos / 8] & !(1 << shift) | bit << shift;
+    }
+    Ok(())
+}
+
+/// The `bit_len` bit two's complement encoding of `val`.
+pub fn to_signed_bits(val: i64, bit_len: u32) -> PyResult<u64> {
+    let min = -(1i128 << (bit_len - 1));
+    let max = (1i128 << (bit_len - 1)) - 1;
+    if !(min..=max).contains(&i128::from(val)) {
+        return Err(PyValueError::new_err(format!(
+            "value {val} does not fit in {bit_len} signed bits"
+        )));
+    }
+    Ok(val as u64 & (u64::MAX >> (64 - bit_len)))
+}
+
+/// Reverse the order of the bytes of a `bit_len` bit value, where `bit_len` is a
+/// multiple of 8.
+pub fn swap_bytes(val: u64, bit_len: u32) -> u64 {
+    val.swap_bytes() >> (64 - bit_len)
+}
+
+pub fn sign_extend(val: u64, bit_len: u32) -> i64 {
+    let shift = 64 - bit_len;
+    ((val << shift) as i64) >> shift
+}
+
+/// Decode a MIL-STD-1750A 32-bit float: a 24-bit two's complement mantissa followed
+/// by an 8-bit two's complement exponent.
+pub fn mil1750a_32(val: u64) -> f64 {
+    let mantissa = sign_extend(val >> 8, 24);
+    let exponent = sign_extend(val & 0xff, 8);
+    mantissa as f64 * 2f64.powi(exponent as i32 - 23)
+}
+
+/// Decode a MIL-STD-1750A 48-bit extended float: the 24 most significant bits of a
+/// 40-bit two's complement mantissa, an 8-bit two's complement exponent, then the 16
+/// least significant bits of the mantissa.
+pub fn mil1750a_48(val: u64) -> f64 {
+    let mantissa = sign_extend((val >> 24) << 16 | (val & 0xffff), 40);
+    let exponent = sign_extend((val >> 16) & 0xff, 8);
+    mantissa as f64 * 2f64.powi(exponent as i32 - 39)
+}
+
+/// Normalized MIL-STD-1750A mantissa of `mantissa_bits` bits and exponent for `val`.
+fn mil1750a_parts(val: f64, mantissa_bits: u32) -> PyResult<(i64, i64)> {
+    if !val.is_finite() {
+        return Err(PyValueError::new_err(format!(
+            "value {val} cannot be encoded as MIL-STD-1750A"
+        )));
+    }
+    if val == 0.0 {
+        return Ok((0, 0));
+    }
+    let scale = 2f64.powi(mantissa_bits as i32 - 1);
+    // mantissa in [0.5, 1) or [-1, -0.5)
+    let mut exponent = val.abs().log2().floor() as i64 + 1;
+    let mut mantissa = (val / 2f64.powi(exponent as i32) * scale).round() as i64;
+    if mantissa == scale as i64 {
+        mantissa /= 2;
+        exponent += 1;
+    } else if mantissa == -(scale as i64) / 2 {
+        mantissa *= 2;
+        exponent -= 1;
+    }
+    if !(-128..=127).contains(&exponent) {
+        return Err(PyValueError::new_err(format!(
+            "value {val} is out of range for MIL-STD-1750A"
+        )));
+    }
+    Ok((mantissa, exponent))
+}
+
+/// Encode a MIL-STD-1750A 32-bit float.
+pub fn to_mil1750a_32(val: f64) -> PyResult<u64> {
+    let (mantissa, exponent) = mil1750a_parts(val, 24)?;
+    Ok((mantissa as u64 & 0xff_ffff) << 8 | (exponent as u64 & 0xff))
+}
+
+/// Encode a MIL-STD-1750A 48-bit extended float.
+pub fn to_mil1750a_48(val: f64) -> PyResult<u64> {
+    let (mantissa, exponent) = mil1750a_parts(val, 40)?;
+    let mantissa = mantissa as u64;
+    Ok((mantissa >> 16 & 0xff_ffff) << 24 | (exponent as u64 & 0xff) << 16 | (mantissa & 0xffff))
+}
+
+/// Read a 32-bit float, either IEEE 754 or MIL-STD-1750A.
+pub fn get_f32(dat: &[u8], bit_offset: usize, mil1750a: bool) -> PyResult<f64> {
+    let val = get_bits(dat, bit_offset, 32)?;
+    Ok(if mil1750a {
+        mil1750a_32(val)
+    } else {
+        f64::from(f32::from_bits(val as u32))
+    })
+}
+
+/// Read a 64-bit IEEE 754 float, or a 48-bit MIL-STD-1750A extended float.
+pub fn get_f64(dat: &[u8], bit_offset: usize, mil1750a: bool) -> PyResult<f64> {
+    if mil1750a {
+        return Ok(mil1750a_48(get_bits(dat, bit_offset, 48)?));
+    }
+    Ok(f64::from_bits(get_bits(dat, bit_offset, 64)?))
+}
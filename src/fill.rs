@@ -0,0 +1,178 @@
+//! Detection of fill patterns in packet payloads, e.g., from on-board buffer underruns
+//! that produce well-formed packets without real data.
+use std::collections::HashSet;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    anomaly::{self, Anomaly, AnomalyCollector, Severity},
+    packets::ViolationPolicy,
+    repr::repr,
+};
+
+/// Thresholds for flagging packets whose payload is mostly runs of fill bytes.
+///
+/// Parameters
+/// ----------
+/// patterns : Iterable[int]
+///     The fill byte values.
+///
+/// min_run : int
+///     Minimum number of consecutive fill bytes counted as fill, so short runs of
+///     these values in real data are ignored.
+///
+/// threshold : float
+///     Fraction of the payload, from 0 to 1, in fill runs for a packet to be flagged.
+///
+/// offset : int
+///     Byte offset of the payload in the packet data, i.e., after the primary header,
+///     e.g., to skip a secondary header.
+///
+/// apids : Iterable[int], optional
+///     Only check packets with these APIDs. All packets are checked if not provided.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct FillDetector {
+    #[pyo3(get)]
+    patterns: Vec<u8>,
+    #[pyo3(get)]
+    min_run: usize,
+    #[pyo3(get)]
+    threshold: f64,
+    #[pyo3(get)]
+    offset: usize,
+    #[pyo3(get)]
+    apids: Option<HashSet<u16>>,
+}
+
+#[pymethods]
+impl FillDetector {
+    #[new]
+    #[pyo3(signature=(patterns=vec![0xAA, 0x55, 0x00], min_run=16, threshold=0.9, offset=0, apids=None))]
+    fn py_new(
+        patterns: Vec<u8>,
+        min_run: usize,
+        threshold: f64,
+        offset: usize,
+        apids: Option<&PyAny>,
+    ) -> PyResult<Self> {
+        let apids: Option<HashSet<u16>> = crate::set_arg(apids)?;
+        if patterns.is_empty() {
+            return Err(PyValueError::new_err(
+                "invalid patterns; expected at least one fill byte",
+            ));
+        }
+        if min_run == 0 {
+            return Err(PyValueError::new_err(
+                "invalid min_run; expected > 0, got 0",
+            ));
+        }
+        if !(threshold > 0.0 && threshold <= 1.0) {
+            return Err(PyValueError::new_err(format!(
+                "invalid threshold; expected 0 < threshold <= 1, got {threshold}"
+            )));
+        }
+        Ok(Self {
+            patterns,
+            min_run,
+            threshold,
+            offset,
+            apids,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "FillDetector(patterns={}, min_run={}, threshold={}, offset={}, apids={})",
+            repr(&self.patterns),
+            self.min_run,
+            repr(&self.threshold),
+            self.offset,
+            repr(&self.apids),
+        )
+    }
+}
+
+impl FillDetector {
+    /// The fill byte and the fraction of the payload in runs of it, for the fill byte
+    /// covering the most of the payload, or `None` if there are no runs of at least
+    /// `min_run` fill bytes.
+    fn measure(&self, data: &[u8]) -> Option<(u8, f64)> {
+        let payload = data.get(self.offset..).unwrap_or_default();
+        let mut counts = vec![0usize; self.patterns.len()];
+        for run in payload.chunk_by(|a, b| a == b) {
+            if run.len() < self.min_run {
+                continue;
+            }
+            if let Some(idx) = self.patterns.iter().position(|p| *p == run[0]) {
+                counts[idx] += run.len();
+            }
+        }
+        let (idx, count) = counts.iter().enumerate().max_by_key(|(_, c)| **c)?;
+        (*count > 0).then(|| (self.patterns[idx], *count as f64 / payload.len() as f64))
+    }
+}
+
+/// Flags packets detected as fill by a [FillDetector].
+#[derive(Clone, Debug, Default)]
+pub struct FillCheck {
+    pub detector: Option<FillDetector>,
+    pub policy: ViolationPolicy,
+    /// APID, fill byte, fraction of the payload that is fill and source offset, if known,
+    /// of each packet flagged.
+    pub violations: Vec<(u16, u8, f64, Option<u64>)>,
+    pub anomalies: Option<AnomalyCollector>,
+}
+
+impl FillCheck {
+    /// Create a check from the `fill` and `fill_policy` arguments of the decode
+    /// functions.
+    pub fn from_args(detector: Option<FillDetector>, policy: &str) -> PyResult<Self> {
+        Ok(Self {
+            detector,
+            policy: ViolationPolicy::from_name(policy, "fill policy")?,
+            violations: Vec::default(),
+            anomalies: None,
+        })
+    }
+
+    /// Check the data of a packet, returning whether the packet should be yielded or an
+    /// error if the policy is to raise.
+    pub fn check(&mut self, apid: u16, data: &[u8], offset: Option<u64>) -> PyResult<bool> {
+        let Some(detector) = &self.detector else {
+            return Ok(true);
+        };
+        if detector.apids.as_ref().is_some_and(|a| !a.contains(&apid)) {
+            return Ok(true);
+        }
+        let Some((pattern, fraction)) = detector.measure(data) else {
+            return Ok(true);
+        };
+        if fraction < detector.threshold {
+            return Ok(true);
+        }
+        if self.policy == ViolationPolicy::Raise {
+            return Err(PyValueError::new_err(format!(
+                "suspected fill in packet for apid {apid}; {:.1}% of payload is {pattern:#04x}",
+                fraction * 100.0
+            )));
+        }
+        self.violations.push((apid, pattern, fraction, offset));
+        anomaly::emit(&self.anomalies, || {
+            Anomaly::new(
+                "fill",
+                Severity::Warning,
+                format!(
+                    "suspected fill; {:.1}% of payload is {pattern:#04x}",
+                    fraction * 100.0
+                ),
+            )
+            .apid(apid)
+            .offset(offset)
+        });
+        Ok(self.policy == ViolationPolicy::Flag)
+    }
+}
@@ -0,0 +1,419 @@
+//! Packet filter expressions, compiled once and evaluated in Rust per packet.
+//!
+//! Expressions compare packet header values to integer literals, e.g.,
+//! `apid in (1289, 1290) and seq_flags == 3`. The grammar is:
+//!
+//! ```text
+//! expr  := and ("or" and)*
+//! and   := not ("and" not)*
+//! not   := "not" not | term
+//! term  := "(" expr ")" | var op int | var ["not"] "in" "(" int ("," int)* ")"
+//! op    := "==" | "!=" | "<" | "<=" | ">" | ">="
+//! ```
+//!
+//! Integers may be decimal or `0x` hexadecimal, and `true` and `false` are 1 and 0.
+// pyo3 0.19 generates the constructor text signature as an impl nested in a function
+#![allow(non_local_definitions)]
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{repr::repr, DecodedPacket, Packet, PrimaryHeader};
+
+/// A packet value that may be referenced by an expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Var {
+    Version,
+    TypeFlag,
+    HasSecondaryHeader,
+    Apid,
+    SequenceFlags,
+    SequenceId,
+    LenMinus1,
+    DataLen,
+    Scid,
+    Vcid,
+}
+
+impl Var {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "version" => Self::Version,
+            "type_flag" | "type" => Self::TypeFlag,
+            "has_secondary_header" | "shdr" => Self::HasSecondaryHeader,
+            "apid" => Self::Apid,
+            "sequence_flags" | "seq_flags" => Self::SequenceFlags,
+            "sequence_id" | "seq" => Self::SequenceId,
+            "len_minus1" => Self::LenMinus1,
+            "data_len" => Self::DataLen,
+            "scid" => Self::Scid,
+            "vcid" => Self::Vcid,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn apply(&self, a: i64, b: i64) -> bool {
+        match self {
+            Self::Eq => a == b,
+            Self::Ne => a != b,
+            Self::Lt => a < b,
+            Self::Le => a <= b,
+            Self::Gt => a > b,
+            Self::Ge => a >= b,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cmp(Var, Op, i64),
+    In(Var, Vec<i64>),
+}
+
+/// The values of a packet an expression is evaluated against.
+pub(crate) struct Values<'a> {
+    pub(crate) header: &'a PrimaryHeader,
+    pub(crate) data_len: usize,
+    /// Spacecraft and virtual channel ids, if the packet was decoded from frames.
+    pub(crate) ids: Option<(u16, u16)>,
+}
+
+impl Values<'_> {
+    fn get(&self, var: Var) -> Option<i64> {
+        let hdr = self.header;
+        Some(match var {
+            Var::Version => hdr.version.into(),
+            Var::TypeFlag => hdr.type_flag.into(),
+            Var::HasSecondaryHeader => hdr.has_secondary_header.into(),
+            Var::Apid => hdr.apid.into(),
+            Var::SequenceFlags => hdr.sequence_flags.into(),
+            Var::SequenceId => hdr.sequence_id.into(),
+            Var::LenMinus1 => hdr.len_minus1.into(),
+            Var::DataLen => self.data_len as i64,
+            Var::Scid => self.ids?.0.into(),
+            Var::Vcid => self.ids?.1.into(),
+        })
+    }
+}
+
+impl Expr {
+    /// Evaluate against `values`. Comparisons of values a packet does not have, e.g.,
+    /// the scid of a packet not decoded from frames, are false.
+    fn eval(&self, values: &Values) -> bool {
+        match self {
+            Self::Or(a, b) => a.eval(values) || b.eval(values),
+            Self::And(a, b) => a.eval(values) && b.eval(values),
+            Self::Not(a) => !a.eval(values),
+            Self::Cmp(var, op, v) => values.get(*var).is_some_and(|x| op.apply(x, *v)),
+            Self::In(var, vs) => values.get(*var).is_some_and(|x| vs.contains(&x)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Op(Op),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(expr: &str) -> PyResult<Vec<(usize, Token)>> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = Vec::default();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = if c.is_ascii_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect::<String>().to_lowercase();
+            match word.as_str() {
+                "true" => Token::Int(1),
+                "false" => Token::Int(0),
+                _ => Token::Ident(word),
+            }
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let value = match literal.strip_prefix("0x").or(literal.strip_prefix("0X")) {
+                Some(hex) => i64::from_str_radix(hex, 16),
+                None => literal.parse(),
+            };
+            match value {
+                Ok(value) => Token::Int(value),
+                Err(_) => return Err(syntax_error(start, &format!("invalid integer {literal}"))),
+            }
+        } else {
+            let next = chars.get(i + 1).copied();
+            let (token, len) = match (c, next) {
+                ('=', Some('=')) => (Token::Op(Op::Eq), 2),
+                ('!', Some('=')) => (Token::Op(Op::Ne), 2),
+                ('<', Some('=')) => (Token::Op(Op::Le), 2),
+                ('>', Some('=')) => (Token::Op(Op::Ge), 2),
+                ('<', _) => (Token::Op(Op::Lt), 1),
+                ('>', _) => (Token::Op(Op::Gt), 1),
+                ('(', _) => (Token::LParen, 1),
+                (')', _) => (Token::RParen, 1),
+                (',', _) => (Token::Comma, 1),
+                _ => return Err(syntax_error(start, &format!("unexpected character {c:?}"))),
+            };
+            i += len;
+            token
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+fn syntax_error(pos: usize, msg: &str) -> PyErr {
+    PyValueError::new_err(format!("invalid filter at position {pos}: {msg}"))
+}
+
+/// Deepest nesting of parentheses, `not` and chained `and`/`or` accepted, bounding the
+/// recursion of parsing and evaluating an expression.
+const MAX_DEPTH: usize = 256;
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// Length of the expression, the position reported for errors at its end.
+    len: usize,
+    /// Nesting depth of the expression being parsed.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn error(&self, msg: &str) -> PyErr {
+        let pos = self.tokens.get(self.pos).map_or(self.len, |(p, _)| *p);
+        syntax_error(pos, msg)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(w)) if w == word) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> PyResult<()> {
+        if self.peek() == Some(&expected) {
+            self.pos += 1;
+            return Ok(());
+        }
+        Err(self.error(&format!("expected {what}")))
+    }
+
+    /// Descend a level into the expression, failing if it is nested too deeply.
+    fn descend(&mut self) -> PyResult<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(&format!("expression nested deeper than {MAX_DEPTH} levels")));
+        }
+        Ok(())
+    }
+
+    /// Parse with `parse` a level deeper into the expression.
+    fn nested(&mut self, parse: fn(&mut Self) -> PyResult<Expr>) -> PyResult<Expr> {
+        self.descend()?;
+        let expr = parse(self)?;
+        self.depth -= 1;
+        Ok(expr)
+    }
+
+    fn int(&mut self) -> PyResult<i64> {
+        match self.peek() {
+            Some(Token::Int(v)) => {
+                let v = *v;
+                self.pos += 1;
+                Ok(v)
+            }
+            _ => Err(self.error("expected an integer")),
+        }
+    }
+
+    fn or(&mut self) -> PyResult<Expr> {
+        let depth = self.depth;
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            // each operator of a chain nests the expression a level deeper
+            self.descend()?;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> PyResult<Expr> {
+        let depth = self.depth;
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            self.descend()?;
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> PyResult<Expr> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.nested(Self::not)?)));
+        }
+        self.term()
+    }
+
+    fn term(&mut self) -> PyResult<Expr> {
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.nested(Self::or)?;
+            self.expect(Token::RParen, "')'")?;
+            return Ok(expr);
+        }
+        let var = match self.peek() {
+            Some(Token::Ident(name)) => {
+                Var::from_name(name).ok_or_else(|| self.error(&format!("unknown value {name}")))?
+            }
+            _ => return Err(self.error("expected a packet value, e.g., apid")),
+        };
+        self.pos += 1;
+        let negate = self.keyword("not");
+        if self.keyword("in") {
+            self.expect(Token::LParen, "'('")?;
+            let mut values = vec![self.int()?];
+            while self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                values.push(self.int()?);
+            }
+            self.expect(Token::RParen, "')'")?;
+            let expr = Expr::In(var, values);
+            return Ok(if negate {
+                Expr::Not(Box::new(expr))
+            } else {
+                expr
+            });
+        }
+        if negate {
+            return Err(self.error("expected 'in'"));
+        }
+        match self.next() {
+            Some(Token::Op(op)) => Ok(Expr::Cmp(var, op, self.int()?)),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected a comparison or 'in'"))
+            }
+        }
+    }
+}
+
+/// A compiled packet filter expression.
+///
+/// Expressions compare the packet values `version`, `type_flag` (or `type`),
+/// `has_secondary_header` (or `shdr`), `apid`, `sequence_flags` (or `seq_flags`),
+/// `sequence_id` (or `seq`), `len_minus1`, `data_len`, and, for packets decoded from
+/// frames, `scid` and `vcid` to integers using `==`, `!=`, `<`, `<=`, `>`, `>=`, `in`
+/// and `not in`, combined with `and`, `or`, `not` and parentheses, e.g.,
+/// `apid in (1289, 1290) and seq_flags == 3`. Expressions may nest up to 256 levels,
+/// counting each parenthesis, `not`, and `and` or `or` of a chain.
+///
+/// Parameters
+/// ----------
+/// expr : str
+///     The filter expression. A ValueError is raised if it is not valid.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct PacketFilter {
+    /// The source expression.
+    #[pyo3(get)]
+    expr: String,
+    compiled: Expr,
+}
+
+#[pymethods]
+impl PacketFilter {
+    #[new]
+    pub fn py_new(expr: &str) -> PyResult<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(expr)?,
+            pos: 0,
+            len: expr.chars().count(),
+            depth: 0,
+        };
+        let compiled = parser.or()?;
+        if parser.peek().is_some() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+        Ok(Self {
+            expr: expr.to_string(),
+            compiled,
+        })
+    }
+
+    /// True if a Packet or DecodedPacket matches the filter.
+    fn matches(&self, packet: &PyAny) -> PyResult<bool> {
+        if let Ok(decoded) = packet.extract::<PyRef<DecodedPacket>>() {
+            return Ok(self.eval(&Values {
+                header: &decoded.packet.header,
+                data_len: decoded.packet.payload().len(),
+                ids: Some((decoded.scid, decoded.vcid)),
+            }));
+        }
+        let packet: PyRef<Packet> = packet.extract()?;
+        Ok(self.eval(&Values {
+            header: &packet.header,
+            data_len: packet.payload().len(),
+            ids: None,
+        }))
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!("PacketFilter({})", repr(&self.expr))
+    }
+}
+
+impl PacketFilter {
+    pub(crate) fn eval(&self, values: &Values) -> bool {
+        self.compiled.eval(values)
+    }
+}
+
+/// Compile an optional `filter` argument of the decode functions.
+pub fn filter_arg(filter: Option<&str>) -> PyResult<Option<PacketFilter>> {
+    filter.map(PacketFilter::py_new).transpose()
+}
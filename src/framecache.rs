@@ -0,0 +1,70 @@
+//! Ring cache of recently decoded frames for debugging downstream anomalies.
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::Frame;
+
+/// The last decoded frames, shared with the decoding thread, if any.
+#[derive(Clone, Debug)]
+pub struct FrameCache {
+    capacity: usize,
+    frames: Arc<Mutex<VecDeque<Frame>>>,
+}
+
+impl FrameCache {
+    /// Create a cache from the `frame_cache` argument of the decode functions, or `None`
+    /// if frames are not cached.
+    pub fn from_arg(capacity: Option<usize>) -> PyResult<Option<Self>> {
+        match capacity {
+            None => Ok(None),
+            Some(0) => Err(PyValueError::new_err(
+                "invalid frame_cache; expected > 0, got 0",
+            )),
+            Some(capacity) => Ok(Some(Self {
+                capacity,
+                frames: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            })),
+        }
+    }
+
+    /// Add a frame, evicting the oldest if the cache is full.
+    pub fn push(&self, frame: Frame) {
+        let mut frames = self.frames.lock().unwrap();
+        if frames.len() == self.capacity {
+            frames.pop_front();
+        }
+        frames.push_back(frame);
+    }
+
+    /// The most recent frame with the given VCID and frame counter, and spacecraft id if
+    /// provided. The counter is the extended counter for frames using the counter cycle.
+    pub fn get(&self, vcid: u16, counter: u32, scid: Option<u16>) -> Option<Frame> {
+        let frames = self.frames.lock().unwrap();
+        frames
+            .iter()
+            .rev()
+            .find(|frame| {
+                let hdr = &frame.header;
+                hdr.vcid == vcid
+                    && hdr.extended().0 == counter
+                    && scid.is_none_or(|scid| hdr.scid == scid)
+            })
+            .cloned()
+    }
+
+    /// The cached frames, oldest first.
+    pub fn frames(&self) -> Vec<Frame> {
+        self.frames.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// The cache, or an error for methods that require the `frame_cache` argument.
+pub fn enabled(cache: &Option<FrameCache>) -> PyResult<&FrameCache> {
+    cache
+        .as_ref()
+        .ok_or_else(|| PyValueError::new_err("frames are only cached with frame_cache"))
+}
@@ -0,0 +1,219 @@
+//! Validation of the framing parameters of the frame decode functions. The frame length,
+//! ASM and Reed-Solomon interleave are cross-checked up front, since a mismatch would
+//! otherwise only show up as frames of garbage.
+use std::path::Path;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{rs, services::VCDU_HEADER_LEN, sync};
+
+/// Length of the M_PDU header preceding the packet zone of a frame.
+const MPDU_HEADER_LEN: usize = 2;
+
+/// The argument the frame length was provided as, used to word errors in terms of what
+/// the caller passed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LenArg {
+    /// The frame length, excluding the ASM.
+    Frame,
+    /// The CADU length, including the ASM.
+    Cadu,
+}
+
+impl LenArg {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Frame => "frame_len",
+            Self::Cadu => "cadu_len",
+        }
+    }
+}
+
+/// Validated framing parameters of a frame decode.
+#[derive(Clone, Debug)]
+pub struct FrameDecodeConfig {
+    /// Attached sync marker preceding each frame.
+    pub asm: Vec<u8>,
+    /// Length of each frame following the ASM, including any Reed-Solomon parity.
+    pub frame_len: usize,
+    /// Reed-Solomon interleave, if the frames include parity.
+    pub interleave: Option<u8>,
+}
+
+impl FrameDecodeConfig {
+    /// Resolve and validate the `frame_len`, `interleave` and `asm` arguments of
+    /// `decode_frames`. The interleave is not resolved for `headers_only`, and may be a
+    /// shortened code for `rs_check_only`, which checks the parity itself.
+    pub fn new(
+        source: &Path,
+        frame_len: i32,
+        interleave: Option<&PyAny>,
+        asm: Option<&PyAny>,
+        headers_only: bool,
+        rs_check_only: bool,
+    ) -> PyResult<Self> {
+        if frame_len <= 0 {
+            return Err(PyValueError::new_err(format!(
+                "invalid frame_len; expected > 0, got {frame_len}"
+            )));
+        }
+        let asm = sync::asm_arg(source, asm)?;
+        let interleave = if headers_only { None } else { interleave };
+        let config = Self::resolve(source, asm, frame_len as usize, interleave, LenArg::Frame)?;
+        match config.interleave {
+            None if rs_check_only => Err(PyValueError::new_err(
+                "rs_check_only requires an interleave",
+            )),
+            Some(interleave) if rs_check_only => {
+                if !rs::valid_block_len(config.frame_len, interleave.into()) {
+                    return Err(PyValueError::new_err(format!(
+                        "invalid frame_len {} for interleave {interleave}; expected a \
+                         multiple of {interleave} with {} to {} byte codewords",
+                        config.frame_len,
+                        rs::PARITY_LEN + 1,
+                        rs::N,
+                    )));
+                }
+                Ok(config)
+            }
+            Some(_) => config.check_rs(LenArg::Frame),
+            None => Ok(config),
+        }
+    }
+
+    /// Resolve and validate the `cadu_len`, `interleave` and `asm` arguments of the
+    /// framed packet decode functions, where the length includes the ASM.
+    pub fn from_cadu_len(
+        source: &Path,
+        cadu_len: i64,
+        interleave: Option<&PyAny>,
+        asm: Option<&PyAny>,
+    ) -> PyResult<Self> {
+        let asm = sync::asm_arg(source, asm)?;
+        let Some(frame_len) = usize::try_from(cadu_len)
+            .ok()
+            .and_then(|len| len.checked_sub(asm.len()))
+            .filter(|len| *len > 0)
+        else {
+            return Err(PyValueError::new_err(format!(
+                "invalid cadu_len; expected more than the {} byte ASM, got {cadu_len}",
+                asm.len()
+            )));
+        };
+        Self::resolve_cadu(source, asm, frame_len, interleave)
+    }
+
+    /// As [Self::from_cadu_len], but for a frame length following the ASM, e.g., from
+    /// the spacecraft database when `cadu_len` is not provided.
+    pub fn from_frame_len(
+        source: &Path,
+        frame_len: usize,
+        interleave: Option<&PyAny>,
+        asm: Option<&PyAny>,
+    ) -> PyResult<Self> {
+        let asm = sync::asm_arg(source, asm)?;
+        Self::resolve_cadu(source, asm, frame_len, interleave)
+    }
+
+    fn resolve_cadu(
+        source: &Path,
+        asm: Vec<u8>,
+        frame_len: usize,
+        interleave: Option<&PyAny>,
+    ) -> PyResult<Self> {
+        let config = Self::resolve(source, asm, frame_len, interleave, LenArg::Cadu)?;
+        match config.interleave {
+            Some(_) => config.check_rs(LenArg::Cadu),
+            None => Ok(config),
+        }
+    }
+
+    fn resolve(
+        source: &Path,
+        asm: Vec<u8>,
+        frame_len: usize,
+        interleave: Option<&PyAny>,
+        arg: LenArg,
+    ) -> PyResult<Self> {
+        let config = Self {
+            asm,
+            frame_len,
+            interleave: None,
+        };
+        if frame_len < VCDU_HEADER_LEN {
+            return Err(PyValueError::new_err(format!(
+                "invalid {}; frames must hold at least the {VCDU_HEADER_LEN} byte VCDU header, \
+                 got {} byte frames",
+                arg.name(),
+                frame_len,
+            )));
+        }
+        let interleave = sync::interleave_arg(source, &config.asm, frame_len, interleave)?;
+        Ok(Self {
+            interleave,
+            ..config
+        })
+    }
+
+    /// Check the frame length is that of `interleave` Reed-Solomon codewords, as
+    /// required by the Reed-Solomon decoder, explaining the likely mistake if not.
+    fn check_rs(self, arg: LenArg) -> PyResult<Self> {
+        let interleave = usize::from(self.interleave.unwrap_or_default());
+        if self.frame_len == interleave * rs::N {
+            return Ok(self);
+        }
+        let name = arg.name();
+        let asm_len = self.asm.len();
+        let (given, expected) = match arg {
+            LenArg::Frame => (self.frame_len, interleave * rs::N),
+            LenArg::Cadu => (self.cadu_len(), asm_len + interleave * rs::N),
+        };
+        let mut msg = match arg {
+            LenArg::Frame => format!(
+                "{name} {given} does not match interleave {interleave}; Reed-Solomon frames are \
+                 interleave * {} = {expected} bytes, including the parity",
+                rs::N
+            ),
+            LenArg::Cadu => format!(
+                "{name} {given} does not match interleave {interleave}; Reed-Solomon CADUs are \
+                 the {asm_len} byte ASM plus interleave * {} = {expected} bytes",
+                rs::N
+            ),
+        };
+        let codewords = self.frame_len / rs::N;
+        if arg == LenArg::Frame && self.frame_len == interleave * rs::N + asm_len {
+            msg +=
+                &format!("; {name} appears to include the {asm_len} byte ASM, which it should not");
+        } else if arg == LenArg::Cadu && self.cadu_len() == interleave * rs::N {
+            msg += &format!(
+                "; {name} appears to exclude the {asm_len} byte ASM, which it should include"
+            );
+        } else if self.frame_len.is_multiple_of(rs::N) && (2..=10).contains(&codewords) {
+            msg += &format!("; {name} {given} matches interleave {codewords}");
+        } else if arg == LenArg::Frame && !self.frame_len.is_multiple_of(rs::N) {
+            msg += "; shortened Reed-Solomon codes are only supported with rs_check_only";
+        }
+        Err(PyValueError::new_err(msg))
+    }
+
+    /// Length of each CADU, i.e., the ASM plus the frame.
+    pub fn cadu_len(&self) -> usize {
+        self.asm.len() + self.frame_len
+    }
+
+    /// Check that frames with an insert zone and trailer of these lengths leave room for
+    /// the M_PDU header and packet data once any Reed-Solomon parity is removed.
+    pub fn check_layout(&self, izone_len: usize, trailer_len: usize) -> PyResult<()> {
+        let parity_len = usize::from(self.interleave.unwrap_or_default()) * rs::PARITY_LEN;
+        let data_len = self.frame_len.saturating_sub(parity_len);
+        let overhead = VCDU_HEADER_LEN + izone_len + MPDU_HEADER_LEN + trailer_len;
+        if data_len <= overhead {
+            return Err(PyValueError::new_err(format!(
+                "frames of {data_len} bytes, excluding Reed-Solomon parity, leave no room for \
+                 packet data after the {VCDU_HEADER_LEN} byte VCDU header, {izone_len} byte \
+                 insert zone, {MPDU_HEADER_LEN} byte M_PDU header and {trailer_len} byte trailer"
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,368 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::Serialize;
+
+use crate::{
+    compress,
+    packets::{PacketReader, RawPacket},
+    repr::repr,
+    source::open_source,
+    timecode::{optional_registry_arg, TimecodeRegistry},
+};
+
+/// A discontinuity in the sequence counter of an APID.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Serialize)]
+pub struct Gap {
+    #[pyo3(get)]
+    pub apid: u16,
+    /// Sequence id of the last packet before the gap.
+    #[pyo3(get)]
+    pub start_sequence_id: u16,
+    /// Sequence id of the first packet after the gap.
+    #[pyo3(get)]
+    pub end_sequence_id: u16,
+    /// Number of missing packets.
+    #[pyo3(get)]
+    pub count: u16,
+    /// Byte offset of the last packet before the gap.
+    #[pyo3(get)]
+    pub start_offset: u64,
+    /// Byte offset of the first packet after the gap.
+    #[pyo3(get)]
+    pub end_offset: u64,
+    /// Time of the last packet before the gap in UTC milliseconds, if available.
+    #[pyo3(get)]
+    pub start_time: Option<i64>,
+    /// Time of the first packet after the gap in UTC milliseconds, if available.
+    #[pyo3(get)]
+    pub end_time: Option<i64>,
+}
+
+#[pymethods]
+impl Gap {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "Gap(apid={}, start_sequence_id={}, end_sequence_id={}, count={}, start_offset={}, end_offset={}, start_time={}, end_time={})",
+            self.apid, self.start_sequence_id, self.end_sequence_id, self.count, self.start_offset, self.end_offset, repr(&self.start_time), repr(&self.end_time),
+        )
+    }
+}
+
+struct Last {
+    sequence_id: u16,
+    offset: u64,
+    time: Option<i64>,
+}
+
+/// Tracks per-APID sequence counters to detect gaps in a packet stream.
+#[derive(Default)]
+pub struct GapTracker {
+    last: HashMap<u16, Last>,
+}
+
+impl GapTracker {
+    /// Add the next packet, returning the gap preceding it, if any.
+    pub fn add(&mut self, packet: &RawPacket, time: Option<i64>) -> Option<Gap> {
+        let hdr = &packet.header;
+        let cur = Last {
+            sequence_id: hdr.sequence_id,
+            offset: packet.offset,
+            time,
+        };
+        let last = self.last.insert(hdr.apid, cur)?;
+        let count = ccsds::missing_packets(hdr.sequence_id, last.sequence_id);
+        if count == 0 {
+            return None;
+        }
+        Some(Gap {
+            apid: hdr.apid,
+            start_sequence_id: last.sequence_id,
+            end_sequence_id: hdr.sequence_id,
+            count,
+            start_offset: last.offset,
+            end_offset: packet.offset,
+            start_time: last.time,
+            end_time: time,
+        })
+    }
+}
+
+/// A discontinuity in the frame counter of a virtual channel.
+///
+/// AOS frames do not carry a master channel counter, so continuity is tracked for each
+/// virtual channel of each master channel, i.e., each spacecraft id and VCID pair.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Serialize)]
+pub struct FrameGap {
+    #[pyo3(get)]
+    pub scid: u16,
+    #[pyo3(get)]
+    pub vcid: u16,
+    /// Counter of the last frame before the gap.
+    #[pyo3(get)]
+    pub start_counter: u32,
+    /// Counter of the first frame after the gap.
+    #[pyo3(get)]
+    pub end_counter: u32,
+    /// Number of missing frames.
+    #[pyo3(get)]
+    pub count: u32,
+    /// Byte offset of the last frame before the gap, if known.
+    #[pyo3(get)]
+    pub start_offset: Option<u64>,
+    /// Byte offset of the first frame after the gap, if known.
+    #[pyo3(get)]
+    pub end_offset: Option<u64>,
+}
+
+#[pymethods]
+impl FrameGap {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "FrameGap(scid={}, vcid={}, start_counter={}, end_counter={}, count={}, start_offset={}, end_offset={})",
+            self.scid, self.vcid, self.start_counter, self.end_counter, self.count, repr(&self.start_offset), repr(&self.end_offset),
+        )
+    }
+}
+
+/// Bits of the AOS VCDU frame counter.
+pub const AOS_COUNTER_BITS: u8 = 24;
+/// Bits of the AOS VCDU frame counter extended by the 4-bit counter cycle.
+pub const AOS_CYCLE_COUNTER_BITS: u8 = 28;
+
+/// Number of counts missing between `last` and `cur` for a counter of `bits` bits that
+/// wraps to zero.
+pub fn missing_counts(cur: u32, last: u32, bits: u8) -> u32 {
+    let modulus = 1u64 << bits;
+    let (cur, last) = (u64::from(cur) % modulus, u64::from(last) % modulus);
+    ((cur + modulus - last - 1) % modulus) as u32
+}
+
+/// Validate a counter width argument.
+pub fn counter_bits_arg(bits: u8) -> PyResult<u8> {
+    if !(1..=32).contains(&bits) {
+        return Err(PyValueError::new_err(format!(
+            "invalid counter_bits; expected 1..=32, got {bits}"
+        )));
+    }
+    Ok(bits)
+}
+
+/// Tracks the frame counters of each virtual channel of each master channel to detect
+/// gaps in a frame stream.
+#[derive(Default)]
+pub struct FrameGapTracker {
+    last: HashMap<(u16, u16), (u32, Option<u64>)>,
+}
+
+impl FrameGapTracker {
+    /// Add the next frame, returning the gap preceding it, if any. `counter` is a counter
+    /// of `bits` bits, e.g., the AOS frame counter extended by the counter cycle if it
+    /// is in use.
+    pub fn add(
+        &mut self,
+        scid: u16,
+        vcid: u16,
+        counter: u32,
+        bits: u8,
+        offset: Option<u64>,
+    ) -> Option<FrameGap> {
+        let (last, last_offset) = self.last.insert((scid, vcid), (counter, offset))?;
+        let count = missing_counts(counter, last, bits);
+        if count == 0 {
+            return None;
+        }
+        Some(FrameGap {
+            scid,
+            vcid,
+            start_counter: last,
+            end_counter: counter,
+            count,
+            start_offset: last_offset,
+            end_offset: offset,
+        })
+    }
+}
+
+/// Decode the packet time using `timecode`, if the packet has a secondary header.
+pub fn packet_time(packet: &RawPacket, timecode: Option<&TimecodeRegistry>) -> Option<i64> {
+    match timecode {
+        Some(tc) if packet.header.has_secondary_header => {
+            tc.decode(packet.header.apid, &packet.data)
+        }
+        _ => None,
+    }
+}
+
+fn collect_gaps(source: &Path, timecode: Option<&TimecodeRegistry>) -> PyResult<Vec<Gap>> {
+    let reader = PacketReader::new(BufReader::new(open_source(source)?));
+    let mut tracker = GapTracker::default();
+    let mut gaps = Vec::default();
+    for packet in reader {
+        let packet = packet?;
+        let time = packet_time(&packet, timecode);
+        if let Some(gap) = tracker.add(&packet, time) {
+            gaps.push(gap);
+        }
+    }
+    Ok(gaps)
+}
+
+fn write_csv<W: Write>(mut w: W, gaps: &[Gap]) -> std::io::Result<()> {
+    writeln!(
+        w,
+        "apid,start_sequence_id,end_sequence_id,count,start_offset,end_offset,start_time,end_time"
+    )?;
+    let opt = |v: Option<i64>| v.map(|v| v.to_string()).unwrap_or_default();
+    for gap in gaps {
+        writeln!(
+            w,
+            "{},{},{},{},{},{},{},{}",
+            gap.apid,
+            gap.start_sequence_id,
+            gap.end_sequence_id,
+            gap.count,
+            gap.start_offset,
+            gap.end_offset,
+            opt(gap.start_time),
+            opt(gap.end_time),
+        )?;
+    }
+    w.flush()
+}
+
+/// Days from the CCSDS epoch, 1958-01-01, to the Unix epoch.
+const CDS_EPOCH_DAYS: i64 = 4383;
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// Encode UTC milliseconds as an 8-byte CDS time, i.e., 2-byte day, 4-byte millisecond
+/// of day and 2-byte microsecond of millisecond, or zeros if the time is not available
+/// or out of range.
+fn cds_bytes(time: Option<i64>) -> [u8; 8] {
+    let mut buf = [0u8; 8];
+    let Some(time) = time else {
+        return buf;
+    };
+    let Ok(days) = u16::try_from(time.div_euclid(MILLIS_PER_DAY) + CDS_EPOCH_DAYS) else {
+        return buf;
+    };
+    let millis = time.rem_euclid(MILLIS_PER_DAY) as u32;
+    buf[..2].copy_from_slice(&days.to_be_bytes());
+    buf[2..6].copy_from_slice(&millis.to_be_bytes());
+    buf
+}
+
+/// Write gaps as the per-APID gap lists of an EDOS construction record.
+///
+/// For each APID with gaps, in order of APID, a 4-byte APID and 4-byte gap count are
+/// followed by a 32-byte entry per gap: the 4-byte sequence count of the first missing
+/// packet, the 8-byte offset of the packet following the gap, the 4-byte number of
+/// missing packets, and the 8-byte CDS times of the packets preceding and following
+/// the gap. All values are big-endian and unavailable times are zero.
+fn write_edos<W: Write>(mut w: W, gaps: &[Gap]) -> std::io::Result<()> {
+    let mut by_apid: BTreeMap<u16, Vec<&Gap>> = BTreeMap::default();
+    for gap in gaps {
+        by_apid.entry(gap.apid).or_default().push(gap);
+    }
+    for (apid, gaps) in by_apid {
+        w.write_all(&u32::from(apid).to_be_bytes())?;
+        w.write_all(&(gaps.len() as u32).to_be_bytes())?;
+        for gap in gaps {
+            let first_missing = (gap.start_sequence_id + 1) % 16384;
+            w.write_all(&u32::from(first_missing).to_be_bytes())?;
+            w.write_all(&gap.end_offset.to_be_bytes())?;
+            w.write_all(&u32::from(gap.count).to_be_bytes())?;
+            w.write_all(&cds_bytes(gap.start_time))?;
+            w.write_all(&cds_bytes(gap.end_time))?;
+        }
+    }
+    w.flush()
+}
+
+/// Generate a report of the sequence counter gaps for each APID in a stream of packets.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing stream of space packets.
+///
+/// timecode : str or TimecodeRegistry, optional
+///     Format of the timecode at the start of the secondary header, one of `cds` or
+///     `eoscuc`, or the timecode of each APID. If provided, gap start and end times are
+///     included in the report.
+///
+/// Returns
+/// -------
+/// list of Gap
+///     Gaps in the order they were detected.
+#[pyfunction(signature=(source, timecode=None))]
+pub fn gap_report(source: PathBuf, timecode: Option<&PyAny>) -> PyResult<Vec<Gap>> {
+    collect_gaps(&source, optional_registry_arg(timecode)?.as_ref())
+}
+
+/// Generate a gap report for a stream of packets and write it to a file.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing stream of space packets.
+///
+/// dest : str or os.PathLike
+///     Path of the report file to write. Any existing file will be overwritten.
+///
+/// format : str
+///     Report format, one of `csv`, `json`, or `edos` for the binary gap lists of an
+///     EDOS construction record, as expected by archive interfaces for production data
+///     sets.
+///
+/// timecode : str or TimecodeRegistry, optional
+///     Format of the timecode at the start of the secondary header, one of `cds` or
+///     `eoscuc`, or the timecode of each APID. If provided, gap start and end times are
+///     included in the report.
+///
+/// compression : str, optional
+///     Compress the report, either `gzip`, `zstd` or `none`. Defaults to `gzip` or
+///     `zstd` if `dest` ends with `.gz` or `.zst`, otherwise `none`.
+///
+/// Returns
+/// -------
+/// int
+///     The number of gaps written.
+#[pyfunction(signature=(source, dest, format="csv", timecode=None, compression=None))]
+pub fn write_gap_report(
+    source: PathBuf,
+    dest: PathBuf,
+    format: &str,
+    timecode: Option<&PyAny>,
+    compression: Option<&str>,
+) -> PyResult<usize> {
+    if !["csv", "json", "edos"].contains(&format) {
+        return Err(PyValueError::new_err(format!(
+            "unsupported format; expected csv, json or edos, got {format}"
+        )));
+    }
+    let gaps = collect_gaps(&source, optional_registry_arg(timecode)?.as_ref())?;
+    let mut w = compress::create(&dest, compression)?;
+    match format {
+        "csv" => write_csv(w, &gaps)?,
+        "edos" => write_edos(w, &gaps)?,
+        _ => {
+            serde_json::to_writer_pretty(&mut w, &gaps)
+                .map_err(|err| PyValueError::new_err(format!("{err}")))?;
+            w.flush()?;
+        }
+    }
+    Ok(gaps.len())
+}
@@ -0,0 +1,387 @@
+//! Decoding of the GOES Rebroadcast (GRB) downlink, whose data is split over two
+//! polarization channels, each a CADU stream, and whose payloads, e.g., an ABI image
+//! block, are segmented over groups of packets using the sequence flags.
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashSet},
+    io::BufReader,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::mpsc::{sync_channel, SyncSender},
+    thread,
+};
+
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
+
+use crate::{
+    frameconfig::FrameDecodeConfig,
+    idle::FILL_APID,
+    packets::HEADER_LEN,
+    prefetch::{Next, Pipeline, DEFAULT_PREFETCH},
+    repr::repr,
+    source::open_source,
+    sync,
+};
+
+/// Length of the GRB CADUs, the ASM and a 2044 byte frame.
+const GRB_CADU_LEN: i64 = 2048;
+
+/// Length of the secondary header of GRB packets.
+const SECONDARY_HEADER_LEN: usize = 8;
+
+/// The GRB time epoch, 2000-01-01T12:00:00 UTC, in UTC milliseconds.
+const J2000_MILLIS: i64 = 946_728_000_000;
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+const SEQ_CONTINUATION: u8 = 0;
+const SEQ_FIRST: u8 = 1;
+const SEQ_LAST: u8 = 2;
+
+/// A GRB payload reassembled from the packets of a sequence flag group.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct GrbPayload {
+    /// Polarization channel the payload was received on, `rhcp` or `lhcp`.
+    #[pyo3(get)]
+    pub channel: &'static str,
+    #[pyo3(get)]
+    pub vcid: u16,
+    #[pyo3(get)]
+    pub apid: u16,
+    /// Sequence id of the first packet of the payload.
+    #[pyo3(get)]
+    pub sequence_id: u16,
+    /// Time from the secondary header of the first packet in UTC milliseconds, or
+    /// `None` if the first packet was not received.
+    #[pyo3(get)]
+    pub time: Option<i64>,
+    /// GRB version, payload variant, assembler identifier and system environment from
+    /// the secondary header of the first packet, or `None` as for `time`.
+    #[pyo3(get)]
+    pub version: Option<u8>,
+    #[pyo3(get)]
+    pub payload_variant: Option<u8>,
+    #[pyo3(get)]
+    pub assembler_id: Option<u8>,
+    #[pyo3(get)]
+    pub system_environment: Option<u8>,
+    /// Number of packets the payload was reassembled from.
+    #[pyo3(get)]
+    pub packets: usize,
+    /// True if the first and last packets and all packets between them were received.
+    #[pyo3(get)]
+    pub complete: bool,
+    data: Vec<u8>,
+}
+
+#[pymethods]
+impl GrbPayload {
+    /// The payload, i.e., the packet data following the secondary headers.
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.data)
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "GrbPayload(channel={}, vcid={}, apid={}, sequence_id={}, time={}, packets={}, complete={}, len={})",
+            repr(self.channel),
+            self.vcid,
+            self.apid,
+            self.sequence_id,
+            repr(&self.time),
+            self.packets,
+            repr(&self.complete),
+            self.data.len(),
+        )
+    }
+}
+
+impl GrbPayload {
+    /// Start a payload with its first packet, or the first packet received of it.
+    fn start(channel: &'static str, decoded: &ccsds::DecodedPacket, first: bool) -> Self {
+        let hdr = &decoded.packet.header;
+        let secondary = decoded
+            .packet
+            .data
+            .get(HEADER_LEN..HEADER_LEN + SECONDARY_HEADER_LEN)
+            .filter(|_| first && hdr.has_secondary_header);
+        let field = |f: fn(&[u8]) -> u8| secondary.map(f);
+        let mut payload = Self {
+            channel,
+            vcid: decoded.vcid,
+            apid: hdr.apid,
+            sequence_id: hdr.sequence_id,
+            time: secondary.map(|s| {
+                let days = i64::from(u16::from_be_bytes([s[0], s[1]]));
+                let millis = i64::from(u32::from_be_bytes([s[2], s[3], s[4], s[5]]));
+                J2000_MILLIS + days * MILLIS_PER_DAY + millis
+            }),
+            version: field(|s| s[6] >> 3),
+            payload_variant: field(|s| (s[6] & 0x7) << 2 | s[7] >> 6),
+            assembler_id: field(|s| s[7] >> 4 & 0x3),
+            system_environment: field(|s| s[7] & 0xf),
+            packets: 0,
+            complete: first,
+            data: Vec::default(),
+        };
+        payload.append(decoded);
+        payload
+    }
+
+    fn append(&mut self, decoded: &ccsds::DecodedPacket) {
+        let packet = &decoded.packet;
+        let skip = if packet.header.has_secondary_header {
+            HEADER_LEN + SECONDARY_HEADER_LEN
+        } else {
+            HEADER_LEN
+        };
+        self.data
+            .extend_from_slice(packet.data.get(skip..).unwrap_or_default());
+        self.packets += 1;
+    }
+}
+
+/// A payload being reassembled and the sequence id of its last packet.
+struct Partial {
+    payload: GrbPayload,
+    last_sequence_id: u16,
+}
+
+/// Reassembles the payloads of the packets of both channels.
+#[derive(Default)]
+struct Assembler {
+    partials: BTreeMap<(&'static str, u16), Partial>,
+}
+
+impl Assembler {
+    /// Add a packet, returning any payloads it completes or cuts short.
+    fn add(&mut self, channel: &'static str, decoded: &ccsds::DecodedPacket) -> Vec<GrbPayload> {
+        let hdr = &decoded.packet.header;
+        let key = (channel, hdr.apid);
+        let mut done = Vec::default();
+        let mut partial = match (hdr.sequence_flags, self.partials.remove(&key)) {
+            (SEQ_CONTINUATION | SEQ_LAST, Some(mut partial)) => {
+                let expected = (partial.last_sequence_id + 1) % 16384;
+                partial.payload.complete &= hdr.sequence_id == expected;
+                partial.payload.append(decoded);
+                partial
+            }
+            (flags, previous) => {
+                // a new group, or a group whose first packet was missed
+                if let Some(mut previous) = previous {
+                    previous.payload.complete = false;
+                    done.push(previous.payload);
+                }
+                let first = flags & SEQ_FIRST != 0;
+                Partial {
+                    payload: GrbPayload::start(channel, decoded, first),
+                    last_sequence_id: hdr.sequence_id,
+                }
+            }
+        };
+        partial.last_sequence_id = hdr.sequence_id;
+        if hdr.sequence_flags & SEQ_LAST != 0 {
+            done.push(partial.payload);
+        } else {
+            self.partials.insert(key, partial);
+        }
+        done
+    }
+
+    /// The incomplete payloads of groups whose last packet never arrived.
+    fn finish(&mut self) -> Vec<GrbPayload> {
+        std::mem::take(&mut self.partials)
+            .into_values()
+            .map(|mut partial| {
+                partial.payload.complete = false;
+                partial.payload
+            })
+            .collect()
+    }
+}
+
+/// A packet decoded from a channel, or why decoding the channel failed.
+type ChannelPacket = Result<(&'static str, ccsds::DecodedPacket), String>;
+
+/// Iterator of the [GrbPayload]s decoded by `decode_grb`.
+#[pyclass]
+pub struct GrbPayloadIterator {
+    packets: Pipeline<ChannelPacket>,
+    assembler: Assembler,
+    ready: Vec<GrbPayload>,
+    done: bool,
+}
+
+#[pymethods]
+impl GrbPayloadIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<GrbPayload>> {
+        loop {
+            if !self.ready.is_empty() {
+                return Ok(Some(self.ready.remove(0)));
+            }
+            if self.done {
+                return Ok(None);
+            }
+            match self.packets.wait(py, None)? {
+                Next::Item(Ok((channel, decoded))) => {
+                    self.ready = self.assembler.add(channel, &decoded);
+                }
+                Next::Item(Err(err)) => {
+                    self.done = true;
+                    return Err(PyRuntimeError::new_err(err));
+                }
+                Next::Pending => {}
+                Next::Done => {
+                    self.ready = self.assembler.finish();
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+/// Message of a panic caught by [panic::catch_unwind].
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(msg), _) => msg,
+        (_, Some(msg)) => msg,
+        _ => "unknown error",
+    }
+}
+
+/// Decode the packets of a channel on a dedicated thread, sending them to `tx`. If
+/// decoding panics, e.g., on a corrupt frame, the panic is sent as an error rather than
+/// ending the channel as if its source was exhausted.
+fn spawn_channel(
+    channel: &'static str,
+    source: PathBuf,
+    scid: u16,
+    config: FrameDecodeConfig,
+    vcids: Option<HashSet<u16>>,
+    tx: SyncSender<ChannelPacket>,
+) -> PyResult<()> {
+    let reader = BufReader::new(open_source(&source)?);
+    thread::Builder::new()
+        .name(format!("ccsds-grb-{channel}"))
+        .spawn(move || {
+            let decode = AssertUnwindSafe(|| {
+                let blocks = sync::Synchronizer::new(reader, &config.asm, config.frame_len)
+                    .filter_map(|block| block.ok().map(|(_, block)| block));
+                let mut builder = ccsds::FrameDecoderBuilder::default();
+                if let Some(interleave) = config.interleave {
+                    builder = builder.reed_solomon(interleave);
+                }
+                let frames = builder.build().start(blocks).filter_map(Result::ok);
+                let packets = ccsds::decode_framed_packets(scid, frames, 0, 0).filter(|decoded| {
+                    decoded.packet.header.apid != FILL_APID
+                        && vcids.as_ref().is_none_or(|v| v.contains(&decoded.vcid))
+                });
+                for decoded in packets {
+                    if tx.send(Ok((channel, decoded))).is_err() {
+                        break; // iterator dropped
+                    }
+                }
+            });
+            if let Err(panic) = panic::catch_unwind(decode) {
+                let msg = panic_message(panic.as_ref());
+                let _ = tx.send(Err(format!("decoding the {channel} channel failed: {msg}")));
+            }
+        })?;
+    Ok(())
+}
+
+/// Decode GOES Rebroadcast (GRB) CADU streams into GRB payloads, reassembling the
+/// payloads segmented over groups of packets using the sequence flags.
+///
+/// The right and left hand circular polarization channels are each decoded on a
+/// dedicated thread, so live feeds of both are consumed as the data arrives, and
+/// their payloads are yielded in the order they complete. Payloads of a channel are
+/// reassembled per APID.
+///
+/// Parameters
+/// ----------
+/// scid : int
+///     Spacecraft identifier of the GRB frames.
+///
+/// rhcp : str or os.PathLike, optional
+///     Source of the CADUs of the right hand circular polarization channel, as for
+///     `decode_framed_packets`.
+///
+/// lhcp : str or os.PathLike, optional
+///     Source of the CADUs of the left hand circular polarization channel.
+///
+/// cadu_len : int
+///     The length of each CADU, including the ASM.
+///
+/// vcids : Iterable[int], optional
+///     Only decode the packets of these virtual channels, e.g., those of a single
+///     instrument. Packets of all virtual channels are decoded if not provided.
+///
+/// interleave : int, optional
+///     Reed-Solomon interleave of the CADUs, if any. GRB frames are protected by the
+///     DVB-S2 coding of the link rather than Reed-Solomon, so there is none by default.
+///
+/// asm : bytes, optional
+///     Attached sync marker. Defaults to the standard CCSDS ASM.
+///
+/// Returns
+/// -------
+/// GrbPayloadIterator
+///     An iterable of GrbPayloads. Payloads whose first or last packet or any packet
+///     between them was lost are yielded with `complete` false.
+///
+/// Raises
+/// ------
+/// RuntimeError
+///     From the iterator if decoding a channel fails, e.g., on a frame whose first
+///     header pointer is beyond the end of its data due to an incorrect `cadu_len`.
+#[pyfunction(signature=(scid, rhcp=None, lhcp=None, cadu_len=GRB_CADU_LEN, vcids=None, interleave=None, asm=None))]
+pub fn decode_grb(
+    scid: u16,
+    rhcp: Option<PathBuf>,
+    lhcp: Option<PathBuf>,
+    cadu_len: i64,
+    vcids: Option<&PyAny>,
+    interleave: Option<&PyAny>,
+    asm: Option<&PyAny>,
+) -> PyResult<GrbPayloadIterator> {
+    let vcids: Option<HashSet<u16>> = crate::set_arg(vcids)?;
+    if scid > 0xff {
+        return Err(PyValueError::new_err(format!(
+            "invalid scid; expected 0..=255, got {scid}"
+        )));
+    }
+    let channels: Vec<_> = [("rhcp", rhcp), ("lhcp", lhcp)]
+        .into_iter()
+        .filter_map(|(channel, source)| source.map(|source| (channel, source)))
+        .collect();
+    if channels.is_empty() {
+        return Err(PyValueError::new_err(
+            "at least one of rhcp or lhcp is required",
+        ));
+    }
+    let (tx, rx) = sync_channel(DEFAULT_PREFETCH);
+    for (channel, source) in channels {
+        let config = FrameDecodeConfig::from_cadu_len(&source, cadu_len, interleave, asm)?;
+        spawn_channel(channel, source, scid, config, vcids.clone(), tx.clone())?;
+    }
+    Ok(GrbPayloadIterator {
+        packets: Pipeline::queued(rx),
+        assembler: Assembler::default(),
+        ready: Vec::default(),
+        done: false,
+    })
+}
@@ -0,0 +1,150 @@
+//! Streaming grouping of packets into lists.
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyIterator};
+
+use crate::{
+    timecode::{registry_or_cds, TimecodeRegistry},
+    DecodedPacket, Packet,
+};
+
+enum Grouping {
+    /// Runs of consecutive packets with the same APID.
+    Apid,
+    /// Packets whose time falls in the same window of `millis` since the epoch.
+    Window {
+        millis: i64,
+        timecode: TimecodeRegistry,
+    },
+}
+
+/// Iterator yielding lists of consecutive packets that belong to the same group.
+#[pyclass]
+pub struct PacketGroups {
+    iter: Py<PyIterator>,
+    grouping: Grouping,
+    /// First item of the next group, and its key.
+    pending: Option<(Option<i64>, PyObject)>,
+}
+
+impl PacketGroups {
+    fn new(packets: &PyAny, grouping: Grouping) -> PyResult<Self> {
+        Ok(Self {
+            iter: packets.iter()?.into(),
+            grouping,
+            pending: None,
+        })
+    }
+
+    /// Group key of an item, which may be a Packet or DecodedPacket. For time windows
+    /// the key is `None` if the packet time cannot be decoded.
+    fn key(&self, item: &PyAny) -> PyResult<Option<i64>> {
+        if let Ok(decoded) = item.extract::<PyRef<DecodedPacket>>() {
+            return Ok(self.packet_key(&decoded.packet));
+        }
+        let packet: PyRef<Packet> = item.extract()?;
+        Ok(self.packet_key(&packet))
+    }
+
+    fn packet_key(&self, packet: &Packet) -> Option<i64> {
+        match &self.grouping {
+            Grouping::Apid => Some(i64::from(packet.header.apid)),
+            Grouping::Window { .. } if !packet.header.has_secondary_header => None,
+            Grouping::Window { millis, timecode } => timecode
+                .decode(packet.header.apid, packet.payload())
+                .map(|time| time.div_euclid(*millis)),
+        }
+    }
+}
+
+#[pymethods]
+impl PacketGroups {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<Vec<PyObject>>> {
+        let mut group = Vec::default();
+        let mut key = None;
+        if let Some((pending_key, item)) = self.pending.take() {
+            key = pending_key;
+            group.push(item);
+        }
+        for item in self.iter.as_ref(py) {
+            let item = item?;
+            let item_key = self.key(item)?;
+            // packets without a time stay in the current window
+            let same = match (key, item_key) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            };
+            if group.is_empty() || same {
+                key = key.or(item_key);
+                group.push(item.into_py(py));
+                continue;
+            }
+            self.pending = Some((item_key, item.into_py(py)));
+            return Ok(Some(group));
+        }
+        Ok(if group.is_empty() { None } else { Some(group) })
+    }
+}
+
+/// Group runs of consecutive packets having the same APID.
+///
+/// Parameters
+/// ----------
+/// packets : Iterable[Packet] or Iterable[DecodedPacket]
+///     Packets to group, e.g., from `decode_packets`.
+///
+/// Returns
+/// -------
+/// Iterator of list
+///     A list of packets for each run of packets with the same APID, in stream order.
+#[pyfunction]
+pub fn group_packets_by_apid(packets: &PyAny) -> PyResult<PacketGroups> {
+    PacketGroups::new(packets, Grouping::Apid)
+}
+
+/// Group consecutive packets whose times fall in the same fixed length window.
+///
+/// Windows are aligned to multiples of `seconds` since the Unix epoch. Packet times are
+/// decoded from the timecode at the start of the secondary header; packets whose time
+/// cannot be decoded are kept in the current window.
+///
+/// Parameters
+/// ----------
+/// packets : Iterable[Packet] or Iterable[DecodedPacket]
+///     Packets to group, e.g., from `decode_packets`.
+///
+/// seconds : float
+///     Window length in seconds.
+///
+/// timecode : str or TimecodeRegistry
+///     Timecode format at the start of the secondary header, either `cds` or `eoscuc`,
+///     or the timecode of each APID. Defaults to `cds`.
+///
+/// Returns
+/// -------
+/// Iterator of list
+///     A list of packets for each window, in stream order. A window appears more than
+///     once if packets are not in time order.
+#[pyfunction(signature=(packets, seconds, timecode=None))]
+pub fn window_packets_by_time(
+    packets: &PyAny,
+    seconds: f64,
+    timecode: Option<&PyAny>,
+) -> PyResult<PacketGroups> {
+    let millis = (seconds * 1000.0).round();
+    if !(millis >= 1.0 && millis.is_finite()) {
+        return Err(PyValueError::new_err(format!(
+            "invalid window; expected seconds >= 0.001, got {seconds}"
+        )));
+    }
+    let timecode = registry_or_cds(timecode)?;
+    PacketGroups::new(
+        packets,
+        Grouping::Window {
+            millis: millis as i64,
+            timecode,
+        },
+    )
+}
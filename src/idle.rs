@@ -0,0 +1,309 @@
+//! Accounting of the fill and overhead bytes in a CADU stream.
+use std::{collections::HashMap, io::BufReader, path::PathBuf};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    packets::HEADER_LEN, services::VCDU_HEADER_LEN, source::open_source, summary::FrameCounters,
+    sync,
+};
+
+/// VCID reserved for fill frames.
+pub const FILL_VCID: u16 = 63;
+/// APID reserved for fill packets.
+pub const FILL_APID: u16 = 0x7ff;
+/// Length of the M_PDU header containing the first header pointer.
+pub const MPDU_HEADER_LEN: usize = 2;
+/// First header pointer of an M_PDU packet zone that contains only idle data.
+pub const FHP_IDLE: usize = 0x7fe;
+/// First header pointer of an M_PDU packet zone that does not contain a packet header.
+pub const FHP_NONE: usize = 0x7ff;
+
+/// How the bytes of a CADU stream are used. Each byte of the stream is counted in
+/// exactly one of the `*_bytes` totals.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default)]
+pub struct IdleAccounting {
+    /// Number of frames.
+    #[pyo3(get)]
+    pub frames: u64,
+    /// Number of fill frames, i.e., frames on VCID 63.
+    #[pyo3(get)]
+    pub fill_frames: u64,
+    /// Number of packets, excluding fill packets, that start in the stream.
+    #[pyo3(get)]
+    pub packets: u64,
+    /// Number of fill packets, i.e., packets with APID 2047, that start in the stream.
+    #[pyo3(get)]
+    pub fill_packets: u64,
+    /// Bytes of the CADUs in the stream.
+    #[pyo3(get)]
+    pub total_bytes: u64,
+    /// Bytes of packets other than fill packets, including their primary headers.
+    #[pyo3(get)]
+    pub packet_bytes: u64,
+    /// Bytes of fill packets.
+    #[pyo3(get)]
+    pub fill_packet_bytes: u64,
+    /// Bytes of fill frames, including their ASM and parity.
+    #[pyo3(get)]
+    pub fill_frame_bytes: u64,
+    /// Bytes of packet zones containing only idle data.
+    #[pyo3(get)]
+    pub pad_bytes: u64,
+    /// Bytes of the ASM, VCDU and M_PDU headers, insert zone, trailer, and any
+    /// Reed-Solomon parity of frames other than fill frames.
+    #[pyo3(get)]
+    pub overhead_bytes: u64,
+    /// Bytes of packet zones that could not be attributed to a packet, e.g., because
+    /// of missing frames or uncorrectable frames.
+    #[pyo3(get)]
+    pub unknown_bytes: u64,
+}
+
+#[pymethods]
+impl IdleAccounting {
+    /// Downlink efficiency, the ratio of `packet_bytes` to `total_bytes`, or 0 for
+    /// an empty stream.
+    #[getter]
+    fn efficiency(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
+        self.packet_bytes as f64 / self.total_bytes as f64
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "IdleAccounting(frames={}, fill_frames={}, packets={}, fill_packets={}, total_bytes={}, packet_bytes={}, fill_packet_bytes={}, fill_frame_bytes={}, pad_bytes={}, overhead_bytes={}, unknown_bytes={}, efficiency={:.4})",
+            self.frames, self.fill_frames, self.packets, self.fill_packets, self.total_bytes, self.packet_bytes, self.fill_packet_bytes, self.fill_frame_bytes, self.pad_bytes, self.overhead_bytes, self.unknown_bytes, self.efficiency(),
+        )
+    }
+}
+
+/// Progress through the packet spanning the end of the previous packet zone of a VCID.
+enum Spanning {
+    /// The primary header bytes seen so far.
+    Header(Vec<u8>),
+    /// Bytes remaining in a packet whose header is known.
+    Body { fill: bool, remaining: usize },
+}
+
+impl IdleAccounting {
+    fn add_packet_bytes(&mut self, fill: bool, len: usize) {
+        if fill {
+            self.fill_packet_bytes += len as u64;
+        } else {
+            self.packet_bytes += len as u64;
+        }
+    }
+
+    /// Count the bytes of a spanning packet header that will not be completed, e.g.,
+    /// because of a missing frame. Bytes of spanning packet bodies are already counted.
+    fn drop_spanning(&mut self, spanning: Option<Spanning>) {
+        if let Some(Spanning::Header(header)) = spanning {
+            self.unknown_bytes += header.len() as u64;
+        }
+    }
+
+    /// Account for the packets starting at `zone[pos..]`, returning the packet that
+    /// spans the end of the zone, if any.
+    fn add_packets(&mut self, zone: &[u8], mut pos: usize) -> Option<Spanning> {
+        while pos < zone.len() {
+            let avail = &zone[pos..];
+            if avail.len() < HEADER_LEN {
+                return Some(Spanning::Header(avail.to_vec()));
+            }
+            let (fill, total) = self.start_packet(avail);
+            if avail.len() < total {
+                self.add_packet_bytes(fill, avail.len());
+                return Some(Spanning::Body {
+                    fill,
+                    remaining: total - avail.len(),
+                });
+            }
+            self.add_packet_bytes(fill, total);
+            pos += total;
+        }
+        None
+    }
+
+    /// Count the packet whose primary header starts `dat`, returning whether it is a
+    /// fill packet and its total length.
+    fn start_packet(&mut self, dat: &[u8]) -> (bool, usize) {
+        let apid = u16::from(dat[0] & 0x7) << 8 | u16::from(dat[1]);
+        let len_minus1 = usize::from(u16::from_be_bytes([dat[4], dat[5]]));
+        let fill = apid == FILL_APID;
+        if fill {
+            self.fill_packets += 1;
+        } else {
+            self.packets += 1;
+        }
+        (fill, HEADER_LEN + len_minus1 + 1)
+    }
+
+    /// Account for the M_PDU packet zone of a frame, i.e., the bytes following the
+    /// M_PDU header, continuing the packet `spanning` the previous zone of its VCID.
+    fn add_zone(
+        &mut self,
+        spanning: Option<Spanning>,
+        fhp: usize,
+        zone: &[u8],
+    ) -> Option<Spanning> {
+        if fhp == FHP_IDLE {
+            self.drop_spanning(spanning);
+            self.pad_bytes += zone.len() as u64;
+            return None;
+        }
+        if fhp != FHP_NONE && fhp > zone.len() {
+            self.drop_spanning(spanning);
+            self.unknown_bytes += zone.len() as u64;
+            return None;
+        }
+        // bytes before the first header, or the whole zone if there is none
+        let limit = if fhp == FHP_NONE { zone.len() } else { fhp };
+        let mut pos = 0;
+        match spanning {
+            Some(Spanning::Header(mut header)) => {
+                let n = (HEADER_LEN - header.len()).min(limit);
+                header.extend_from_slice(&zone[..n]);
+                pos = n;
+                if header.len() == HEADER_LEN {
+                    let (fill, total) = self.start_packet(&header);
+                    let n = (total - HEADER_LEN).min(limit - pos);
+                    self.add_packet_bytes(fill, HEADER_LEN + n);
+                    pos += n;
+                    if fhp == FHP_NONE && pos == zone.len() && HEADER_LEN + n < total {
+                        let remaining = total - HEADER_LEN - n;
+                        return Some(Spanning::Body { fill, remaining });
+                    }
+                } else if fhp == FHP_NONE {
+                    return Some(Spanning::Header(header));
+                } else {
+                    // cut short by the next header
+                    self.drop_spanning(Some(Spanning::Header(header)));
+                }
+            }
+            Some(Spanning::Body { fill, remaining }) => {
+                let n = remaining.min(limit);
+                self.add_packet_bytes(fill, n);
+                pos = n;
+                if fhp == FHP_NONE && n < remaining {
+                    let remaining = remaining - n;
+                    return Some(Spanning::Body { fill, remaining });
+                }
+            }
+            None => (),
+        }
+        self.unknown_bytes += (limit - pos) as u64;
+        if fhp == FHP_NONE {
+            return None;
+        }
+        self.add_packets(zone, fhp)
+    }
+}
+
+/// Account for how the bytes of a CADU stream are used, separating packet data from
+/// fill frames, fill packets, idle data, and framing overhead, to compute the downlink
+/// efficiency of a pass.
+///
+/// Frames are decoded as by `decode_framed_packets`, and all virtual channels other
+/// than VCID 63 are assumed to carry packets.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of CADUs.
+///
+/// cadu_len : int
+///     The length of the CADU, i.e., the ASM length plus the length of the frame plus the
+///     length of any integrity or parity bytes.
+///
+/// izone_len : int
+///     Frame insert-zone number of bytes used by the spacecraft, if any.
+///
+/// trailer_len : int
+///     Frame trailer number of bytes used by the spacecraft, if any.
+///
+/// interleave : int or str, optional
+///     The Reed-Solomon interleave, or `auto`, as accepted by `decode_frames`.
+///
+/// asm : bytes or str, optional
+///     Attached sync marker, as accepted by `decode_frames`. Defaults to the standard
+///     CCSDS ASM.
+///
+/// Returns
+/// -------
+/// IdleAccounting
+#[pyfunction(signature=(source, cadu_len, izone_len=0, trailer_len=0, interleave=None, asm=None))]
+pub fn idle_accounting(
+    source: PathBuf,
+    cadu_len: usize,
+    izone_len: usize,
+    trailer_len: usize,
+    interleave: Option<&PyAny>,
+    asm: Option<&PyAny>,
+) -> PyResult<IdleAccounting> {
+    let asm = sync::asm_arg(&source, asm)?;
+    if izone_len >= 16 || trailer_len >= 16 {
+        return Err(PyValueError::new_err(format!(
+            "invalid izone_len or trailer_len; expected 0..16, got {izone_len} and {trailer_len}"
+        )));
+    }
+    let zone_start = VCDU_HEADER_LEN + izone_len + MPDU_HEADER_LEN;
+    if cadu_len < asm.len() + zone_start + trailer_len {
+        return Err(PyValueError::new_err(format!(
+            "cadu_len too short for the ASM and frame headers, got {cadu_len}"
+        )));
+    }
+    let block_len = cadu_len - asm.len();
+    let interleave = sync::interleave_arg(&source, &asm, block_len, interleave)?;
+    let blocks = sync::Synchronizer::new(BufReader::new(open_source(&source)?), &asm, block_len)
+        .filter_map(|block| block.ok().map(|(_, block)| block));
+    let mut builder = ccsds::FrameDecoderBuilder::default();
+    if let Some(interleave) = interleave {
+        builder = builder.reed_solomon(interleave);
+    }
+
+    let mut acct = IdleAccounting::default();
+    let mut counters = FrameCounters::default();
+    let mut spanning: HashMap<u16, Spanning> = HashMap::default();
+    for decoded in builder.build().start(blocks).filter_map(Result::ok) {
+        let frame = decoded.frame;
+        let vcid = frame.header.vcid;
+        acct.frames += 1;
+        acct.total_bytes += cadu_len as u64;
+        if vcid == FILL_VCID {
+            acct.fill_frames += 1;
+            acct.fill_frame_bytes += cadu_len as u64;
+            continue;
+        }
+        let missing = counters.add(vcid, frame.header.counter);
+        let mut cur = spanning.remove(&vcid);
+        if missing > 0 || matches!(decoded.rsstate, ccsds::RSState::Uncorrectable(_)) {
+            acct.drop_spanning(cur.take());
+        }
+        if frame.data.len() < zone_start + trailer_len {
+            acct.unknown_bytes += cadu_len as u64;
+            continue;
+        }
+        let zone = &frame.data[zone_start..frame.data.len() - trailer_len];
+        acct.overhead_bytes += (cadu_len - zone.len()) as u64;
+        if matches!(decoded.rsstate, ccsds::RSState::Uncorrectable(_)) {
+            acct.unknown_bytes += zone.len() as u64;
+            continue;
+        }
+        let mpdu = &frame.data[zone_start - MPDU_HEADER_LEN..];
+        let fhp = usize::from(u16::from_be_bytes([mpdu[0], mpdu[1]]) & 0x7ff);
+        if let Some(next) = acct.add_zone(cur, fhp, zone) {
+            spanning.insert(vcid, next);
+        }
+    }
+    for (_, cur) in spanning.drain() {
+        acct.drop_spanning(Some(cur));
+    }
+    Ok(acct)
+}
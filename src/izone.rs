@@ -0,0 +1,172 @@
+//! Validation of checksums some missions use to protect the frame insert zone.
+use std::collections::HashMap;
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    anomaly::{self, Anomaly, AnomalyCollector, Severity},
+    packets::ViolationPolicy,
+    services::VCDU_HEADER_LEN,
+};
+
+/// Checksum algorithms for the insert zone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Checksum {
+    /// CRC-16-CCITT with an initial value of 0xFFFF, as used for the frame error
+    /// control field.
+    Crc16,
+    /// CRC-32 as used by Ethernet and zlib.
+    Crc32,
+}
+
+impl Checksum {
+    /// Lookup an algorithm by the name used in the Python API.
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_lowercase().as_str() {
+            "crc16" => Ok(Self::Crc16),
+            "crc32" => Ok(Self::Crc32),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported insert zone checksum; expected one of crc16, crc32, got {name}"
+            ))),
+        }
+    }
+
+    /// Number of bytes of the checksum.
+    fn len(&self) -> usize {
+        match self {
+            Self::Crc16 => 2,
+            Self::Crc32 => 4,
+        }
+    }
+
+    fn compute(&self, dat: &[u8]) -> u32 {
+        match self {
+            Self::Crc16 => {
+                let mut crc: u16 = 0xFFFF;
+                for byte in dat {
+                    crc ^= u16::from(*byte) << 8;
+                    for _ in 0..8 {
+                        crc = if crc & 0x8000 != 0 {
+                            crc << 1 ^ 0x1021
+                        } else {
+                            crc << 1
+                        };
+                    }
+                }
+                u32::from(crc)
+            }
+            Self::Crc32 => {
+                let mut crc: u32 = 0xFFFF_FFFF;
+                for byte in dat {
+                    crc ^= u32::from(*byte);
+                    for _ in 0..8 {
+                        crc = if crc & 1 != 0 {
+                            crc >> 1 ^ 0xEDB8_8320
+                        } else {
+                            crc >> 1
+                        };
+                    }
+                }
+                !crc
+            }
+        }
+    }
+}
+
+/// An insert zone of `izone_len` bytes ending with a big-endian checksum of the bytes
+/// preceding it in the insert zone.
+#[derive(Clone, Copy, Debug)]
+pub struct IzoneCrc {
+    checksum: Checksum,
+    izone_len: usize,
+}
+
+impl IzoneCrc {
+    /// Whether the checksum in the insert zone of a frame matches, or `None` if the
+    /// frame is too short to contain the insert zone.
+    fn verify(&self, frame_data: &[u8]) -> Option<bool> {
+        let izone = frame_data.get(VCDU_HEADER_LEN..VCDU_HEADER_LEN + self.izone_len)?;
+        let (dat, crc) = izone.split_at(self.izone_len - self.checksum.len());
+        let expected = crc.iter().fold(0u32, |acc, b| acc << 8 | u32::from(*b));
+        Some(self.checksum.compute(dat) == expected)
+    }
+}
+
+/// Checks the insert zone checksum of frames of the spacecraft configured with one.
+#[derive(Clone, Debug, Default)]
+pub struct IzoneCrcCheck {
+    crcs: HashMap<u16, IzoneCrc>,
+    policy: ViolationPolicy,
+    pub anomalies: Option<AnomalyCollector>,
+}
+
+impl IzoneCrcCheck {
+    /// Create a check from the `izone_crcs` and `izone_crc_policy` arguments of
+    /// `decode_frames`.
+    pub fn from_args(crcs: Option<HashMap<u16, (&str, usize)>>, policy: &str) -> PyResult<Self> {
+        let crcs = crcs
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(scid, (name, izone_len))| {
+                let checksum = Checksum::from_name(name)?;
+                if izone_len <= checksum.len() {
+                    return Err(PyValueError::new_err(format!(
+                        "invalid insert zone length for scid {scid}; expected > {}, got {izone_len}",
+                        checksum.len()
+                    )));
+                }
+                Ok((
+                    scid,
+                    IzoneCrc {
+                        checksum,
+                        izone_len,
+                    },
+                ))
+            })
+            .collect::<PyResult<_>>()?;
+        Ok(Self {
+            crcs,
+            policy: ViolationPolicy::from_name(policy, "insert zone checksum policy")?,
+            anomalies: None,
+        })
+    }
+
+    /// Check the insert zone of a frame, returning whether the checksum matched, or
+    /// `None` if no checksum is configured for the spacecraft, or an error if it did not
+    /// match and the policy is to raise.
+    pub fn check(
+        &self,
+        scid: u16,
+        vcid: u16,
+        frame_data: &[u8],
+        offset: Option<u64>,
+    ) -> PyResult<Option<bool>> {
+        let Some(crc) = self.crcs.get(&scid) else {
+            return Ok(None);
+        };
+        let valid = crc.verify(frame_data).unwrap_or(false);
+        if valid {
+            return Ok(Some(true));
+        }
+        if self.policy == ViolationPolicy::Raise {
+            return Err(PyValueError::new_err(format!(
+                "insert zone checksum mismatch for scid {scid}"
+            )));
+        }
+        anomaly::emit(&self.anomalies, || {
+            Anomaly::new(
+                "izone_crc",
+                Severity::Warning,
+                "insert zone checksum mismatch".to_string(),
+            )
+            .frame(scid, vcid)
+            .offset(offset)
+        });
+        Ok(Some(false))
+    }
+
+    /// Whether a frame with the result of [IzoneCrcCheck::check] should be yielded.
+    pub fn keep(&self, valid: Option<bool>) -> bool {
+        valid != Some(false) || self.policy != ViolationPolicy::Drop
+    }
+}
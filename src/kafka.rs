@@ -0,0 +1,217 @@
+//! Kafka integration.
+//!
+//! Kafka clients are provided by the caller, e.g., a confluent-kafka or kafka-python
+//! consumer or producer, so no Kafka client library is required to use this package.
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{IntoPyDict, PyBytes, PyIterator},
+};
+
+use crate::{
+    message::{with_packet, Serialization},
+    throttle::{self, Throttle},
+    Frame,
+};
+
+/// Iterator of frames decoded from Kafka messages that each contain a single CADU.
+#[pyclass]
+pub struct MessageFrameIterator {
+    /// Either an iterator of messages, or a consumer to poll for messages.
+    messages: Messages,
+    asm: Vec<u8>,
+    interleave: Option<u8>,
+    /// Number of messages that could not be decoded as a frame.
+    #[pyo3(get)]
+    errors: u64,
+}
+
+enum Messages {
+    Iter(Py<PyIterator>),
+    Poll { consumer: PyObject, timeout: f64 },
+}
+
+impl MessageFrameIterator {
+    /// The next message, or `None` if the messages are exhausted.
+    fn next_message<'py>(&self, py: Python<'py>) -> PyResult<Option<&'py PyAny>> {
+        match &self.messages {
+            Messages::Iter(iter) => iter.clone_ref(py).into_ref(py).next().transpose(),
+            Messages::Poll { consumer, timeout } => loop {
+                let message = consumer.call_method1(py, "poll", (*timeout,))?;
+                if !message.is_none(py) {
+                    return Ok(Some(message.into_ref(py)));
+                }
+                py.check_signals()?;
+            },
+        }
+    }
+}
+
+/// The value of a message, or `None` for confluent-kafka messages that carry an error.
+fn message_value(message: &PyAny) -> PyResult<Option<Vec<u8>>> {
+    if let Ok(value) = message.extract::<&[u8]>() {
+        return Ok(Some(value.to_vec()));
+    }
+    if message.hasattr("error")? && !message.call_method0("error")?.is_none() {
+        return Ok(None);
+    }
+    let value = message.getattr("value")?;
+    let value = if value.is_callable() {
+        value.call0()?
+    } else {
+        value
+    };
+    if value.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(value.extract()?))
+}
+
+#[pymethods]
+impl MessageFrameIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<Frame>> {
+        while let Some(message) = self.next_message(py)? {
+            let Some(mut cadu) = message_value(message)? else {
+                self.errors += 1;
+                continue;
+            };
+            if cadu.starts_with(&self.asm) {
+                cadu.drain(..self.asm.len());
+            }
+            match Frame::from_block(cadu, self.interleave) {
+                Some(frame) => return Ok(Some(frame)),
+                None => self.errors += 1,
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Decode frames from Kafka messages that each contain a single pseudo-randomized CADU,
+/// with or without its attached sync marker.
+///
+/// Parameters
+/// ----------
+/// consumer : Iterable or Consumer
+///     Either an iterable of messages, e.g., a kafka-python `KafkaConsumer`, or a
+///     confluent-kafka `Consumer`, which is polled until interrupted. Messages may be
+///     bytes or message objects whose `value` is the CADU. confluent-kafka messages
+///     carrying an error are counted in the iterator's `errors` and skipped.
+///
+/// interleave : int, optional
+///     The Reed-Solomon interleave. If not provided the frames are assumed to not
+///     include Reed-Solomon parity.
+///
+/// asm : bytes, optional
+///     Attached sync marker removed from the start of messages that begin with it.
+///     Defaults to the standard CCSDS ASM.
+///
+/// poll_timeout : float
+///     Number of seconds each poll of a confluent-kafka consumer waits for a message.
+///
+/// Returns
+/// -------
+/// MessageFrameIterator
+///     An iterable providing a Frame for each message that could be decoded.
+#[pyfunction(signature=(consumer, interleave=None, asm=None, poll_timeout=1.0))]
+pub fn decode_kafka_frames(
+    consumer: &PyAny,
+    interleave: Option<u8>,
+    asm: Option<Vec<u8>>,
+    poll_timeout: f64,
+) -> PyResult<MessageFrameIterator> {
+    if let Some(interleave) = interleave.filter(|i| !(2..=10).contains(i)) {
+        return Err(PyValueError::new_err(format!(
+            "improbable interleave value; expected 2..10: got {interleave}"
+        )));
+    }
+    let messages = match consumer.iter() {
+        Ok(iter) => Messages::Iter(iter.into()),
+        Err(_) if consumer.hasattr("poll")? => Messages::Poll {
+            consumer: consumer.into(),
+            timeout: poll_timeout,
+        },
+        Err(err) => return Err(err),
+    };
+    Ok(MessageFrameIterator {
+        messages,
+        asm: asm.unwrap_or_else(|| ccsds::ASM.to_vec()),
+        interleave,
+        errors: 0,
+    })
+}
+
+/// Publish packets to a Kafka topic, keyed by APID.
+///
+/// Parameters
+/// ----------
+/// packets : Iterable[Packet] or Iterable[DecodedPacket]
+///     Packets to publish, e.g., from `decode_framed_packets`.
+///
+/// producer : Producer
+///     Either a confluent-kafka `Producer` or a kafka-python `KafkaProducer`.
+///
+/// topic : str
+///     Topic to publish to.
+///
+/// serialization : str
+///     Either `raw` for the packet bytes, or `json` for a JSON object of the header
+///     fields, the spacecraft and virtual channel ids of DecodedPackets, and the packet
+///     data following the header as a hex string.
+///
+/// flush : bool
+///     Flush the producer once all packets are published.
+///
+/// rate : float or str, optional
+///     Pace publishing to this many bits per second, or `realtime` to replay at the rate
+///     given by the packet CDS times, or `realtime:eoscuc` for EOS CUC times. Packets
+///     are published as fast as possible if not provided.
+///
+/// Returns
+/// -------
+/// int
+///     The number of packets published.
+#[pyfunction(signature=(packets, producer, topic, serialization="raw", flush=true, rate=None))]
+pub fn publish_kafka_packets(
+    packets: &PyAny,
+    producer: &PyAny,
+    topic: &str,
+    serialization: &str,
+    flush: bool,
+    rate: Option<&PyAny>,
+) -> PyResult<usize> {
+    let py = packets.py();
+    let serialization = Serialization::from_name(serialization)?;
+    let mut throttle = Throttle::from_arg(rate)?;
+    // confluent-kafka produces and must be polled to serve delivery callbacks;
+    // kafka-python sends
+    let confluent = producer.hasattr("produce")?;
+    let mut count = 0;
+    for item in packets.iter()? {
+        let (apid, value, delay) = with_packet(item?, |p| {
+            let delay = throttle.as_mut().and_then(|t| t.delay(p.packet));
+            (p.packet.header.apid, p.encode(serialization), delay)
+        })?;
+        throttle::sleep(py, delay)?;
+        let kwargs = [
+            ("value", PyBytes::new(py, &value)),
+            ("key", PyBytes::new(py, apid.to_string().as_bytes())),
+        ]
+        .into_py_dict(py);
+        if confluent {
+            producer.call_method("produce", (topic,), Some(kwargs))?;
+            producer.call_method1("poll", (0,))?;
+        } else {
+            producer.call_method("send", (topic,), Some(kwargs))?;
+        }
+        count += 1;
+    }
+    if flush {
+        producer.call_method0("flush")?;
+    }
+    Ok(count)
+}
@@ -0,0 +1,983 @@
+//! Packet layouts describing named fields to extract from packet data.
+// pyo3 0.19 generates constructor text signatures as impls nested in a function
+#![allow(non_local_definitions)]
+
+use std::{collections::BTreeMap, fmt};
+
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyDict, PyList},
+};
+
+use crate::{
+    alarm::{AlarmLimits, Exceeded, Violation},
+    derived::{Derived, Num},
+    fields,
+    repr::{repr, PyRepr},
+    timecode::{optional_registry_arg, TimecodeRegistry},
+    Packet,
+};
+
+/// How the raw bits of a field are interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Uint,
+    Int,
+    Float,
+    Mil1750a,
+}
+
+impl Kind {
+    fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_lowercase().as_str() {
+            "uint" => Ok(Self::Uint),
+            "int" => Ok(Self::Int),
+            "float" => Ok(Self::Float),
+            "mil1750a" => Ok(Self::Mil1750a),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported kind; expected one of uint, int, float, mil1750a, got {name}"
+            ))),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Uint => "uint",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Mil1750a => "mil1750a",
+        }
+    }
+}
+
+/// Order of the bytes of a multi-byte field.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ByteOrder {
+    #[default]
+    Big,
+    Little,
+}
+
+impl ByteOrder {
+    fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_lowercase().as_str() {
+            "big" => Ok(Self::Big),
+            "little" => Ok(Self::Little),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported byte_order; expected one of big, little, got {name}"
+            ))),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Big => "big",
+            Self::Little => "little",
+        }
+    }
+}
+
+/// A raw field value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Uint(u64),
+    Int(i64),
+    Float(f64),
+}
+
+impl Value {
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Self::Uint(v) => *v as f64,
+            Self::Int(v) => *v as f64,
+            Self::Float(v) => *v,
+        }
+    }
+}
+
+impl IntoPy<PyObject> for Value {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        match self {
+            Self::Uint(v) => v.into_py(py),
+            Self::Int(v) => v.into_py(py),
+            Self::Float(v) => v.into_py(py),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum CalibrationKind {
+    /// Coefficients, lowest order first.
+    Polynomial(Vec<f64>),
+    /// (raw, engineering) points sorted by raw value.
+    PointPair(Vec<(f64, f64)>),
+}
+
+/// Conversion of a raw field value into engineering units.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct Calibration {
+    kind: CalibrationKind,
+}
+
+#[pymethods]
+impl Calibration {
+    /// Polynomial calibration, `c[0] + c[1] * x + c[2] * x**2 + ...`.
+    #[staticmethod]
+    pub fn polynomial(coefficients: Vec<f64>) -> PyResult<Self> {
+        if coefficients.is_empty() {
+            return Err(PyValueError::new_err(
+                "polynomial requires at least one coefficient",
+            ));
+        }
+        Ok(Self {
+            kind: CalibrationKind::Polynomial(coefficients),
+        })
+    }
+
+    /// Point-pair calibration using linear interpolation between (raw, engineering)
+    /// points. Values outside the points are extrapolated from the nearest segment.
+    #[staticmethod]
+    pub fn point_pair(mut points: Vec<(f64, f64)>) -> PyResult<Self> {
+        if points.len() < 2 {
+            return Err(PyValueError::new_err(
+                "point_pair requires at least two points",
+            ));
+        }
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if points.windows(2).any(|w| w[0].0 == w[1].0) {
+            return Err(PyValueError::new_err(
+                "point_pair raw values must be unique",
+            ));
+        }
+        Ok(Self {
+            kind: CalibrationKind::PointPair(points),
+        })
+    }
+
+    /// Apply the calibration to a raw value.
+    pub fn apply(&self, raw: f64) -> f64 {
+        match &self.kind {
+            CalibrationKind::Polynomial(coefs) => {
+                coefs.iter().rev().fold(0.0, |acc, c| acc * raw + c)
+            }
+            CalibrationKind::PointPair(points) => {
+                let idx = points
+                    .partition_point(|(x, _)| *x <= raw)
+                    .clamp(1, points.len() - 1);
+                let ((x0, y0), (x1, y1)) = (points[idx - 1], points[idx]);
+                y0 + (raw - x0) * (y1 - y0) / (x1 - x0)
+            }
+        }
+    }
+
+    /// The raw value that calibrates to `value`, or `None` if the calibration is not
+    /// invertible, i.e., it is not a linear polynomial or point pairs with strictly
+    /// monotonic engineering values.
+    pub fn invert(&self, value: f64) -> Option<f64> {
+        match &self.kind {
+            CalibrationKind::Polynomial(coefs) => match coefs[..] {
+                [c0, c1] if c1 != 0.0 => Some((value - c0) / c1),
+                _ => None,
+            },
+            CalibrationKind::PointPair(points) => {
+                let mut inverse: Vec<(f64, f64)> = points.iter().map(|(x, y)| (*y, *x)).collect();
+                let increasing = inverse.windows(2).all(|w| w[0].0 < w[1].0);
+                let decreasing = inverse.windows(2).all(|w| w[0].0 > w[1].0);
+                if decreasing {
+                    inverse.reverse();
+                } else if !increasing {
+                    return None;
+                }
+                let cal = Calibration {
+                    kind: CalibrationKind::PointPair(inverse),
+                };
+                Some(cal.apply(value))
+            }
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        match &self.kind {
+            CalibrationKind::Polynomial(coefs) => {
+                format!("Calibration.polynomial({})", repr(coefs))
+            }
+            CalibrationKind::PointPair(points) => {
+                format!("Calibration.point_pair({})", repr(points))
+            }
+        }
+    }
+}
+
+/// A named field within packet data.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct Field {
+    #[pyo3(get)]
+    pub name: String,
+    /// Offset of the field in bits from the start of the packet data, i.e., following
+    /// the primary header.
+    #[pyo3(get)]
+    pub bit_offset: usize,
+    #[pyo3(get)]
+    pub bit_len: u32,
+    kind: Kind,
+    #[pyo3(get)]
+    pub calibration: Option<Calibration>,
+    /// (raw, label) pairs for enumerated fields, in declaration order.
+    states: Option<Vec<(i64, String)>>,
+    /// Limits checked against the calibrated value.
+    #[pyo3(get)]
+    pub limits: Option<AlarmLimits>,
+    /// Number of elements of an array-valued field, e.g., detector samples.
+    #[pyo3(get)]
+    pub count: Option<usize>,
+    /// Offset in bits between the starts of consecutive elements of an array-valued
+    /// field.
+    #[pyo3(get)]
+    pub stride: usize,
+    byte_order: ByteOrder,
+    /// Number of fractional bits of a fixed-point integer field.
+    #[pyo3(get)]
+    pub fraction_bits: u32,
+}
+
+impl PyRepr for Calibration {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.__str__())
+    }
+}
+
+#[pymethods]
+impl Field {
+    #[new]
+    #[pyo3(signature=(name, bit_offset, bit_len, kind="uint", calibration=None, states=None, limits=None, count=None, stride=None, byte_order="big", fraction_bits=0))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        name: String,
+        bit_offset: usize,
+        bit_len: u32,
+        kind: &str,
+        calibration: Option<Calibration>,
+        states: Option<&PyDict>,
+        limits: Option<AlarmLimits>,
+        count: Option<usize>,
+        stride: Option<usize>,
+        byte_order: &str,
+        fraction_bits: u32,
+    ) -> PyResult<Self> {
+        let states = match states {
+            Some(states) => {
+                let mut pairs = Vec::with_capacity(states.len());
+                for (raw, label) in states {
+                    pairs.push((raw.extract::<i64>()?, label.extract::<String>()?));
+                }
+                Some(pairs)
+            }
+            None => None,
+        };
+        let field = Self::new(
+            name,
+            bit_offset,
+            bit_len,
+            Kind::from_name(kind)?,
+            calibration,
+            states,
+            limits,
+        )?
+        .with_encoding(ByteOrder::from_name(byte_order)?, fraction_bits)?;
+        match count {
+            Some(count) => field.with_count(count, stride),
+            None => Ok(field),
+        }
+    }
+
+    /// Mapping of raw values to state labels, if this is an enumerated field.
+    #[getter]
+    fn states<'py>(&self, py: Python<'py>) -> PyResult<Option<&'py PyDict>> {
+        let Some(states) = &self.states else {
+            return Ok(None);
+        };
+        let dict = PyDict::new(py);
+        for (raw, label) in states {
+            dict.set_item(raw, label)?;
+        }
+        Ok(Some(dict))
+    }
+
+    /// Byte order of the field, either "big" or "little".
+    #[getter]
+    fn byte_order(&self) -> &'static str {
+        self.byte_order.name()
+    }
+
+    /// Encode `value` as this field into a copy of the packet data `data`, the inverse
+    /// of extracting the field.
+    ///
+    /// Parameters
+    /// ----------
+    /// data : bytes
+    ///     Packet data, i.e., following the primary header.
+    ///
+    /// value : int, float, str or list
+    ///     The value, as extracted: a state label for an enumerated field, a calibrated
+    ///     value for a field with a calibration, and a list of values for an
+    ///     array-valued field.
+    ///
+    /// raw : bool
+    ///     If True, `value` is the raw value rather than a calibrated value.
+    ///
+    /// Returns
+    /// -------
+    /// bytes
+    #[pyo3(signature=(data, value, raw=false))]
+    fn pack<'py>(
+        &self,
+        py: Python<'py>,
+        data: Vec<u8>,
+        value: &PyAny,
+        raw: bool,
+    ) -> PyResult<&'py PyBytes> {
+        let mut data = data;
+        self.pack_value(&mut data, value, raw)?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        let states: Option<BTreeMap<_, _>> = self
+            .states
+            .as_ref()
+            .map(|states| states.iter().map(|(raw, label)| (raw, label)).collect());
+        format!(
+            "Field(name={}, bit_offset={}, bit_len={}, kind={}, calibration={}, states={}, limits={}, count={}, stride={}, byte_order={}, fraction_bits={})",
+            repr(&self.name),
+            self.bit_offset,
+            self.bit_len,
+            repr(self.kind.name()),
+            repr(&self.calibration),
+            repr(&states),
+            repr(&self.limits),
+            repr(&self.count),
+            self.stride,
+            repr(self.byte_order.name()),
+            self.fraction_bits,
+        )
+    }
+}
+
+impl Field {
+    pub fn new(
+        name: String,
+        bit_offset: usize,
+        bit_len: u32,
+        kind: Kind,
+        calibration: Option<Calibration>,
+        states: Option<Vec<(i64, String)>>,
+        limits: Option<AlarmLimits>,
+    ) -> PyResult<Self> {
+        let valid = match kind {
+            Kind::Uint | Kind::Int => (1..=64).contains(&bit_len),
+            Kind::Float => bit_len == 32 || bit_len == 64,
+            Kind::Mil1750a => bit_len == 32 || bit_len == 48,
+        };
+        if !valid {
+            return Err(PyValueError::new_err(format!(
+                "invalid bit_len {bit_len} for {kind:?} field {name}"
+            )));
+        }
+        if states.is_some() {
+            if !matches!(kind, Kind::Uint | Kind::Int) {
+                return Err(PyValueError::new_err(format!(
+                    "states require an integer field, got {kind:?} field {name}"
+                )));
+            }
+            if calibration.is_some() || limits.is_some() {
+                return Err(PyValueError::new_err(format!(
+                    "field {name} cannot have states with a calibration or limits"
+                )));
+            }
+        }
+        Ok(Self {
+            name,
+            bit_offset,
+            bit_len,
+            kind,
+            calibration,
+            states,
+            limits,
+            count: None,
+            stride: bit_len as usize,
+            byte_order: ByteOrder::Big,
+            fraction_bits: 0,
+        })
+    }
+
+    /// Set the byte order of the field and, for integer fields, the number of
+    /// fractional bits of a fixed-point value.
+    pub fn with_encoding(mut self, byte_order: ByteOrder, fraction_bits: u32) -> PyResult<Self> {
+        if byte_order == ByteOrder::Little && !self.bit_len.is_multiple_of(8) {
+            return Err(PyValueError::new_err(format!(
+                "little-endian field {} requires a bit_len multiple of 8, got {}",
+                self.name, self.bit_len
+            )));
+        }
+        if fraction_bits > 0 {
+            if !matches!(self.kind, Kind::Uint | Kind::Int) || self.states.is_some() {
+                return Err(PyValueError::new_err(format!(
+                    "fixed-point field {} must be a non-enumerated integer field",
+                    self.name
+                )));
+            }
+            if fraction_bits > self.bit_len {
+                return Err(PyValueError::new_err(format!(
+                    "invalid fraction_bits for field {}; expected 0..={}, got {fraction_bits}",
+                    self.name, self.bit_len
+                )));
+            }
+        }
+        self.byte_order = byte_order;
+        self.fraction_bits = fraction_bits;
+        Ok(self)
+    }
+
+    /// Make this an array-valued field of `count` elements, starting every `stride`
+    /// bits, by default the field's `bit_len`.
+    pub fn with_count(mut self, count: usize, stride: Option<usize>) -> PyResult<Self> {
+        let stride = stride.unwrap_or(self.bit_len as usize);
+        if count == 0 || stride < self.bit_len as usize {
+            return Err(PyValueError::new_err(format!(
+                "invalid array field {}; expected count > 0 and stride >= bit_len, got count={count} and stride={stride}",
+                self.name
+            )));
+        }
+        if self.limits.is_some() {
+            return Err(PyValueError::new_err(format!(
+                "array field {} cannot have limits",
+                self.name
+            )));
+        }
+        self.count = Some(count);
+        self.stride = stride;
+        Ok(self)
+    }
+
+    /// Read the raw value of this field, or of the first element of an array-valued
+    /// field, from packet data.
+    pub fn raw(&self, dat: &[u8]) -> PyResult<Value> {
+        self.raw_at(dat, 0)
+    }
+
+    /// Read the raw value of element `index` of this field from packet data. The value
+    /// of a fixed-point field is scaled by its fractional bits.
+    fn raw_at(&self, dat: &[u8], index: usize) -> PyResult<Value> {
+        let (offset, len) = (self.bit_offset + index * self.stride, self.bit_len);
+        let mut bits = fields::get_bits(dat, offset, len)?;
+        if self.byte_order == ByteOrder::Little {
+            bits = fields::swap_bytes(bits, len);
+        }
+        let value = match self.kind {
+            Kind::Uint => Value::Uint(bits),
+            Kind::Int => Value::Int(fields::sign_extend(bits, len)),
+            Kind::Float if len == 32 => Value::Float(f64::from(f32::from_bits(bits as u32))),
+            Kind::Float => Value::Float(f64::from_bits(bits)),
+            Kind::Mil1750a if len == 32 => Value::Float(fields::mil1750a_32(bits)),
+            Kind::Mil1750a => Value::Float(fields::mil1750a_48(bits)),
+        };
+        if self.fraction_bits > 0 {
+            return Ok(Value::Float(
+                value.as_f64() / 2f64.powi(self.fraction_bits as i32),
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Write the raw value `value` as element `index` of this field into packet data.
+    fn write_raw_at(&self, dat: &mut [u8], index: usize, value: Value) -> PyResult<()> {
+        let (offset, len) = (self.bit_offset + index * self.stride, self.bit_len);
+        let value = if self.fraction_bits > 0 {
+            let scaled = (value.as_f64() * 2f64.powi(self.fraction_bits as i32)).round();
+            match self.kind {
+                Kind::Uint if scaled >= 0.0 && scaled <= u64::MAX as f64 => {
+                    Value::Uint(scaled as u64)
+                }
+                Kind::Int if scaled >= i64::MIN as f64 && scaled <= i64::MAX as f64 => {
+                    Value::Int(scaled as i64)
+                }
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "value {} out of range for field {}",
+                        value.as_f64(),
+                        self.name
+                    )))
+                }
+            }
+        } else {
+            value
+        };
+        let out_of_range = || {
+            PyValueError::new_err(format!(
+                "value {} out of range for field {}",
+                value.as_f64(),
+                self.name
+            ))
+        };
+        let mut bits = match (self.kind, value) {
+            (Kind::Uint, Value::Uint(v)) => v,
+            (Kind::Uint, Value::Int(v)) => u64::try_from(v).map_err(|_| out_of_range())?,
+            (Kind::Int, Value::Int(v)) => fields::to_signed_bits(v, len)?,
+            (Kind::Int, Value::Uint(v)) => {
+                fields::to_signed_bits(i64::try_from(v).map_err(|_| out_of_range())?, len)?
+            }
+            (Kind::Uint | Kind::Int, Value::Float(_)) => {
+                return Err(PyValueError::new_err(format!(
+                    "integer field {} requires an integer value, got {}",
+                    self.name,
+                    value.as_f64()
+                )))
+            }
+            (Kind::Float, v) if len == 32 => u64::from((v.as_f64() as f32).to_bits()),
+            (Kind::Float, v) => v.as_f64().to_bits(),
+            (Kind::Mil1750a, v) if len == 32 => fields::to_mil1750a_32(v.as_f64())?,
+            (Kind::Mil1750a, v) => fields::to_mil1750a_48(v.as_f64())?,
+        };
+        if self.byte_order == ByteOrder::Little {
+            bits = fields::swap_bytes(bits, len);
+        }
+        fields::set_bits(dat, offset, len, bits)
+    }
+
+    /// Convert an extracted value back into a raw value, the inverse of `convert`.
+    fn unconvert(&self, value: &PyAny, raw: bool) -> PyResult<Value> {
+        if let (Some(states), Ok(label)) = (&self.states, value.extract::<&str>()) {
+            return match states.iter().find(|(_, l)| l == label) {
+                Some((v, _)) => Ok(Value::Int(*v)),
+                None => Err(PyValueError::new_err(format!(
+                    "unknown state {label} for field {}",
+                    self.name
+                ))),
+            };
+        }
+        if let (Some(cal), false) = (&self.calibration, raw) {
+            let value: f64 = value.extract()?;
+            let Some(raw) = cal.invert(value) else {
+                return Err(PyValueError::new_err(format!(
+                    "calibration of field {} is not invertible; pack the raw value instead",
+                    self.name
+                )));
+            };
+            return Ok(match self.kind {
+                Kind::Uint | Kind::Int if self.fraction_bits == 0 => Value::Int(raw.round() as i64),
+                _ => Value::Float(raw),
+            });
+        }
+        if let Ok(v) = value.extract::<i64>() {
+            return Ok(Value::Int(v));
+        }
+        if let Ok(v) = value.extract::<u64>() {
+            return Ok(Value::Uint(v));
+        }
+        Ok(Value::Float(value.extract()?))
+    }
+
+    /// Encode `value`, as extracted by `value`, as this field into packet data.
+    pub fn pack_value(&self, dat: &mut [u8], value: &PyAny, raw: bool) -> PyResult<()> {
+        let Some(count) = self.count else {
+            return self.write_raw_at(dat, 0, self.unconvert(value, raw)?);
+        };
+        let values: Vec<&PyAny> = value.extract()?;
+        if values.len() != count {
+            return Err(PyValueError::new_err(format!(
+                "array field {} requires {count} values, got {}",
+                self.name,
+                values.len()
+            )));
+        }
+        for (index, value) in values.into_iter().enumerate() {
+            self.write_raw_at(dat, index, self.unconvert(value, raw)?)?;
+        }
+        Ok(())
+    }
+
+    /// Number of bytes of packet data needed to contain this field.
+    pub fn data_len(&self) -> usize {
+        let count = self.count.unwrap_or(1);
+        (self.bit_offset + (count - 1) * self.stride + self.bit_len as usize).div_ceil(8)
+    }
+
+    /// Label for a raw value of an enumerated field. Values without a label map to
+    /// `None`.
+    fn label(&self, raw: Value) -> Option<&str> {
+        let raw = match raw {
+            Value::Uint(v) => i64::try_from(v).ok()?,
+            Value::Int(v) => v,
+            Value::Float(_) => return None,
+        };
+        self.states
+            .as_ref()?
+            .iter()
+            .find(|(v, _)| *v == raw)
+            .map(|(_, label)| label.as_str())
+    }
+
+    /// Read the value of this field from packet data, calibrated if the field has a
+    /// calibration, or as a state label if the field is enumerated. The value of an
+    /// array-valued field is a list of its element values.
+    pub fn value(&self, py: Python<'_>, dat: &[u8]) -> PyResult<PyObject> {
+        let Some(count) = self.count else {
+            return Ok(self.convert(py, self.raw(dat)?));
+        };
+        let mut values = Vec::with_capacity(count);
+        for index in 0..count {
+            values.push(self.convert(py, self.raw_at(dat, index)?));
+        }
+        Ok(values.into_py(py))
+    }
+
+    fn convert(&self, py: Python<'_>, raw: Value) -> PyObject {
+        if self.states.is_some() {
+            return self.label(raw).into_py(py);
+        }
+        match &self.calibration {
+            Some(cal) => cal.apply(raw.as_f64()).into_py(py),
+            None => raw.into_py(py),
+        }
+    }
+
+    /// The calibrated or raw numeric value of a scalar field, as referenced by derived
+    /// parameters, or `None` for an array-valued field.
+    fn number(&self, dat: &[u8]) -> PyResult<Option<Num>> {
+        if self.count.is_some() {
+            return Ok(None);
+        }
+        let raw = self.raw(dat)?;
+        Ok(Some(match (&self.calibration, raw) {
+            (Some(cal), raw) => Num::Float(cal.apply(raw.as_f64())),
+            (None, Value::Uint(v)) => i64::try_from(v).map_or(Num::Float(v as f64), Num::Int),
+            (None, Value::Int(v)) => Num::Int(v),
+            (None, Value::Float(v)) => Num::Float(v),
+        }))
+    }
+
+    /// Check the calibrated value of this field against its limits, if any.
+    fn check(&self, dat: &[u8]) -> PyResult<Option<(f64, Exceeded)>> {
+        let Some(limits) = &self.limits else {
+            return Ok(None);
+        };
+        let raw = self.raw(dat)?.as_f64();
+        let value = match &self.calibration {
+            Some(cal) => cal.apply(raw),
+            None => raw,
+        };
+        Ok(limits.check(value).map(|exceeded| (value, exceeded)))
+    }
+}
+
+/// A set of fields extracted from packets, and parameters derived from them.
+///
+/// Parameters
+/// ----------
+/// fields : list of Field
+///     The fields.
+///
+/// derived : list of Derived, optional
+///     Parameters computed from the fields, and earlier derived parameters, after the
+///     fields are extracted. Their values follow those of the fields.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct Layout {
+    #[pyo3(get)]
+    pub fields: Vec<Field>,
+    #[pyo3(get)]
+    pub derived: Vec<Derived>,
+}
+
+#[pymethods]
+impl Layout {
+    #[new]
+    #[pyo3(signature=(fields, derived=None))]
+    pub fn py_new(fields: Vec<Field>, derived: Option<Vec<Derived>>) -> PyResult<Self> {
+        let derived = derived.unwrap_or_default();
+        let names: Vec<&str> = fields
+            .iter()
+            .map(|f| f.name.as_str())
+            .chain(derived.iter().map(|d| d.name.as_str()))
+            .collect();
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                return Err(PyValueError::new_err(format!(
+                    "duplicate field name {name}"
+                )));
+            }
+        }
+        // derived parameters may reference scalar fields and earlier derived parameters
+        let derived = derived
+            .iter()
+            .enumerate()
+            .map(|(i, param)| {
+                param.resolve(|name| {
+                    let slot = names[..fields.len() + i].iter().position(|n| *n == name)?;
+                    (slot >= fields.len() || fields[slot].count.is_none()).then_some(slot)
+                })
+            })
+            .collect::<PyResult<_>>()?;
+        Ok(Self { fields, derived })
+    }
+
+    /// Extract the fields from a packet.
+    ///
+    /// Returns
+    /// -------
+    /// dict
+    ///     Field values by name.
+    fn extract<'py>(&self, py: Python<'py>, packet: PyRef<Packet>) -> PyResult<&'py PyDict> {
+        let values = PyDict::new(py);
+        for field in &self.fields {
+            values.set_item(&field.name, field.value(py, packet.payload())?)?;
+        }
+        for (param, value) in self
+            .derived
+            .iter()
+            .zip(self.derived_values(packet.payload())?)
+        {
+            values.set_item(&param.name, value.into_py(py))?;
+        }
+        Ok(values)
+    }
+
+    /// Extract the fields from each of an iterable of packets into columns.
+    ///
+    /// Parameters
+    /// ----------
+    /// packets : Iterable[Packet]
+    ///     Packets to extract fields from.
+    ///
+    /// categorical : bool
+    ///     If True, columns for enumerated fields are returned as `pandas.Categorical`
+    ///     with the field's labels as categories, in declaration order. Requires pandas.
+    ///
+    /// Returns
+    /// -------
+    /// dict
+    ///     A list of values for each field by name, in packet order. Enumerated fields
+    ///     contain state labels, or None for values without a label.
+    #[pyo3(signature=(packets, categorical=false))]
+    fn extract_all<'py>(
+        &self,
+        py: Python<'py>,
+        packets: &PyAny,
+        categorical: bool,
+    ) -> PyResult<&'py PyDict> {
+        let columns = self.empty_columns(py);
+        for packet in packets.iter()? {
+            let packet: PyRef<Packet> = packet?.extract()?;
+            self.extract_row(py, packet.payload(), &columns)?;
+        }
+        let values = PyDict::new(py);
+        self.set_columns(py, columns, categorical, values)?;
+        Ok(values)
+    }
+
+    /// Encode field values into packet data, the inverse of `extract`.
+    ///
+    /// Parameters
+    /// ----------
+    /// values : dict
+    ///     Values by field name, as returned by `extract`. Fields without a value are
+    ///     left unchanged and derived parameters are ignored.
+    ///
+    /// data : bytes, optional
+    ///     Packet data following the primary header to encode the values into, e.g.,
+    ///     `bytes(packet.data[6:])` of a decoded packet, whose `data` includes the
+    ///     primary header. Defaults to zeros long enough for all the fields.
+    ///
+    /// raw : bool
+    ///     If True, values of fields with a calibration are raw values.
+    ///
+    /// Returns
+    /// -------
+    /// bytes
+    ///     A copy of `data` with the values encoded.
+    #[pyo3(signature=(values, data=None, raw=false))]
+    fn pack<'py>(
+        &self,
+        py: Python<'py>,
+        values: &PyDict,
+        data: Option<Vec<u8>>,
+        raw: bool,
+    ) -> PyResult<&'py PyBytes> {
+        for name in values.keys() {
+            let name: &str = name.extract()?;
+            if !self.fields.iter().any(|f| f.name == name)
+                && !self.derived.iter().any(|d| d.name == name)
+            {
+                return Err(PyValueError::new_err(format!("unknown field {name}")));
+            }
+        }
+        let mut data = data.unwrap_or_else(|| {
+            vec![
+                0;
+                self.fields
+                    .iter()
+                    .map(Field::data_len)
+                    .max()
+                    .unwrap_or_default()
+            ]
+        });
+        for field in &self.fields {
+            if let Some(value) = values.get_item(&field.name) {
+                field.pack_value(&mut data, value, raw)?;
+            }
+        }
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Check fields with limits in a packet.
+    ///
+    /// Parameters
+    /// ----------
+    /// packet : Packet
+    ///     The packet to check.
+    ///
+    /// timecode : str or TimecodeRegistry, optional
+    ///     Timecode at the start of the packet secondary header, either "cds" or
+    ///     "eoscuc", or the timecode of each APID, used to timestamp violations.
+    ///
+    /// Returns
+    /// -------
+    /// list of Violation
+    ///     Fields outside of their limits, in field order.
+    #[pyo3(signature=(packet, timecode=None))]
+    fn check(&self, packet: PyRef<Packet>, timecode: Option<&PyAny>) -> PyResult<Vec<Violation>> {
+        let timecode = optional_registry_arg(timecode)?;
+        self.check_packet(&packet, timecode.as_ref())
+    }
+
+    /// Check fields with limits in each of an iterable of packets.
+    ///
+    /// Parameters
+    /// ----------
+    /// packets : Iterable[Packet]
+    ///     Packets to check.
+    ///
+    /// timecode : str or TimecodeRegistry, optional
+    ///     Timecode at the start of the packet secondary header, either "cds" or
+    ///     "eoscuc", or the timecode of each APID, used to timestamp violations.
+    ///
+    /// Returns
+    /// -------
+    /// list of Violation
+    ///     Fields outside of their limits, in packet then field order.
+    #[pyo3(signature=(packets, timecode=None))]
+    fn check_all(&self, packets: &PyAny, timecode: Option<&PyAny>) -> PyResult<Vec<Violation>> {
+        let timecode = optional_registry_arg(timecode)?;
+        let mut violations = Vec::default();
+        for packet in packets.iter()? {
+            let packet: PyRef<Packet> = packet?.extract()?;
+            violations.extend(self.check_packet(&packet, timecode.as_ref())?);
+        }
+        Ok(violations)
+    }
+}
+
+impl Layout {
+    /// An empty column for each field and derived parameter, for use with
+    /// `extract_row` and `set_columns`.
+    pub fn empty_columns<'py>(&self, py: Python<'py>) -> Vec<&'py PyList> {
+        (0..self.fields.len() + self.derived.len())
+            .map(|_| PyList::empty(py))
+            .collect()
+    }
+
+    /// Append the field and derived parameter values of the packet data `dat` to
+    /// `columns`.
+    pub fn extract_row(&self, py: Python<'_>, dat: &[u8], columns: &[&PyList]) -> PyResult<()> {
+        for (field, column) in self.fields.iter().zip(columns) {
+            column.append(field.value(py, dat)?)?;
+        }
+        let derived_columns = &columns[self.fields.len()..];
+        for (value, column) in self.derived_values(dat)?.into_iter().zip(derived_columns) {
+            column.append(value.into_py(py))?;
+        }
+        Ok(())
+    }
+
+    /// Values of the derived parameters for the packet data `dat`, in order.
+    fn derived_values(&self, dat: &[u8]) -> PyResult<Vec<Option<Num>>> {
+        if self.derived.is_empty() {
+            return Ok(Vec::default());
+        }
+        let mut values = Vec::with_capacity(self.fields.len() + self.derived.len());
+        for field in &self.fields {
+            values.push(field.number(dat)?);
+        }
+        for param in &self.derived {
+            let value = param.eval(&values);
+            values.push(value);
+        }
+        Ok(values.split_off(self.fields.len()))
+    }
+
+    /// Set the field `columns` in `values` by field name, as pandas.Categorical for
+    /// scalar enumerated fields if `categorical`.
+    pub fn set_columns(
+        &self,
+        py: Python<'_>,
+        columns: Vec<&PyList>,
+        categorical: bool,
+        values: &PyDict,
+    ) -> PyResult<()> {
+        let pandas = if categorical {
+            Some(py.import("pandas")?)
+        } else {
+            None
+        };
+        let mut columns = columns.into_iter();
+        for (field, column) in self.fields.iter().zip(columns.by_ref()) {
+            match (pandas, &field.states) {
+                (Some(pandas), Some(states)) if field.count.is_none() => {
+                    let categories: Vec<&str> =
+                        states.iter().map(|(_, label)| label.as_str()).collect();
+                    let kwargs = PyDict::new(py);
+                    kwargs.set_item("categories", categories)?;
+                    let column = pandas.call_method("Categorical", (column,), Some(kwargs))?;
+                    values.set_item(&field.name, column)?;
+                }
+                _ => values.set_item(&field.name, column)?,
+            }
+        }
+        for (param, column) in self.derived.iter().zip(columns) {
+            values.set_item(&param.name, column)?;
+        }
+        Ok(())
+    }
+
+    fn check_packet(
+        &self,
+        packet: &Packet,
+        timecode: Option<&TimecodeRegistry>,
+    ) -> PyResult<Vec<Violation>> {
+        let mut violations = Vec::default();
+        for field in &self.fields {
+            let Some((value, exceeded)) = field.check(packet.payload())? else {
+                continue;
+            };
+            let time = timecode.and_then(|tc| tc.decode(packet.header.apid, packet.payload()));
+            violations.push(Violation::new(
+                &field.name,
+                value,
+                exceeded,
+                packet.header.apid,
+                packet.header.sequence_id,
+                time,
+            ));
+        }
+        Ok(violations)
+    }
+}
@@ -1,14 +1,97 @@
+// pyo3 0.19 generates the constructor text signature as an impl nested in a function
+#![allow(non_local_definitions)]
+mod aio;
+mod alarm;
+mod anomaly;
+mod archive;
+mod batch;
+mod cancel;
+mod cli;
+mod clock;
+mod compare;
+mod compress;
+mod convert;
+mod dataframe;
+mod dedup;
+mod demux;
+mod derived;
+mod digest;
+mod dump;
+mod dvbs2;
+mod encode;
+mod epoch;
+mod eps;
+mod fields;
+mod fill;
+mod filter;
+mod framecache;
+mod frameconfig;
+mod gaps;
+mod grb;
+mod group;
+mod idle;
+mod izone;
+mod kafka;
+mod layout;
+mod manifest;
+mod merge;
+mod message;
+mod mib;
+mod mission;
+mod packets;
+mod pcap;
+mod pn;
+mod position;
+mod prefetch;
+mod progress;
+mod push;
+mod quicklook;
+mod redis;
+mod report;
+mod repr;
+mod rewrite;
+mod rice;
+mod roundtrip;
+mod rs;
+mod sample;
+mod services;
+mod session;
+mod source;
+mod split;
+mod stage;
+mod subset;
+mod summary;
+mod sync;
+mod tee;
+mod throttle;
+mod timecode;
+mod trend;
+mod turbo;
+mod udp;
+mod validate;
+mod ws;
+
 use pyo3::{
-    exceptions::{PyFileNotFoundError, PyValueError},
+    exceptions::{PyEOFError, PyFileNotFoundError, PyStopIteration, PyTimeoutError, PyValueError},
     prelude::*,
-    types::PyType,
+    types::{PyBytes, PyDict, PyType},
 };
 use std::{
-    fs::File,
-    io::{BufReader, Read},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    hash::Hash,
+    io::{self, BufReader, Read},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::Duration,
 };
 
-#[pyclass]
+use prefetch::Next;
+use repr::repr;
+
+#[pyclass(frozen)]
 #[derive(Clone, Debug)]
 struct PrimaryHeader {
     #[pyo3(get)]
@@ -35,13 +118,36 @@ impl PrimaryHeader {
     fn __str__(&self) -> String {
         format!(
             "PrimaryHeader(version={}, type_flag={}, has_secondary_header={}, apid={}, sequence_flags={}, sequence_id={}, len_minus1={})",
-            self.version, self.type_flag, self.has_secondary_header, self.apid, self.sequence_flags, self.sequence_id, self.len_minus1,
+            self.version, self.type_flag, repr(&self.has_secondary_header), self.apid, self.sequence_flags, self.sequence_id, self.len_minus1,
         ).to_owned()
     }
 
     #[classmethod]
     fn decode(_cls: &PyType, dat: &[u8]) -> Option<Self> {
-        ccsds::PrimaryHeader::decode(dat).map(|hdr| Self {
+        ccsds::PrimaryHeader::decode(dat).map(PrimaryHeader::from)
+    }
+}
+
+impl PrimaryHeader {
+    /// Encode this header into its 6-byte wire format.
+    fn encode(&self) -> [u8; packets::HEADER_LEN] {
+        [
+            (self.version & 0x7) << 5
+                | (self.type_flag & 0x1) << 4
+                | u8::from(self.has_secondary_header) << 3
+                | ((self.apid >> 8) & 0x7) as u8,
+            (self.apid & 0xff) as u8,
+            (self.sequence_flags & 0x3) << 6 | ((self.sequence_id >> 8) & 0x3f) as u8,
+            (self.sequence_id & 0xff) as u8,
+            (self.len_minus1 >> 8) as u8,
+            (self.len_minus1 & 0xff) as u8,
+        ]
+    }
+}
+
+impl From<ccsds::PrimaryHeader> for PrimaryHeader {
+    fn from(hdr: ccsds::PrimaryHeader) -> Self {
+        PrimaryHeader {
             version: hdr.version,
             type_flag: hdr.type_flag,
             has_secondary_header: hdr.has_secondary_header,
@@ -49,18 +155,36 @@ impl PrimaryHeader {
             sequence_flags: hdr.sequence_flags,
             sequence_id: hdr.sequence_id,
             len_minus1: hdr.len_minus1,
-        })
+        }
     }
 }
 
-#[pyclass]
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
 struct Packet {
     #[pyo3(get)]
     header: PrimaryHeader,
     #[pyo3(get)]
     data: Vec<u8>,
+    /// True if the stream ended before the end of this packet, in which case `data`
+    /// only contains the bytes that were available.
+    #[pyo3(get)]
+    truncated: bool,
+    /// Decompressed samples if decoded with a Rice configuration for this APID.
+    #[pyo3(get)]
+    samples: Option<Vec<i64>>,
+    /// Byte offset of the primary header in the source, if decoded from a packet stream.
+    #[pyo3(get)]
+    offset: Option<u64>,
+    /// Length of the secondary header, if configured for this APID when decoding.
+    #[pyo3(get)]
+    secondary_header_len: Option<usize>,
 }
 
+/// Secondary header length assumed when not otherwise configured, i.e., that of an
+/// 8-byte CDS timecode.
+const DEFAULT_SECONDARY_HEADER_LEN: usize = 8;
+
 #[pymethods]
 impl Packet {
     fn __repr__(&self) -> String {
@@ -68,9 +192,10 @@ impl Packet {
     }
     fn __str__(&self) -> String {
         format!(
-            "Packet(header={}, data_len={})",
+            "Packet(header={}, data_len={}, offset={})",
             self.header.__str__(),
-            self.data.len()
+            self.data.len(),
+            repr(&self.offset),
         )
         .to_owned()
     }
@@ -78,35 +203,190 @@ impl Packet {
     fn decode(_cls: &PyType, dat: &[u8]) -> Option<Self> {
         ccsds::Packet::decode(dat).map(Packet::new)
     }
+
+    /// Unsigned big-endian integer of `bit_len` bits starting `bit_offset` bits into
+    /// the packet data, i.e., following the primary header.
+    fn u(&self, bit_offset: usize, bit_len: u32) -> PyResult<u64> {
+        fields::get_bits(self.payload(), bit_offset, bit_len)
+    }
+
+    /// Two's complement signed big-endian integer of `bit_len` bits starting
+    /// `bit_offset` bits into the packet data.
+    fn i(&self, bit_offset: usize, bit_len: u32) -> PyResult<i64> {
+        fields::get_signed(self.payload(), bit_offset, bit_len)
+    }
+
+    /// 32-bit float starting `bit_offset` bits into the packet data, either IEEE 754
+    /// or, if `mil1750a` is true, MIL-STD-1750A.
+    #[pyo3(signature=(bit_offset, mil1750a=false))]
+    fn f32(&self, bit_offset: usize, mil1750a: bool) -> PyResult<f64> {
+        fields::get_f32(self.payload(), bit_offset, mil1750a)
+    }
+
+    /// 64-bit IEEE 754 float starting `bit_offset` bits into the packet data or, if
+    /// `mil1750a` is true, a 48-bit MIL-STD-1750A extended precision float.
+    #[pyo3(signature=(bit_offset, mil1750a=false))]
+    fn f64(&self, bit_offset: usize, mil1750a: bool) -> PyResult<f64> {
+        fields::get_f64(self.payload(), bit_offset, mil1750a)
+    }
+
+    /// The packet bytes, i.e., the primary header followed by the packet data.
+    fn encode<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.data)
+    }
+
+    fn __bytes__<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        self.encode(py)
+    }
+
+    /// Bytes of the secondary header, empty if the packet does not have one. The length
+    /// is that configured for the APID when decoding, or 8 bytes.
+    #[getter]
+    fn secondary_header_bytes<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        let len = self.user_data_start(None);
+        PyBytes::new(py, &self.payload()[..len])
+    }
+
+    /// The application data, i.e., the packet data following any secondary header.
+    ///
+    /// Parameters
+    /// ----------
+    /// secondary_header_len : int, optional
+    ///     Length of the secondary header. Defaults to the length configured for the
+    ///     APID when decoding, or 8 bytes.
+    #[pyo3(signature=(secondary_header_len=None))]
+    fn user_data<'py>(&self, py: Python<'py>, secondary_header_len: Option<usize>) -> &'py PyBytes {
+        let start = self.user_data_start(secondary_header_len);
+        PyBytes::new(py, &self.payload()[start..])
+    }
+
+    /// Hex and ASCII dump of the packet data following the primary header, `width`
+    /// bytes per line.
+    #[pyo3(signature=(width=16))]
+    fn hexdump(&self, width: usize) -> PyResult<String> {
+        dump::hexdump(self.payload(), width)
+    }
+
+    /// Readable block of the primary header fields followed by a dump of the first
+    /// `max_bytes` of the packet data following the primary header.
+    #[pyo3(signature=(max_bytes=dump::DESCRIBE_LEN))]
+    fn describe(&self, max_bytes: usize) -> String {
+        let hdr = &self.header;
+        let mut fields = vec![
+            ("version", hdr.version.to_string()),
+            ("type_flag", hdr.type_flag.to_string()),
+            ("has_secondary_header", hdr.has_secondary_header.to_string()),
+            ("apid", hdr.apid.to_string()),
+            ("sequence_flags", hdr.sequence_flags.to_string()),
+            ("sequence_id", hdr.sequence_id.to_string()),
+            ("len_minus1", hdr.len_minus1.to_string()),
+            ("data_len", self.payload().len().to_string()),
+        ];
+        if self.truncated {
+            fields.push(("truncated", "true".to_string()));
+        }
+        if let Some(offset) = self.offset {
+            fields.push(("offset", offset.to_string()));
+        }
+        dump::describe("Packet", &fields, self.payload(), max_bytes)
+    }
 }
 
 impl Packet {
     fn new(packet: ccsds::Packet) -> Self {
         Packet {
-            header: PrimaryHeader {
-                version: packet.header.version,
-                type_flag: packet.header.type_flag,
-                has_secondary_header: packet.header.has_secondary_header,
-                apid: packet.header.apid,
-                sequence_flags: packet.header.sequence_flags,
-                sequence_id: packet.header.sequence_id,
-                len_minus1: packet.header.len_minus1,
-            },
-            data: packet.data.clone(),
+            header: PrimaryHeader::from(packet.header),
+            data: packet.data,
+            truncated: false,
+            samples: None,
+            offset: None,
+            secondary_header_len: None,
         }
     }
+
+    /// The packet data following the primary header.
+    fn payload(&self) -> &[u8] {
+        self.data.get(packets::HEADER_LEN..).unwrap_or_default()
+    }
+
+    /// Offset into the payload of the application data following any secondary header.
+    fn user_data_start(&self, secondary_header_len: Option<usize>) -> usize {
+        if !self.header.has_secondary_header {
+            return 0;
+        }
+        let len = secondary_header_len
+            .or(self.secondary_header_len)
+            .unwrap_or(DEFAULT_SECONDARY_HEADER_LEN);
+        len.min(self.payload().len())
+    }
 }
 
-#[pyclass]
+impl From<packets::RawPacket> for Packet {
+    fn from(packet: packets::RawPacket) -> Self {
+        let mut data = Vec::with_capacity(packets::HEADER_LEN + packet.data.len());
+        data.extend_from_slice(&packet.header.encode());
+        data.extend_from_slice(&packet.data);
+        Packet {
+            header: packet.header,
+            data,
+            truncated: packet.truncated,
+            samples: None,
+            offset: Some(packet.offset),
+            secondary_header_len: None,
+        }
+    }
+}
+
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
 struct DecodedPacket {
     #[pyo3(get)]
     scid: u16,
+    /// Virtual channel the packet was decoded from.
+    #[pyo3(get)]
     vcid: u16,
+    #[pyo3(get)]
     packet: Packet,
 }
 
 #[pymethods]
 impl DecodedPacket {
+    #[new]
+    fn py_new(scid: u16, vcid: u16, packet: PyRef<Packet>) -> PyResult<Self> {
+        check_scid(scid)?;
+        if vcid > 0x3f {
+            return Err(PyValueError::new_err(format!(
+                "invalid vcid; expected 0..=63, got {vcid}"
+            )));
+        }
+        Ok(DecodedPacket {
+            scid,
+            vcid,
+            packet: packet.clone(),
+        })
+    }
+
+    /// Flat dict of the spacecraft and virtual channel ids, the primary header fields,
+    /// and the packet data following the primary header, e.g., for building a DataFrame
+    /// from a list of packets.
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let hdr = &self.packet.header;
+        let dict = PyDict::new(py);
+        dict.set_item("scid", self.scid)?;
+        dict.set_item("vcid", self.vcid)?;
+        dict.set_item("version", hdr.version)?;
+        dict.set_item("type_flag", hdr.type_flag)?;
+        dict.set_item("has_secondary_header", hdr.has_secondary_header)?;
+        dict.set_item("apid", hdr.apid)?;
+        dict.set_item("sequence_flags", hdr.sequence_flags)?;
+        dict.set_item("sequence_id", hdr.sequence_id)?;
+        dict.set_item("len_minus1", hdr.len_minus1)?;
+        dict.set_item("data", PyBytes::new(py, self.packet.payload()))?;
+        dict.set_item("truncated", self.packet.truncated)?;
+        dict.set_item("offset", self.packet.offset)?;
+        Ok(dict)
+    }
+
     fn __repr__(&self) -> String {
         self.__str__()
     }
@@ -133,7 +413,166 @@ impl DecodedPacket {
 
 #[pyclass]
 struct PacketIterator {
-    packets: Box<dyn Iterator<Item = ccsds::Packet> + Send>,
+    packets: prefetch::Pipeline<io::Result<packets::RawPacket>>,
+    timeout: Option<Duration>,
+    bytes_read: Arc<AtomicU64>,
+    gaps: gaps::GapTracker,
+    summary: summary::Summary,
+    cancel: cancel::CancelToken,
+    skipped: Vec<(u64, u64)>,
+    rice: HashMap<u16, rice::RiceConfig>,
+    filter: Option<filter::PacketFilter>,
+    secondary_headers: packets::SecondaryHeaderCheck,
+    secondary_header_lens: HashMap<u16, usize>,
+    lengths: packets::LengthCheck,
+    fill: fill::FillCheck,
+    sequence_flags: packets::SequenceFlagCheck,
+    clock: clock::ClockMonitor,
+    progress: progress::Progress,
+    sampler: Option<sample::Sampler>,
+    quicklooks: quicklook::QuickLooks,
+    anomalies: Option<anomaly::AnomalyCollector>,
+    /// Position of the most recent item, added to errors.
+    position: position::Position,
+    /// Packet returned by `peek` but not yet consumed.
+    peeked: Option<Py<Packet>>,
+}
+
+impl PacketIterator {
+    fn advance(&mut self, py: Python, poll: bool) -> PyResult<Next<Py<Packet>>> {
+        self.next_item(py, poll)
+            .map_err(|err| self.position.annotate(py, err))
+    }
+
+    fn next_item(&mut self, py: Python, poll: bool) -> PyResult<Next<Py<Packet>>> {
+        loop {
+            if self.cancel.is_cancelled() {
+                return Ok(Next::Done);
+            }
+            if let Err(err) = py.check_signals() {
+                self.cancel();
+                return Err(err);
+            }
+            let next = if poll {
+                self.packets.poll()
+            } else {
+                match self.packets.wait(py, self.timeout) {
+                    Ok(next) => next,
+                    Err(err) => {
+                        self.cancel();
+                        return Err(err);
+                    }
+                }
+            };
+            let packet = match next {
+                Next::Item(Ok(packet)) => packet,
+                Next::Item(Err(err)) => {
+                    self.summary.errors += 1;
+                    anomaly::emit(&self.anomalies, || {
+                        anomaly::Anomaly::new(
+                            "packet_error",
+                            anomaly::Severity::Error,
+                            err.to_string(),
+                        )
+                    });
+                    if err.kind() == io::ErrorKind::UnexpectedEof {
+                        return Err(PyEOFError::new_err(err.to_string()));
+                    }
+                    if err
+                        .get_ref()
+                        .is_some_and(|err| err.is::<packets::LimitViolation>())
+                    {
+                        return Err(PyValueError::new_err(err.to_string()));
+                    }
+                    continue;
+                }
+                Next::Pending => return Ok(Next::Pending),
+                Next::Done => {
+                    self.summary.complete = true;
+                    self.progress.finish();
+                    self.sequence_flags.finish();
+                    return Ok(Next::Done);
+                }
+            };
+            self.position = position::Position {
+                offset: Some(packet.offset),
+                frame: None,
+                packet: Some((packet.header.apid, packet.header.sequence_id)),
+            };
+            // packet data is empty when only decoding headers, so use the header length
+            let data_len = if packet.data.is_empty() && !packet.truncated {
+                packet.header.len_minus1 as usize + 1
+            } else {
+                packet.data.len()
+            };
+            self.progress
+                .read_to(packet.offset + (packets::HEADER_LEN + data_len) as u64);
+            if let Some(span) = packet.skipped {
+                self.summary.skipped_bytes += span.1;
+                self.skipped.push(span);
+                anomaly::emit(&self.anomalies, || {
+                    anomaly::Anomaly::new(
+                        "resync",
+                        anomaly::Severity::Warning,
+                        format!("skipped {} corrupt bytes", span.1),
+                    )
+                    .offset(Some(span.0))
+                });
+            }
+            if let Some(gap) = self.gaps.add(&packet, None) {
+                self.summary.missing += u64::from(gap.count);
+                anomaly::emit(&self.anomalies, || {
+                    anomaly::Anomaly::new(
+                        "packet_gap",
+                        anomaly::Severity::Warning,
+                        format!(
+                            "{} packets missing between sequence ids {} and {}",
+                            gap.count, gap.start_sequence_id, gap.end_sequence_id
+                        ),
+                    )
+                    .apid(gap.apid)
+                    .offset(Some(gap.end_offset))
+                });
+            }
+            let hdr = &packet.header;
+            let offset = Some(packet.offset);
+            self.sequence_flags.check(hdr, offset);
+            self.clock.check(hdr, &packet.data, offset);
+            if !self
+                .secondary_headers
+                .check(hdr.apid, hdr.has_secondary_header, offset)?
+                || !self.lengths.check(hdr, offset)?
+                || !self.fill.check(hdr.apid, &packet.data, offset)?
+            {
+                continue;
+            }
+            self.quicklooks.add(hdr.apid, &packet.data);
+            if let Some(filter) = &self.filter {
+                let values = filter::Values {
+                    header: &packet.header,
+                    data_len,
+                    ids: None,
+                };
+                if !filter.eval(&values) {
+                    continue;
+                }
+            }
+            if !sample::keep(&mut self.sampler) {
+                continue;
+            }
+            self.summary.items += 1;
+            self.progress.yielded();
+            let samples = self.rice.get(&packet.header.apid).map(|config| {
+                let dat = packet.data.get(config.offset..).unwrap_or_default();
+                config.decode(dat, None)
+            });
+            let secondary_header_len = self.secondary_header_lens.get(&packet.header.apid);
+            let mut packet = Packet::from(packet);
+            packet.samples = samples;
+            packet.secondary_header_len = secondary_header_len.copied();
+            return Py::new(py, packet).map(Next::Item);
+        }
+    }
 }
 
 #[pymethods]
@@ -142,42 +581,547 @@ impl PacketIterator {
         slf
     }
 
-    fn __next__(mut slf: PyRefMut<Self>) -> Option<Py<Packet>> {
-        match slf.packets.next() {
-            Some(packet) => Py::new(slf.py(), Packet::new(packet)).ok(),
-            None => None,
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<Py<Packet>>> {
+        if let Some(item) = slf.peeked.take() {
+            return Ok(Some(item));
+        }
+        let py = slf.py();
+        next_or_timeout(slf.advance(py, false)?)
+    }
+
+    /// Return the next packet if one is available without blocking, otherwise `None`.
+    /// Raises StopIteration once the stream is exhausted.
+    fn poll(mut slf: PyRefMut<Self>) -> PyResult<Option<Py<Packet>>> {
+        if let Some(item) = slf.peeked.take() {
+            return Ok(Some(item));
+        }
+        let py = slf.py();
+        poll_result(slf.advance(py, true)?)
+    }
+
+    /// Return the next packet without consuming it, blocking until it is available, or
+    /// `None` once the stream is exhausted. The packet is returned again by the next
+    /// call to `next` or `poll`.
+    fn peek(mut slf: PyRefMut<Self>) -> PyResult<Option<Py<Packet>>> {
+        let py = slf.py();
+        if slf.peeked.is_none() {
+            let next = slf.advance(py, false)?;
+            slf.peeked = next_or_timeout(next)?;
+        }
+        Ok(slf.peeked.as_ref().map(|item| item.clone_ref(py)))
+    }
+
+    /// Stop decoding. Any background decoding stops promptly and the iterator is
+    /// exhausted on the next call to `next`.
+    fn cancel(&mut self) {
+        self.cancel.cancel();
+        self.summary.cancelled = true;
+        self.peeked = None;
+    }
+
+    /// Totals for the packets decoded so far. Once the iterator is exhausted the
+    /// summary is marked as complete.
+    fn summary(&self) -> summary::Summary {
+        let mut summary = self.summary.clone();
+        summary.bytes_read = self.bytes_read.load(Ordering::Relaxed);
+        summary
+    }
+
+    /// Offset and length of each span of corrupt bytes skipped so far when decoding
+    /// with `resync=True`.
+    fn skipped(&self) -> Vec<(u64, u64)> {
+        self.skipped.clone()
+    }
+
+    /// APID and offset of each packet so far whose secondary header flag did not match
+    /// `secondary_headers`.
+    fn secondary_header_violations(&self) -> Vec<(u16, Option<u64>)> {
+        self.secondary_headers.violations.clone()
+    }
+
+    /// APID, length and offset of each packet so far whose length did not match
+    /// `apid_lengths`.
+    fn length_violations(&self) -> Vec<(u16, usize, Option<u64>)> {
+        self.lengths.violations.clone()
+    }
+
+    /// APID, fill byte, fraction of the payload that is fill and offset of each packet
+    /// so far detected as fill by `fill`.
+    fn fill_violations(&self) -> Vec<(u16, u8, f64, Option<u64>)> {
+        self.fill.violations.clone()
+    }
+
+    /// APID, kind of violation and offset of each sequence flag violation so far when
+    /// decoding with `check_sequence_flags`. Groups in progress at the end of the stream
+    /// are included once the iterator is exhausted.
+    fn sequence_flag_violations(&self) -> Vec<(u16, &'static str, Option<u64>)> {
+        self.sequence_flags.violations.clone()
+    }
+
+    /// APID, kind of violation, previous and current packet time and offset of each
+    /// packet so far whose time regressed or jumped according to `clock`.
+    fn clock_violations(&self) -> Vec<(u16, &'static str, i64, i64, Option<u64>)> {
+        self.clock.violations.clone()
+    }
+
+    /// Quick-look images accumulated so far for the APIDs configured with
+    /// `quicklook`, by APID.
+    fn quicklook(&self) -> BTreeMap<u16, quicklook::QuickLook> {
+        self.quicklooks.images()
+    }
+
+    /// Fraction of the source consumed so far, from 0 to 1, or `None` if the source
+    /// is not a regular file.
+    fn progress(&self) -> Option<f64> {
+        self.progress.fraction()
+    }
+
+    /// Estimated number of packets remaining, from the average packet size so far.
+    fn __length_hint__(&self, py: Python) -> PyObject {
+        self.progress.length_hint(py)
+    }
+}
+
+/// Convert the result of advancing an iterator for `__next__`.
+fn next_or_timeout<T>(next: Next<T>) -> PyResult<Option<T>> {
+    match next {
+        Next::Item(item) => Ok(Some(item)),
+        Next::Pending => Err(PyTimeoutError::new_err("no data received before timeout")),
+        Next::Done => Ok(None),
+    }
+}
+
+/// Convert the result of advancing an iterator for `poll`.
+fn poll_result<T>(next: Next<T>) -> PyResult<Option<T>> {
+    match next {
+        Next::Item(item) => Ok(Some(item)),
+        Next::Pending => Ok(None),
+        Next::Done => Err(PyStopIteration::new_err(())),
+    }
+}
+
+/// Convert a timeout in seconds into a duration.
+fn timeout_arg(timeout: Option<f64>) -> PyResult<Option<Duration>> {
+    match timeout {
+        Some(secs) if !(secs > 0.0 && secs.is_finite()) => Err(PyValueError::new_err(format!(
+            "invalid timeout; expected seconds > 0, got {secs}"
+        ))),
+        Some(secs) => Ok(Some(Duration::from_secs_f64(secs))),
+        None => Ok(None),
+    }
+}
+
+/// Check a spacecraft id fits the 14 bits of the AOS frame header, which also covers
+/// the 10-bit TM frame SCID.
+fn check_scid(scid: impl Into<i64> + Copy + std::fmt::Display) -> PyResult<()> {
+    if !(0..16384).contains(&scid.into()) {
+        return Err(PyValueError::new_err(format!(
+            "invalid scid value; expected 0..16384, got {scid}"
+        )));
+    }
+    Ok(())
+}
+
+/// Convert an iterable of ids, e.g., a list or set of APIDs, into a set.
+fn set_arg<'py, T>(ids: Option<&'py PyAny>) -> PyResult<Option<HashSet<T>>>
+where
+    T: FromPyObject<'py> + Eq + Hash,
+{
+    ids.map(|ids| ids.iter()?.map(|id| id?.extract()).collect())
+        .transpose()
+}
+
+/// Convert the packet sanity limit arguments into limits, if any were provided.
+fn limits_arg(
+    max_packet_len: Option<usize>,
+    versions: Option<&PyAny>,
+    apids: Option<&PyAny>,
+) -> PyResult<Option<packets::Limits>> {
+    let versions: Option<HashSet<u8>> = set_arg(versions)?;
+    let apids: Option<HashSet<u16>> = set_arg(apids)?;
+    if let Some(len) = max_packet_len {
+        if len <= packets::HEADER_LEN {
+            return Err(PyValueError::new_err(format!(
+                "invalid max_packet_len; expected > {}, got {len}",
+                packets::HEADER_LEN
+            )));
         }
     }
+    if let Some(version) = versions.iter().flatten().find(|v| **v > 7) {
+        return Err(PyValueError::new_err(format!(
+            "invalid version; expected 0..=7, got {version}"
+        )));
+    }
+    if let Some(apid) = apids.iter().flatten().find(|a| **a > 0x7ff) {
+        return Err(PyValueError::new_err(format!(
+            "invalid apid; expected 0..=2047, got {apid}"
+        )));
+    }
+    if max_packet_len.is_none() && versions.is_none() && apids.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(packets::Limits {
+        max_len: max_packet_len,
+        versions,
+        apids,
+    }))
 }
 
 /// Decode space packet data from the provided source.
 ///
 /// Parameters
 /// ----------
-/// source : str
-///     Source providing stream of space packets to decode. Currently only local
-///     file paths are supported.
+/// source : str or os.PathLike
+///     Source providing stream of space packets to decode. This may be a local file
+///     path, a named pipe, `tcp://host:port` to read from a TCP server,
+///     `pcap://path?port=port` to read the UDP payloads of a pcap or pcapng capture,
+///     optionally only those to or from a comma-separated list of ports, or
+///     `udp://host:port` to bind to the address and read the payloads of the datagrams
+///     received. Offsets of UDP sources are those in the concatenated payloads.
+///
+///     Datagrams of ground station feeds that carry an annotation header before each
+///     CADU or frame are handled by adding `header_len=<bytes>` to the query of either
+///     UDP source, e.g., `udp://0.0.0.0:5000?header_len=16`. A header field giving the
+///     length of the CADU or frame, to drop any padding, is configured with
+///     `length_offset=<offset>`, `length_size=<1, 2 or 4>` and
+///     `byte_order=<big or little>`, and `add_asm=true` adds the standard ASM before
+///     frames sent without one.
+///
+///     It may also be `eps://path` to read the packets of the measurement data records
+///     of an EPS Level 0 product, e.g., of MetOp, where offsets are those in the
+///     concatenated packets.
+///
+/// prefetch : int, optional
+///     If provided, decoding runs on a dedicated thread that fills a queue of up to this
+///     many items ahead of the consumer, overlapping I/O and decoding with Python-side
+///     processing.
+///
+/// timeout : float, optional
+///     Number of seconds to wait for the next item before raising a TimeoutError. This
+///     is intended for live sources and implies decoding on a background thread.
+///
+/// resync : bool
+///     If true, a packet whose length does not lead to another plausible packet header
+///     is treated as corrupt and the stream is scanned forward for the next plausible
+///     packet, rather than losing the remainder of the stream. Skipped spans are
+///     available from `PacketIterator.skipped`. This requires reading one header ahead,
+///     so each packet is only yielded once the next header has been received.
+///
+/// max_packet_len : int, optional
+///     Maximum plausible packet length in bytes, including the primary header.
+///
+/// versions : Iterable[int], optional
+///     Allowed packet version numbers. Defaults to only version 0 if any limit is set.
+///
+/// apids : Iterable[int], optional
+///     Allowed APIDs.
+///
+/// truncated : str
+///     What to do if the stream ends part way through a packet; `drop` to silently drop
+///     it, `partial` to yield the incomplete packet with `Packet.truncated` set, or
+///     `raise` to raise an EOFError. If the stream ends part way through a primary
+///     header `partial` also raises. When `resync` is true an incomplete final packet
+///     cannot be distinguished from a corrupt length and is treated as corrupt.
+///
+/// rice : dict of int to RiceConfig, optional
+///     Rice (CCSDS 121.0) configuration for APIDs carrying compressed data. Packets with
+///     these APIDs have their data decompressed into `Packet.samples`.
+///
+/// secondary_headers : dict of int to bool, optional
+///     Whether packets with each APID are expected to have a secondary header, to catch
+///     packets whose secondary header flag does not match, e.g., because of a mux
+///     configuration error. Packets with other APIDs are not checked.
+///
+/// secondary_header_policy : str
+///     What to do with packets that do not match `secondary_headers`; `flag` to yield
+///     them, `drop` to drop them, or `raise` to raise a ValueError. Flagged and dropped
+///     packets are available from `secondary_header_violations`.
+///
+/// secondary_header_lens : dict of int to int, optional
+///     Length of the secondary header of packets with each APID, used by
+///     `Packet.user_data` and `Packet.secondary_header_bytes`. Defaults to 8 bytes.
+///
+/// apid_lengths : dict of int to (int or None, int or None), optional
+///     Minimum and maximum length in bytes, including the primary header, of packets
+///     with each APID, e.g., from `MibPacket.min_len`, to catch malformed packets.
+///     Either bound may be None. Packets with other APIDs are not checked.
+///
+/// length_policy : str
+///     What to do with packets that do not match `apid_lengths`, as for
+///     `secondary_header_policy`. Flagged and dropped packets are available from
+///     `length_violations`.
+///
+/// fill : FillDetector, optional
+///     Thresholds for detecting packets whose payload is mostly fill, e.g., from an
+///     on-board buffer underrun, which pass every other check but contain no real data.
+///
+/// fill_policy : str
+///     What to do with packets detected as fill by `fill`, as for
+///     `secondary_header_policy`. Flagged and dropped packets are available from
+///     `fill_violations`.
+///
+/// filter : str, optional
+///     Only yield packets matching this filter expression, e.g.,
+///     `"apid in (1289, 1290) and seq_flags == 3"`. See `PacketFilter`. Filtered packets
+///     are still tracked for gaps.
+///
+/// quicklook : dict of int to QuickLookConfig, optional
+///     Image-bearing APIDs to accumulate decimated quick-look images for, with each
+///     packet carrying a line, e.g., for a visual check of instrument data quality. The
+///     images are available from `quicklook` and include packets that do not pass
+///     `filter` or sampling.
+///
+/// sample : int, optional
+///     Only yield every Nth packet, starting with the first, for quick-look analysis of
+///     large sources. Sampling applies to the packets that pass `filter`, and
+///     unsampled packets are still tracked for gaps.
+///
+/// probability : float, optional
+///     Only yield each packet with this probability, as an alternative to `sample`.
+///
+/// seed : int, optional
+///     Seed for `probability` sampling, for a reproducible sample.
+///
+/// anomalies : AnomalyCollector, optional
+///     Collector to emit an `Anomaly` to for each read error, resync, sequence gap,
+///     secondary header violation, length violation, suspected fill, sequence flag
+///     violation and clock violation.
+///
+/// headers_only : bool
+///     Only decode the primary header of each packet, skipping over the packet data
+///     without copying it, for gap analysis and indexing where payloads are irrelevant.
+///     The `data` of each packet is only its primary header. Cannot be used with
+///     `rice`, `fill`, `quicklook` or `clock`, which need the packet data.
+///
+/// check_sequence_flags : bool
+///     Check the sequence flags of the packets of each APID for violations of the
+///     packet grouping protocol, which indicate an onboard packetization error:
+///     `orphan_continuation` and `orphan_last` for a continuation or last packet
+///     without a preceding first packet, `unsegmented_in_group` for an unsegmented
+///     packet inside a group, and `unterminated_group` for a group followed by another
+///     first packet, or the end of the stream, rather than a last packet. Violations
+///     are available from `sequence_flag_violations`, with the offset of the packet or
+///     the first packet of the group. Packets lost in transmission also cause
+///     violations, so compare them with any sequence gaps.
+///
+/// clock : ClockCheck, optional
+///     Thresholds for flagging packets whose time regresses or jumps from that of the
+///     previous packet with the same APID, e.g., from an onboard clock reset, before
+///     it scrambles time-ordered processing. Flagged packets are still yielded, and are
+///     available from `clock_violations`.
+///
+/// If any of `max_packet_len`, `versions` or `apids` is provided each header is
+/// validated against them. A header that fails validation raises a ValueError giving
+/// its offset and the limit it violates, ending decoding, or is skipped if `resync` is
+/// true.
 ///
 /// Returns
 /// -------
 ///     Iterator of Packets
-#[pyfunction]
-fn decode_packets(source: PyObject) -> PyResult<PacketIterator> {
-    let path = match Python::with_gil(|py| -> PyResult<String> { source.extract(py) }) {
-        Ok(s) => s,
-        Err(e) => return Err(e),
-    };
+#[pyfunction(signature=(source, prefetch=None, timeout=None, resync=false, max_packet_len=None, versions=None, apids=None, truncated="drop", rice=None, filter=None, secondary_headers=None, secondary_header_policy="flag", secondary_header_lens=None, apid_lengths=None, length_policy="flag", fill=None, fill_policy="flag", sample=None, probability=None, seed=None, quicklook=None, anomalies=None, headers_only=false, check_sequence_flags=false, clock=None))]
+#[allow(clippy::too_many_arguments)]
+fn decode_packets(
+    source: PathBuf,
+    prefetch: Option<usize>,
+    timeout: Option<f64>,
+    resync: bool,
+    max_packet_len: Option<usize>,
+    versions: Option<&PyAny>,
+    apids: Option<&PyAny>,
+    truncated: &str,
+    rice: Option<HashMap<u16, rice::RiceConfig>>,
+    filter: Option<&str>,
+    secondary_headers: Option<HashMap<u16, bool>>,
+    secondary_header_policy: &str,
+    secondary_header_lens: Option<HashMap<u16, usize>>,
+    apid_lengths: Option<packets::ApidLengths>,
+    length_policy: &str,
+    fill: Option<fill::FillDetector>,
+    fill_policy: &str,
+    sample: Option<u64>,
+    probability: Option<f64>,
+    seed: Option<u64>,
+    quicklook: Option<HashMap<u16, quicklook::QuickLookConfig>>,
+    anomalies: Option<anomaly::AnomalyCollector>,
+    headers_only: bool,
+    check_sequence_flags: bool,
+    clock: Option<clock::ClockCheck>,
+) -> PyResult<PacketIterator> {
+    if headers_only {
+        let conflicts = [
+            ("rice", rice.is_some()),
+            ("fill", fill.is_some()),
+            ("quicklook", quicklook.is_some()),
+            ("clock", clock.is_some()),
+        ];
+        if let Some((name, _)) = conflicts.iter().find(|(_, used)| *used) {
+            return Err(PyValueError::new_err(format!(
+                "headers_only cannot be used with {name}"
+            )));
+        }
+    }
+    let sampler = sample::Sampler::from_args(sample, probability, seed)?;
+    let timeout = timeout_arg(timeout)?;
+    let filter = filter::filter_arg(filter)?;
+    let mut secondary_headers =
+        packets::SecondaryHeaderCheck::from_args(secondary_headers, secondary_header_policy)?;
+    secondary_headers.anomalies = anomalies.clone();
+    let mut lengths = packets::LengthCheck::from_args(apid_lengths, length_policy)?;
+    lengths.anomalies = anomalies.clone();
+    let mut fill = fill::FillCheck::from_args(fill, fill_policy)?;
+    fill.anomalies = anomalies.clone();
+    let mut sequence_flags = packets::SequenceFlagCheck::new(check_sequence_flags);
+    sequence_flags.anomalies = anomalies.clone();
+    let mut clock = clock::ClockMonitor::new(clock);
+    clock.anomalies = anomalies.clone();
+    let limits = limits_arg(max_packet_len, versions, apids)?;
+    let truncated = packets::Truncated::from_name(truncated)?;
 
-    let file: Box<dyn Read + Send> = Box::new(File::open(path)?);
-    let packets: Box<dyn Iterator<Item = ccsds::Packet> + Send + 'static> =
-        Box::new(ccsds::read_packets(file).filter_map(Result::ok));
+    let cancel = cancel::CancelToken::default();
+    let progress = progress::Progress::new(&source);
+    let reader = summary::CountingReader::new(BufReader::new(source::open_source(&source)?));
+    let bytes_read = reader.counter();
+    let reader = cancel::CancellableReader::new(reader, cancel.clone());
 
-    Ok(PacketIterator { packets })
+    Ok(PacketIterator {
+        packets: prefetch::Pipeline::with_timeout(
+            Box::new(
+                packets::PacketReader::new(reader)
+                    .resync(resync)
+                    .limits(limits)
+                    .truncated(truncated)
+                    .headers_only(headers_only),
+            ),
+            prefetch,
+            timeout,
+        )?,
+        timeout,
+        bytes_read,
+        gaps: gaps::GapTracker::default(),
+        summary: summary::Summary::default(),
+        cancel,
+        skipped: Vec::default(),
+        rice: rice.unwrap_or_default(),
+        filter,
+        secondary_headers,
+        secondary_header_lens: secondary_header_lens.unwrap_or_default(),
+        lengths,
+        fill,
+        sequence_flags,
+        clock,
+        progress,
+        sampler,
+        quicklooks: quicklook::QuickLooks::new(quicklook),
+        anomalies,
+        position: position::Position::default(),
+        peeked: None,
+    })
 }
 
 #[pyclass]
 struct DecodedPacketIterator {
-    packets: Box<dyn Iterator<Item = ccsds::DecodedPacket> + Send>,
+    packets: prefetch::Pipeline<services::FramedItem>,
+    timeout: Option<Duration>,
+    cancel: cancel::CancelToken,
+    /// The Reed-Solomon interleave, if used, including when detected automatically.
+    #[pyo3(get)]
+    interleave: Option<u8>,
+    filter: Option<filter::PacketFilter>,
+    secondary_headers: packets::SecondaryHeaderCheck,
+    secondary_header_lens: HashMap<u16, usize>,
+    lengths: packets::LengthCheck,
+    fill: fill::FillCheck,
+    sequence_flags: packets::SequenceFlagCheck,
+    clock: clock::ClockMonitor,
+    sampler: Option<sample::Sampler>,
+    quicklooks: quicklook::QuickLooks,
+    frame_cache: Option<framecache::FrameCache>,
+    /// Position of the most recent item, added to errors.
+    position: position::Position,
+    /// Item returned by `peek` but not yet consumed.
+    peeked: Option<PyObject>,
+}
+
+impl DecodedPacketIterator {
+    fn advance(&mut self, py: Python, poll: bool) -> PyResult<Next<PyObject>> {
+        self.next_item(py, poll)
+            .map_err(|err| self.position.annotate(py, err))
+    }
+
+    fn next_item(&mut self, py: Python, poll: bool) -> PyResult<Next<PyObject>> {
+        loop {
+            if self.cancel.is_cancelled() {
+                return Ok(Next::Done);
+            }
+            if let Err(err) = py.check_signals() {
+                self.cancel.cancel();
+                return Err(err);
+            }
+            let next = if poll {
+                self.packets.poll()
+            } else {
+                match self.packets.wait(py, self.timeout) {
+                    Ok(next) => next,
+                    Err(err) => {
+                        self.cancel.cancel();
+                        return Err(err);
+                    }
+                }
+            };
+            return Ok(match next {
+                Next::Item(services::FramedItem::Packet(packet, frame)) => {
+                    let mut packet = DecodedPacket::new(packet);
+                    let apid = packet.packet.header.apid;
+                    self.position = position::Position {
+                        offset: None,
+                        frame,
+                        packet: Some((apid, packet.packet.header.sequence_id)),
+                    };
+                    packet.packet.secondary_header_len =
+                        self.secondary_header_lens.get(&apid).copied();
+                    let hdr = &packet.packet.header;
+                    self.sequence_flags.check(hdr, None);
+                    self.clock.check(hdr, packet.packet.payload(), None);
+                    if !self
+                        .secondary_headers
+                        .check(hdr.apid, hdr.has_secondary_header, None)?
+                        || !self.lengths.check(hdr, None)?
+                        || !self.fill.check(hdr.apid, packet.packet.payload(), None)?
+                    {
+                        continue;
+                    }
+                    self.quicklooks.add(hdr.apid, packet.packet.payload());
+                    if let Some(filter) = &self.filter {
+                        let values = filter::Values {
+                            header: &packet.packet.header,
+                            data_len: packet.packet.payload().len(),
+                            ids: Some((packet.scid, packet.vcid)),
+                        };
+                        if !filter.eval(&values) {
+                            continue;
+                        }
+                    }
+                    if !sample::keep(&mut self.sampler) {
+                        continue;
+                    }
+                    Next::Item(Py::new(py, packet)?.into_py(py))
+                }
+                Next::Item(services::FramedItem::Bitstream(data)) => {
+                    self.position = position::Position {
+                        offset: None,
+                        frame: Some((data.scid, data.vcid, data.counter)),
+                        packet: None,
+                    };
+                    Next::Item(Py::new(py, data)?.into_py(py))
+                }
+                Next::Pending => Next::Pending,
+                Next::Done => {
+                    self.sequence_flags.finish();
+                    Next::Done
+                }
+            });
+        }
+    }
 }
 
 #[pymethods]
@@ -186,11 +1130,91 @@ impl DecodedPacketIterator {
         slf
     }
 
-    fn __next__(mut slf: PyRefMut<Self>) -> Option<Py<DecodedPacket>> {
-        match slf.packets.next() {
-            Some(packet) => Py::new(slf.py(), DecodedPacket::new(packet)).ok(),
-            None => None,
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<PyObject>> {
+        if let Some(item) = slf.peeked.take() {
+            return Ok(Some(item));
+        }
+        let py = slf.py();
+        next_or_timeout(slf.advance(py, false)?)
+    }
+
+    /// Return the next packet if one is available without blocking, otherwise `None`.
+    /// Raises StopIteration once the stream is exhausted.
+    fn poll(mut slf: PyRefMut<Self>) -> PyResult<Option<PyObject>> {
+        if let Some(item) = slf.peeked.take() {
+            return Ok(Some(item));
+        }
+        let py = slf.py();
+        poll_result(slf.advance(py, true)?)
+    }
+
+    /// Return the next item without consuming it, blocking until it is available, or
+    /// `None` once the stream is exhausted. The item is returned again by the next
+    /// call to `next` or `poll`.
+    fn peek(mut slf: PyRefMut<Self>) -> PyResult<Option<PyObject>> {
+        let py = slf.py();
+        if slf.peeked.is_none() {
+            let next = slf.advance(py, false)?;
+            slf.peeked = next_or_timeout(next)?;
         }
+        Ok(slf.peeked.as_ref().map(|item| item.clone_ref(py)))
+    }
+
+    /// Stop decoding. Any background decoding stops promptly and the iterator is
+    /// exhausted on the next call to `next`.
+    fn cancel(&mut self) {
+        self.cancel.cancel();
+        self.peeked = None;
+    }
+
+    /// APID of each packet so far whose secondary header flag did not match
+    /// `secondary_headers`, with an offset of `None` as offsets are not known for
+    /// packets decoded from frames.
+    fn secondary_header_violations(&self) -> Vec<(u16, Option<u64>)> {
+        self.secondary_headers.violations.clone()
+    }
+
+    /// APID and length of each packet so far whose length did not match
+    /// `apid_lengths`, with an offset of `None`.
+    fn length_violations(&self) -> Vec<(u16, usize, Option<u64>)> {
+        self.lengths.violations.clone()
+    }
+
+    /// APID, fill byte and fraction of the payload that is fill of each packet so far
+    /// detected as fill by `fill`, with an offset of `None`.
+    fn fill_violations(&self) -> Vec<(u16, u8, f64, Option<u64>)> {
+        self.fill.violations.clone()
+    }
+
+    /// APID, kind of violation and offset, always `None`, of each sequence flag
+    /// violation so far when decoding with `check_sequence_flags`.
+    fn sequence_flag_violations(&self) -> Vec<(u16, &'static str, Option<u64>)> {
+        self.sequence_flags.violations.clone()
+    }
+
+    /// APID, kind of violation, previous and current packet time and offset of each
+    /// packet so far whose time regressed or jumped according to `clock`.
+    fn clock_violations(&self) -> Vec<(u16, &'static str, i64, i64, Option<u64>)> {
+        self.clock.violations.clone()
+    }
+
+    /// Quick-look images accumulated so far for the APIDs configured with
+    /// `quicklook`, by APID.
+    fn quicklook(&self) -> BTreeMap<u16, quicklook::QuickLook> {
+        self.quicklooks.images()
+    }
+
+    /// The most recent cached frame with the given VCID and frame counter, and
+    /// spacecraft id if provided, or `None` if it is not cached. The counter is the
+    /// extended counter for frames using the counter cycle. Requires `frame_cache`.
+    #[pyo3(signature=(vcid, counter, scid=None))]
+    fn cached_frame(&self, vcid: u16, counter: u32, scid: Option<u16>) -> PyResult<Option<Frame>> {
+        Ok(framecache::enabled(&self.frame_cache)?.get(vcid, counter, scid))
+    }
+
+    /// The cached frames, oldest first. Requires `frame_cache`.
+    fn cached_frames(&self) -> PyResult<Vec<Frame>> {
+        Ok(framecache::enabled(&self.frame_cache)?.frames())
     }
 }
 
@@ -201,6 +1225,8 @@ enum RSState {
     Corrected,
     Uncorrectable,
     NotPerformed,
+    /// Correctable errors were found but, in check-only mode, not corrected.
+    Detected,
 }
 
 #[pymethods]
@@ -214,12 +1240,24 @@ impl RSState {
             Self::Corrected => "corrected",
             Self::Uncorrectable => "uncorrectable",
             Self::NotPerformed => "notperformed",
+            Self::Detected => "detected",
         }
         .to_owned()
     }
 }
 
-#[pyclass]
+impl From<&ccsds::RSState> for RSState {
+    fn from(rsstate: &ccsds::RSState) -> Self {
+        match rsstate {
+            ccsds::RSState::Ok => Self::Ok,
+            ccsds::RSState::Corrected(_) => Self::Corrected,
+            ccsds::RSState::Uncorrectable(_) => Self::Uncorrectable,
+            ccsds::RSState::NotPerformed => Self::NotPerformed,
+        }
+    }
+}
+
+#[pyclass(frozen)]
 #[derive(Clone, Debug)]
 struct VCDUHeader {
     #[pyo3(get)]
@@ -246,20 +1284,55 @@ impl VCDUHeader {
     fn __str__(&self) -> String {
         format!(
             "VCDUHeader(version={}, scid={}, vcid={}, counter={}, replay={}, cycle={}, counter_cycle={})",
-            self.version, self.scid, self.vcid, self.counter, self.replay, self.cycle, self.counter_cycle,
+            self.version, self.scid, self.vcid, self.counter, repr(&self.replay), repr(&self.cycle), self.counter_cycle,
         ).to_owned()
     }
+
+    /// The frame counter extended by the 4-bit counter cycle if the cycle is in use,
+    /// otherwise the 24-bit frame counter.
+    #[getter]
+    fn extended_counter(&self) -> u32 {
+        self.extended().0
+    }
 }
 
-#[pyclass]
+impl VCDUHeader {
+    /// The extended frame counter and its width in bits.
+    fn extended(&self) -> (u32, u8) {
+        if self.cycle {
+            let counter = u32::from(self.counter_cycle) << 24 | self.counter;
+            (counter, gaps::AOS_CYCLE_COUNTER_BITS)
+        } else {
+            (self.counter, gaps::AOS_COUNTER_BITS)
+        }
+    }
+}
+
+#[pyclass(frozen)]
 #[derive(Clone, Debug)]
 struct Frame {
     #[pyo3(get)]
     header: VCDUHeader,
     #[pyo3(get)]
     rsstate: RSState,
-    #[pyo3(get)]
     data: Vec<u8>,
+    /// Python `bytes` copy of `data`, created on first access and shared by later ones.
+    data_bytes: OnceLock<Py<PyBytes>>,
+    /// Byte offset of the frame's attached sync marker in the source, if known.
+    #[pyo3(get)]
+    offset: Option<u64>,
+    /// Time in UTC milliseconds decoded from the insert zone, or approximated from the
+    /// first packet in the frame, if an insert zone or packet timecode is configured
+    /// for the frame's spacecraft.
+    #[pyo3(get)]
+    time: Option<i64>,
+    /// True if the insert zone checksum matched, if one is configured for the frame's
+    /// spacecraft.
+    #[pyo3(get)]
+    izone_crc: Option<bool>,
+    /// Number of bits in error in the frame's attached sync marker, if known.
+    #[pyo3(get)]
+    asm_errors: Option<u32>,
 }
 
 #[pymethods]
@@ -269,20 +1342,73 @@ impl Frame {
     }
     fn __str__(&self) -> String {
         format!(
-            "Frame(header={}, rsstate={}, data_len={})",
+            "Frame(header={}, rsstate={}, data_len={}, offset={})",
             self.header.__str__(),
             self.rsstate.__str__(),
             self.data.len(),
+            repr(&self.offset),
         )
         .to_owned()
     }
+
+    /// The frame data as immutable `bytes`. The data is copied into Python only on the
+    /// first access; later accesses return the same object.
+    #[getter]
+    fn data(&self, py: Python) -> Py<PyBytes> {
+        self.data_bytes
+            .get_or_init(|| PyBytes::new(py, &self.data).into())
+            .clone_ref(py)
+    }
+
+    fn __bytes__(&self, py: Python) -> Py<PyBytes> {
+        self.data(py)
+    }
+
+    /// Hex and ASCII dump of the frame data, `width` bytes per line.
+    #[pyo3(signature=(width=16))]
+    fn hexdump(&self, width: usize) -> PyResult<String> {
+        dump::hexdump(&self.data, width)
+    }
+
+    /// Readable block of the VCDU header fields and RS state followed by a dump of the
+    /// first `max_bytes` of the frame data.
+    #[pyo3(signature=(max_bytes=dump::DESCRIBE_LEN))]
+    fn describe(&self, max_bytes: usize) -> String {
+        let hdr = &self.header;
+        let mut fields = vec![
+            ("version", hdr.version.to_string()),
+            ("scid", hdr.scid.to_string()),
+            ("vcid", hdr.vcid.to_string()),
+            ("counter", hdr.counter.to_string()),
+            ("replay", hdr.replay.to_string()),
+            ("cycle", hdr.cycle.to_string()),
+            ("counter_cycle", hdr.counter_cycle.to_string()),
+            ("rsstate", self.rsstate.__str__()),
+            ("data_len", self.data.len().to_string()),
+        ];
+        if let Some(offset) = self.offset {
+            fields.push(("offset", offset.to_string()));
+        }
+        if let Some(time) = self.time {
+            fields.push(("time", time.to_string()));
+        }
+        dump::describe("Frame", &fields, &self.data, max_bytes)
+    }
 }
 
 impl Frame {
-    fn new(decoded_frame: ccsds::DecodedFrame) -> Self {
-        use ccsds::RSState::{Corrected, NotPerformed, Ok, Uncorrectable};
-        let frame = decoded_frame.frame;
-        let h = frame.header;
+    fn new(mut decoded_frame: ccsds::DecodedFrame) -> Self {
+        let data = std::mem::take(&mut decoded_frame.frame.data);
+        Self::from_parts(&decoded_frame, data)
+    }
+
+    /// A copy of a decoded frame, leaving the original for further decoding.
+    fn from_ref(decoded_frame: &ccsds::DecodedFrame) -> Self {
+        Self::from_parts(decoded_frame, decoded_frame.frame.data.clone())
+    }
+
+    fn from_parts(decoded_frame: &ccsds::DecodedFrame, data: Vec<u8>) -> Self {
+        let h = &decoded_frame.frame.header;
         Frame {
             header: VCDUHeader {
                 version: h.version,
@@ -293,20 +1419,220 @@ impl Frame {
                 cycle: h.cycle,
                 counter_cycle: h.counter_cycle,
             },
-            rsstate: match decoded_frame.rsstate {
-                Ok => RSState::Ok,
-                Corrected(_) => RSState::Corrected,
-                Uncorrectable(_) => RSState::Uncorrectable,
-                NotPerformed => RSState::NotPerformed,
-            },
-            data: frame.data,
+            rsstate: RSState::from(&decoded_frame.rsstate),
+            data,
+            data_bytes: OnceLock::new(),
+            offset: None,
+            time: None,
+            izone_crc: None,
+            asm_errors: None,
+        }
+    }
+
+    /// Decode a single frame from a pseudo-randomized CADU `block` following its
+    /// attached sync marker, correcting and removing any Reed-Solomon parity. Returns
+    /// `None` if the block is too short to contain a frame.
+    fn from_block(mut block: Vec<u8>, interleave: Option<u8>) -> Option<Self> {
+        pn::apply(&mut block);
+        let outcome = match interleave.map(usize::from) {
+            Some(interleave) if !rs::valid_block_len(block.len(), interleave) => return None,
+            Some(interleave) => Some(rs::decode_interleaved(&mut block, interleave)),
+            None => None,
+        };
+        Self::from_decoded_block(block, outcome)
+    }
+
+    /// Decode a single frame from a derandomized CADU `block`, whose Reed-Solomon
+    /// codewords, if any, have been decoded with `outcome`, removing the parity.
+    fn from_decoded_block(mut block: Vec<u8>, outcome: Option<rs::Outcome>) -> Option<Self> {
+        let rsstate = match &outcome {
+            None => RSState::NotPerformed,
+            Some(outcome) if outcome.uncorrectable > 0 => RSState::Uncorrectable,
+            Some(outcome) if outcome.corrected > 0 => RSState::Corrected,
+            Some(_) => RSState::Ok,
+        };
+        if let Some(outcome) = outcome {
+            block.truncate(block.len() - rs::PARITY_LEN * outcome.codewords.len());
+        }
+        if block.len() < services::VCDU_HEADER_LEN {
+            return None;
         }
+        Some(Frame {
+            header: VCDUHeader {
+                version: block[0] >> 6,
+                scid: u16::from(block[0] & 0x3f) << 2 | u16::from(block[1] >> 6),
+                vcid: u16::from(block[1] & 0x3f),
+                counter: u32::from_be_bytes([0, block[2], block[3], block[4]]),
+                replay: block[5] & 0x80 != 0,
+                cycle: block[5] & 0x40 != 0,
+                counter_cycle: block[5] & 0xf,
+            },
+            rsstate,
+            data: block,
+            data_bytes: OnceLock::new(),
+            offset: None,
+            time: None,
+            izone_crc: None,
+            asm_errors: None,
+        })
     }
 }
 
 #[pyclass]
 struct FrameIterator {
-    frames: Box<dyn Iterator<Item = ccsds::DecodedFrame> + Send>,
+    /// Decoded frames with their offsets in the source, or `None` for frames that failed
+    /// to decode.
+    frames: prefetch::Pipeline<Option<Frame>>,
+    timeout: Option<Duration>,
+    bytes_read: Arc<AtomicU64>,
+    sync_errors: Arc<AtomicU64>,
+    gap_tracker: gaps::FrameGapTracker,
+    gaps: Vec<gaps::FrameGap>,
+    summary: summary::Summary,
+    cancel: cancel::CancelToken,
+    /// The attached sync marker frames are synchronized on.
+    #[pyo3(get)]
+    asm: Vec<u8>,
+    /// The Reed-Solomon interleave, if used, including when detected automatically.
+    #[pyo3(get)]
+    interleave: Option<u8>,
+    /// True if Reed-Solomon parity is only checked, in which case frames the decoder
+    /// reports as corrected were not modified.
+    rs_check_only: bool,
+    izone_timecodes: HashMap<u16, timecode::Timecode>,
+    packet_timecodes: HashMap<u16, (timecode::TimecodeRegistry, usize)>,
+    izone_crcs: izone::IzoneCrcCheck,
+    /// Length of each CADU in the source, i.e., the ASM length plus `frame_len`.
+    cadu_len: u64,
+    progress: progress::Progress,
+    sampler: Option<sample::Sampler>,
+    anomalies: Option<anomaly::AnomalyCollector>,
+    frame_cache: Option<framecache::FrameCache>,
+    /// Position of the most recent item, added to errors.
+    position: position::Position,
+    /// Frame returned by `peek` but not yet consumed.
+    peeked: Option<Py<Frame>>,
+}
+
+impl FrameIterator {
+    fn advance(&mut self, py: Python, poll: bool) -> PyResult<Next<Py<Frame>>> {
+        self.next_item(py, poll)
+            .map_err(|err| self.position.annotate(py, err))
+    }
+
+    fn next_item(&mut self, py: Python, poll: bool) -> PyResult<Next<Py<Frame>>> {
+        loop {
+            if self.cancel.is_cancelled() {
+                return Ok(Next::Done);
+            }
+            if let Err(err) = py.check_signals() {
+                self.cancel();
+                return Err(err);
+            }
+            let next = if poll {
+                self.frames.poll()
+            } else {
+                match self.frames.wait(py, self.timeout) {
+                    Ok(next) => next,
+                    Err(err) => {
+                        self.cancel();
+                        return Err(err);
+                    }
+                }
+            };
+            let mut frame = match next {
+                Next::Item(Some(frame)) => frame,
+                Next::Item(None) => {
+                    self.summary.errors += 1;
+                    anomaly::emit(&self.anomalies, || {
+                        anomaly::Anomaly::new(
+                            "frame_error",
+                            anomaly::Severity::Error,
+                            "failed to decode frame".to_string(),
+                        )
+                    });
+                    continue;
+                }
+                Next::Pending => return Ok(Next::Pending),
+                Next::Done => {
+                    self.summary.complete = true;
+                    self.progress.finish();
+                    return Ok(Next::Done);
+                }
+            };
+            self.position = position::Position {
+                offset: frame.offset,
+                frame: Some((frame.header.scid, frame.header.vcid, frame.header.counter)),
+                packet: None,
+            };
+            if let Some(offset) = frame.offset {
+                self.progress.read_to(offset + self.cadu_len);
+            }
+            if self.rs_check_only && matches!(frame.rsstate, RSState::Corrected) {
+                frame.rsstate = RSState::Detected;
+            }
+            self.summary.add_rsstate(&frame.rsstate);
+            let hdr = &frame.header;
+            if matches!(frame.rsstate, RSState::Uncorrectable) {
+                anomaly::emit(&self.anomalies, || {
+                    anomaly::Anomaly::new(
+                        "rs_uncorrectable",
+                        anomaly::Severity::Error,
+                        "uncorrectable Reed-Solomon codeword".to_string(),
+                    )
+                    .frame(hdr.scid, hdr.vcid)
+                    .offset(frame.offset)
+                });
+            }
+            let (counter, bits) = hdr.extended();
+            let gap = self
+                .gap_tracker
+                .add(hdr.scid, hdr.vcid, counter, bits, frame.offset);
+            if let Some(gap) = gap {
+                self.summary.missing += u64::from(gap.count);
+                anomaly::emit(&self.anomalies, || {
+                    anomaly::Anomaly::new(
+                        "frame_gap",
+                        anomaly::Severity::Warning,
+                        format!(
+                            "{} frames missing between counters {} and {}",
+                            gap.count, gap.start_counter, gap.end_counter
+                        ),
+                    )
+                    .frame(gap.scid, gap.vcid)
+                    .offset(gap.end_offset)
+                });
+                self.gaps.push(gap);
+            }
+            if let Some(timecode) = self.izone_timecodes.get(&frame.header.scid) {
+                let izone = frame.data.get(services::VCDU_HEADER_LEN..);
+                frame.time = izone.and_then(|izone| timecode.decode(izone));
+            }
+            if frame.time.is_none() {
+                if let Some((timecode, izone_len)) = self.packet_timecodes.get(&frame.header.scid) {
+                    frame.time = services::first_packet_time(&frame.data, *izone_len, timecode);
+                }
+            }
+            frame.izone_crc = self.izone_crcs.check(
+                frame.header.scid,
+                frame.header.vcid,
+                &frame.data,
+                frame.offset,
+            )?;
+            if let Some(cache) = &self.frame_cache {
+                cache.push(frame.clone());
+            }
+            if !self.izone_crcs.keep(frame.izone_crc) {
+                continue;
+            }
+            if !sample::keep(&mut self.sampler) {
+                continue;
+            }
+            self.summary.items += 1;
+            self.progress.yielded();
+            return Py::new(py, frame).map(Next::Item);
+        }
+    }
 }
 
 #[pymethods]
@@ -315,11 +1641,80 @@ impl FrameIterator {
         slf
     }
 
-    fn __next__(mut slf: PyRefMut<Self>) -> Option<Py<Frame>> {
-        match slf.frames.next() {
-            Some(decoded_frame) => Py::new(slf.py(), Frame::new(decoded_frame)).ok(),
-            None => None,
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<Py<Frame>>> {
+        if let Some(item) = slf.peeked.take() {
+            return Ok(Some(item));
         }
+        let py = slf.py();
+        next_or_timeout(slf.advance(py, false)?)
+    }
+
+    /// Return the next frame if one is available without blocking, otherwise `None`.
+    /// Raises StopIteration once the stream is exhausted.
+    fn poll(mut slf: PyRefMut<Self>) -> PyResult<Option<Py<Frame>>> {
+        if let Some(item) = slf.peeked.take() {
+            return Ok(Some(item));
+        }
+        let py = slf.py();
+        poll_result(slf.advance(py, true)?)
+    }
+
+    /// Return the next frame without consuming it, blocking until it is available, or
+    /// `None` once the stream is exhausted. The frame is returned again by the next
+    /// call to `next` or `poll`.
+    fn peek(mut slf: PyRefMut<Self>) -> PyResult<Option<Py<Frame>>> {
+        let py = slf.py();
+        if slf.peeked.is_none() {
+            let next = slf.advance(py, false)?;
+            slf.peeked = next_or_timeout(next)?;
+        }
+        Ok(slf.peeked.as_ref().map(|item| item.clone_ref(py)))
+    }
+
+    /// Stop decoding. Any background decoding stops promptly and the iterator is
+    /// exhausted on the next call to `next`.
+    fn cancel(&mut self) {
+        self.cancel.cancel();
+        self.summary.cancelled = true;
+        self.peeked = None;
+    }
+
+    /// Totals for the frames decoded so far. Once the iterator is exhausted the
+    /// summary is marked as complete.
+    fn summary(&self) -> summary::Summary {
+        let mut summary = self.summary.clone();
+        summary.bytes_read = self.bytes_read.load(Ordering::Relaxed);
+        summary.errors += self.sync_errors.load(Ordering::Relaxed);
+        summary
+    }
+
+    /// Frame counter discontinuities detected so far, in the order they were detected.
+    fn gaps(&self) -> Vec<gaps::FrameGap> {
+        self.gaps.clone()
+    }
+
+    /// Fraction of the source consumed so far, from 0 to 1, or `None` if the source
+    /// is not a regular file.
+    fn progress(&self) -> Option<f64> {
+        self.progress.fraction()
+    }
+
+    /// Estimated number of frames remaining, from the average CADU spacing so far.
+    fn __length_hint__(&self, py: Python) -> PyObject {
+        self.progress.length_hint(py)
+    }
+
+    /// The most recent cached frame with the given VCID and frame counter, and
+    /// spacecraft id if provided, or `None` if it is not cached. The counter is the
+    /// extended counter for frames using the counter cycle. Requires `frame_cache`.
+    #[pyo3(signature=(vcid, counter, scid=None))]
+    fn cached_frame(&self, vcid: u16, counter: u32, scid: Option<u16>) -> PyResult<Option<Frame>> {
+        Ok(framecache::enabled(&self.frame_cache)?.get(vcid, counter, scid))
+    }
+
+    /// The cached frames, oldest first. Requires `frame_cache`.
+    fn cached_frames(&self) -> PyResult<Vec<Frame>> {
+        Ok(framecache::enabled(&self.frame_cache)?.frames())
     }
 }
 
@@ -334,54 +1729,410 @@ impl FrameIterator {
 ///
 /// Parameters
 /// ----------
-/// source: str
+/// source: str or os.PathLike
 ///     Source of stream containing CADUs using the standard CCSDS ASM that are pseudo
-///     randomized. Currently, only local file paths are supported.
+///     randomized. This may be a local file path, a named pipe, `tcp://host:port`
+///     to read from a TCP server, `pcap://path?port=port` to read the UDP payloads of a
+///     capture, or `udp://host:port` to receive UDP datagrams, optionally stripping an
+///     annotation header from each, as for `decode_packets`.
 ///
 /// frame_len : int
 ///     Length of each frame. This will be the overall CADU length minus the ASM bytes.
-///     If using Reed-Solomon this must be the interleave * RS message size (255). If
-///     this value is < 0 a ValueError will be raised.
+///     If using Reed-Solomon this must be the interleave * RS message size (255). A
+///     ValueError explaining the mismatch is raised if it is not, or if it is too short
+///     to hold a VCDU header.
 ///
-/// interleave : int
+/// interleave : int or str
 ///     The Reed-Solomon interleave. Typical values include 4 o4 5. If this is not set
 ///     no Reed-Solomon FEC is used and it is assumed the frames will not include any
-///     Reed-Solomon parity bytes.
+///     Reed-Solomon parity bytes. Use `auto` to detect the interleave by decoding the
+///     first frames of the stream, see `detect_interleave`; the result is available
+///     from the iterator's `interleave` attribute.
+///
+/// prefetch : int, optional
+///     If provided, decoding runs on a dedicated thread that fills a queue of up to this
+///     many items ahead of the consumer, overlapping I/O and decoding with Python-side
+///     processing.
+///
+/// timeout : float, optional
+///     Number of seconds to wait for the next item before raising a TimeoutError. This
+///     is intended for live sources and implies decoding on a background thread.
+///
+/// asm : bytes or str, optional
+///     Attached sync marker to synchronize on. This may be the marker bytes, the name of
+///     a built-in marker (see `detect_asm`), or `auto` to detect the marker from the
+///     start of the source. Defaults to the standard CCSDS ASM.
+///
+/// rs_check_only : bool
+///     Only check the Reed-Solomon parity, never modifying the frame data, which
+///     includes the parity bytes. Frames with correctable errors have the `detected`
+///     RSState rather than `corrected`. Requires `interleave`.
+///
+/// uncorrectable_dest : str or os.PathLike, optional
+///     Path of a file to write the CADUs of uncorrectable frames to, as received, i.e.,
+///     including the ASM and still randomized, e.g., for analysis of the link.
+///
+/// corrected_dest : str or os.PathLike, optional
+///     Path of a file to write the CADUs of corrected frames to, as received. Failures
+///     writing either file are counted as errors in the iterator summary.
+///
+/// izone_timecodes : dict of int to str, optional
+///     Timecode at the start of the frame insert zone for each spacecraft id, one of
+///     "cds", "eoscuc" or "cuc", as for TimecodeRegistry. Frames of these spacecraft have
+///     their `time` decoded from the insert zone.
+///
+/// packet_timecodes : dict of int to (str or TimecodeRegistry, int), optional
+///     Packet timecode, either a format name or a TimecodeRegistry, and insert zone
+///     length in bytes for each spacecraft id. Frames of these spacecraft have an approximate
+///     `time`, that of the first packet whose primary header starts in the frame, for
+///     frame-level time filtering. Frames without such a packet, or whose packet
+///     timecode does not fit in the frame, have no time. The insert zone timecode is
+///     used instead if both are configured and it decodes.
+///
+/// izone_crcs : dict of int to (str, int), optional
+///     Checksum algorithm, either "crc16" or "crc32", and insert zone length in bytes
+///     for each spacecraft id whose insert zone ends with a big-endian checksum of the
+///     rest of the insert zone. Frames of these spacecraft have `izone_crc` set to
+///     whether the checksum matched.
+///
+/// izone_crc_policy : str
+///     What to do with a frame whose insert zone checksum does not match, one of
+///     "flag" to yield it, "drop" to drop it or "raise" to raise a ValueError.
+///
+/// sample : int, optional
+///     Only yield every Nth frame, starting with the first, for quick-look analysis of
+///     large sources. Unsampled frames are still counted in the summary Reed-Solomon
+///     totals and tracked for gaps.
+///
+/// probability : float, optional
+///     Only yield each frame with this probability, as an alternative to `sample`.
+///
+/// seed : int, optional
+///     Seed for `probability` sampling, for a reproducible sample.
+///
+/// anomalies : AnomalyCollector, optional
+///     Collector to emit an `Anomaly` to for each sync loss, decode error,
+///     uncorrectable frame, frame counter gap and insert zone checksum mismatch.
+///
+/// frame_cache : int, optional
+///     Number of the most recently decoded frames to keep in memory, so the frames
+///     associated with a downstream anomaly can be retrieved with `cached_frame` without
+///     re-reading the source.
+///
+/// headers_only : bool
+///     Only decode the VCDU header of each frame, skipping Reed-Solomon decoding and
+///     copying of the rest of the frame, to quickly inventory the VCIDs and counters of
+///     a recording. The `data` of each frame is only its header and its `rsstate` is
+///     not performed. Cannot be used with the options that need the rest of the frame.
+///
+/// rs_threads : int, optional
+///     Number of threads to decode the Reed-Solomon codewords on, e.g., `os.cpu_count()`.
+///     The codewords of each frame, and of consecutive frames, are decoded in parallel
+///     and the frames yielded in stream order. Frames are decoded in batches of 16 per
+///     thread, so are yielded later than they are received from a live source.
+///     Requires `interleave`.
+///
+/// report : PassReporter, optional
+///     Reporter to add the lock statistics and frames to as they are decoded, so a
+///     `PassReport` is available once the iterator is exhausted.
+///
+/// asm_errors : int
+///     Number of bits of the attached sync marker, 0 to 3, that may be in error for it
+///     to be accepted, so lock is acquired and held on noisy streams. A marker is first
+///     looked for where it is expected following the previous frame. The number of bits
+///     in error in the marker of each frame is available from its `asm_errors`.
 ///
 /// Returns
 /// -------
 /// FrameIterator
 ///     An interable providing all decoded Frames.
-#[pyfunction(signature=(source, frame_len, interleave=None))]
-fn decode_frames(source: &str, frame_len: i32, interleave: Option<i32>) -> PyResult<FrameIterator> {
-    if frame_len < 0 {
-        return Err(PyValueError::new_err("frame_size cannot be > 0"));
-    }
-    let file: Box<dyn Read + Send> = Box::new(File::open(source)?);
-    let blocks =
-        ccsds::Synchronizer::new(file, &ccsds::ASM.to_vec(), frame_len.try_into().unwrap())
-            .into_iter()
-            .filter_map(Result::ok);
-
-    let mut builder = ccsds::FrameDecoderBuilder::default();
-
-    if let Some(interleave) = interleave {
-        if !(2..=10).contains(&interleave) {
+#[pyfunction(signature=(source, frame_len, interleave=None, prefetch=None, timeout=None, asm=None, rs_check_only=false, uncorrectable_dest=None, corrected_dest=None, izone_timecodes=None, izone_crcs=None, izone_crc_policy="flag", sample=None, probability=None, seed=None, anomalies=None, frame_cache=None, headers_only=false, rs_threads=None, report=None, asm_errors=0, packet_timecodes=None))]
+#[allow(clippy::too_many_arguments)]
+fn decode_frames(
+    source: PathBuf,
+    frame_len: i32,
+    interleave: Option<&PyAny>,
+    prefetch: Option<usize>,
+    timeout: Option<f64>,
+    asm: Option<&PyAny>,
+    rs_check_only: bool,
+    uncorrectable_dest: Option<PathBuf>,
+    corrected_dest: Option<PathBuf>,
+    izone_timecodes: Option<HashMap<u16, &str>>,
+    izone_crcs: Option<HashMap<u16, (&str, usize)>>,
+    izone_crc_policy: &str,
+    sample: Option<u64>,
+    probability: Option<f64>,
+    seed: Option<u64>,
+    anomalies: Option<anomaly::AnomalyCollector>,
+    frame_cache: Option<usize>,
+    headers_only: bool,
+    rs_threads: Option<usize>,
+    report: Option<report::PassReporter>,
+    asm_errors: u32,
+    packet_timecodes: Option<HashMap<u16, (&PyAny, usize)>>,
+) -> PyResult<FrameIterator> {
+    sync::check_asm_errors(asm_errors)?;
+    if headers_only {
+        let conflicts = [
+            ("rs_check_only", rs_check_only),
+            ("rs_threads", rs_threads.is_some()),
+            ("uncorrectable_dest", uncorrectable_dest.is_some()),
+            ("corrected_dest", corrected_dest.is_some()),
+            ("izone_timecodes", izone_timecodes.is_some()),
+            ("izone_crcs", izone_crcs.is_some()),
+            ("packet_timecodes", packet_timecodes.is_some()),
+        ];
+        if let Some((name, _)) = conflicts.iter().find(|(_, used)| *used) {
             return Err(PyValueError::new_err(format!(
-                "improbable interleave value; expected 2..10: got {interleave}"
+                "headers_only cannot be used with {name}"
             )));
         }
-        let interleave: u8 = interleave.try_into().unwrap(); // checked above
-        builder = builder.reed_solomon(interleave);
     }
+    let frame_cache = framecache::FrameCache::from_arg(frame_cache)?;
+    let sampler = sample::Sampler::from_args(sample, probability, seed)?;
+    let izone_timecodes = izone_timecodes
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(scid, name)| Ok((scid, timecode::Timecode::from_name(name)?)))
+        .collect::<PyResult<HashMap<_, _>>>()?;
+    let packet_timecodes = packet_timecodes
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(scid, (timecode, izone_len))| {
+            if izone_len >= 16 {
+                return Err(PyValueError::new_err(format!(
+                    "invalid packet_timecodes izone_len; expected 0..16, got {izone_len}"
+                )));
+            }
+            Ok((scid, (timecode::registry_arg(timecode)?, izone_len)))
+        })
+        .collect::<PyResult<HashMap<_, _>>>()?;
+    let mut izone_crcs = izone::IzoneCrcCheck::from_args(izone_crcs, izone_crc_policy)?;
+    izone_crcs.anomalies = anomalies.clone();
+    let frameconfig::FrameDecodeConfig {
+        asm,
+        frame_len,
+        interleave,
+    } = frameconfig::FrameDecodeConfig::new(
+        &source,
+        frame_len,
+        interleave,
+        asm,
+        headers_only,
+        rs_check_only,
+    )?;
+    if let Some(rs_threads) = rs_threads {
+        if rs_threads == 0 {
+            return Err(PyValueError::new_err(
+                "invalid rs_threads; expected > 0, got 0",
+            ));
+        }
+        if rs_check_only {
+            return Err(PyValueError::new_err(
+                "rs_threads cannot be used with rs_check_only",
+            ));
+        }
+        if interleave.is_none() {
+            return Err(PyValueError::new_err("rs_threads requires an interleave"));
+        }
+    }
+    let timeout = timeout_arg(timeout)?;
+    let cancel = cancel::CancelToken::default();
+    let cadu_len = (asm.len() + frame_len) as u64;
+    let progress = progress::Progress::new(&source);
+    let reader = summary::CountingReader::new(source::open_source(&source)?);
+    let bytes_read = reader.counter();
+    let file: Box<dyn Read + Send> =
+        Box::new(cancel::CancellableReader::new(reader, cancel.clone()));
+    let sync_errors = Arc::new(AtomicU64::default());
+    let errors = sync_errors.clone();
+    let tee_errors = sync_errors.clone();
+    let mut tee = tee::RsTee::new(&asm, uncorrectable_dest, corrected_dest)?;
+    // Blocks, as received, waiting for the result of decoding them, kept for the tee
+    // and to recover the uncorrectable frames the frame decoder drops.
+    let keep_raw = tee.is_some()
+        || (interleave.is_some() && !rs_check_only && rs_threads.is_none() && !headers_only);
+    let raw_blocks: Option<Arc<Mutex<VecDeque<Vec<u8>>>>> = keep_raw.then(Arc::default);
+    let pending = raw_blocks.clone();
+    // The frame decoder yields a result for each block in order, so the offsets and
+    // marker errors of blocks handed to it are matched up with its results in the same
+    // order.
+    let offsets: Arc<Mutex<VecDeque<(u64, u32)>>> = Arc::default();
+    let block_offsets = offsets.clone();
+    let sync_anomalies = anomalies.clone();
+    let block_report = report.clone();
+    let mut expected_offset: Option<u64> = None;
+    let mut synchronizer = sync::Synchronizer::new(file, &asm, frame_len).asm_errors(asm_errors);
+    if headers_only {
+        synchronizer = synchronizer.prefix(services::VCDU_HEADER_LEN);
+    }
+    let blocks = synchronizer.blocks().filter_map(move |block| match block {
+        Ok(sync::SyncBlock {
+            offset,
+            block,
+            asm_errors,
+        }) => {
+            if let Some(expected) = expected_offset.filter(|expected| offset > *expected) {
+                anomaly::emit(&sync_anomalies, || {
+                    anomaly::Anomaly::new(
+                        "sync_loss",
+                        anomaly::Severity::Warning,
+                        format!("lost sync for {} bytes", offset - expected),
+                    )
+                    .offset(Some(expected))
+                });
+            }
+            expected_offset = Some(offset + cadu_len);
+            if let Some(reporter) = &block_report {
+                reporter.add_cadu(offset, cadu_len);
+            }
+            block_offsets
+                .lock()
+                .unwrap()
+                .push_back((offset, asm_errors));
+            if let Some(pending) = &pending {
+                pending.lock().unwrap().push_back(block.clone());
+            }
+            Some(block)
+        }
+        Err(_) => {
+            errors.fetch_add(1, Ordering::Relaxed);
+            None
+        }
+    });
+
+    let frames: Box<dyn Iterator<Item = Option<Frame>> + Send> = if headers_only {
+        Box::new(blocks.map(move |header| {
+            let (offset, asm_errors) = offsets.lock().unwrap().pop_front().unzip();
+            Frame::from_block(header, None).map(|frame| Frame {
+                offset,
+                asm_errors,
+                ..frame
+            })
+        }))
+    } else if let (Some(interleave), Some(threads)) = (interleave, rs_threads) {
+        let blocks = Box::new(blocks.map(|mut block| {
+            pn::apply(&mut block);
+            block
+        }));
+        let decoded = rs::ParallelDecoder::new(blocks, interleave.into(), threads);
+        Box::new(decoded.map(move |(block, outcome)| {
+            let (offset, asm_errors) = offsets.lock().unwrap().pop_front().unzip();
+            let raw = raw_blocks
+                .as_ref()
+                .and_then(|raw| raw.lock().unwrap().pop_front());
+            if let (Some(tee), Some(raw)) = (tee.as_mut(), raw) {
+                if tee
+                    .write(&raw, outcome.as_ref().map(rs_state).as_ref())
+                    .is_err()
+                {
+                    tee_errors.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            let frame = Frame::from_decoded_block(block, Some(outcome?))?;
+            Some(Frame {
+                offset,
+                asm_errors,
+                ..frame
+            })
+        }))
+    } else {
+        let mut builder = ccsds::FrameDecoderBuilder::default();
+        let decoded: Box<dyn Iterator<Item = Option<ccsds::DecodedFrame>> + Send> = match interleave
+        {
+            Some(interleave) if rs_check_only => Box::new(
+                builder
+                    .build()
+                    .start(blocks)
+                    .map(move |frame| frame.ok().map(|frame| check_rs(frame, interleave))),
+            ),
+            Some(interleave) => {
+                builder = builder.reed_solomon(interleave);
+                Box::new(builder.build().start(blocks).map(Result::ok))
+            }
+            None => Box::new(builder.build().start(blocks).map(Result::ok)),
+        };
+        Box::new(decoded.map(move |frame| {
+            let (offset, asm_errors) = offsets.lock().unwrap().pop_front().unzip();
+            let raw = raw_blocks
+                .as_ref()
+                .and_then(|raw| raw.lock().unwrap().pop_front());
+            if let (Some(tee), Some(raw)) = (tee.as_mut(), &raw) {
+                if tee.write(raw, frame.as_ref().map(|f| &f.rsstate)).is_err() {
+                    tee_errors.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            let frame = match frame {
+                Some(frame) => Frame::new(frame),
+                // the frame decoder drops uncorrectable frames, so decode their blocks
+                // again to yield them flagged as uncorrectable
+                None => Frame::from_block(raw?, interleave)?,
+            };
+            Some(Frame {
+                offset,
+                asm_errors,
+                ..frame
+            })
+        }))
+    };
 
-    let frames = builder.build().start(blocks).filter_map(Result::ok);
+    let frames: Box<dyn Iterator<Item = Option<Frame>> + Send> = match report {
+        Some(reporter) => Box::new(frames.inspect(move |frame| {
+            if let Some(frame) = frame {
+                let hdr = &frame.header;
+                reporter.add_frame(hdr.scid, hdr.vcid, hdr.counter, &frame.rsstate);
+            }
+        })),
+        None => frames,
+    };
 
     Ok(FrameIterator {
-        frames: Box::new(frames),
+        frames: prefetch::Pipeline::with_timeout(frames, prefetch, timeout)?,
+        timeout,
+        bytes_read,
+        sync_errors,
+        gap_tracker: gaps::FrameGapTracker::default(),
+        gaps: Vec::default(),
+        summary: summary::Summary::default(),
+        cancel,
+        asm,
+        interleave,
+        rs_check_only,
+        izone_timecodes,
+        packet_timecodes,
+        izone_crcs,
+        cadu_len,
+        progress,
+        sampler,
+        anomalies,
+        frame_cache,
+        position: position::Position::default(),
+        peeked: None,
     })
 }
 
+/// Check the Reed-Solomon parity of a frame that was decoded without Reed-Solomon,
+/// setting the state a correcting decoder would have reported without modifying the
+/// frame data.
+fn check_rs(mut frame: ccsds::DecodedFrame, interleave: u8) -> ccsds::DecodedFrame {
+    let outcome = rs::decode_interleaved(&mut frame.frame.data.clone(), interleave.into());
+    frame.rsstate = rs_state(&outcome);
+    frame
+}
+
+/// The state the Reed-Solomon decoder reports for a frame decoded with `outcome`.
+fn rs_state(outcome: &rs::Outcome) -> ccsds::RSState {
+    if outcome.uncorrectable > 0 {
+        let msg = format!("{} uncorrectable codewords", outcome.uncorrectable);
+        ccsds::RSState::Uncorrectable(msg)
+    } else if outcome.corrected > 0 {
+        ccsds::RSState::Corrected(outcome.corrected as i32)
+    } else {
+        ccsds::RSState::Ok
+    }
+}
+
 /// Decode space packets from the byte stream provided by source.
 ///
 /// The decode synchronization process starts immediately in the background and progresses
@@ -393,55 +2144,261 @@ fn decode_frames(source: &str, frame_len: i32, interleave: Option<i32>) -> PyRes
 ///
 /// Parameters
 /// ----------
-/// source: str
+/// source: str or os.PathLike
 ///     Source of stream containing CADUs using the standard CCSDS ASM that are pseudo
-///     randomized. Currently, only local file paths are supported.
+///     randomized. This may be a local file path, a named pipe, `tcp://host:port`
+///     to read from a TCP server, `pcap://path?port=port` to read the UDP payloads of a
+///     capture, or `udp://host:port` to receive UDP datagrams, optionally stripping an
+///     annotation header from each, as for `decode_packets`.
 ///
-/// scid : int
-///     Spacecraft identifier for the spacecraft that is the source of the data
+/// scid : int, optional
+///     Spacecraft identifier for the spacecraft that is the source of the data. Required
+///     unless `mission` is provided.
 ///
-/// cadu_len: int
+/// cadu_len: int, optional
 ///     The length of the CADU, i.e., the ASM length plus the length of the frame plus the
 ///     length of any integrity or parity bytes.
 ///
 ///     When using Reed-Solomon, this will typically be 1024 for interleave=4 and 1279 when
 ///     using interleave=5.
 ///
-/// izone_len : int
-///     Frame insert-zone number of bytes used by the spacecraft, if any.
+///     If not provided it is taken from the `framing_config` of the spacecraft, as are
+///     `izone_len`, `trailer_len` and `interleave` if not provided, so the spacecraft
+///     must be in the spacecraftsdb database.
+///
+/// izone_len : int, optional
+///     Frame insert-zone number of bytes used by the spacecraft, if any. Defaults to that
+///     of the spacecraft's `framing_config` if available, otherwise 0.
 ///
-/// trailer_len : int
-///     Frame trailer number of bytes used by the spacecraft, if any.
+/// trailer_len : int, optional
+///     Frame trailer number of bytes used by the spacecraft, if any. Defaults to that of
+///     the spacecraft's `framing_config` if available, otherwise 0.
 ///
-/// interleave : int
+/// interleave : int or str, optional
 ///     The Reed-Solomon interleave. Typical values include 4 o4 5. If this is not set
-///     no Reed-Solomon FEC is used and it is assumed the frames will not include any
-///     Reed-Solomon parity bytes.
+///     the interleave of the spacecraft's `framing_config` is used, if available,
+///     otherwise no Reed-Solomon FEC is used and it is assumed the frames will not
+///     include any Reed-Solomon parity bytes. Use `auto` to detect the interleave by
+///     decoding the first frames of the stream, see `detect_interleave`; the result is
+///     available from the iterator's `interleave` attribute.
+///
+/// prefetch : int, optional
+///     If provided, decoding runs on a dedicated thread that fills a queue of up to this
+///     many items ahead of the consumer, overlapping I/O and decoding with Python-side
+///     processing.
+///
+/// timeout : float, optional
+///     Number of seconds to wait for the next item before raising a TimeoutError. This
+///     is intended for live sources and implies decoding on a background thread.
+///
+/// asm : bytes or str, optional
+///     Attached sync marker to synchronize on, as accepted by `decode_frames`. Defaults
+///     to the standard CCSDS ASM.
+///
+/// services : dict of int to str, optional
+///     Service carried by each VCID, one of `packets`, `bitstream`, `idle` or `ignore`.
+///     Frames on `bitstream` VCIDs are yielded as BitstreamData, interleaved with the
+///     decoded packets in approximately stream order. Frames on `idle` and `ignore`
+///     VCIDs are dropped. VCIDs not in the map are decoded as packets.
+///
+/// secondary_headers : dict of int to bool, optional
+///     Expected presence of a secondary header for each APID, as accepted by
+///     `decode_packets`.
+///
+/// secondary_header_policy : str
+///     What to do with packets that do not match `secondary_headers`, as accepted by
+///     `decode_packets`.
+///
+/// secondary_header_lens : dict of int to int, optional
+///     Length of the secondary header of packets with each APID, as accepted by
+///     `decode_packets`.
+///
+/// apid_lengths : dict of int to (int or None, int or None), optional
+///     Minimum and maximum packet lengths for each APID, as accepted by
+///     `decode_packets`.
+///
+/// length_policy : str
+///     What to do with packets that do not match `apid_lengths`, as accepted by
+///     `decode_packets`.
+///
+/// fill : FillDetector, optional
+///     Thresholds for detecting packets whose payload is mostly fill, as accepted by
+///     `decode_packets`.
+///
+/// fill_policy : str
+///     What to do with packets detected as fill by `fill`, as accepted by
+///     `decode_packets`.
+///
+/// filter : str, optional
+///     Only yield packets matching this filter expression, which may also compare
+///     `scid` and `vcid`. See `PacketFilter`. BitstreamData is not filtered.
+///
+/// quicklook : dict of int to QuickLookConfig, optional
+///     Image-bearing APIDs to accumulate decimated quick-look images for, with each
+///     packet carrying a line, e.g., for a visual check of instrument data quality. The
+///     images are available from `quicklook` and include packets that do not pass
+///     `filter` or sampling.
+///
+/// sample : int, optional
+///     Only yield every Nth packet, starting with the first, for quick-look analysis of
+///     large sources. Sampling applies to the packets that pass `filter`.
+///     BitstreamData is not sampled.
+///
+/// probability : float, optional
+///     Only yield each packet with this probability, as an alternative to `sample`.
+///
+/// seed : int, optional
+///     Seed for `probability` sampling, for a reproducible sample.
+///
+/// anomalies : AnomalyCollector, optional
+///     Collector to emit an `Anomaly` to for each decode error, uncorrectable frame,
+///     secondary header violation, length violation, suspected fill, sequence flag
+///     violation and clock violation.
+///
+/// frame_cache : int, optional
+///     Number of the most recently decoded frames to keep in memory, so the frames
+///     associated with a downstream anomaly can be retrieved with `cached_frame` without
+///     re-reading the source.
+///
+/// report : PassReporter, optional
+///     Reporter to add the lock statistics, frames and packets to as they are decoded,
+///     so a `PassReport` is available once the iterator is exhausted.
+///
+/// asm_errors : int
+///     Number of bits of the attached sync marker, 0 to 3, that may be in error for it
+///     to be accepted, as for `decode_frames`.
+///
+/// check_sequence_flags : bool
+///     Check the sequence flags of the packets of each APID for violations of the
+///     packet grouping protocol, as for `decode_packets`.
+///
+/// mission : str, optional
+///     Name or alias of a mission in the spacecraft database, e.g., `snpp` or `metop-b`,
+///     see `missions`, whose `MissionPreset` provides the `scid`, framing parameters
+///     and `services` not otherwise provided.
+///
+/// clock : ClockCheck, optional
+///     Thresholds for flagging packets whose time regresses or jumps, as accepted by
+///     `decode_packets`.
 ///
 /// Returns
 /// -------
 /// DecodedPacketIterator
-///     An interable providing all DecodedPackets
-#[pyfunction(signature=(source, scid, cadu_len, izone_len=0, trailer_len=0, interleave=None))]
+///     An interable providing all DecodedPackets, and BitstreamData if any VCIDs are
+///     configured for bitstream service.
+#[pyfunction(signature=(source, scid=None, cadu_len=None, izone_len=None, trailer_len=None, interleave=None, prefetch=None, timeout=None, asm=None, services=None, filter=None, secondary_headers=None, secondary_header_policy="flag", secondary_header_lens=None, apid_lengths=None, length_policy="flag", fill=None, fill_policy="flag", sample=None, probability=None, seed=None, quicklook=None, anomalies=None, frame_cache=None, report=None, asm_errors=0, check_sequence_flags=false, mission=None, clock=None))]
+#[allow(clippy::too_many_arguments)]
 fn decode_framed_packets(
-    source: &str,
-    scid: i32,
-    cadu_len: i32,
+    py: Python,
+    source: PathBuf,
+    scid: Option<i32>,
+    cadu_len: Option<i32>,
     izone_len: Option<i32>,
     trailer_len: Option<i32>,
-    interleave: Option<i32>,
+    interleave: Option<&PyAny>,
+    prefetch: Option<usize>,
+    timeout: Option<f64>,
+    asm: Option<&PyAny>,
+    services: Option<HashMap<u16, &str>>,
+    filter: Option<&str>,
+    secondary_headers: Option<HashMap<u16, bool>>,
+    secondary_header_policy: &str,
+    secondary_header_lens: Option<HashMap<u16, usize>>,
+    apid_lengths: Option<packets::ApidLengths>,
+    length_policy: &str,
+    fill: Option<fill::FillDetector>,
+    fill_policy: &str,
+    sample: Option<u64>,
+    probability: Option<f64>,
+    seed: Option<u64>,
+    quicklook: Option<HashMap<u16, quicklook::QuickLookConfig>>,
+    anomalies: Option<anomaly::AnomalyCollector>,
+    frame_cache: Option<usize>,
+    report: Option<report::PassReporter>,
+    asm_errors: u32,
+    check_sequence_flags: bool,
+    mission: Option<&str>,
+    clock: Option<clock::ClockCheck>,
 ) -> PyResult<DecodedPacketIterator> {
-    if cadu_len < 4 {
+    sync::check_asm_errors(asm_errors)?;
+    let frame_cache = framecache::FrameCache::from_arg(frame_cache)?;
+    let sampler = sample::Sampler::from_args(sample, probability, seed)?;
+    let timeout = timeout_arg(timeout)?;
+    let filter = filter::filter_arg(filter)?;
+    let mut secondary_headers =
+        packets::SecondaryHeaderCheck::from_args(secondary_headers, secondary_header_policy)?;
+    secondary_headers.anomalies = anomalies.clone();
+    let mut lengths = packets::LengthCheck::from_args(apid_lengths, length_policy)?;
+    lengths.anomalies = anomalies.clone();
+    let mut fill = fill::FillCheck::from_args(fill, fill_policy)?;
+    fill.anomalies = anomalies.clone();
+    let mut sequence_flags = packets::SequenceFlagCheck::new(check_sequence_flags);
+    sequence_flags.anomalies = anomalies.clone();
+    let mut clock = clock::ClockMonitor::new(clock);
+    clock.anomalies = anomalies.clone();
+    let preset = mission
+        .map(|name| mission::MissionPreset::from_name(name, None))
+        .transpose()?;
+    let services = services.or_else(|| preset.as_ref().map(|preset| preset.services()));
+    let services = services::services_arg(services)?;
+    let Some(scid) = scid.or_else(|| preset.as_ref().map(|preset| preset.scid.into())) else {
         return Err(PyValueError::new_err(
-            "cadu_len cannot be less than the ASM size (4)",
+            "scid is required if mission is not provided",
         ));
-    }
-    if !(0..16384).contains(&scid) {
-        return Err(PyValueError::new_err(format!(
-            "invalid scid value; expected 0..16384, got {scid}"
-        )));
-    }
+    };
+    check_scid(scid)?;
     let scid: ccsds::SCID = scid.try_into().unwrap();
+
+    // framing parameters not provided default to those of the mission preset, otherwise
+    // those of the spacecraft in the database
+    let framing = if let Some(preset) = &preset {
+        Some(preset.framing_config.clone())
+    } else if cadu_len.is_none()
+        || izone_len.is_none()
+        || trailer_len.is_none()
+        || interleave.is_none()
+    {
+        ccsds::framing_config(scid, None)
+            .ok()
+            .flatten()
+            .map(FramingConfig::new)
+    } else {
+        None
+    };
+    let db_interleave = framing
+        .as_ref()
+        .and_then(|framing| framing.reed_solomon.as_ref())
+        .map(|rs| rs.interleave.into_py(py));
+    let interleave = interleave.or_else(|| db_interleave.as_ref().map(|i| i.as_ref(py)));
+    let config = match (cadu_len, &framing) {
+        (Some(cadu_len), _) => frameconfig::FrameDecodeConfig::from_cadu_len(
+            &source,
+            cadu_len.into(),
+            interleave,
+            asm,
+        )?,
+        (None, Some(framing)) => frameconfig::FrameDecodeConfig::from_frame_len(
+            &source,
+            framing.codeblock_len(),
+            interleave,
+            asm,
+        )?,
+        (None, None) => {
+            return Err(PyValueError::new_err(format!(
+                "cadu_len not provided and no framing config found for spacecraft {scid}"
+            )))
+        }
+    };
+    let izone_len = izone_len.or_else(|| {
+        framing
+            .as_ref()
+            .map(|framing| framing.insert_zone_length as i32)
+    });
+    let trailer_len = trailer_len.or_else(|| {
+        framing
+            .as_ref()
+            .map(|framing| framing.trailer_length as i32)
+    });
     let izone_len: usize = if let Some(x) = izone_len {
         if !(0..16).contains(&x) {
             return Err(PyValueError::new_err(format!(
@@ -463,29 +2420,101 @@ fn decode_framed_packets(
         0
     };
 
-    let file = BufReader::new(File::open(source)?);
-    let block_size: usize = usize::try_from(cadu_len).unwrap() - ccsds::ASM.len();
-    let blocks = ccsds::Synchronizer::new(file, &ccsds::ASM.to_vec(), block_size)
-        .into_iter()
-        .filter_map(Result::ok);
+    config.check_layout(izone_len, trailer_len)?;
+
+    let cancel = cancel::CancelToken::default();
+    let file = cancel::CancellableReader::new(
+        BufReader::new(source::open_source(&source)?),
+        cancel.clone(),
+    );
+    let frameconfig::FrameDecodeConfig {
+        asm,
+        frame_len,
+        interleave,
+    } = config;
+    if let Some(reporter) = &report {
+        reporter.set_scid(scid);
+    }
+    let cadu_len = (asm.len() + frame_len) as u64;
+    let block_report = report.clone();
+    let synchronizer = sync::Synchronizer::new(file, &asm, frame_len).asm_errors(asm_errors);
+    let blocks = synchronizer.filter_map(move |block| {
+        let (offset, block) = block.ok()?;
+        if let Some(reporter) = &block_report {
+            reporter.add_cadu(offset, cadu_len);
+        }
+        Some(block)
+    });
 
     let mut builder = ccsds::FrameDecoderBuilder::default();
     if let Some(interleave) = interleave {
-        if !(2..=10).contains(&interleave) {
-            return Err(PyValueError::new_err(
-                "invalid interleave value; expected 2..10: got {interleave}",
-            ));
-        }
-        let interleave: u8 = interleave.try_into().unwrap(); // checked above
         builder = builder.reed_solomon(interleave);
     }
-    let frames = builder.build().start(blocks).filter_map(Result::ok);
+    let frame_anomalies = anomalies.clone();
+    let cache = frame_cache.clone();
+    let frame_report = report.clone();
+    let frames = builder
+        .build()
+        .start(blocks)
+        .filter_map(move |frame| match frame {
+            Ok(frame) => {
+                if let ccsds::RSState::Uncorrectable(msg) = &frame.rsstate {
+                    let hdr = &frame.frame.header;
+                    anomaly::emit(&frame_anomalies, || {
+                        anomaly::Anomaly::new(
+                            "rs_uncorrectable",
+                            anomaly::Severity::Error,
+                            msg.clone(),
+                        )
+                        .frame(hdr.scid, hdr.vcid)
+                    });
+                }
+                if let Some(cache) = &cache {
+                    cache.push(Frame::from_ref(&frame));
+                }
+                if let Some(reporter) = &frame_report {
+                    let hdr = &frame.frame.header;
+                    let rsstate = RSState::from(&frame.rsstate);
+                    reporter.add_frame(hdr.scid, hdr.vcid, hdr.counter, &rsstate);
+                }
+                Some(frame)
+            }
+            Err(err) => {
+                anomaly::emit(&frame_anomalies, || {
+                    anomaly::Anomaly::new("frame_error", anomaly::Severity::Error, err.to_string())
+                });
+                None
+            }
+        });
 
-    let packets: Box<dyn Iterator<Item = ccsds::DecodedPacket> + Send + 'static> = Box::new(
-        ccsds::decode_framed_packets(scid, frames, izone_len, trailer_len),
-    );
+    let packets = services::decode(scid, frames, izone_len, trailer_len, services);
+    let packets: Box<dyn Iterator<Item = services::FramedItem> + Send + 'static> = match report {
+        Some(reporter) => Box::new(packets.inspect(move |item| {
+            if let services::FramedItem::Packet(decoded, _) = item {
+                reporter.add_packet(&decoded.packet);
+            }
+        })),
+        None => Box::new(packets),
+    };
 
-    Ok(DecodedPacketIterator { packets })
+    Ok(DecodedPacketIterator {
+        packets: prefetch::Pipeline::with_timeout(packets, prefetch, timeout)?,
+        timeout,
+        cancel,
+        interleave,
+        filter,
+        secondary_headers,
+        secondary_header_lens: secondary_header_lens.unwrap_or_default(),
+        lengths,
+        fill,
+        sequence_flags,
+        clock,
+        sampler,
+        quicklooks: quicklook::QuickLooks::new(quicklook),
+        frame_cache,
+        position: position::Position::default(),
+        peeked: None,
+    })
 }
 
 /// Decode the provided CCSDS Day-Segmented timecode bytes into UTC milliseconds.
@@ -503,6 +2532,21 @@ fn decode_cds_timecode(dat: &[u8]) -> PyResult<i64> {
     }
 }
 
+/// Decode the provided CCSDS Day-Segmented timecode bytes into UTC nanoseconds,
+/// keeping the precision of the microsecond field that `decode_cds_timecode` truncates.
+///
+/// Parameters
+/// ----------
+/// dat : bytearray
+///     Byte array of at least 8 bytes for a CSD timecode. Only the first 8 are used
+///     if there are more. Raises a ValueError if there are not enough bytes to decode.
+#[pyfunction(signature=(dat))]
+fn decode_cds_timecode_ns(dat: &[u8]) -> PyResult<i64> {
+    timecode::Timecode::Cds
+        .decode_ns(dat)
+        .ok_or_else(|| PyValueError::new_err("not enough bytes"))
+}
+
 /// Decode provided bytes representing a CCSDS Unsegmented Timecode as used by the
 /// NASA EOS mission (Aqua & Terra) into a UTC timestamp in milliseconds.
 #[pyfunction(signature=(dat))]
@@ -513,6 +2557,46 @@ fn decode_eoscuc_timecode(dat: &[u8]) -> PyResult<i64> {
     }
 }
 
+/// As `decode_eoscuc_timecode`, but into UTC nanoseconds, keeping the precision of the
+/// fine time.
+#[pyfunction(signature=(dat))]
+fn decode_eoscuc_timecode_ns(dat: &[u8]) -> PyResult<i64> {
+    timecode::Timecode::EosCuc
+        .decode_ns(dat)
+        .ok_or_else(|| PyValueError::new_err("not enough bytes"))
+}
+
+/// Decode the provided CCSDS Unsegmented timecode bytes into UTC milliseconds, counting
+/// from a named epoch.
+///
+/// Parameters
+/// ----------
+/// dat : bytearray
+///     Byte array of at least `coarse` + `fine` bytes. Raises a ValueError if there are
+///     not enough bytes to decode.
+///
+/// coarse : int
+///     Octets of whole seconds, 1 to 4.
+///
+/// fine : int
+///     Octets of binary fractions of a second, 0 to 3.
+///
+/// epoch : str
+///     Name of the epoch the timecode counts from, one of `epochs()`.
+#[pyfunction(signature=(dat, coarse=4, fine=2, epoch="tai1958"))]
+fn decode_cuc_timecode(dat: &[u8], coarse: usize, fine: usize, epoch: &str) -> PyResult<i64> {
+    Ok(decode_cuc_timecode_ns(dat, coarse, fine, epoch)?.div_euclid(1_000_000))
+}
+
+/// As `decode_cuc_timecode`, but into UTC nanoseconds, keeping the precision of the
+/// fine time.
+#[pyfunction(signature=(dat, coarse=4, fine=2, epoch="tai1958"))]
+fn decode_cuc_timecode_ns(dat: &[u8], coarse: usize, fine: usize, epoch: &str) -> PyResult<i64> {
+    timecode::CucFormat::new(coarse, fine, epoch)?
+        .decode_ns(dat)
+        .ok_or_else(|| PyValueError::new_err("not enough bytes"))
+}
+
 /// Calculate the number of missing packets between cur and last.
 ///
 /// Note, packet sequence counters are per-APID.
@@ -521,12 +2605,27 @@ fn missing_packets(cur: u16, last: u16) -> u16 {
     ccsds::missing_packets(cur, last)
 }
 
-/// Calculate the number of missing frames between cur and last.
+/// Calculate the number of missing frames between cur and last, accounting for the
+/// counter wrapping to zero.
 ///
 /// Note frame sequence counts are per-VCID.
-#[pyfunction(signature=(cur, last))]
-fn missing_frames(cur: u32, last: u32) -> u32 {
-    ccsds::missing_frames(cur, last)
+///
+/// Parameters
+/// ----------
+/// cur : int
+///     Counter of the current frame.
+///
+/// last : int
+///     Counter of the previous frame on the same virtual channel.
+///
+/// counter_bits : int
+///     Width of the frame counter; 24 for the AOS frame counter, 28 for the AOS frame
+///     counter extended by the counter cycle (see `VCDUHeader.extended_counter`), or 8
+///     for the TM virtual channel and master channel frame counters.
+#[pyfunction(signature=(cur, last, counter_bits=gaps::AOS_COUNTER_BITS))]
+fn missing_frames(cur: u32, last: u32, counter_bits: u8) -> PyResult<u32> {
+    let bits = gaps::counter_bits_arg(counter_bits)?;
+    Ok(gaps::missing_counts(cur, last, bits))
 }
 
 #[pyclass]
@@ -535,9 +2634,7 @@ pub struct PnConfig;
 
 impl PnConfig {
     fn new(config: Option<spacecrafts::PnConfig>) -> Option<Self> {
-        config.map(|_| {
-            Self{}
-        })
+        config.map(|_| Self {})
     }
 }
 
@@ -577,17 +2674,15 @@ impl RSConfig {
 
 impl RSConfig {
     fn new(config: Option<spacecrafts::RSConfig>) -> Option<Self> {
-        config.map(|rs| {
-            RSConfig {
-                interleave: rs.interleave,
-                virtual_fill_length: rs.virtual_fill_length,
-                num_correctable: rs.num_correctable,
-            }
+        config.map(|rs| RSConfig {
+            interleave: rs.interleave,
+            virtual_fill_length: rs.virtual_fill_length,
+            num_correctable: rs.num_correctable,
         })
     }
 }
 
-#[pyclass]
+#[pyclass(frozen)]
 #[derive(Clone, Debug)]
 pub struct FramingConfig {
     #[pyo3(get)]
@@ -632,7 +2727,7 @@ impl FramingConfig {
         self.length, self.insert_zone_length, self.trailer_length, pn, rs).to_string()
     }
 
-    /// Return the computed length of a CADU block, i.e., CADU length - ASM length, from 
+    /// Return the computed length of a CADU block, i.e., CADU length - ASM length, from
     /// our config.
     pub fn codeblock_len(&self) -> usize {
         match &self.reed_solomon {
@@ -644,7 +2739,7 @@ impl FramingConfig {
 
 /// Lookup the FramingConfig for a spacecraft.
 ///
-/// This makes use of a spacecraftsdb formatted database file. See the releases at 
+/// This makes use of a spacecraftsdb formatted database file. See the releases at
 /// https://github.com/bmflynn/spacecraftsdb to download a database file.
 ///
 /// Parameters
@@ -652,8 +2747,8 @@ impl FramingConfig {
 /// scid : int
 ///     The spacecraft identifier for a spacecraft.
 ///
-/// path : str, optional
-///     Local path to a specific spacecraftsdb database file. If not provided this will 
+/// path : str or os.PathLike, optional
+///     Local path to a specific spacecraftsdb database file. If not provided this will
 ///     attempt to load the database from ./spacecraftsdb.json,
 ///     $XDG_DATA_HOME/spacecraftsdb/spacecraftsdb.json, ~/.spacecraftsdb.json.
 ///
@@ -662,7 +2757,8 @@ impl FramingConfig {
 /// FramingConfig or None
 ///     The configuration for the specified spacecraft if available, otherwise `None`
 #[pyfunction]
-fn framing_config(scid: u16, path: Option<&str>) -> PyResult<Option<FramingConfig>> {
+fn framing_config(scid: u16, path: Option<PathBuf>) -> PyResult<Option<FramingConfig>> {
+    let path = path.as_deref().map(source::path_str).transpose()?;
     match ccsds::framing_config(scid, path) {
         Ok(Some(framing)) => Ok(Some(FramingConfig::new(framing))),
         Ok(None) => Ok(None),
@@ -673,26 +2769,142 @@ fn framing_config(scid: u16, path: Option<&str>) -> PyResult<Option<FramingConfi
 /// ccsds
 ///
 /// Python wrapper for the [ccsds](https://github.com/bmflynn/ccsds) Rust crate.
+///
+/// Thread safety: value types, e.g., Packet, Frame and Layout, are immutable and may be
+/// shared freely between threads. Iterators and writers are mutable and must only be
+/// used by one thread at a time; a concurrent call raises a RuntimeError rather than
+/// corrupting state. Decoding releases the GIL where it runs on background threads.
+/// The module is not supported in subinterpreters or free-threaded builds of Python.
 #[pymodule]
 #[pyo3(name = "ccsds")]
-fn ccsdspy(_py: Python, m: &PyModule) -> PyResult<()> {
+fn ccsdspy(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(decode_packets, m)?)?;
     m.add_class::<Packet>()?;
     m.add_class::<DecodedPacket>()?;
+    m.add_class::<filter::PacketFilter>()?;
+    m.add_class::<services::BitstreamData>()?;
     m.add_class::<PrimaryHeader>()?;
     m.add_class::<RSState>()?;
+    m.add_class::<summary::Summary>()?;
 
     m.add_function(wrap_pyfunction!(decode_frames, m)?)?;
     m.add_function(wrap_pyfunction!(decode_framed_packets, m)?)?;
+    m.add_class::<session::DecodePipeline>()?;
+    m.add_function(wrap_pyfunction!(batch::process_files, m)?)?;
+    m.add_class::<batch::BatchSummary>()?;
+    m.add_class::<batch::FileSummary>()?;
+    m.add_function(wrap_pyfunction!(stage::map_batches, m)?)?;
+    m.add_class::<stage::BatchStage>()?;
     m.add_class::<Frame>()?;
     m.add_class::<VCDUHeader>()?;
+    m.add_function(wrap_pyfunction!(encode::encode_cadu, m)?)?;
+    m.add_class::<encode::PacketWriter>()?;
+    m.add_function(wrap_pyfunction!(roundtrip::roundtrip, m)?)?;
+    m.add_class::<roundtrip::RoundtripReport>()?;
+    m.add_class::<push::PushDecoder>()?;
+    m.add_class::<archive::PacketArchiveWriter>()?;
+    m.add_class::<archive::PacketArchive>()?;
+    m.add_class::<archive::ArchiveChunk>()?;
+    m.add_function(wrap_pyfunction!(split::split_cadus_by_vcid, m)?)?;
+    m.add_function(wrap_pyfunction!(turbo::decode_turbo, m)?)?;
+    m.add_function(wrap_pyfunction!(turbo::encode_turbo, m)?)?;
+    m.add_function(wrap_pyfunction!(dvbs2::extract_dvbs2, m)?)?;
+    m.add_class::<dvbs2::Dvbs2Summary>()?;
+    m.add_function(wrap_pyfunction!(cli::info_main, m)?)?;
+    m.add_function(wrap_pyfunction!(cli::filter_main, m)?)?;
+    m.add_function(wrap_pyfunction!(cli::merge_main, m)?)?;
+    m.add_function(wrap_pyfunction!(rice::rice_decompress, m)?)?;
+    m.add_class::<rice::RiceConfig>()?;
+    m.add_class::<fill::FillDetector>()?;
+    m.add_class::<clock::ClockCheck>()?;
+    m.add_class::<quicklook::QuickLookConfig>()?;
+    m.add_class::<quicklook::QuickLook>()?;
+
+    m.add_class::<layout::Field>()?;
+    m.add_class::<derived::Derived>()?;
+    m.add_class::<alarm::AlarmLimits>()?;
+    m.add_class::<alarm::Violation>()?;
+    m.add_class::<anomaly::Anomaly>()?;
+    m.add_class::<anomaly::AnomalyCollector>()?;
+    m.add_class::<layout::Layout>()?;
+    m.add_function(wrap_pyfunction!(dataframe::packets_to_dataframe, m)?)?;
+    m.add_function(wrap_pyfunction!(dataframe::packets_to_dataset, m)?)?;
+    m.add_class::<layout::Calibration>()?;
+    m.add_class::<timecode::TimecodeRegistry>()?;
+    m.add_class::<mib::MibPacket>()?;
+
+    m.add_function(wrap_pyfunction!(group::group_packets_by_apid, m)?)?;
+    m.add_function(wrap_pyfunction!(group::window_packets_by_time, m)?)?;
+    m.add_class::<group::PacketGroups>()?;
+    m.add_function(wrap_pyfunction!(mib::load_mib, m)?)?;
 
     m.add_function(wrap_pyfunction!(decode_cds_timecode, m)?)?;
     m.add_function(wrap_pyfunction!(decode_eoscuc_timecode, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_cds_timecode_ns, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_eoscuc_timecode_ns, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_cuc_timecode, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_cuc_timecode_ns, m)?)?;
+    m.add_function(wrap_pyfunction!(epoch::register_epoch, m)?)?;
+    m.add_function(wrap_pyfunction!(epoch::epochs, m)?)?;
 
     m.add_function(wrap_pyfunction!(missing_packets, m)?)?;
     m.add_function(wrap_pyfunction!(missing_frames, m)?)?;
     m.add_function(wrap_pyfunction!(framing_config, m)?)?;
+    m.add_function(wrap_pyfunction!(demux::demux_frames, m)?)?;
+    m.add_class::<mission::MissionPreset>()?;
+    m.add_function(wrap_pyfunction!(mission::mission_preset, m)?)?;
+    m.add_function(wrap_pyfunction!(mission::missions, m)?)?;
+    m.add_class::<eps::EpsProduct>()?;
+    m.add_function(wrap_pyfunction!(eps::read_eps_product, m)?)?;
+    m.add_class::<grb::GrbPayload>()?;
+    m.add_class::<grb::GrbPayloadIterator>()?;
+    m.add_function(wrap_pyfunction!(grb::decode_grb, m)?)?;
+    m.add_class::<demux::VcidFrameIterator>()?;
+    m.add_function(wrap_pyfunction!(validate::validate, m)?)?;
+    m.add_class::<validate::ValidationProfile>()?;
+    m.add_class::<validate::ValidationReport>()?;
+    m.add_class::<validate::ValidationViolation>()?;
+
+    m.add_function(wrap_pyfunction!(gaps::gap_report, m)?)?;
+    m.add_function(wrap_pyfunction!(gaps::write_gap_report, m)?)?;
+    m.add_class::<gaps::Gap>()?;
+    m.add_class::<gaps::FrameGap>()?;
+
+    m.add_function(wrap_pyfunction!(compare::compare_packets, m)?)?;
+    m.add_class::<compare::PacketRef>()?;
+    m.add_class::<compare::PacketComparison>()?;
+    m.add_function(wrap_pyfunction!(dedup::dedup_packets, m)?)?;
+    m.add_function(wrap_pyfunction!(rewrite::rewrite_packets, m)?)?;
+    m.add_function(wrap_pyfunction!(subset::subset, m)?)?;
+    m.add_function(wrap_pyfunction!(merge::merge_realtime_playback, m)?)?;
+    m.add_function(wrap_pyfunction!(convert::convert, m)?)?;
+    m.add_function(wrap_pyfunction!(idle::idle_accounting, m)?)?;
+    m.add_class::<idle::IdleAccounting>()?;
+    m.add_function(wrap_pyfunction!(report::pass_report, m)?)?;
+    m.add_function(wrap_pyfunction!(trend::rs_trend, m)?)?;
+    m.add_function(wrap_pyfunction!(manifest::manifest, m)?)?;
+    m.add_class::<trend::RsTrendRecord>()?;
+    m.add_class::<merge::MergeSummary>()?;
+    m.add_class::<manifest::Manifest>()?;
+    m.add_class::<manifest::ApidManifest>()?;
+    m.add_class::<report::PassReport>()?;
+    m.add_class::<report::PassReporter>()?;
+    m.add_class::<report::VcidReport>()?;
+    m.add_class::<report::ApidReport>()?;
+    m.add_function(wrap_pyfunction!(kafka::decode_kafka_frames, m)?)?;
+    m.add_function(wrap_pyfunction!(kafka::publish_kafka_packets, m)?)?;
+    m.add_class::<kafka::MessageFrameIterator>()?;
+    m.add_function(wrap_pyfunction!(redis::publish_redis_packets, m)?)?;
+    m.add_function(wrap_pyfunction!(ws::serve_packets, m)?)?;
+
+    m.add_function(wrap_pyfunction!(sync::detect_asm, m)?)?;
+    m.add_class::<sync::AsmDetection>()?;
+    m.add_function(wrap_pyfunction!(sync::detect_framing, m)?)?;
+    m.add_function(wrap_pyfunction!(sync::detect_interleave, m)?)?;
+    m.add_class::<sync::InterleaveDetection>()?;
+    m.add_class::<sync::FramingDetection>()?;
+
+    m.add_submodule(aio::module(py)?)?;
 
     Ok(())
 }
@@ -0,0 +1,190 @@
+//! Content hashes of packet files for archive integrity manifests.
+use std::{
+    collections::BTreeMap,
+    io::{self, BufReader, Read},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::Serialize;
+
+use crate::{
+    digest::{hex, Sha256},
+    packets::{PacketReader, HEADER_LEN},
+    repr::repr,
+    source::open_source,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Algorithm {
+    Sha256,
+}
+
+impl Algorithm {
+    /// Lookup a hash algorithm by the name used in the Python API.
+    fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_lowercase().as_str() {
+            "sha256" => Ok(Self::Sha256),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported algorithm; expected one of sha256, got {name}"
+            ))),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+        }
+    }
+}
+
+/// Reader that hashes the bytes read through it.
+struct HashingReader<R> {
+    reader: R,
+    hasher: Arc<Mutex<Sha256>>,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.hasher.lock().unwrap().update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Content hash and counts of the packets of an APID.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Serialize)]
+pub struct ApidManifest {
+    #[pyo3(get)]
+    pub apid: u16,
+    #[pyo3(get)]
+    pub packets: u64,
+    /// Number of packet bytes, including primary headers.
+    #[pyo3(get)]
+    pub bytes: u64,
+    /// Hex digest of the APID's packets, including primary headers, in stream order.
+    #[pyo3(get)]
+    pub digest: String,
+}
+
+#[pymethods]
+impl ApidManifest {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "ApidManifest(apid={}, packets={}, bytes={}, digest={})",
+            self.apid,
+            self.packets,
+            self.bytes,
+            repr(&self.digest),
+        )
+    }
+}
+
+/// Content hashes and counts of a packet file, as generated by `manifest`.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Serialize)]
+pub struct Manifest {
+    #[pyo3(get)]
+    pub algorithm: String,
+    /// Hex digest of the whole file.
+    #[pyo3(get)]
+    pub digest: String,
+    /// Size of the file in bytes.
+    #[pyo3(get)]
+    pub bytes: u64,
+    #[pyo3(get)]
+    pub packets: u64,
+    /// Per-APID hashes, ordered by APID.
+    #[pyo3(get)]
+    pub apids: Vec<ApidManifest>,
+}
+
+#[pymethods]
+impl Manifest {
+    /// The manifest as a JSON document.
+    #[pyo3(signature=(indent=true))]
+    fn to_json(&self, indent: bool) -> PyResult<String> {
+        let result = if indent {
+            serde_json::to_string_pretty(self)
+        } else {
+            serde_json::to_string(self)
+        };
+        result.map_err(|err| PyValueError::new_err(format!("{err}")))
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "Manifest(algorithm={}, digest={}, bytes={}, packets={}, apids={})",
+            repr(&self.algorithm),
+            repr(&self.digest),
+            self.bytes,
+            self.packets,
+            self.apids.len(),
+        )
+    }
+}
+
+/// Compute content hashes of a file of space packets for an archive integrity manifest
+/// or delivery receipt, hashing the whole file and the packets of each APID in a single
+/// pass.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of space packets.
+///
+/// algorithm : str
+///     Hash algorithm. Currently only "sha256" is supported.
+///
+/// Returns
+/// -------
+/// Manifest
+#[pyfunction(signature=(source, algorithm="sha256"))]
+pub fn manifest(py: Python, source: PathBuf, algorithm: &str) -> PyResult<Manifest> {
+    let algorithm = Algorithm::from_name(algorithm)?;
+    let file_hasher: Arc<Mutex<Sha256>> = Arc::default();
+    let reader = HashingReader {
+        reader: open_source(&source)?,
+        hasher: file_hasher.clone(),
+    };
+    let reader = PacketReader::new(BufReader::new(reader));
+
+    py.allow_threads(|| -> PyResult<Manifest> {
+        let mut apids: BTreeMap<u16, (u64, u64, Sha256)> = BTreeMap::default();
+        let mut packets = 0;
+        for packet in reader {
+            let packet = packet?;
+            let (count, bytes, hasher) = apids.entry(packet.header.apid).or_default();
+            *count += 1;
+            *bytes += (HEADER_LEN + packet.data.len()) as u64;
+            hasher.update(&packet.header.encode());
+            hasher.update(&packet.data);
+            packets += 1;
+        }
+        let file_hasher = file_hasher.lock().unwrap().clone();
+        let bytes = file_hasher.hashed();
+        Ok(Manifest {
+            algorithm: algorithm.name().to_string(),
+            digest: hex(&file_hasher.finish()),
+            bytes,
+            packets,
+            apids: apids
+                .into_iter()
+                .map(|(apid, (packets, bytes, hasher))| ApidManifest {
+                    apid,
+                    packets,
+                    bytes,
+                    digest: hex(&hasher.finish()),
+                })
+                .collect(),
+        })
+    })
+}
@@ -0,0 +1,236 @@
+//! Merge of the realtime and playback downlinks of a pass into a single time-ordered
+//! packet stream.
+use std::{
+    collections::HashMap,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{compress, source::open_source, sync, timecode::registry_or_cds, Packet};
+
+/// Counts of the packets merged by `merge_realtime_playback`.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default)]
+pub struct MergeSummary {
+    /// Packets written from realtime frames.
+    #[pyo3(get)]
+    pub realtime: u64,
+    /// Packets written from playback frames, i.e., those not also received in realtime.
+    #[pyo3(get)]
+    pub playback: u64,
+    /// Packets dropped as duplicates of a packet already merged.
+    #[pyo3(get)]
+    pub duplicates: u64,
+    /// Packets dropped because their time could not be decoded.
+    #[pyo3(get)]
+    pub untimed: u64,
+}
+
+#[pymethods]
+impl MergeSummary {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "MergeSummary(realtime={}, playback={}, duplicates={}, untimed={})",
+            self.realtime, self.playback, self.duplicates, self.untimed,
+        )
+    }
+}
+
+/// A packet to merge and whether it was received in realtime.
+struct Candidate {
+    time: i64,
+    realtime: bool,
+    packet: Packet,
+}
+
+/// Packets decoded from a stream of CADUs along with whether the frame each was decoded
+/// from had its replay flag clear.
+///
+/// The replay flag is tracked per VCID as of the most recently decoded frame, as the
+/// flag applies to all the frames of a virtual channel in a downlink.
+fn decode(
+    source: &Path,
+    scid: u16,
+    cadu_len: usize,
+    izone_len: usize,
+    trailer_len: usize,
+    interleave: Option<&PyAny>,
+) -> PyResult<impl Iterator<Item = (bool, Packet)>> {
+    let asm = sync::asm_arg(source, None)?;
+    if cadu_len <= asm.len() {
+        return Err(PyValueError::new_err(format!(
+            "cadu_len cannot be less than the ASM size ({})",
+            asm.len()
+        )));
+    }
+    let block_len = cadu_len - asm.len();
+    let interleave = sync::interleave_arg(source, &asm, block_len, interleave)?;
+    let blocks = sync::Synchronizer::new(BufReader::new(open_source(source)?), &asm, block_len)
+        .filter_map(|block| block.ok().map(|(_, block)| block));
+    let mut builder = ccsds::FrameDecoderBuilder::default();
+    if let Some(interleave) = interleave {
+        builder = builder.reed_solomon(interleave);
+    }
+    let replay: Arc<Mutex<HashMap<u16, bool>>> = Arc::default();
+    let frame_replay = replay.clone();
+    let frames = builder
+        .build()
+        .start(blocks)
+        .filter_map(Result::ok)
+        .inspect(move |frame| {
+            let hdr = &frame.frame.header;
+            frame_replay.lock().unwrap().insert(hdr.vcid, hdr.replay);
+        });
+    let packets = ccsds::decode_framed_packets(scid, frames, izone_len, trailer_len);
+    Ok(packets.map(move |decoded| {
+        let realtime = !replay
+            .lock()
+            .unwrap()
+            .get(&decoded.vcid)
+            .copied()
+            .unwrap_or_default();
+        (realtime, Packet::new(decoded.packet))
+    }))
+}
+
+/// Merge the realtime and playback CADU streams of a pass into a single time-ordered
+/// stream of packets, preferring packets received in realtime.
+///
+/// Packets are decoded from each stream as by `decode_framed_packets`. A packet is
+/// realtime if it was decoded from the realtime stream from frames with the VCDU replay
+/// flag clear, and playback otherwise, so playback frames interleaved in the realtime
+/// downlink are handled. Packets with the same APID, sequence id and time are
+/// duplicates, of which the realtime packet, or else the first packet, is kept. The
+/// merged packets are written to `dest` in time order.
+///
+/// All timed packets are held in memory until both streams are read.
+///
+/// Parameters
+/// ----------
+/// realtime : str or os.PathLike
+///     Source providing the CADUs of the realtime downlink.
+///
+/// playback : str or os.PathLike
+///     Source providing the CADUs of the playback downlink.
+///
+/// dest : str or os.PathLike
+///     Path of the file to write the merged packets to.
+///
+/// scid : int
+///     Spacecraft identifier of the frames to decode packets from.
+///
+/// cadu_len : int
+///     The length of the CADU, i.e., the ASM length plus the frame and parity length.
+///
+/// izone_len : int
+///     Frame insert-zone number of bytes used by the spacecraft, if any.
+///
+/// trailer_len : int
+///     Frame trailer number of bytes used by the spacecraft, if any.
+///
+/// interleave : int or str, optional
+///     The Reed-Solomon interleave, or `auto`, as accepted by `decode_frames`.
+///
+/// timecode : str or TimecodeRegistry
+///     Timecode at the start of the secondary header, either "cds" or "eoscuc", or the
+///     timecode of each APID. Defaults to "cds". Packets without a time are not merged.
+///
+/// compression : str, optional
+///     Compress the output, either `gzip`, `zstd` or `none`. Defaults to `gzip` or
+///     `zstd` if `dest` ends with `.gz` or `.zst`, otherwise `none`.
+///
+/// Returns
+/// -------
+/// MergeSummary
+#[pyfunction(signature=(realtime, playback, dest, scid, cadu_len, izone_len=0, trailer_len=0, interleave=None, timecode=None, compression=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn merge_realtime_playback(
+    py: Python,
+    realtime: PathBuf,
+    playback: PathBuf,
+    dest: PathBuf,
+    scid: u16,
+    cadu_len: usize,
+    izone_len: usize,
+    trailer_len: usize,
+    interleave: Option<&PyAny>,
+    timecode: Option<&PyAny>,
+    compression: Option<&str>,
+) -> PyResult<MergeSummary> {
+    let timecode = registry_or_cds(timecode)?;
+    if izone_len >= 16 || trailer_len >= 16 {
+        return Err(PyValueError::new_err(format!(
+            "invalid izone_len or trailer_len; expected 0..16, got {izone_len} and {trailer_len}"
+        )));
+    }
+    let realtime = decode(
+        &realtime,
+        scid,
+        cadu_len,
+        izone_len,
+        trailer_len,
+        interleave,
+    )?;
+    let playback = decode(
+        &playback,
+        scid,
+        cadu_len,
+        izone_len,
+        trailer_len,
+        interleave,
+    )?
+    .map(|(_, packet)| (false, packet));
+    let mut writer = compress::create(&dest, compression)?;
+
+    py.allow_threads(|| -> PyResult<MergeSummary> {
+        let mut summary = MergeSummary::default();
+        let mut merged: Vec<Candidate> = Vec::default();
+        let mut index: HashMap<(u16, u16, i64), usize> = HashMap::default();
+        for (realtime, packet) in realtime.chain(playback) {
+            let hdr = &packet.header;
+            let time = hdr
+                .has_secondary_header
+                .then(|| timecode.decode(hdr.apid, packet.payload()))
+                .flatten();
+            let Some(time) = time else {
+                summary.untimed += 1;
+                continue;
+            };
+            let key = (hdr.apid, hdr.sequence_id, time);
+            let candidate = Candidate {
+                time,
+                realtime,
+                packet,
+            };
+            match index.get(&key) {
+                Some(&i) => {
+                    summary.duplicates += 1;
+                    if realtime && !merged[i].realtime {
+                        merged[i] = candidate;
+                    }
+                }
+                None => {
+                    index.insert(key, merged.len());
+                    merged.push(candidate);
+                }
+            }
+        }
+        merged.sort_by_key(|candidate| candidate.time);
+        for candidate in merged {
+            writer.write_all(&candidate.packet.data)?;
+            if candidate.realtime {
+                summary.realtime += 1;
+            } else {
+                summary.playback += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(summary)
+    })
+}
@@ -0,0 +1,98 @@
+//! Serialization of packets into messages for publishing to message brokers.
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::Serialize;
+
+use crate::{DecodedPacket, Packet};
+
+/// Format of a published packet message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Serialization {
+    /// The packet bytes, i.e., the primary header followed by the packet data.
+    Raw,
+    /// A JSON object of the primary header fields and other metadata with the packet
+    /// data following the header hex encoded.
+    Json,
+}
+
+impl Serialization {
+    /// Lookup a serialization by the name used in the Python API.
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_lowercase().as_str() {
+            "raw" => Ok(Self::Raw),
+            "json" => Ok(Self::Json),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported serialization; expected one of raw, json, got {name}"
+            ))),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonPacket<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scid: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vcid: Option<u16>,
+    version: u8,
+    type_flag: u8,
+    has_secondary_header: bool,
+    apid: u16,
+    sequence_flags: u8,
+    sequence_id: u16,
+    len_minus1: u16,
+    offset: Option<u64>,
+    data: &'a str,
+}
+
+/// A packet, and the spacecraft and virtual channel it was decoded from, if known.
+pub struct PacketRef<'a> {
+    pub ids: Option<(u16, u16)>,
+    pub packet: &'a Packet,
+}
+
+impl PacketRef<'_> {
+    /// Serialize the packet.
+    pub fn encode(&self, serialization: Serialization) -> Vec<u8> {
+        let packet = self.packet;
+        let hdr = &packet.header;
+        match serialization {
+            Serialization::Raw => packet.data.to_vec(),
+            Serialization::Json => {
+                let data: String = packet
+                    .payload()
+                    .iter()
+                    .map(|b| format!("{b:02x}"))
+                    .collect();
+                let msg = JsonPacket {
+                    scid: self.ids.map(|ids| ids.0),
+                    vcid: self.ids.map(|ids| ids.1),
+                    version: hdr.version,
+                    type_flag: hdr.type_flag,
+                    has_secondary_header: hdr.has_secondary_header,
+                    apid: hdr.apid,
+                    sequence_flags: hdr.sequence_flags,
+                    sequence_id: hdr.sequence_id,
+                    len_minus1: hdr.len_minus1,
+                    offset: packet.offset,
+                    data: &data,
+                };
+                serde_json::to_vec(&msg).expect("packet messages are serializable")
+            }
+        }
+    }
+}
+
+/// Call `f` with the packet of `item`, which may be a Packet or DecodedPacket.
+pub fn with_packet<T>(item: &PyAny, f: impl FnOnce(PacketRef) -> T) -> PyResult<T> {
+    if let Ok(decoded) = item.extract::<PyRef<DecodedPacket>>() {
+        return Ok(f(PacketRef {
+            ids: Some((decoded.scid, decoded.vcid)),
+            packet: &decoded.packet,
+        }));
+    }
+    let packet: PyRef<Packet> = item.extract()?;
+    Ok(f(PacketRef {
+        ids: None,
+        packet: &packet,
+    }))
+}
@@ -0,0 +1,376 @@
+//! Import of telemetry packet definitions from a SCOS-2000 Mission Information Base.
+//!
+//! A MIB is a directory of tab separated ASCII tables without header rows. The
+//! packet (pid), parameter (pcf) and location (plf) tables are required; numerical
+//! (caf/cap), polynomial (mcf) and textual (txf/txp) calibrations and monitoring
+//! checks (ocf/ocp) are used if present.
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    alarm::AlarmLimits,
+    layout::{Calibration, Field, Kind, Layout},
+    packets::HEADER_LEN,
+    repr::repr,
+};
+
+/// A telemetry packet definition from the MIB packet identification table.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct MibPacket {
+    /// SCOS-2000 packet id.
+    #[pyo3(get)]
+    pub spid: u32,
+    #[pyo3(get)]
+    pub apid: u16,
+    /// PUS service type.
+    #[pyo3(get)]
+    pub service_type: u8,
+    /// PUS service subtype.
+    #[pyo3(get)]
+    pub service_subtype: u8,
+    /// Values of the additional identification fields, if used.
+    #[pyo3(get)]
+    pub pi1_val: i64,
+    #[pyo3(get)]
+    pub pi2_val: i64,
+    #[pyo3(get)]
+    pub description: String,
+    /// Fields for the parameters located in this packet.
+    #[pyo3(get)]
+    pub layout: Layout,
+}
+
+#[pymethods]
+impl MibPacket {
+    /// Minimum length in bytes, including the primary header, of a packet containing
+    /// all the located parameters, e.g., for the `apid_lengths` argument of
+    /// `decode_packets`.
+    #[getter]
+    fn min_len(&self) -> usize {
+        HEADER_LEN
+            + self
+                .layout
+                .fields
+                .iter()
+                .map(Field::data_len)
+                .max()
+                .unwrap_or_default()
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "MibPacket(spid={}, apid={}, service_type={}, service_subtype={}, description={}, fields={}, min_len={})",
+            self.spid, self.apid, self.service_type, self.service_subtype, repr(&self.description), self.layout.fields.len(), self.min_len(),
+        )
+    }
+}
+
+/// Rows of a MIB table, or an empty table if it does not exist and is not required.
+fn read_table(dir: &Path, name: &str, required: bool) -> io::Result<Vec<Vec<String>>> {
+    let path = [name.to_lowercase(), name.to_uppercase()]
+        .iter()
+        .map(|n| dir.join(format!("{n}.dat")))
+        .find(|p| p.exists());
+    let Some(path) = path else {
+        if required {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("MIB table {name}.dat not found in {}", dir.display()),
+            ));
+        }
+        return Ok(Vec::default());
+    };
+    let text = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    Ok(text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split('\t').map(|col| col.trim().to_string()).collect())
+        .collect())
+}
+
+/// Column `idx` of a row, which may be missing if trailing columns are empty.
+fn col(row: &[String], idx: usize) -> &str {
+    row.get(idx).map_or("", String::as_str)
+}
+
+fn parse<T: std::str::FromStr>(table: &str, row: &[String], idx: usize) -> PyResult<T> {
+    col(row, idx).parse().map_err(|_| {
+        PyValueError::new_err(format!(
+            "invalid value {:?} in column {} of {table} row {:?}",
+            col(row, idx),
+            idx + 1,
+            row.join("\t"),
+        ))
+    })
+}
+
+/// Field kind and bit length for a parameter type code (PTC) and format code (PFC),
+/// or `None` for types that are not supported, e.g., strings and times.
+fn field_type(ptc: u32, pfc: u32) -> Option<(Kind, u32)> {
+    let int_len = |pfc| match pfc {
+        0..=12 => Some(pfc + 4),
+        13 => Some(24),
+        14 => Some(32),
+        15 => Some(48),
+        16 => Some(64),
+        _ => None,
+    };
+    match (ptc, pfc) {
+        (1, 0) => Some((Kind::Uint, 1)),
+        (2, 1..=32) => Some((Kind::Uint, pfc)),
+        (3, _) => Some((Kind::Uint, int_len(pfc)?)),
+        (4, _) => Some((Kind::Int, int_len(pfc)?)),
+        (5, 1) => Some((Kind::Float, 32)),
+        (5, 2) => Some((Kind::Float, 64)),
+        (5, 3) => Some((Kind::Mil1750a, 32)),
+        (5, 4) => Some((Kind::Mil1750a, 48)),
+        _ => None,
+    }
+}
+
+/// Parse a raw calibration value in the given radix: D(ecimal), H(ex) or O(ctal).
+fn parse_raw(val: &str, radix: &str) -> Option<f64> {
+    let radix = match radix {
+        "H" => 16,
+        "O" => 8,
+        _ => return val.parse().ok(),
+    };
+    i64::from_str_radix(val, radix).ok().map(|v| v as f64)
+}
+
+struct Calibrations {
+    numerical: HashMap<String, Calibration>,
+    textual: HashMap<String, Vec<(i64, String)>>,
+}
+
+impl Calibrations {
+    fn load(dir: &Path) -> PyResult<Self> {
+        let mut radixes: HashMap<String, String> = HashMap::default();
+        for row in read_table(dir, "caf", false)? {
+            radixes.insert(col(&row, 0).to_string(), col(&row, 4).to_string());
+        }
+        let mut points: HashMap<String, Vec<(f64, f64)>> = HashMap::default();
+        for row in read_table(dir, "cap", false)? {
+            let id = col(&row, 0);
+            let radix = radixes.get(id).map_or("D", String::as_str);
+            let (Some(x), Ok(y)) = (parse_raw(col(&row, 1), radix), col(&row, 2).parse()) else {
+                return Err(PyValueError::new_err(format!(
+                    "invalid calibration point in cap row {:?}",
+                    row.join("\t")
+                )));
+            };
+            points.entry(id.to_string()).or_default().push((x, y));
+        }
+
+        let mut numerical = HashMap::default();
+        for (id, points) in points {
+            numerical.insert(id, Calibration::point_pair(points)?);
+        }
+        for row in read_table(dir, "mcf", false)? {
+            let mut coefficients = Vec::with_capacity(5);
+            for idx in 2..7 {
+                coefficients.push(if col(&row, idx).is_empty() {
+                    0.0
+                } else {
+                    parse("mcf", &row, idx)?
+                });
+            }
+            numerical.insert(
+                col(&row, 0).to_string(),
+                Calibration::polynomial(coefficients)?,
+            );
+        }
+
+        let mut textual: HashMap<String, Vec<(i64, String)>> = HashMap::default();
+        for row in read_table(dir, "txp", false)? {
+            let from: i64 = parse("txp", &row, 1)?;
+            let to: i64 = parse("txp", &row, 2)?;
+            let states = textual.entry(col(&row, 0).to_string()).or_default();
+            states.extend((from..=to).map(|raw| (raw, col(&row, 3).to_string())));
+        }
+        Ok(Self { numerical, textual })
+    }
+}
+
+/// Unconditional soft (yellow) and hard (red) limits by parameter name.
+fn load_limits(dir: &Path, calibrated: &HashSet<String>) -> PyResult<HashMap<String, AlarmLimits>> {
+    let mut uncalibrated: HashSet<String> = HashSet::default();
+    for row in read_table(dir, "ocf", false)? {
+        // limits on raw values of calibrated parameters cannot be checked against the
+        // calibrated value
+        if col(&row, 3) == "U" && calibrated.contains(col(&row, 0)) {
+            uncalibrated.insert(col(&row, 0).to_string());
+        }
+    }
+    let mut bounds: HashMap<String, [Option<f64>; 4]> = HashMap::default();
+    for row in read_table(dir, "ocp", false)? {
+        let name = col(&row, 0);
+        if uncalibrated.contains(name) || !col(&row, 5).is_empty() {
+            continue;
+        }
+        let (Ok(low), Ok(high)) = (col(&row, 3).parse(), col(&row, 4).parse()) else {
+            continue;
+        };
+        let entry = bounds.entry(name.to_string()).or_default();
+        let (low_idx, high_idx) = match col(&row, 2) {
+            "H" => (0, 3),
+            "S" => (1, 2),
+            _ => continue,
+        };
+        entry[low_idx].get_or_insert(low);
+        entry[high_idx].get_or_insert(high);
+    }
+    let mut limits = HashMap::default();
+    for (name, [red_low, yellow_low, yellow_high, red_high]) in bounds {
+        limits.insert(
+            name,
+            AlarmLimits::py_new(red_low, yellow_low, yellow_high, red_high)?,
+        );
+    }
+    Ok(limits)
+}
+
+/// Parameter definition from the pcf table.
+struct Parameter {
+    kind: Kind,
+    bit_len: u32,
+    categ: String,
+    curtx: String,
+}
+
+/// Load telemetry packet definitions from a SCOS-2000 MIB.
+///
+/// Each packet's layout contains a field for every parameter located in the packet
+/// with a supported type, i.e., booleans, enumerations, integers and reals. Numerical
+/// and polynomial calibrations, textual calibrations (as states) and unconditional
+/// soft (yellow) and hard (red) monitoring limits are applied to the fields. Repeated
+/// parameters are named `NAME[i]` for each occurrence.
+///
+/// Parameters
+/// ----------
+/// path : str or os.PathLike
+///     Directory containing the MIB tables, e.g., `pid.dat`, `pcf.dat` and `plf.dat`.
+///
+/// Returns
+/// -------
+/// list of MibPacket
+///     Packet definitions in pid table order.
+#[pyfunction]
+pub fn load_mib(path: PathBuf) -> PyResult<Vec<MibPacket>> {
+    let mut params: HashMap<String, Parameter> = HashMap::default();
+    for row in read_table(&path, "pcf", true)? {
+        let (ptc, pfc) = (parse("pcf", &row, 4)?, parse("pcf", &row, 5)?);
+        let Some((kind, bit_len)) = field_type(ptc, pfc) else {
+            continue;
+        };
+        params.insert(
+            col(&row, 0).to_string(),
+            Parameter {
+                kind,
+                bit_len,
+                categ: col(&row, 9).to_string(),
+                curtx: col(&row, 11).to_string(),
+            },
+        );
+    }
+    let calibrations = Calibrations::load(&path)?;
+    let calibrated: HashSet<String> = params
+        .iter()
+        .filter(|(_, p)| p.categ == "N" && calibrations.numerical.contains_key(&p.curtx))
+        .map(|(name, _)| name.clone())
+        .collect();
+    let limits = load_limits(&path, &calibrated)?;
+
+    let mut fields: HashMap<u32, Vec<Field>> = HashMap::default();
+    for row in read_table(&path, "plf", true)? {
+        let name = col(&row, 0);
+        let Some(param) = params.get(name) else {
+            continue;
+        };
+        let spid: u32 = parse("plf", &row, 1)?;
+        let offby: usize = parse("plf", &row, 2)?;
+        let offbi: usize = parse("plf", &row, 3)?;
+        if offby < HEADER_LEN {
+            continue;
+        }
+        let nbocc: usize = if col(&row, 4).is_empty() {
+            1
+        } else {
+            parse("plf", &row, 4)?
+        };
+        let lgocc: usize = if col(&row, 5).is_empty() {
+            0
+        } else {
+            parse("plf", &row, 5)?
+        };
+
+        let (calibration, states) = match param.categ.as_str() {
+            "N" => (calibrations.numerical.get(&param.curtx).cloned(), None),
+            "S" if matches!(param.kind, Kind::Uint | Kind::Int) => {
+                (None, calibrations.textual.get(&param.curtx).cloned())
+            }
+            _ => (None, None),
+        };
+        let limits = if states.is_none() {
+            limits.get(name).cloned()
+        } else {
+            None
+        };
+        let packet_fields = fields.entry(spid).or_default();
+        for occ in 0..nbocc.max(1) {
+            let name = if nbocc > 1 {
+                format!("{name}[{occ}]")
+            } else {
+                name.to_string()
+            };
+            if packet_fields.iter().any(|f| f.name == name) {
+                continue;
+            }
+            let bit_offset =
+                (offby - HEADER_LEN) * 8 + offbi + occ * (param.bit_len as usize + lgocc);
+            packet_fields.push(Field::new(
+                name,
+                bit_offset,
+                param.bit_len,
+                param.kind,
+                calibration.clone(),
+                states.clone(),
+                limits.clone(),
+            )?);
+        }
+    }
+
+    let mut packets = Vec::default();
+    for row in read_table(&path, "pid", true)? {
+        let spid: u32 = parse("pid", &row, 5)?;
+        let mut packet_fields = fields.remove(&spid).unwrap_or_default();
+        packet_fields.sort_by_key(|f| f.bit_offset);
+        let pi = |idx| -> PyResult<i64> {
+            if col(&row, idx).is_empty() {
+                Ok(0)
+            } else {
+                parse("pid", &row, idx)
+            }
+        };
+        packets.push(MibPacket {
+            spid,
+            apid: parse("pid", &row, 2)?,
+            service_type: parse("pid", &row, 0)?,
+            service_subtype: parse("pid", &row, 1)?,
+            pi1_val: pi(3)?,
+            pi2_val: pi(4)?,
+            description: col(&row, 6).to_string(),
+            layout: Layout::py_new(packet_fields, None)?,
+        });
+    }
+    Ok(packets)
+}
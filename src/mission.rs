@@ -0,0 +1,178 @@
+//! Named presets of the framing and timecode parameters of the missions in the
+//! spacecraftsdb database, so their data decodes without looking up and passing each
+//! parameter.
+use std::{collections::HashMap, path::PathBuf};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde_json::Value;
+
+use crate::{
+    repr::repr,
+    timecode::{read_spacecraftsdb, TimecodeRegistry},
+    FramingConfig,
+};
+
+/// The VCID of CCSDS fill frames.
+const FILL_VCID: u16 = 63;
+
+/// A name compared ignoring case, hyphens and underscores, e.g., `MetOp_B` matches
+/// `metop-b`.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '-' && *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Name of a spacecraft entry of the database, if it has a name and framing config and
+/// so can be decoded by name.
+fn preset_name(spacecraft: &Value) -> Option<&str> {
+    spacecraft.get("framingConfig")?;
+    spacecraft.get("name")?.as_str()
+}
+
+/// Framing, timecode and service parameters of a mission in the spacecraftsdb
+/// database, as used by the `mission` argument of `decode_framed_packets`.
+///
+/// The framing and timecode parameters are those of the spacecraft's database entry,
+/// with the standard ASM and VCID 63 for fill frames.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct MissionPreset {
+    #[pyo3(get)]
+    pub name: String,
+    #[pyo3(get)]
+    pub scid: u16,
+    #[pyo3(get)]
+    pub cadu_len: usize,
+    /// Reed-Solomon interleave, or `None` if the frames have no Reed-Solomon parity.
+    #[pyo3(get)]
+    pub interleave: Option<u8>,
+    #[pyo3(get)]
+    pub izone_len: usize,
+    #[pyo3(get)]
+    pub trailer_len: usize,
+    /// Timecode of the packets of the mission, for the `timecode` argument of the
+    /// functions that decode packet times.
+    #[pyo3(get)]
+    pub timecode: TimecodeRegistry,
+    /// The framing parameters in the form returned by `framing_config`.
+    #[pyo3(get)]
+    pub framing_config: FramingConfig,
+}
+
+impl MissionPreset {
+    /// Lookup a preset by mission name or alias in the database at `path`, or the one
+    /// found as by `framing_config`.
+    pub fn from_name(name: &str, path: Option<PathBuf>) -> PyResult<Self> {
+        let (path, spacecrafts) = read_spacecraftsdb(path)?;
+        let wanted = normalize(name);
+        let Some(spacecraft) = spacecrafts.iter().find(|sc| {
+            let aliases = sc.get("aliases").and_then(Value::as_array);
+            preset_name(sc).is_some_and(|name| normalize(name) == wanted)
+                || aliases
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .any(|alias| normalize(alias) == wanted)
+        }) else {
+            let names: Vec<_> = spacecrafts.iter().filter_map(preset_name).collect();
+            return Err(PyValueError::new_err(format!(
+                "unsupported mission; expected one of {} from {}, got {name}",
+                names.join(", "),
+                path.display()
+            )));
+        };
+        let invalid = |msg: String| {
+            PyValueError::new_err(format!(
+                "invalid spacecraftsdb entry for mission {name}: {msg}"
+            ))
+        };
+        let scid = spacecraft
+            .get("scid")
+            .and_then(Value::as_u64)
+            .and_then(|scid| u16::try_from(scid).ok())
+            .ok_or_else(|| invalid("missing scid".to_string()))?;
+        let framing: spacecrafts::FramingConfig = spacecraft
+            .get("framingConfig")
+            .map(|framing| serde_json::from_value(framing.clone()))
+            .ok_or_else(|| invalid("missing framingConfig".to_string()))?
+            .map_err(|err| invalid(format!("{err}")))?;
+        let framing_config = FramingConfig::new(framing);
+        Ok(Self {
+            name: preset_name(spacecraft).unwrap_or(name).to_string(),
+            scid,
+            cadu_len: ccsds::ASM.len() + framing_config.codeblock_len(),
+            interleave: framing_config.reed_solomon.as_ref().map(|rs| rs.interleave),
+            izone_len: framing_config.insert_zone_length,
+            trailer_len: framing_config.trailer_length,
+            timecode: TimecodeRegistry::from_spacecraft(spacecraft)?,
+            framing_config,
+        })
+    }
+
+    /// Service of each VCID, as accepted by the `services` argument of
+    /// `decode_framed_packets`.
+    pub fn services(&self) -> HashMap<u16, &'static str> {
+        HashMap::from([(FILL_VCID, "idle")])
+    }
+}
+
+#[pymethods]
+impl MissionPreset {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "MissionPreset(name={}, scid={}, cadu_len={}, interleave={})",
+            repr(&self.name),
+            self.scid,
+            self.cadu_len,
+            repr(&self.interleave),
+        )
+    }
+
+    /// Service of each VCID, for the `services` argument of `decode_framed_packets`.
+    #[getter(services)]
+    fn py_services(&self) -> HashMap<u16, &'static str> {
+        self.services()
+    }
+}
+
+/// Lookup the preset parameters of a mission in the spacecraftsdb database.
+///
+/// Parameters
+/// ----------
+/// name : str
+///     Name of the mission, one of those returned by `missions`, or one of its aliases
+///     in the database. Case, hyphens and underscores are ignored.
+///
+/// path : str or os.PathLike, optional
+///     Local path to the database file, otherwise found as for `framing_config`.
+///
+/// Returns
+/// -------
+/// MissionPreset
+#[pyfunction(signature=(name, path=None))]
+pub fn mission_preset(name: &str, path: Option<PathBuf>) -> PyResult<MissionPreset> {
+    MissionPreset::from_name(name, path)
+}
+
+/// Names of the missions with presets, i.e., the spacecraft in the spacecraftsdb
+/// database with framing parameters, for `mission_preset` and the `mission` argument of
+/// `decode_framed_packets`.
+///
+/// Parameters
+/// ----------
+/// path : str or os.PathLike, optional
+///     Local path to the database file, otherwise found as for `framing_config`.
+#[pyfunction(signature=(path=None))]
+pub fn missions(path: Option<PathBuf>) -> PyResult<Vec<String>> {
+    let (_, spacecrafts) = read_spacecraftsdb(path)?;
+    Ok(spacecrafts
+        .iter()
+        .filter_map(preset_name)
+        .map(str::to_string)
+        .collect())
+}
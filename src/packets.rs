@@ -0,0 +1,776 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    io::{self, ErrorKind, Read, Write},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    anomaly::{self, Anomaly, AnomalyCollector, Severity},
+    PrimaryHeader,
+};
+
+/// Length of a space packet primary header in bytes.
+pub const HEADER_LEN: usize = 6;
+
+/// A packet read from a stream along with where in the stream it was found.
+#[derive(Clone, Debug)]
+pub struct RawPacket {
+    /// Byte offset of the start of the primary header in the source stream.
+    pub offset: u64,
+    pub header: PrimaryHeader,
+    /// Packet bytes following the primary header.
+    pub data: Vec<u8>,
+    /// Offset and length of corrupt bytes skipped immediately before this packet while
+    /// resynchronizing.
+    pub skipped: Option<(u64, u64)>,
+    /// True if the stream ended before the end of the packet, in which case `data` only
+    /// contains the bytes that were available.
+    pub truncated: bool,
+}
+
+impl RawPacket {
+    /// Write the packet, including the primary header, to `w`.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.header.encode())?;
+        w.write_all(&self.data)
+    }
+}
+
+/// Maximum number of packets a resynchronization candidate's sequence counter may be
+/// ahead of the last packet seen with the same APID.
+const RESYNC_MAX_MISSING: u16 = 1024;
+
+/// Size of reads from the underlying reader.
+const READ_SIZE: usize = 8192;
+
+/// What to do when the stream ends part way through a packet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Truncated {
+    /// Silently drop the incomplete packet.
+    #[default]
+    Drop,
+    /// Yield the incomplete packet flagged as truncated.
+    Partial,
+    /// Fail with an `UnexpectedEof` error.
+    Raise,
+}
+
+impl Truncated {
+    /// Lookup a policy by the name used in the Python API.
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_lowercase().as_str() {
+            "drop" => Ok(Self::Drop),
+            "partial" => Ok(Self::Partial),
+            "raise" => Ok(Self::Raise),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported truncated policy; expected one of drop, partial, raise, got {name}"
+            ))),
+        }
+    }
+}
+
+/// What to do with a packet that does not match what is expected for its APID, e.g.,
+/// the presence of a secondary header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ViolationPolicy {
+    /// Yield the packet and record the violation.
+    #[default]
+    Flag,
+    /// Drop the packet and record the violation.
+    Drop,
+    /// Fail with a ValueError.
+    Raise,
+}
+
+impl ViolationPolicy {
+    /// Lookup a policy by the name used in the Python API, where `what` describes the
+    /// policy in errors.
+    pub fn from_name(name: &str, what: &str) -> PyResult<Self> {
+        match name.to_lowercase().as_str() {
+            "flag" => Ok(Self::Flag),
+            "drop" => Ok(Self::Drop),
+            "raise" => Ok(Self::Raise),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported {what}; expected one of flag, drop, raise, got {name}"
+            ))),
+        }
+    }
+}
+
+/// Expected presence of a secondary header for each APID.
+#[derive(Clone, Debug, Default)]
+pub struct SecondaryHeaderCheck {
+    /// Whether packets with each APID are expected to have a secondary header. Packets
+    /// with other APIDs are not checked.
+    pub expected: HashMap<u16, bool>,
+    pub policy: ViolationPolicy,
+    /// APID and source offset, if known, of each violation.
+    pub violations: Vec<(u16, Option<u64>)>,
+    pub anomalies: Option<AnomalyCollector>,
+}
+
+impl SecondaryHeaderCheck {
+    /// Create a check from the `secondary_headers` and `secondary_header_policy`
+    /// arguments of the decode functions.
+    pub fn from_args(expected: Option<HashMap<u16, bool>>, policy: &str) -> PyResult<Self> {
+        Ok(Self {
+            expected: expected.unwrap_or_default(),
+            policy: ViolationPolicy::from_name(policy, "secondary header policy")?,
+            violations: Vec::default(),
+            anomalies: None,
+        })
+    }
+
+    /// Check a packet header, returning whether the packet should be yielded or an error
+    /// if the policy is to raise.
+    pub fn check(
+        &mut self,
+        apid: u16,
+        has_secondary_header: bool,
+        offset: Option<u64>,
+    ) -> PyResult<bool> {
+        match self.expected.get(&apid) {
+            Some(expected) if *expected != has_secondary_header => (),
+            _ => return Ok(true),
+        }
+        if self.policy == ViolationPolicy::Raise {
+            return Err(PyValueError::new_err(format!(
+                "unexpected secondary header flag {has_secondary_header} for apid {apid}"
+            )));
+        }
+        self.violations.push((apid, offset));
+        anomaly::emit(&self.anomalies, || {
+            Anomaly::new(
+                "secondary_header",
+                Severity::Warning,
+                format!("unexpected secondary header flag {has_secondary_header}"),
+            )
+            .apid(apid)
+            .offset(offset)
+        });
+        Ok(self.policy == ViolationPolicy::Flag)
+    }
+}
+
+/// Minimum and maximum packet lengths, including the primary header, by APID.
+pub type ApidLengths = HashMap<u16, (Option<usize>, Option<usize>)>;
+
+/// Expected minimum and maximum packet lengths for each APID.
+#[derive(Clone, Debug, Default)]
+pub struct LengthCheck {
+    /// Minimum and maximum length, including the primary header, of packets with each
+    /// APID. Packets with other APIDs are not checked.
+    pub expected: ApidLengths,
+    pub policy: ViolationPolicy,
+    /// APID, packet length and source offset, if known, of each violation.
+    pub violations: Vec<(u16, usize, Option<u64>)>,
+    pub anomalies: Option<AnomalyCollector>,
+}
+
+impl LengthCheck {
+    /// Create a check from the `apid_lengths` and `length_policy` arguments of the
+    /// decode functions.
+    pub fn from_args(expected: Option<ApidLengths>, policy: &str) -> PyResult<Self> {
+        let expected = expected.unwrap_or_default();
+        for (apid, (min, max)) in &expected {
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    return Err(PyValueError::new_err(format!(
+                        "invalid lengths for apid {apid}; expected min <= max, got {min} and {max}"
+                    )));
+                }
+            }
+        }
+        Ok(Self {
+            expected,
+            policy: ViolationPolicy::from_name(policy, "length policy")?,
+            violations: Vec::default(),
+            anomalies: None,
+        })
+    }
+
+    /// Check the length of a packet from its header, returning whether the packet
+    /// should be yielded or an error if the policy is to raise.
+    pub fn check(&mut self, header: &PrimaryHeader, offset: Option<u64>) -> PyResult<bool> {
+        let Some((min, max)) = self.expected.get(&header.apid) else {
+            return Ok(true);
+        };
+        let len = HEADER_LEN + header.len_minus1 as usize + 1;
+        if min.is_none_or(|min| len >= min) && max.is_none_or(|max| len <= max) {
+            return Ok(true);
+        }
+        let apid = header.apid;
+        if self.policy == ViolationPolicy::Raise {
+            return Err(PyValueError::new_err(format!(
+                "unexpected length {len} for apid {apid}; expected {}..={}",
+                min.map(|v| v.to_string()).unwrap_or_default(),
+                max.map(|v| v.to_string()).unwrap_or_default(),
+            )));
+        }
+        self.violations.push((apid, len, offset));
+        anomaly::emit(&self.anomalies, || {
+            Anomaly::new(
+                "length",
+                Severity::Warning,
+                format!("unexpected packet length {len}"),
+            )
+            .apid(apid)
+            .offset(offset)
+        });
+        Ok(self.policy == ViolationPolicy::Flag)
+    }
+}
+
+/// Sequence flags of a first packet of a group.
+const SEQ_FIRST: u8 = 1;
+/// Sequence flags of a continuation packet of a group.
+const SEQ_CONTINUATION: u8 = 0;
+/// Sequence flags of a last packet of a group.
+const SEQ_LAST: u8 = 2;
+/// Sequence flags of an unsegmented packet.
+const SEQ_UNSEGMENTED: u8 = 3;
+
+/// Check of the sequence flags of consecutive packets of each APID for violations of
+/// the packet grouping protocol, which indicate an onboard packetization error.
+#[derive(Clone, Debug, Default)]
+pub struct SequenceFlagCheck {
+    pub enabled: bool,
+    /// Offset, if known, of the first packet of the group in progress for each APID.
+    groups: HashMap<u16, Option<u64>>,
+    /// APID, kind of violation and source offset, if known, of each violation.
+    pub violations: Vec<(u16, &'static str, Option<u64>)>,
+    pub anomalies: Option<AnomalyCollector>,
+}
+
+impl SequenceFlagCheck {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            ..Self::default()
+        }
+    }
+
+    /// Check the sequence flags of a packet against the group in progress for its APID,
+    /// if any.
+    pub fn check(&mut self, header: &PrimaryHeader, offset: Option<u64>) {
+        if !self.enabled {
+            return;
+        }
+        let apid = header.apid;
+        match header.sequence_flags {
+            SEQ_FIRST => {
+                if let Some(start) = self.groups.insert(apid, offset) {
+                    self.violation(apid, "unterminated_group", start);
+                }
+            }
+            SEQ_CONTINUATION if !self.groups.contains_key(&apid) => {
+                self.violation(apid, "orphan_continuation", offset);
+            }
+            SEQ_LAST if self.groups.remove(&apid).is_none() => {
+                self.violation(apid, "orphan_last", offset);
+            }
+            SEQ_UNSEGMENTED if self.groups.remove(&apid).is_some() => {
+                self.violation(apid, "unsegmented_in_group", offset);
+            }
+            _ => (),
+        }
+    }
+
+    /// Flag the groups still in progress at the end of the stream.
+    pub fn finish(&mut self) {
+        let mut groups: Vec<_> = self.groups.drain().collect();
+        groups.sort();
+        for (apid, start) in groups {
+            self.violation(apid, "unterminated_group", start);
+        }
+    }
+
+    fn violation(&mut self, apid: u16, kind: &'static str, offset: Option<u64>) {
+        self.violations.push((apid, kind, offset));
+        anomaly::emit(&self.anomalies, || {
+            let description = match kind {
+                "orphan_continuation" => "continuation packet without a first packet",
+                "orphan_last" => "last packet without a first packet",
+                "unsegmented_in_group" => "unsegmented packet inside a group",
+                _ => "group never ended with a last packet",
+            };
+            Anomaly::new("sequence_flags", Severity::Warning, description.to_string())
+                .apid(apid)
+                .offset(offset)
+        });
+    }
+}
+
+/// Limits used to reject implausible packet headers.
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    /// Maximum packet length, including the primary header.
+    pub max_len: Option<usize>,
+    /// Allowed version numbers. Only version 0 is allowed if not set.
+    pub versions: Option<HashSet<u8>>,
+    /// Allowed APIDs. Any APID is allowed if not set.
+    pub apids: Option<HashSet<u16>>,
+}
+
+/// A header that violates the [Limits] of a [PacketReader] that is not
+/// resynchronizing, returned as the payload of an [ErrorKind::InvalidData] error.
+#[derive(Debug)]
+pub struct LimitViolation(pub String);
+
+impl fmt::Display for LimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for LimitViolation {}
+
+/// Sorted, comma separated values of `set`.
+fn sorted<T: Ord + ToString>(set: &HashSet<T>) -> String {
+    let mut values: Vec<_> = set.iter().collect();
+    values.sort();
+    let values: Vec<_> = values.iter().map(|v| v.to_string()).collect();
+    values.join(", ")
+}
+
+impl Limits {
+    /// Check `header` against the limits, returning a description of the first
+    /// violation, if any.
+    pub fn check(&self, header: &PrimaryHeader) -> Result<(), String> {
+        self.check_id(header)?;
+        let len = HEADER_LEN + header.len_minus1 as usize + 1;
+        match self.max_len {
+            Some(max_len) if len > max_len => {
+                Err(format!("length {len} exceeds maximum {max_len}"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// True if the version of `header` is allowed.
+    fn version_ok(&self, header: &PrimaryHeader) -> bool {
+        match &self.versions {
+            Some(versions) => versions.contains(&header.version),
+            None => header.version == 0,
+        }
+    }
+
+    /// Check the version and APID of `header`, ignoring the length.
+    fn check_id(&self, header: &PrimaryHeader) -> Result<(), String> {
+        if !self.version_ok(header) {
+            let allowed = self.versions.as_ref().map_or("0".to_string(), sorted);
+            return Err(format!(
+                "version {} not in allowed versions {allowed}",
+                header.version
+            ));
+        }
+        match &self.apids {
+            Some(apids) if !apids.contains(&header.apid) => Err(format!(
+                "apid {} not in allowed apids {}",
+                header.apid,
+                sorted(apids)
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reads space packets from a stream of contiguous packets, tracking the byte offset
+/// of each packet.
+pub struct PacketReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    /// Start of unconsumed bytes in `buf`.
+    start: usize,
+    /// Stream offset of `buf[start]`.
+    offset: u64,
+    eof: bool,
+    done: bool,
+    resync: bool,
+    /// Limits headers are validated against. If not set, headers are only checked when
+    /// resynchronizing, using the default limits.
+    limits: Option<Limits>,
+    truncated: Truncated,
+    /// Skip packet data rather than copying it, yielding packets with empty data.
+    headers_only: bool,
+    /// Last sequence id for each APID, used to check continuity when resynchronizing.
+    last: HashMap<u16, u16>,
+}
+
+impl<R: Read> PacketReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::default(),
+            start: 0,
+            offset: 0,
+            eof: false,
+            done: false,
+            resync: false,
+            limits: None,
+            truncated: Truncated::default(),
+            headers_only: false,
+            last: HashMap::default(),
+        }
+    }
+
+    /// Enable scanning forward for the next plausible packet when a packet appears to
+    /// be corrupt, rather than continuing to trust the corrupt length.
+    ///
+    /// A packet is accepted if its header is plausible and it is followed by another
+    /// plausible header or the end of the stream. Candidates found while scanning must
+    /// also continue the sequence counter of an APID already seen, if any.
+    pub fn resync(mut self, resync: bool) -> Self {
+        self.resync = resync;
+        self
+    }
+
+    /// Validate each header against `limits`. A header that violates the limits is an
+    /// error, or triggers resynchronization if enabled.
+    pub fn limits(mut self, limits: Option<Limits>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set how a packet cut short by the end of the stream is handled.
+    pub fn truncated(mut self, truncated: Truncated) -> Self {
+        self.truncated = truncated;
+        self
+    }
+
+    /// Only decode packet headers, yielding packets with empty data. Unless
+    /// resynchronizing, which must look past each packet, packet data is discarded as it
+    /// is read rather than buffered.
+    pub fn headers_only(mut self, headers_only: bool) -> Self {
+        self.headers_only = headers_only;
+        self
+    }
+
+    fn avail(&self) -> &[u8] {
+        &self.buf[self.start..]
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.start += n;
+        self.offset += n as u64;
+    }
+
+    /// Buffer at least `n` unconsumed bytes, returning false if EOF is reached first.
+    fn fill(&mut self, n: usize) -> io::Result<bool> {
+        while self.avail().len() < n {
+            if self.eof {
+                return Ok(false);
+            }
+            if self.start > 0 {
+                self.buf.drain(..self.start);
+                self.start = 0;
+            }
+            let len = self.buf.len();
+            self.buf.resize(len + READ_SIZE.max(n - len), 0);
+            let result = self.reader.read(&mut self.buf[len..]);
+            self.buf.truncate(len + *result.as_ref().unwrap_or(&0));
+            match result {
+                Ok(0) => self.eof = true,
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(true)
+    }
+
+    fn header_at(&self, pos: usize) -> Option<PrimaryHeader> {
+        self.avail()
+            .get(pos..pos + HEADER_LEN)
+            .and_then(ccsds::PrimaryHeader::decode)
+            .map(PrimaryHeader::from)
+    }
+
+    fn check(&self, header: &PrimaryHeader) -> Result<(), String> {
+        match &self.limits {
+            Some(limits) => limits.check(header),
+            None => Limits::default().check(header),
+        }
+    }
+
+    fn plausible(&self, header: &PrimaryHeader) -> bool {
+        self.check(header).is_ok()
+    }
+
+    /// Like `plausible` but only checking the version, which is used when looking ahead
+    /// to the following header so a corrupt length or disallowed APID there is not
+    /// blamed on this packet.
+    fn plausible_version(&self, header: &PrimaryHeader) -> bool {
+        match &self.limits {
+            Some(limits) => limits.version_ok(header),
+            None => Limits::default().version_ok(header),
+        }
+    }
+
+    /// True if the unconsumed bytes start with a plausible packet followed by another
+    /// plausible header or the end of the stream.
+    fn at_candidate(&mut self, continuity: bool) -> io::Result<bool> {
+        self.fill(HEADER_LEN)?;
+        let Some(header) = self.header_at(0) else {
+            return Ok(false);
+        };
+        if !self.plausible(&header) {
+            return Ok(false);
+        }
+        if continuity && !self.last.is_empty() {
+            match self.last.get(&header.apid) {
+                Some(last)
+                    if ccsds::missing_packets(header.sequence_id, *last) < RESYNC_MAX_MISSING => {}
+                _ => return Ok(false),
+            }
+        }
+        let next = HEADER_LEN + header.len_minus1 as usize + 1;
+        if !self.fill(next + HEADER_LEN)? {
+            return Ok(self.avail().len() >= next);
+        }
+        Ok(self
+            .header_at(next)
+            .is_some_and(|h| self.plausible_version(&h)))
+    }
+
+    /// Skip bytes until the start of a plausible packet, returning the offset and length
+    /// of the skipped span.
+    fn resynchronize(&mut self) -> io::Result<(u64, u64)> {
+        let from = self.offset;
+        loop {
+            self.consume(1);
+            if self.at_candidate(true)? {
+                return Ok((from, self.offset - from));
+            }
+            if self.eof && self.avail().len() < HEADER_LEN {
+                let len = self.avail().len();
+                self.consume(len);
+                let kind = match self.truncated {
+                    Truncated::Raise => ErrorKind::UnexpectedEof,
+                    _ => ErrorKind::InvalidData,
+                };
+                return Err(io::Error::new(
+                    kind,
+                    format!(
+                        "no valid packet found after skipping {} bytes at offset {from}",
+                        self.offset - from
+                    ),
+                ));
+            }
+        }
+    }
+
+    /// Consume `n` bytes without buffering those not already buffered, returning false
+    /// if EOF is reached first.
+    fn skip(&mut self, n: usize) -> io::Result<bool> {
+        let buffered = self.avail().len().min(n);
+        self.consume(buffered);
+        let remaining = (n - buffered) as u64;
+        if remaining == 0 {
+            return Ok(true);
+        }
+        let skipped = io::copy(&mut (&mut self.reader).take(remaining), &mut io::sink())?;
+        self.offset += skipped;
+        if skipped < remaining {
+            self.eof = true;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Skip the data of the packet at the current offset, for `headers_only`.
+    fn skip_packet(
+        &mut self,
+        header: PrimaryHeader,
+        total: usize,
+    ) -> io::Result<Option<RawPacket>> {
+        let offset = self.offset;
+        if self.skip(total)? {
+            return Ok(Some(RawPacket {
+                offset,
+                data: Vec::default(),
+                header,
+                skipped: None,
+                truncated: false,
+            }));
+        }
+        let avail = self.offset - offset;
+        match self.truncated {
+            Truncated::Drop => Ok(None),
+            Truncated::Partial => Ok(Some(RawPacket {
+                offset,
+                data: Vec::default(),
+                header,
+                skipped: None,
+                truncated: true,
+            })),
+            Truncated::Raise => Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "stream ended with an incomplete packet of {avail} bytes at offset {offset}"
+                ),
+            )),
+        }
+    }
+
+    /// Handle the stream ending part way through the packet at the current offset.
+    fn truncate(&mut self) -> io::Result<Option<RawPacket>> {
+        let avail = self.avail().len();
+        if avail == 0 || self.truncated == Truncated::Drop {
+            self.consume(avail);
+            return Ok(None);
+        }
+        let offset = self.offset;
+        let header = self.header_at(0);
+        let data = self.avail().get(HEADER_LEN..).unwrap_or_default().to_vec();
+        self.consume(avail);
+        match (self.truncated, header) {
+            (Truncated::Partial, Some(header)) => Ok(Some(RawPacket {
+                offset,
+                data,
+                header,
+                skipped: None,
+                truncated: true,
+            })),
+            _ => Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "stream ended with an incomplete packet of {avail} bytes at offset {offset}"
+                ),
+            )),
+        }
+    }
+
+    fn read_packet(&mut self) -> io::Result<Option<RawPacket>> {
+        if !self.fill(HEADER_LEN)? {
+            return self.truncate();
+        }
+        let mut skipped = None;
+        if self.resync && !self.at_candidate(false)? {
+            skipped = Some(self.resynchronize()?);
+        }
+        // decode cannot fail with a full header
+        let header = self.header_at(0).unwrap();
+        if self.limits.is_some() {
+            if let Err(msg) = self.check(&header) {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    LimitViolation(format!(
+                        "invalid packet header at offset {}: {msg}",
+                        self.offset
+                    )),
+                ));
+            }
+        }
+        let total = HEADER_LEN + header.len_minus1 as usize + 1;
+        if self.headers_only && !self.resync {
+            return self.skip_packet(header, total);
+        }
+        if !self.fill(total)? {
+            return self.truncate();
+        }
+        if self.resync {
+            self.last.insert(header.apid, header.sequence_id);
+        }
+        let packet = RawPacket {
+            offset: self.offset,
+            data: if self.headers_only {
+                Vec::default()
+            } else {
+                self.avail()[HEADER_LEN..total].to_vec()
+            },
+            header,
+            skipped,
+            truncated: false,
+        };
+        self.consume(total);
+        Ok(Some(packet))
+    }
+}
+
+/// Read into `buf` until it is full or EOF is reached, returning the number of bytes read.
+pub fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(total)
+}
+
+impl<R: Read> Iterator for PacketReader<R> {
+    type Item = io::Result<RawPacket>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_packet() {
+            Ok(Some(packet)) => Some(Ok(packet)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Assembles packets from arbitrarily sized chunks of a contiguous packet stream, for
+/// use where the stream is pushed to us rather than read.
+#[derive(Default)]
+pub struct PacketAssembler {
+    buf: Vec<u8>,
+    /// Start of unconsumed bytes in `buf`.
+    start: usize,
+    /// Stream offset of `buf[start]`.
+    offset: u64,
+}
+
+impl PacketAssembler {
+    pub fn push(&mut self, chunk: &[u8]) {
+        if self.start > 0 {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Number of bytes pushed that have not yet been returned.
+    pub fn buffered(&self) -> usize {
+        self.buf.len() - self.start
+    }
+
+    /// Return the next complete packet, if enough bytes have been pushed.
+    pub fn next_packet(&mut self) -> Option<RawPacket> {
+        let avail = &self.buf[self.start..];
+        if avail.len() < HEADER_LEN {
+            return None;
+        }
+        let header = PrimaryHeader::from(ccsds::PrimaryHeader::decode(avail)?);
+        let total = HEADER_LEN + header.len_minus1 as usize + 1;
+        if avail.len() < total {
+            return None;
+        }
+        let packet = RawPacket {
+            offset: self.offset,
+            header,
+            data: avail[HEADER_LEN..total].to_vec(),
+            skipped: None,
+            truncated: false,
+        };
+        self.start += total;
+        self.offset += total as u64;
+        Some(packet)
+    }
+}
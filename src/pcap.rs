@@ -0,0 +1,388 @@
+//! Extraction of the UDP payloads of pcap and pcapng captures, e.g., of a downlink feed
+//! captured with tcpdump, as a contiguous byte stream.
+use std::{
+    collections::HashSet,
+    io::{self, ErrorKind, Read},
+};
+
+use crate::udp::Annotation;
+
+const PCAP_MAGIC_US: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE: u32 = 1;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: [u16; 2] = [0x8100, 0x88a8];
+const IPPROTO_UDP: u8 = 17;
+const UDP_HEADER_LEN: usize = 8;
+
+pub fn u16_at(dat: &[u8], pos: usize, big_endian: bool) -> Option<u16> {
+    let bytes = dat.get(pos..pos + 2)?.try_into().unwrap();
+    Some(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+pub fn u32_at(dat: &[u8], pos: usize, big_endian: bool) -> Option<u32> {
+    let bytes = dat.get(pos..pos + 4)?.try_into().unwrap();
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("invalid capture; {msg}"))
+}
+
+/// Read exactly `buf.len()` bytes, returning false if the stream ends first, e.g., for
+/// a capture still being written or cut short.
+fn read_record<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    Ok(crate::packets::read_full(reader, buf)? == buf.len())
+}
+
+enum Format {
+    Pcap {
+        big_endian: bool,
+        linktype: u32,
+    },
+    PcapNg {
+        big_endian: bool,
+        linktypes: Vec<u32>,
+    },
+}
+
+/// A reader of the UDP payloads in a pcap or pcapng capture, in capture order.
+///
+/// Ethernet, with or without VLAN tags, Linux cooked, BSD loopback and raw IP captures
+/// of IPv4 and IPv6 are supported. Fragmented IP datagrams and other traffic are
+/// skipped.
+pub struct PcapReader<R> {
+    reader: R,
+    format: Option<Format>,
+    /// Only include datagrams to or from these ports, if set.
+    ports: Option<HashSet<u16>>,
+    /// Annotation header to strip from each payload, if any.
+    annotation: Option<Annotation>,
+    payload: Vec<u8>,
+    /// Position in `payload` of the next byte to read.
+    pos: usize,
+}
+
+impl<R: Read> PcapReader<R> {
+    pub fn new(reader: R, ports: Option<HashSet<u16>>) -> Self {
+        Self {
+            reader,
+            format: None,
+            ports,
+            annotation: None,
+            payload: Vec::default(),
+            pos: 0,
+        }
+    }
+
+    /// Strip an annotation header from each payload.
+    pub fn annotation(mut self, annotation: Option<Annotation>) -> Self {
+        self.annotation = annotation;
+        self
+    }
+
+    /// Read the file header of a pcap capture or the section header of a pcapng one.
+    fn read_header(&mut self) -> io::Result<Format> {
+        let mut magic = [0u8; 4];
+        if !read_record(&mut self.reader, &mut magic)? {
+            return Err(invalid("not a pcap or pcapng capture"));
+        }
+        if u32::from_be_bytes(magic) == PCAPNG_SECTION_HEADER {
+            let big_endian = self.read_section_header()?;
+            return Ok(Format::PcapNg {
+                big_endian,
+                linktypes: Vec::default(),
+            });
+        }
+        let big_endian = match (u32::from_be_bytes(magic), u32::from_le_bytes(magic)) {
+            (PCAP_MAGIC_US | PCAP_MAGIC_NS, _) => true,
+            (_, PCAP_MAGIC_US | PCAP_MAGIC_NS) => false,
+            _ => return Err(invalid("not a pcap or pcapng capture")),
+        };
+        let mut header = [0u8; 20];
+        if !read_record(&mut self.reader, &mut header)? {
+            return Err(invalid("truncated pcap header"));
+        }
+        Ok(Format::Pcap {
+            big_endian,
+            linktype: u32_at(&header, 16, big_endian).unwrap() & 0xffff,
+        })
+    }
+
+    /// Read the rest of a pcapng section header block following its type, returning
+    /// whether the section is big-endian.
+    fn read_section_header(&mut self) -> io::Result<bool> {
+        let mut head = [0u8; 8];
+        if !read_record(&mut self.reader, &mut head)? {
+            return Err(invalid("truncated pcapng section header"));
+        }
+        let big_endian = match u32::from_be_bytes(head[4..].try_into().unwrap()) {
+            PCAPNG_BYTE_ORDER_MAGIC => true,
+            magic if magic.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => false,
+            _ => return Err(invalid("bad pcapng byte-order magic")),
+        };
+        let len = u32_at(&head, 0, big_endian).unwrap() as usize;
+        let mut rest = vec![0u8; len.saturating_sub(12)];
+        if !read_record(&mut self.reader, &mut rest)? {
+            return Err(invalid("truncated pcapng section header"));
+        }
+        Ok(big_endian)
+    }
+
+    /// Read the next captured link-layer frame and its link type, or `None` at the end
+    /// of the capture.
+    fn next_frame(&mut self) -> io::Result<Option<(u32, Vec<u8>)>> {
+        if self.format.is_none() {
+            self.format = Some(self.read_header()?);
+        }
+        match self.format.as_mut().unwrap() {
+            Format::Pcap {
+                big_endian,
+                linktype,
+            } => {
+                let mut header = [0u8; 16];
+                if !read_record(&mut self.reader, &mut header)? {
+                    return Ok(None);
+                }
+                let len = u32_at(&header, 8, *big_endian).unwrap() as usize;
+                let mut frame = vec![0u8; len];
+                if !read_record(&mut self.reader, &mut frame)? {
+                    return Ok(None);
+                }
+                Ok(Some((*linktype, frame)))
+            }
+            Format::PcapNg { .. } => self.next_block(),
+        }
+    }
+
+    /// Read pcapng blocks up to the next packet block.
+    fn next_block(&mut self) -> io::Result<Option<(u32, Vec<u8>)>> {
+        loop {
+            let mut head = [0u8; 8];
+            if !read_record(&mut self.reader, &mut head)? {
+                return Ok(None);
+            }
+            if u32::from_be_bytes(head[..4].try_into().unwrap()) == PCAPNG_SECTION_HEADER {
+                // a new section, possibly of different byte order, with its own interfaces
+                let mut rest = [0u8; 4];
+                if !read_record(&mut self.reader, &mut rest)? {
+                    return Ok(None);
+                }
+                let big_endian = match u32::from_be_bytes(rest) {
+                    PCAPNG_BYTE_ORDER_MAGIC => true,
+                    magic if magic.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => false,
+                    _ => return Err(invalid("bad pcapng byte-order magic")),
+                };
+                let len = u32_at(&head, 4, big_endian).unwrap() as usize;
+                let mut body = vec![0u8; len.saturating_sub(12)];
+                if !read_record(&mut self.reader, &mut body)? {
+                    return Ok(None);
+                }
+                self.format = Some(Format::PcapNg {
+                    big_endian,
+                    linktypes: Vec::default(),
+                });
+                continue;
+            }
+            let Some(Format::PcapNg {
+                big_endian,
+                linktypes,
+            }) = self.format.as_mut()
+            else {
+                unreachable!("blocks are only read from pcapng captures");
+            };
+            let kind = u32_at(&head, 0, *big_endian).unwrap();
+            let len = u32_at(&head, 4, *big_endian).unwrap() as usize;
+            if len < 12 || !len.is_multiple_of(4) {
+                return Err(invalid(&format!("bad pcapng block length {len}")));
+            }
+            // the body and the trailing copy of the block length
+            let mut body = vec![0u8; len - 8];
+            if !read_record(&mut self.reader, &mut body)? {
+                return Ok(None);
+            }
+            match kind {
+                PCAPNG_INTERFACE => {
+                    let linktype = u16_at(&body, 0, *big_endian).unwrap_or_default();
+                    linktypes.push(u32::from(linktype));
+                }
+                PCAPNG_ENHANCED_PACKET => {
+                    let (Some(interface), Some(caplen)) = (
+                        u32_at(&body, 0, *big_endian),
+                        u32_at(&body, 12, *big_endian),
+                    ) else {
+                        continue;
+                    };
+                    let Some(frame) = body.get(20..20 + caplen as usize) else {
+                        continue;
+                    };
+                    if let Some(linktype) = linktypes.get(interface as usize) {
+                        return Ok(Some((*linktype, frame.to_vec())));
+                    }
+                }
+                PCAPNG_SIMPLE_PACKET => {
+                    let Some(origlen) = u32_at(&body, 0, *big_endian) else {
+                        continue;
+                    };
+                    let caplen = (origlen as usize).min(body.len().saturating_sub(8));
+                    if let (Some(linktype), Some(frame)) =
+                        (linktypes.first(), body.get(4..4 + caplen))
+                    {
+                        return Ok(Some((*linktype, frame.to_vec())));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Load the next UDP payload passing the port filter, returning false at the end of
+    /// the capture.
+    fn next_payload(&mut self) -> io::Result<bool> {
+        while let Some((linktype, frame)) = self.next_frame()? {
+            let Some((src_port, dst_port, payload)) =
+                ip_packet(linktype, &frame).and_then(udp_datagram)
+            else {
+                continue;
+            };
+            if let Some(ports) = &self.ports {
+                if !ports.contains(&src_port) && !ports.contains(&dst_port) {
+                    continue;
+                }
+            }
+            self.payload.clear();
+            match &self.annotation {
+                Some(annotation) => annotation.extract(payload, &mut self.payload),
+                None => self.payload.extend_from_slice(payload),
+            }
+            self.pos = 0;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+impl<R: Read> Read for PcapReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.payload.len() {
+            if !self.next_payload()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.payload.len() - self.pos);
+        buf[..n].copy_from_slice(&self.payload[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// The IP packet carried by a link-layer frame, if any.
+fn ip_packet(linktype: u32, frame: &[u8]) -> Option<&[u8]> {
+    let (ethertype, ip) = match linktype {
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => return Some(frame),
+        // the address family is in host byte order, so use the IP version instead
+        LINKTYPE_NULL | LINKTYPE_LOOP => return frame.get(4..),
+        LINKTYPE_ETHERNET => {
+            let mut pos = 12;
+            let mut ethertype = u16_at(frame, pos, true)?;
+            while ETHERTYPE_VLAN.contains(&ethertype) {
+                pos += 4;
+                ethertype = u16_at(frame, pos, true)?;
+            }
+            (ethertype, frame.get(pos + 2..)?)
+        }
+        LINKTYPE_LINUX_SLL => (u16_at(frame, 14, true)?, frame.get(16..)?),
+        LINKTYPE_LINUX_SLL2 => (u16_at(frame, 0, true)?, frame.get(20..)?),
+        _ => return None,
+    };
+    matches!(ethertype, ETHERTYPE_IPV4 | ETHERTYPE_IPV6).then_some(ip)
+}
+
+/// The source port, destination port and payload of a UDP datagram in an unfragmented
+/// IPv4 or IPv6 packet.
+fn udp_datagram(ip: &[u8]) -> Option<(u16, u16, &[u8])> {
+    let udp = match ip.first()? >> 4 {
+        4 => {
+            let header_len = usize::from(ip[0] & 0xf) * 4;
+            let total_len = usize::from(u16_at(ip, 2, true)?);
+            // more fragments flag or a fragment offset
+            let fragmented = u16_at(ip, 6, true)? & 0x3fff != 0;
+            if fragmented || *ip.get(9)? != IPPROTO_UDP {
+                return None;
+            }
+            ip.get(header_len..total_len.min(ip.len()))?
+        }
+        6 => {
+            let payload_len = usize::from(u16_at(ip, 4, true)?);
+            let mut next = *ip.get(6)?;
+            let mut pos = 40;
+            // hop-by-hop, routing and destination options extension headers
+            while matches!(next, 0 | 43 | 60) {
+                next = *ip.get(pos)?;
+                pos += (usize::from(*ip.get(pos + 1)?) + 1) * 8;
+            }
+            if next != IPPROTO_UDP {
+                return None;
+            }
+            ip.get(pos..(40 + payload_len).min(ip.len()))?
+        }
+        _ => return None,
+    };
+    let len = usize::from(u16_at(udp, 4, true)?);
+    if len < UDP_HEADER_LEN {
+        return None;
+    }
+    Some((
+        u16_at(udp, 0, true)?,
+        u16_at(udp, 2, true)?,
+        udp.get(UDP_HEADER_LEN..len.min(udp.len()))?,
+    ))
+}
+
+/// Parse the query of a `pcap://` source, e.g., `port=5000,5001`, into the ports to
+/// include.
+pub fn ports_arg(query: &str) -> io::Result<Option<HashSet<u16>>> {
+    let mut ports = None;
+    for param in query.split('&').filter(|p| !p.is_empty()) {
+        let parsed = match param.split_once('=') {
+            Some(("port", value)) => value
+                .split(',')
+                .map(|p| p.trim().parse::<u16>().ok())
+                .collect::<Option<HashSet<u16>>>(),
+            _ => None,
+        };
+        let Some(parsed) = parsed else {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "invalid pcap source parameter; expected port=<port>[,<port>...], got {param}"
+                ),
+            ));
+        };
+        ports.get_or_insert_with(HashSet::default).extend(parsed);
+    }
+    Ok(ports)
+}
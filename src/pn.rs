@@ -0,0 +1,36 @@
+use std::sync::OnceLock;
+
+/// Length of the CCSDS pseudo-noise sequence in bytes, after which it repeats.
+pub const PN_LEN: usize = 255;
+
+/// The CCSDS pseudo-randomizer sequence, h(x) = x^8 + x^7 + x^5 + x^3 + 1, starting
+/// from the all ones state.
+pub fn sequence() -> &'static [u8; PN_LEN] {
+    static SEQUENCE: OnceLock<[u8; PN_LEN]> = OnceLock::new();
+    SEQUENCE.get_or_init(|| {
+        let mut seq = [0u8; PN_LEN];
+        let mut state: u8 = 0xff;
+        for byte in seq.iter_mut() {
+            for _ in 0..8 {
+                *byte = *byte << 1 | (state & 1);
+                let feedback = (state & 0xa9).count_ones() as u8 & 1;
+                state = state >> 1 | feedback << 7;
+            }
+        }
+        seq
+    })
+}
+
+/// Apply the pseudo-noise sequence to `dat` in place. The same operation both
+/// randomizes and derandomizes.
+///
+/// Each sequence length chunk is XORed as a pair of slices, rather than cycling the
+/// sequence, so the compiler vectorizes the loop.
+pub fn apply(dat: &mut [u8]) {
+    let seq = sequence();
+    for chunk in dat.chunks_mut(PN_LEN) {
+        for (b, pn) in chunk.iter_mut().zip(seq) {
+            *b ^= pn;
+        }
+    }
+}
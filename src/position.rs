@@ -0,0 +1,53 @@
+//! Position of the most recent item in a decoded stream, used to give errors raised
+//! while decoding the context needed to find the problem in the source.
+use std::fmt;
+
+use pyo3::{
+    exceptions::{PyEOFError, PyValueError},
+    prelude::*,
+};
+
+/// Offset, frame and packet identifiers of the item being decoded, or the most recent
+/// one if an error occurs between items.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Position {
+    /// Byte offset in the source, if known.
+    pub offset: Option<u64>,
+    /// SCID, VCID and VCDU counter of the frame.
+    pub frame: Option<(u16, u16, u32)>,
+    /// APID and sequence id of the packet.
+    pub packet: Option<(u16, u16)>,
+}
+
+impl Position {
+    /// Add the position to the message of a ValueError or EOFError, keeping its type.
+    /// Other errors, e.g., timeouts and interrupts, are returned unchanged.
+    pub fn annotate(&self, py: Python, err: PyErr) -> PyErr {
+        if self.offset.is_none() && self.frame.is_none() && self.packet.is_none() {
+            return err;
+        }
+        if !err.is_instance_of::<PyValueError>(py) && !err.is_instance_of::<PyEOFError>(py) {
+            return err;
+        }
+        let annotated =
+            PyErr::from_type(err.get_type(py), format!("{} (near {self})", err.value(py)));
+        annotated.set_cause(py, Some(err));
+        annotated
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::default();
+        if let Some(offset) = self.offset {
+            parts.push(format!("offset {offset}"));
+        }
+        if let Some((scid, vcid, counter)) = self.frame {
+            parts.push(format!("scid {scid}, vcid {vcid}, frame counter {counter}"));
+        }
+        if let Some((apid, sequence_id)) = self.packet {
+            parts.push(format!("apid {apid}, sequence id {sequence_id}"));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// Queue size used when a pipeline is switched to background decoding without an
+/// explicit prefetch size.
+pub const DEFAULT_PREFETCH: usize = 64;
+
+/// Longest time spent waiting on a background pipeline before checking for Python
+/// signals, e.g., KeyboardInterrupt.
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Result of asking a [Pipeline] for its next item.
+pub enum Next<T> {
+    Item(T),
+    /// No item was available before the timeout, or immediately when polling.
+    Pending,
+    /// The pipeline is exhausted.
+    Done,
+}
+
+enum Inner<T> {
+    Direct(Box<dyn Iterator<Item = T> + Send>),
+    Queued(Receiver<T>),
+    Empty,
+}
+
+/// A decode pipeline that is either driven directly by the consumer, or on a dedicated
+/// thread feeding a bounded queue.
+pub struct Pipeline<T> {
+    inner: Inner<T>,
+}
+
+/// Drive `iter` on a dedicated thread that fills a queue of up to `size` items.
+///
+/// The thread exits when `iter` is exhausted or the receiver is dropped.
+pub fn spawn<T: Send + 'static>(
+    iter: Box<dyn Iterator<Item = T> + Send>,
+    size: usize,
+) -> Receiver<T> {
+    let (tx, rx) = sync_channel(size);
+    thread::Builder::new()
+        .name("ccsds-prefetch".to_string())
+        .spawn(move || {
+            for item in iter {
+                if tx.send(item).is_err() {
+                    break; // receiver dropped
+                }
+            }
+        })
+        .expect("failed to spawn prefetch thread");
+    rx
+}
+
+impl<T: Send + 'static> Pipeline<T> {
+    /// Create a pipeline for `iter`, running it on a background thread if a prefetch
+    /// queue size is provided.
+    pub fn new(
+        iter: Box<dyn Iterator<Item = T> + Send>,
+        prefetch: Option<usize>,
+    ) -> PyResult<Self> {
+        let inner = match prefetch {
+            Some(0) => return Err(PyValueError::new_err("prefetch must be > 0")),
+            Some(size) => Inner::Queued(spawn(iter, size)),
+            None => Inner::Direct(iter),
+        };
+        Ok(Self { inner })
+    }
+
+    /// Create a pipeline for items sent to `rx` by a background thread.
+    pub fn queued(rx: Receiver<T>) -> Self {
+        Self {
+            inner: Inner::Queued(rx),
+        }
+    }
+
+    /// Create a pipeline for `iter` that is required to support timeouts, running it on a
+    /// background thread if a timeout or prefetch size is provided.
+    pub fn with_timeout(
+        iter: Box<dyn Iterator<Item = T> + Send>,
+        prefetch: Option<usize>,
+        timeout: Option<Duration>,
+    ) -> PyResult<Self> {
+        match (prefetch, timeout) {
+            (None, Some(_)) => Self::new(iter, Some(DEFAULT_PREFETCH)),
+            _ => Self::new(iter, prefetch),
+        }
+    }
+
+    /// Get the next item, waiting up to `timeout` if the pipeline runs in the background.
+    /// Directly driven pipelines always block.
+    pub fn next(&mut self, timeout: Option<Duration>) -> Next<T> {
+        let next = match (&mut self.inner, timeout) {
+            (Inner::Direct(iter), _) => iter.next().ok_or(true),
+            (Inner::Queued(rx), None) => rx.recv().map_err(|_| true),
+            (Inner::Queued(rx), Some(timeout)) => rx
+                .recv_timeout(timeout)
+                .map_err(|err| matches!(err, RecvTimeoutError::Disconnected)),
+            (Inner::Empty, _) => Err(true),
+        };
+        self.resolve(next)
+    }
+
+    /// Get the next item with the GIL released, waiting up to `timeout` if the pipeline
+    /// runs in the background. Python signals are checked periodically while waiting so
+    /// a KeyboardInterrupt is raised promptly.
+    pub fn wait(&mut self, py: Python, timeout: Option<Duration>) -> PyResult<Next<T>> {
+        if !matches!(self.inner, Inner::Queued(_)) {
+            return Ok(py.allow_threads(|| self.next(None)));
+        }
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            let slice = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Ok(Next::Pending);
+                    }
+                    remaining.min(SIGNAL_CHECK_INTERVAL)
+                }
+                None => SIGNAL_CHECK_INTERVAL,
+            };
+            match py.allow_threads(|| self.next(Some(slice))) {
+                Next::Pending => py.check_signals()?,
+                next => return Ok(next),
+            }
+        }
+    }
+
+    /// Get the next item if one is available without blocking. A directly driven
+    /// pipeline is switched to background decoding on the first poll.
+    pub fn poll(&mut self) -> Next<T> {
+        if matches!(self.inner, Inner::Direct(_)) {
+            if let Inner::Direct(iter) = std::mem::replace(&mut self.inner, Inner::Empty) {
+                self.inner = Inner::Queued(spawn(iter, DEFAULT_PREFETCH));
+            }
+        }
+        let next = match &mut self.inner {
+            Inner::Queued(rx) => rx
+                .try_recv()
+                .map_err(|err| matches!(err, TryRecvError::Disconnected)),
+            _ => Err(true),
+        };
+        self.resolve(next)
+    }
+
+    /// Convert a receive result, where the error indicates whether the pipeline is done.
+    fn resolve(&mut self, next: Result<T, bool>) -> Next<T> {
+        match next {
+            Ok(item) => Next::Item(item),
+            Err(false) => Next::Pending,
+            Err(true) => {
+                self.inner = Inner::Empty;
+                Next::Done
+            }
+        }
+    }
+}
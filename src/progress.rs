@@ -0,0 +1,83 @@
+//! Progress through a source of known size, for progress bars over the iterators.
+use std::{fs, path::Path};
+
+use pyo3::prelude::*;
+
+/// Tracks the position in a source of the items yielded by an iterator.
+#[derive(Clone, Debug, Default)]
+pub struct Progress {
+    /// Size of the source in bytes, if it is a regular file.
+    size: Option<u64>,
+    /// Offset just past the most recently yielded item.
+    position: u64,
+    /// Number of items yielded.
+    items: u64,
+    complete: bool,
+}
+
+impl Progress {
+    /// Progress through `source`, whose size is only known if it is a regular file,
+    /// i.e., not a named pipe or TCP stream.
+    pub fn new(source: &Path) -> Self {
+        let size = fs::metadata(source)
+            .ok()
+            .filter(|meta| meta.is_file())
+            .map(|meta| meta.len());
+        Self {
+            size,
+            ..Self::default()
+        }
+    }
+
+    /// Record that the source has been consumed up to `end`, including items that are
+    /// not yielded, e.g., because they are filtered.
+    pub fn read_to(&mut self, end: u64) {
+        self.position = self.position.max(end);
+    }
+
+    /// Record that an item was yielded.
+    pub fn yielded(&mut self) {
+        self.items += 1;
+    }
+
+    /// Record that the source is exhausted.
+    pub fn finish(&mut self) {
+        self.complete = true;
+    }
+
+    /// Fraction of the source consumed, from 0 to 1, or `None` if the size of the
+    /// source is not known.
+    pub fn fraction(&self) -> Option<f64> {
+        if self.complete {
+            return Some(1.0);
+        }
+        match self.size? {
+            0 => Some(0.0),
+            size => Some((self.position as f64 / size as f64).min(1.0)),
+        }
+    }
+
+    /// Estimate of the total number of items, from the average number of bytes per item
+    /// so far, or `None` if it cannot be estimated yet.
+    pub fn total(&self) -> Option<u64> {
+        if self.complete {
+            return Some(self.items);
+        }
+        let size = self.size?;
+        if self.items == 0 || self.position == 0 {
+            return None;
+        }
+        let remaining = size.saturating_sub(self.position) as f64;
+        let per_item = self.position as f64 / self.items as f64;
+        Some(self.items + (remaining / per_item).round() as u64)
+    }
+
+    /// `__length_hint__` for an iterator, i.e., the estimated number of items remaining,
+    /// or `NotImplemented` if it cannot be estimated.
+    pub fn length_hint(&self, py: Python) -> PyObject {
+        match self.total() {
+            Some(total) => total.saturating_sub(self.items).into_py(py),
+            None => py.NotImplemented(),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Incremental decoding of bytes fed by the application, for event loops, GUI toolkits
+//! and protocols where the library cannot own the read loop.
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    packets::PacketAssembler,
+    repr::repr,
+    sync::{self, BlockAssembler},
+    Frame, Packet,
+};
+
+enum Assembler {
+    Packets(PacketAssembler),
+    Frames {
+        blocks: BlockAssembler,
+        interleave: Option<u8>,
+    },
+}
+
+/// Decoder fed arbitrarily sized chunks of a packet or CADU stream with `feed`, with
+/// the packets or frames completed so far retrieved with `take`. Decodes space packets,
+/// or CADUs if `frame_len` is provided.
+///
+/// Parameters
+/// ----------
+/// frame_len : int, optional
+///     Length of each CADU following its attached sync marker, including any
+///     Reed-Solomon parity. If not provided the stream is space packets.
+///
+/// interleave : int, optional
+///     The Reed-Solomon interleave of the CADUs. If not provided the frames are assumed
+///     to not include Reed-Solomon parity.
+///
+/// asm : bytes or str, optional
+///     Attached sync marker bytes, or the name of a built-in marker, preceding each
+///     CADU. Defaults to the standard CCSDS ASM.
+#[pyclass]
+pub struct PushDecoder {
+    assembler: Assembler,
+    /// Number of bytes fed.
+    #[pyo3(get)]
+    fed: u64,
+    /// Number of CADUs that could not be decoded as a frame.
+    #[pyo3(get)]
+    errors: u64,
+}
+
+#[pymethods]
+impl PushDecoder {
+    #[new]
+    #[pyo3(signature=(frame_len=None, interleave=None, asm=None))]
+    fn new(
+        frame_len: Option<usize>,
+        interleave: Option<u8>,
+        asm: Option<&PyAny>,
+    ) -> PyResult<Self> {
+        let assembler = match frame_len {
+            None if interleave.is_some() || asm.is_some() => {
+                return Err(PyValueError::new_err(
+                    "interleave and asm require frame_len",
+                ))
+            }
+            None => Assembler::Packets(PacketAssembler::default()),
+            Some(0) => return Err(PyValueError::new_err("frame_len must be > 0")),
+            Some(frame_len) => {
+                if let Some(interleave) = interleave.filter(|i| !(2..=10).contains(i)) {
+                    return Err(PyValueError::new_err(format!(
+                        "improbable interleave value; expected 2..10: got {interleave}"
+                    )));
+                }
+                let asm = sync::fixed_asm_arg(asm)?;
+                Assembler::Frames {
+                    blocks: BlockAssembler::new(&asm, frame_len),
+                    interleave,
+                }
+            }
+        };
+        Ok(Self {
+            assembler,
+            fed: 0,
+            errors: 0,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        let mode = match self.assembler {
+            Assembler::Packets(_) => "packets",
+            Assembler::Frames { .. } => "frames",
+        };
+        format!(
+            "PushDecoder(mode={}, fed={}, buffered={}, errors={})",
+            repr(mode),
+            self.fed,
+            self.buffered(),
+            self.errors
+        )
+    }
+
+    /// Add the next chunk of the stream. Chunks may be of any size and need not align
+    /// with packet or CADU boundaries.
+    fn feed(&mut self, chunk: &[u8]) {
+        self.fed += chunk.len() as u64;
+        match &mut self.assembler {
+            Assembler::Packets(packets) => packets.push(chunk),
+            Assembler::Frames { blocks, .. } => blocks.push(chunk),
+        }
+    }
+
+    /// Return the packets, or frames, completed by the chunks fed so far, in stream
+    /// order. Each item is only returned once.
+    ///
+    /// Returns
+    /// -------
+    /// list of Packet or list of Frame
+    fn take(&mut self, py: Python) -> Vec<PyObject> {
+        let mut items = Vec::default();
+        match &mut self.assembler {
+            Assembler::Packets(packets) => {
+                while let Some(packet) = packets.next_packet() {
+                    items.push(Packet::from(packet).into_py(py));
+                }
+            }
+            Assembler::Frames { blocks, interleave } => {
+                while let Some((offset, block)) = blocks.next_block() {
+                    match Frame::from_block(block, *interleave) {
+                        Some(frame) => items.push(
+                            Frame {
+                                offset: Some(offset),
+                                ..frame
+                            }
+                            .into_py(py),
+                        ),
+                        None => self.errors += 1,
+                    }
+                }
+            }
+        }
+        items
+    }
+
+    /// Number of bytes fed that are not yet part of a returned item, e.g., the start
+    /// of an incomplete packet or CADU.
+    #[getter]
+    fn buffered(&self) -> usize {
+        match &self.assembler {
+            Assembler::Packets(packets) => packets.buffered(),
+            Assembler::Frames { blocks, .. } => blocks.buffered(),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Decimated quick-look images accumulated from image-bearing packets while decoding.
+use std::collections::{BTreeMap, HashMap};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::fields;
+
+/// Where the pixels of an image line are in the packets of an APID, with each packet
+/// carrying one line.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct QuickLookConfig {
+    /// Byte offset of the first pixel in the packet data, i.e., after the primary header.
+    #[pyo3(get)]
+    pub offset: usize,
+    /// Number of pixels in each line.
+    #[pyo3(get)]
+    pub width: usize,
+    /// Number of bits per pixel.
+    #[pyo3(get)]
+    pub bit_depth: u32,
+    /// Only every Nth pixel of every Nth line is kept.
+    #[pyo3(get)]
+    pub decimate: usize,
+}
+
+#[pymethods]
+impl QuickLookConfig {
+    #[new]
+    #[pyo3(signature=(offset, width, bit_depth, decimate=1))]
+    fn py_new(offset: usize, width: usize, bit_depth: u32, decimate: usize) -> PyResult<Self> {
+        if width == 0 {
+            return Err(PyValueError::new_err("invalid width; expected > 0, got 0"));
+        }
+        if !(1..=32).contains(&bit_depth) {
+            return Err(PyValueError::new_err(format!(
+                "invalid bit_depth; expected 1..=32, got {bit_depth}"
+            )));
+        }
+        if decimate == 0 {
+            return Err(PyValueError::new_err(
+                "invalid decimate; expected > 0, got 0",
+            ));
+        }
+        Ok(Self {
+            offset,
+            width,
+            bit_depth,
+            decimate,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "QuickLookConfig(offset={}, width={}, bit_depth={}, decimate={})",
+            self.offset, self.width, self.bit_depth, self.decimate,
+        )
+    }
+}
+
+impl QuickLookConfig {
+    /// The decimated pixels of the line in `dat`. Pixels beyond the end of the data
+    /// are 0.
+    fn line(&self, dat: &[u8]) -> Vec<u32> {
+        let depth = self.bit_depth as usize;
+        (0..self.width)
+            .step_by(self.decimate)
+            .map(|i| {
+                let bit_offset = self.offset * 8 + i * depth;
+                if bit_offset + depth > dat.len() * 8 {
+                    return 0;
+                }
+                fields::get_bits(dat, bit_offset, self.bit_depth).unwrap_or_default() as u32
+            })
+            .collect()
+    }
+}
+
+/// A decimated quick-look image of the packets of an APID.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct QuickLook {
+    #[pyo3(get)]
+    pub apid: u16,
+    /// Number of packets, i.e., full resolution lines, seen.
+    #[pyo3(get)]
+    pub packets: u64,
+    /// Number of pixels in each decimated line.
+    #[pyo3(get)]
+    pub width: usize,
+    /// Decimated lines of pixel values, e.g., for `numpy.array`.
+    #[pyo3(get)]
+    pub lines: Vec<Vec<u32>>,
+}
+
+#[pymethods]
+impl QuickLook {
+    /// Number of decimated lines.
+    #[getter]
+    fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "QuickLook(apid={}, packets={}, width={}, height={})",
+            self.apid,
+            self.packets,
+            self.width,
+            self.lines.len(),
+        )
+    }
+}
+
+/// Accumulates quick-look images for the configured APIDs.
+#[derive(Clone, Debug, Default)]
+pub struct QuickLooks {
+    configs: HashMap<u16, QuickLookConfig>,
+    images: BTreeMap<u16, QuickLook>,
+}
+
+impl QuickLooks {
+    pub fn new(configs: Option<HashMap<u16, QuickLookConfig>>) -> Self {
+        Self {
+            configs: configs.unwrap_or_default(),
+            images: BTreeMap::default(),
+        }
+    }
+
+    /// Add the line carried by a packet, if its APID is configured.
+    pub fn add(&mut self, apid: u16, dat: &[u8]) {
+        let Some(config) = self.configs.get(&apid) else {
+            return;
+        };
+        let image = self.images.entry(apid).or_insert_with(|| QuickLook {
+            apid,
+            packets: 0,
+            width: config.width.div_ceil(config.decimate),
+            lines: Vec::default(),
+        });
+        if (image.packets as usize).is_multiple_of(config.decimate) {
+            image.lines.push(config.line(dat));
+        }
+        image.packets += 1;
+    }
+
+    /// The images accumulated so far, by APID.
+    pub fn images(&self) -> BTreeMap<u16, QuickLook> {
+        self.images.clone()
+    }
+}
@@ -0,0 +1,117 @@
+//! Redis stream sink.
+//!
+//! The Redis client is provided by the caller, e.g., a redis-py `Redis`, so no Redis
+//! client library is required to use this package.
+use std::collections::HashSet;
+
+use pyo3::{
+    prelude::*,
+    types::{IntoPyDict, PyBytes},
+};
+
+use crate::{
+    message::{with_packet, Serialization},
+    throttle::{self, Throttle},
+};
+
+/// Number of commands queued in a pipeline before it is executed.
+const PIPELINE_LEN: usize = 1000;
+
+/// Add packets to Redis streams, one stream per APID.
+///
+/// Each packet is added as a stream entry with a single `packet` field. Entries are
+/// added using a non-transactional pipeline if the client supports pipelines, unless
+/// `rate` is provided.
+///
+/// Parameters
+/// ----------
+/// packets : Iterable[Packet] or Iterable[DecodedPacket]
+///     Packets to add, e.g., from `decode_framed_packets`.
+///
+/// client : Redis
+///     A redis-py client, or any object with a compatible `xadd` method.
+///
+/// key_prefix : str
+///     Prefix of the stream keys, which are the prefix followed by the APID.
+///
+/// apids : Iterable[int], optional
+///     Only add packets with these APIDs.
+///
+/// serialization : str
+///     Serialization of the `packet` field, as accepted by `publish_kafka_packets`.
+///
+/// maxlen : int, optional
+///     Approximate maximum length of each stream, after which the oldest entries are
+///     trimmed.
+///
+/// rate : float or str, optional
+///     Pace adding to this many bits per second, or `realtime` to replay at the rate
+///     given by the packet CDS times, or `realtime:eoscuc` for EOS CUC times. Packets
+///     are added as fast as possible if not provided.
+///
+/// Returns
+/// -------
+/// int
+///     The number of packets added.
+#[pyfunction(signature=(packets, client, key_prefix="ccsds:apid:", apids=None, serialization="raw", maxlen=None, rate=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn publish_redis_packets(
+    packets: &PyAny,
+    client: &PyAny,
+    key_prefix: &str,
+    apids: Option<&PyAny>,
+    serialization: &str,
+    maxlen: Option<usize>,
+    rate: Option<&PyAny>,
+) -> PyResult<usize> {
+    let apids: Option<HashSet<u16>> = crate::set_arg(apids)?;
+    let py = packets.py();
+    let serialization = Serialization::from_name(serialization)?;
+    let mut throttle = Throttle::from_arg(rate)?;
+    // a paced stream is added entry by entry rather than in batches
+    let pipeline = if client.hasattr("pipeline")? && throttle.is_none() {
+        Some(client.call_method(
+            "pipeline",
+            (),
+            Some([("transaction", false)].into_py_dict(py)),
+        )?)
+    } else {
+        None
+    };
+    let target = pipeline.unwrap_or(client);
+    let kwargs = [
+        ("maxlen", maxlen.into_py(py)),
+        ("approximate", true.into_py(py)),
+    ]
+    .into_py_dict(py);
+
+    let mut count: usize = 0;
+    for item in packets.iter()? {
+        let encoded = with_packet(item?, |p| {
+            let apid = p.packet.header.apid;
+            let wanted = apids.as_ref().is_none_or(|apids| apids.contains(&apid));
+            wanted.then(|| {
+                let delay = throttle.as_mut().and_then(|t| t.delay(p.packet));
+                (apid, p.encode(serialization), delay)
+            })
+        })?;
+        let Some((apid, value, delay)) = encoded else {
+            continue;
+        };
+        throttle::sleep(py, delay)?;
+        let fields = [("packet", PyBytes::new(py, &value))].into_py_dict(py);
+        target.call_method(
+            "xadd",
+            (format!("{key_prefix}{apid}"), fields),
+            Some(kwargs),
+        )?;
+        count += 1;
+        if pipeline.is_some() && count.is_multiple_of(PIPELINE_LEN) {
+            target.call_method0("execute")?;
+        }
+    }
+    if pipeline.is_some() {
+        target.call_method0("execute")?;
+    }
+    Ok(count)
+}
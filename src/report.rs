@@ -0,0 +1,499 @@
+//! Per-pass quality report.
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::BufReader,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::Serialize;
+
+use crate::{
+    frameconfig::FrameDecodeConfig,
+    idle::{FILL_APID, FILL_VCID},
+    packets::HEADER_LEN,
+    repr::repr,
+    source::open_source,
+    summary::FrameCounters,
+    sync,
+    timecode::{optional_registry_arg, TimecodeRegistry},
+    RSState,
+};
+
+/// Frame statistics for a virtual channel.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct VcidReport {
+    #[pyo3(get)]
+    pub vcid: u16,
+    #[pyo3(get)]
+    pub frames: u64,
+    /// Number of frames missing according to the frame counter.
+    #[pyo3(get)]
+    pub missing: u64,
+    /// Number of discontinuities in the frame counter.
+    #[pyo3(get)]
+    pub gaps: u64,
+    #[pyo3(get)]
+    pub rs_ok: u64,
+    #[pyo3(get)]
+    pub rs_corrected: u64,
+    #[pyo3(get)]
+    pub rs_uncorrectable: u64,
+    #[pyo3(get)]
+    pub rs_not_performed: u64,
+}
+
+#[pymethods]
+impl VcidReport {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "VcidReport(vcid={}, frames={}, missing={}, gaps={}, rs_ok={}, rs_corrected={}, rs_uncorrectable={}, rs_not_performed={})",
+            self.vcid, self.frames, self.missing, self.gaps, self.rs_ok, self.rs_corrected, self.rs_uncorrectable, self.rs_not_performed,
+        )
+    }
+}
+
+/// Packet statistics for an APID.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ApidReport {
+    #[pyo3(get)]
+    pub apid: u16,
+    #[pyo3(get)]
+    pub packets: u64,
+    /// Bytes of the packets, including their primary headers.
+    #[pyo3(get)]
+    pub bytes: u64,
+    /// Number of packets missing according to the sequence counter.
+    #[pyo3(get)]
+    pub missing: u64,
+    /// Number of discontinuities in the sequence counter.
+    #[pyo3(get)]
+    pub gaps: u64,
+    /// Earliest packet time in UTC milliseconds, if times were decoded.
+    #[pyo3(get)]
+    pub start_time: Option<i64>,
+    /// Latest packet time in UTC milliseconds, if times were decoded.
+    #[pyo3(get)]
+    pub end_time: Option<i64>,
+    #[serde(skip)]
+    last_sequence_id: Option<u16>,
+}
+
+#[pymethods]
+impl ApidReport {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "ApidReport(apid={}, packets={}, bytes={}, missing={}, gaps={}, start_time={}, end_time={})",
+            self.apid, self.packets, self.bytes, self.missing, self.gaps, repr(&self.start_time), repr(&self.end_time),
+        )
+    }
+}
+
+/// Quality report for a pass, as generated by `pass_report`.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PassReport {
+    #[pyo3(get)]
+    pub scid: u16,
+    /// Number of CADUs synchronized.
+    #[pyo3(get)]
+    pub cadus: u64,
+    /// Byte offset of the first CADU, i.e., bytes read before first acquiring lock.
+    #[pyo3(get)]
+    pub lock_offset: Option<u64>,
+    /// Number of times lock was lost, i.e., a CADU did not immediately follow the
+    /// previous one.
+    #[pyo3(get)]
+    pub lock_losses: u64,
+    /// Bytes between CADUs that were skipped while re-acquiring lock.
+    #[pyo3(get)]
+    pub unlocked_bytes: u64,
+    /// Number of frames on all virtual channels, including fill frames.
+    #[pyo3(get)]
+    pub frames: u64,
+    /// Number of fill frames, i.e., frames on VCID 63.
+    #[pyo3(get)]
+    pub fill_frames: u64,
+    /// Number of packets, excluding fill packets.
+    #[pyo3(get)]
+    pub packets: u64,
+    /// Number of fill packets, i.e., packets with APID 2047.
+    #[pyo3(get)]
+    pub fill_packets: u64,
+    /// Earliest packet time in UTC milliseconds, if times were decoded.
+    #[pyo3(get)]
+    pub start_time: Option<i64>,
+    /// Latest packet time in UTC milliseconds, if times were decoded.
+    #[pyo3(get)]
+    pub end_time: Option<i64>,
+    /// Frame statistics for each VCID other than the fill VCID, ordered by VCID.
+    #[pyo3(get)]
+    pub vcids: Vec<VcidReport>,
+    /// Packet statistics for each APID other than the fill APID, ordered by APID.
+    #[pyo3(get)]
+    pub apids: Vec<ApidReport>,
+}
+
+#[pymethods]
+impl PassReport {
+    /// Percentage of frames that are fill frames, or 0 if there are no frames.
+    #[getter]
+    fn fill_frame_percent(&self) -> f64 {
+        percent(self.fill_frames, self.frames)
+    }
+
+    /// Percentage of packets that are fill packets, or 0 if there are no packets.
+    #[getter]
+    fn fill_packet_percent(&self) -> f64 {
+        percent(self.fill_packets, self.packets + self.fill_packets)
+    }
+
+    /// The report as a JSON document.
+    #[pyo3(signature=(indent=true))]
+    fn to_json(&self, indent: bool) -> PyResult<String> {
+        let result = if indent {
+            serde_json::to_string_pretty(self)
+        } else {
+            serde_json::to_string(self)
+        };
+        result.map_err(|err| PyValueError::new_err(format!("{err}")))
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "PassReport(scid={}, cadus={}, lock_losses={}, frames={}, fill_frames={}, packets={}, fill_packets={}, start_time={}, end_time={}, vcids={}, apids={})",
+            self.scid, self.cadus, self.lock_losses, self.frames, self.fill_frames, self.packets, self.fill_packets, repr(&self.start_time), repr(&self.end_time), self.vcids.len(), self.apids.len(),
+        )
+    }
+}
+
+fn percent(n: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    100.0 * n as f64 / total as f64
+}
+
+/// Widen `[start, end]` to include `time`.
+fn cover(start: &mut Option<i64>, end: &mut Option<i64>, time: i64) {
+    *start = Some(start.map_or(time, |t| t.min(time)));
+    *end = Some(end.map_or(time, |t| t.max(time)));
+}
+
+/// Statistics gathered from the synchronized CADUs.
+#[derive(Default)]
+struct LockStats {
+    cadus: u64,
+    lock_offset: Option<u64>,
+    lock_losses: u64,
+    unlocked_bytes: u64,
+    /// Offset expected for the next CADU if lock is maintained.
+    next_offset: Option<u64>,
+}
+
+impl LockStats {
+    fn add(&mut self, offset: u64, cadu_len: u64) {
+        self.cadus += 1;
+        self.lock_offset.get_or_insert(offset);
+        if let Some(next) = self.next_offset {
+            if offset != next {
+                self.lock_losses += 1;
+                self.unlocked_bytes += offset.saturating_sub(next);
+            }
+        }
+        self.next_offset = Some(offset + cadu_len);
+    }
+}
+
+/// Statistics gathered from the decoded frames.
+#[derive(Default)]
+struct FrameStats {
+    frames: u64,
+    fill_frames: u64,
+    counters: FrameCounters,
+    vcids: BTreeMap<u16, VcidReport>,
+}
+
+impl FrameStats {
+    fn add(&mut self, vcid: u16, counter: u32, rsstate: &RSState) {
+        self.frames += 1;
+        if vcid == FILL_VCID {
+            self.fill_frames += 1;
+            return;
+        }
+        let missing = self.counters.add(vcid, counter);
+        let stats = self.vcids.entry(vcid).or_insert_with(|| VcidReport {
+            vcid,
+            ..Default::default()
+        });
+        stats.frames += 1;
+        if missing > 0 {
+            stats.missing += u64::from(missing);
+            stats.gaps += 1;
+        }
+        match rsstate {
+            RSState::Ok => stats.rs_ok += 1,
+            RSState::Corrected | RSState::Detected => stats.rs_corrected += 1,
+            RSState::Uncorrectable => stats.rs_uncorrectable += 1,
+            RSState::NotPerformed => stats.rs_not_performed += 1,
+        }
+    }
+}
+
+/// Statistics gathered from the decoded packets.
+#[derive(Default)]
+struct PacketStats {
+    packets: u64,
+    fill_packets: u64,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    apids: HashMap<u16, ApidReport>,
+}
+
+impl PacketStats {
+    fn add(&mut self, packet: &ccsds::Packet, timecode: Option<&TimecodeRegistry>) {
+        let hdr = &packet.header;
+        if hdr.apid == FILL_APID {
+            self.fill_packets += 1;
+            return;
+        }
+        self.packets += 1;
+        let stats = self.apids.entry(hdr.apid).or_insert_with(|| ApidReport {
+            apid: hdr.apid,
+            ..Default::default()
+        });
+        stats.packets += 1;
+        stats.bytes += packet.data.len() as u64;
+        if let Some(last) = stats.last_sequence_id.replace(hdr.sequence_id) {
+            let missing = ccsds::missing_packets(hdr.sequence_id, last);
+            if missing > 0 {
+                stats.missing += u64::from(missing);
+                stats.gaps += 1;
+            }
+        }
+        let time = match timecode {
+            Some(tc) if hdr.has_secondary_header => {
+                tc.decode(hdr.apid, packet.data.get(HEADER_LEN..).unwrap_or_default())
+            }
+            _ => None,
+        };
+        if let Some(time) = time {
+            cover(&mut stats.start_time, &mut stats.end_time, time);
+            cover(&mut self.start_time, &mut self.end_time, time);
+        }
+    }
+}
+
+#[derive(Default)]
+struct ReportStats {
+    /// Spacecraft the report is for, if not that of the first frame.
+    scid: Option<u16>,
+    lock: LockStats,
+    frames: FrameStats,
+    packets: PacketStats,
+}
+
+/// Accumulates a `PassReport` as a side effect of decoding, so a report can be produced
+/// along with the decoded output without reading the source a second time.
+///
+/// Pass the reporter as the `report` argument of `decode_frames` or
+/// `decode_framed_packets` and call `report` once the iterator is exhausted. The report
+/// covers every frame and packet decoded, including those not yielded because of
+/// filtering or sampling.
+///
+/// Parameters
+/// ----------
+/// timecode : str or TimecodeRegistry, optional
+///     Timecode at the start of the secondary header, as accepted by `pass_report`. If
+///     provided, the report includes the time coverage of the packets.
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct PassReporter {
+    stats: Arc<Mutex<ReportStats>>,
+    timecode: Option<TimecodeRegistry>,
+}
+
+#[pymethods]
+impl PassReporter {
+    #[new]
+    #[pyo3(signature=(timecode=None))]
+    fn py_new(timecode: Option<&PyAny>) -> PyResult<Self> {
+        Ok(Self {
+            stats: Arc::default(),
+            timecode: optional_registry_arg(timecode)?,
+        })
+    }
+
+    /// The report of what has been decoded so far.
+    ///
+    /// Returns
+    /// -------
+    /// PassReport
+    pub fn report(&self) -> PassReport {
+        let stats = self.stats.lock().unwrap();
+        let mut apids: Vec<ApidReport> = stats.packets.apids.values().cloned().collect();
+        apids.sort_by_key(|stats| stats.apid);
+        PassReport {
+            scid: stats.scid.unwrap_or_default(),
+            cadus: stats.lock.cadus,
+            lock_offset: stats.lock.lock_offset,
+            lock_losses: stats.lock.lock_losses,
+            unlocked_bytes: stats.lock.unlocked_bytes,
+            frames: stats.frames.frames,
+            fill_frames: stats.frames.fill_frames,
+            packets: stats.packets.packets,
+            fill_packets: stats.packets.fill_packets,
+            start_time: stats.packets.start_time,
+            end_time: stats.packets.end_time,
+            vcids: stats.frames.vcids.values().cloned().collect(),
+            apids,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        let stats = self.stats.lock().unwrap();
+        format!(
+            "PassReporter(cadus={}, frames={}, packets={})",
+            stats.lock.cadus, stats.frames.frames, stats.packets.packets,
+        )
+    }
+}
+
+impl PassReporter {
+    /// Report on spacecraft `scid` rather than the spacecraft of the first frame.
+    pub fn set_scid(&self, scid: u16) {
+        self.stats.lock().unwrap().scid = Some(scid);
+    }
+
+    /// Add a CADU synchronized at `offset`.
+    pub fn add_cadu(&self, offset: u64, cadu_len: u64) {
+        self.stats.lock().unwrap().lock.add(offset, cadu_len);
+    }
+
+    pub(crate) fn add_frame(&self, scid: u16, vcid: u16, counter: u32, rsstate: &RSState) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.scid.get_or_insert(scid);
+        stats.frames.add(vcid, counter, rsstate);
+    }
+
+    pub fn add_packet(&self, packet: &ccsds::Packet) {
+        let timecode = self.timecode.as_ref();
+        self.stats.lock().unwrap().packets.add(packet, timecode);
+    }
+}
+
+/// Generate a quality report for a pass, decoding frames and packets in a single pass
+/// over the source.
+///
+/// The report includes lock statistics, per-VCID frame counts, gaps and Reed-Solomon
+/// results, per-APID packet counts and gaps, the time coverage of the packets, and the
+/// percentage of fill frames and packets.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of CADUs.
+///
+/// scid : int
+///     Spacecraft identifier of the frames to decode packets from.
+///
+/// cadu_len : int
+///     The length of the CADU, i.e., the ASM length plus the length of the frame plus the
+///     length of any integrity or parity bytes.
+///
+/// izone_len : int
+///     Frame insert-zone number of bytes used by the spacecraft, if any.
+///
+/// trailer_len : int
+///     Frame trailer number of bytes used by the spacecraft, if any.
+///
+/// interleave : int or str, optional
+///     The Reed-Solomon interleave, or `auto`, as accepted by `decode_frames`.
+///
+/// asm : bytes or str, optional
+///     Attached sync marker, as accepted by `decode_frames`. Defaults to the standard
+///     CCSDS ASM.
+///
+/// timecode : str or TimecodeRegistry, optional
+///     Timecode at the start of the secondary header, either "cds" or "eoscuc", or the
+///     timecode of each APID. If provided, the report includes the time coverage of the
+///     packets.
+///
+/// Returns
+/// -------
+/// PassReport
+#[pyfunction(signature=(source, scid, cadu_len, izone_len=0, trailer_len=0, interleave=None, asm=None, timecode=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn pass_report(
+    source: PathBuf,
+    scid: u16,
+    cadu_len: usize,
+    izone_len: usize,
+    trailer_len: usize,
+    interleave: Option<&PyAny>,
+    asm: Option<&PyAny>,
+    timecode: Option<&PyAny>,
+) -> PyResult<PassReport> {
+    let reporter = PassReporter {
+        stats: Arc::default(),
+        timecode: optional_registry_arg(timecode)?,
+    };
+    crate::check_scid(scid)?;
+    reporter.set_scid(scid);
+    if izone_len >= 16 || trailer_len >= 16 {
+        return Err(PyValueError::new_err(format!(
+            "invalid izone_len or trailer_len; expected 0..16, got {izone_len} and {trailer_len}"
+        )));
+    }
+    let config = FrameDecodeConfig::from_cadu_len(&source, cadu_len as i64, interleave, asm)?;
+    config.check_layout(izone_len, trailer_len)?;
+    let FrameDecodeConfig {
+        asm,
+        frame_len,
+        interleave,
+    } = config;
+
+    let blocks = {
+        let reporter = reporter.clone();
+        sync::Synchronizer::new(BufReader::new(open_source(&source)?), &asm, frame_len)
+            .filter_map(Result::ok)
+            .map(move |(offset, block)| {
+                reporter.add_cadu(offset, cadu_len as u64);
+                block
+            })
+    };
+    let mut builder = ccsds::FrameDecoderBuilder::default();
+    if let Some(interleave) = interleave {
+        builder = builder.reed_solomon(interleave);
+    }
+    let frames = {
+        let reporter = reporter.clone();
+        builder
+            .build()
+            .start(blocks)
+            .filter_map(Result::ok)
+            .inspect(move |frame| {
+                let hdr = &frame.frame.header;
+                reporter.add_frame(hdr.scid, hdr.vcid, hdr.counter, &(&frame.rsstate).into());
+            })
+    };
+    for decoded in ccsds::decode_framed_packets(scid, frames, izone_len, trailer_len) {
+        reporter.add_packet(&decoded.packet);
+    }
+    Ok(reporter.report())
+}
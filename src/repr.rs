@@ -0,0 +1,194 @@
+//! Rendering of values the way Python prints them, used by the `__str__` and
+//! `__repr__` of the Python classes so they show `None`, `True` and `'text'` rather than
+//! the Rust `Debug` forms.
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{self, Display, Write},
+};
+
+/// A value that can be written as its Python repr.
+pub trait PyRepr {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result;
+}
+
+/// Display adapter writing a value as its Python repr.
+pub struct Repr<'a, T: ?Sized>(&'a T);
+
+impl<T: PyRepr + ?Sized> Display for Repr<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_py(f)
+    }
+}
+
+/// Format `value` as Python would, e.g., `format!("offset={}", repr(&self.offset))`.
+pub fn repr<T: PyRepr + ?Sized>(value: &T) -> Repr<'_, T> {
+    Repr(value)
+}
+
+/// Display adapter writing bytes as a single hex integer, e.g., an ASM as `0x1acffc1d`.
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("0x")?;
+        for b in self.0 {
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! display_repr {
+    ($($ty:ty),*) => {
+        $(impl PyRepr for $ty {
+            fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{self}")
+            }
+        })*
+    };
+}
+
+display_repr!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl PyRepr for f64 {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_nan() {
+            f.write_str("nan")
+        } else {
+            // Debug keeps the trailing `.0` of whole numbers, as Python does
+            write!(f, "{self:?}")
+        }
+    }
+}
+
+impl PyRepr for f32 {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f64::from(*self).fmt_py(f)
+    }
+}
+
+impl PyRepr for bool {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(if *self { "True" } else { "False" })
+    }
+}
+
+impl PyRepr for str {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let quote = if self.contains('\'') && !self.contains('"') {
+            '"'
+        } else {
+            '\''
+        };
+        f.write_char(quote)?;
+        for c in self.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if c == quote => write!(f, "\\{c}")?,
+                c if c.is_control() && (c as u32) < 0x100 => write!(f, "\\x{:02x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char(quote)
+    }
+}
+
+impl PyRepr for String {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_str().fmt_py(f)
+    }
+}
+
+impl<T: PyRepr + ?Sized> PyRepr for &T {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt_py(f)
+    }
+}
+
+impl<T: PyRepr> PyRepr for Option<T> {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Some(value) => value.fmt_py(f),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+impl<A: PyRepr, B: PyRepr> PyRepr for (A, B) {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", repr(&self.0), repr(&self.1))
+    }
+}
+
+fn fmt_seq<'a, T: PyRepr + 'a>(
+    f: &mut fmt::Formatter,
+    items: impl IntoIterator<Item = &'a T>,
+) -> fmt::Result {
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        item.fmt_py(f)?;
+    }
+    Ok(())
+}
+
+impl<T: PyRepr> PyRepr for [T] {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_char('[')?;
+        fmt_seq(f, self)?;
+        f.write_char(']')
+    }
+}
+
+impl<T: PyRepr> PyRepr for Vec<T> {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_slice().fmt_py(f)
+    }
+}
+
+/// Sets are written sorted so the output is stable.
+impl<T: PyRepr + Ord, S> PyRepr for HashSet<T, S> {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("set()");
+        }
+        let mut items: Vec<&T> = self.iter().collect();
+        items.sort();
+        f.write_char('{')?;
+        fmt_seq(f, items)?;
+        f.write_char('}')
+    }
+}
+
+fn fmt_map<'a, K: PyRepr + 'a, V: PyRepr + 'a>(
+    f: &mut fmt::Formatter,
+    entries: impl IntoIterator<Item = (&'a K, &'a V)>,
+) -> fmt::Result {
+    f.write_char('{')?;
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}: {}", repr(key), repr(value))?;
+    }
+    f.write_char('}')
+}
+
+impl<K: PyRepr, V: PyRepr> PyRepr for BTreeMap<K, V> {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_map(f, self)
+    }
+}
+
+/// Maps are written sorted by key so the output is stable.
+impl<K: PyRepr + Ord, V: PyRepr, S> PyRepr for HashMap<K, V, S> {
+    fn fmt_py(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut entries: Vec<(&K, &V)> = self.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        fmt_map(f, entries)
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    collections::HashMap,
+    io::{BufReader, Write},
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{compress, packets::PacketReader, source::open_source};
+
+/// Rewrite the primary headers of a stream of packets, writing the packets to `dest`.
+///
+/// Packet data is preserved and the packet length of each header is recomputed from
+/// the data. Space packets do not carry a spacecraft id, so only packet header fields
+/// are rewritten.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing stream of space packets.
+///
+/// dest : str or os.PathLike
+///     Path of the file to write the rewritten packets to.
+///
+/// apid_map : dict of int to int, optional
+///     New APID for each APID to rewrite. Packets with other APIDs are written with
+///     their APID unchanged.
+///
+/// compression : str, optional
+///     Compress the output, either `gzip`, `zstd` or `none`. Defaults to `gzip` or
+///     `zstd` if `dest` ends with `.gz` or `.zst`, otherwise `none`.
+///
+/// Returns
+/// -------
+/// (int, int)
+///     The number of packets written and the number of packets with a rewritten APID.
+#[pyfunction(signature=(source, dest, apid_map=None, compression=None))]
+pub fn rewrite_packets(
+    source: PathBuf,
+    dest: PathBuf,
+    apid_map: Option<HashMap<u16, u16>>,
+    compression: Option<&str>,
+) -> PyResult<(usize, usize)> {
+    let apid_map = apid_map.unwrap_or_default();
+    if let Some(apid) = apid_map.values().find(|a| **a > 0x7ff) {
+        return Err(PyValueError::new_err(format!(
+            "invalid apid; expected 0..=2047, got {apid}"
+        )));
+    }
+    let reader = PacketReader::new(BufReader::new(open_source(&source)?));
+    let mut writer = compress::create(&dest, compression)?;
+
+    let (mut written, mut rewritten) = (0, 0);
+    for packet in reader {
+        let mut packet = packet?;
+        if let Some(apid) = apid_map.get(&packet.header.apid) {
+            packet.header.apid = *apid;
+            rewritten += 1;
+        }
+        // complete packets always have at least one byte of data
+        packet.header.len_minus1 = (packet.data.len() - 1) as u16;
+        packet.write_to(&mut writer)?;
+        written += 1;
+    }
+    writer.flush()?;
+
+    Ok((written, rewritten))
+}
@@ -0,0 +1,373 @@
+//! CCSDS 121.0 lossless data compression (Rice) decoding.
+
+// pyo3 0.19 generates the constructor text signature as an impl nested in a function
+#![allow(non_local_definitions)]
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::repr::repr;
+
+/// Number of blocks in a segment, used by the remainder-of-segment zero block code.
+const SEGMENT_BLOCKS: usize = 64;
+/// Zero block fundamental sequence value indicating the remainder of the segment.
+const ROS: u64 = 4;
+
+/// Reads bits most significant first.
+struct BitReader<'a> {
+    dat: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(dat: &'a [u8]) -> Self {
+        Self { dat, pos: 0 }
+    }
+
+    fn bit(&mut self) -> Option<u64> {
+        let byte = self.dat.get(self.pos / 8)?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+        Some(u64::from(bit))
+    }
+
+    fn bits(&mut self, n: u32) -> Option<u64> {
+        if self.pos + n as usize > self.dat.len() * 8 {
+            return None;
+        }
+        let mut val = 0;
+        for _ in 0..n {
+            val = val << 1 | self.bit()?;
+        }
+        Some(val)
+    }
+
+    /// Decode a fundamental sequence codeword, i.e., the number of zeros before a one.
+    fn fs(&mut self) -> Option<u64> {
+        let mut count = 0;
+        while self.bit()? == 0 {
+            count += 1;
+        }
+        Some(count)
+    }
+
+    fn align(&mut self) {
+        self.pos = self.pos.div_ceil(8) * 8;
+    }
+}
+
+/// Parameters of a CCSDS 121.0 compressed data stream.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct RiceConfig {
+    /// Bits per sample, 1..=32.
+    #[pyo3(get)]
+    pub bits_per_sample: u32,
+    /// Samples per block, one of 8, 16, 32 or 64.
+    #[pyo3(get)]
+    pub block_size: usize,
+    /// Reference sample interval in blocks.
+    #[pyo3(get)]
+    pub rsi: usize,
+    /// True if the unit-delay predictor and mapping were applied before coding.
+    #[pyo3(get)]
+    pub preprocess: bool,
+    /// True if samples are two's complement signed values.
+    #[pyo3(get)]
+    pub signed: bool,
+    /// True if each reference sample interval is padded to a byte boundary.
+    #[pyo3(get)]
+    pub pad_rsi: bool,
+    /// Number of bytes of packet data before the compressed data, e.g., a secondary
+    /// header. Only used when decompressing packets.
+    #[pyo3(get)]
+    pub offset: usize,
+}
+
+#[pymethods]
+impl RiceConfig {
+    #[new]
+    #[pyo3(signature=(bits_per_sample, block_size=16, rsi=128, preprocess=true, signed=false, pad_rsi=false, offset=0))]
+    fn py_new(
+        bits_per_sample: u32,
+        block_size: usize,
+        rsi: usize,
+        preprocess: bool,
+        signed: bool,
+        pad_rsi: bool,
+        offset: usize,
+    ) -> PyResult<Self> {
+        if !(1..=32).contains(&bits_per_sample) {
+            return Err(PyValueError::new_err(format!(
+                "invalid bits_per_sample; expected 1..=32, got {bits_per_sample}"
+            )));
+        }
+        if ![8, 16, 32, 64].contains(&block_size) {
+            return Err(PyValueError::new_err(format!(
+                "invalid block_size; expected one of 8, 16, 32, 64, got {block_size}"
+            )));
+        }
+        if !(1..=4096).contains(&rsi) {
+            return Err(PyValueError::new_err(format!(
+                "invalid rsi; expected 1..=4096, got {rsi}"
+            )));
+        }
+        Ok(Self {
+            bits_per_sample,
+            block_size,
+            rsi,
+            preprocess,
+            signed,
+            pad_rsi,
+            offset,
+        })
+    }
+
+    /// Decompress `data` into samples.
+    #[pyo3(signature=(data, samples=None))]
+    fn decompress(&self, data: &[u8], samples: Option<usize>) -> Vec<i64> {
+        self.decode(data, samples)
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "RiceConfig(bits_per_sample={}, block_size={}, rsi={}, preprocess={}, signed={}, pad_rsi={}, offset={})",
+            self.bits_per_sample, self.block_size, self.rsi, repr(&self.preprocess), repr(&self.signed), repr(&self.pad_rsi), self.offset,
+        )
+    }
+}
+
+/// Accumulates decoded values, undoing the preprocessor if used.
+struct Output<'a> {
+    config: &'a RiceConfig,
+    samples: Vec<i64>,
+    prev: i64,
+    limit: usize,
+}
+
+impl Output<'_> {
+    fn full(&self) -> bool {
+        self.samples.len() >= self.limit
+    }
+
+    fn min_max(&self) -> (i64, i64) {
+        let n = self.config.bits_per_sample;
+        if self.config.signed {
+            (-(1i64 << (n - 1)), (1i64 << (n - 1)) - 1)
+        } else {
+            (0, (1i64 << n) - 1)
+        }
+    }
+
+    fn sign_extend(&self, val: u64) -> i64 {
+        let n = self.config.bits_per_sample;
+        if self.config.signed && val >> (n - 1) & 1 == 1 {
+            val as i64 - (1i64 << n)
+        } else {
+            val as i64
+        }
+    }
+
+    fn reference(&mut self, val: u64) {
+        self.prev = self.sign_extend(val);
+        self.samples.push(self.prev);
+    }
+
+    fn value(&mut self, val: u64) {
+        if !self.config.preprocess {
+            let sample = self.sign_extend(val);
+            self.samples.push(sample);
+            return;
+        }
+        let (min, max) = self.min_max();
+        let delta = val as i64;
+        let theta = (self.prev - min).min(max - self.prev);
+        let diff = if delta <= 2 * theta {
+            if delta % 2 == 0 {
+                delta / 2
+            } else {
+                -(delta + 1) / 2
+            }
+        } else if theta == self.prev - min {
+            delta - theta
+        } else {
+            theta - delta
+        };
+        self.prev += diff;
+        self.samples.push(self.prev);
+    }
+}
+
+impl RiceConfig {
+    fn id_len(&self) -> u32 {
+        match self.bits_per_sample {
+            1..=8 => 3,
+            9..=16 => 4,
+            _ => 5,
+        }
+    }
+
+    /// Decode the block at `block` within the current RSI, returning the number of
+    /// blocks decoded, which may be more than one for a run of zero blocks.
+    fn decode_block(&self, r: &mut BitReader, out: &mut Output, block: usize) -> Option<usize> {
+        let n = self.bits_per_sample;
+        let id_len = self.id_len();
+        let has_ref = self.preprocess && block == 0;
+        let count = self.block_size - usize::from(has_ref);
+
+        let id = r.bits(id_len)?;
+        if id == 0 {
+            let second_extension = r.bit()? == 1;
+            let reference = if has_ref { Some(r.bits(n)?) } else { None };
+            if second_extension {
+                let mut values = Vec::with_capacity(self.block_size);
+                for _ in 0..self.block_size / 2 {
+                    let m = r.fs()?;
+                    let mut beta = 0;
+                    while (beta + 1) * (beta + 2) / 2 <= m {
+                        beta += 1;
+                    }
+                    let second = m - beta * (beta + 1) / 2;
+                    values.push(beta - second);
+                    values.push(second);
+                }
+                if let Some(val) = reference {
+                    out.reference(val);
+                }
+                for val in values.into_iter().skip(usize::from(has_ref)) {
+                    out.value(val);
+                }
+                return Some(1);
+            }
+            let blocks = match r.fs()? {
+                m @ 0..=3 => m as usize + 1,
+                ROS => {
+                    let remaining = self.rsi - block;
+                    remaining.min(SEGMENT_BLOCKS - block % SEGMENT_BLOCKS)
+                }
+                m => m as usize,
+            };
+            if let Some(val) = reference {
+                out.reference(val);
+            }
+            for _ in 0..blocks * self.block_size - usize::from(has_ref) {
+                out.value(0);
+            }
+            return Some(blocks);
+        }
+
+        let reference = if has_ref { Some(r.bits(n)?) } else { None };
+        let mut values = Vec::with_capacity(count);
+        if id == (1 << id_len) - 1 {
+            for _ in 0..count {
+                values.push(r.bits(n)?);
+            }
+        } else {
+            let k = (id - 1) as u32;
+            for _ in 0..count {
+                values.push(r.fs()? << k);
+            }
+            for val in values.iter_mut() {
+                *val |= r.bits(k)?;
+            }
+        }
+        if let Some(val) = reference {
+            out.reference(val);
+        }
+        for val in values {
+            out.value(val);
+        }
+        Some(1)
+    }
+
+    /// Decode samples from `dat`, stopping at `limit` samples or when the data runs out.
+    /// A block that is incomplete at the end of the data is discarded.
+    pub fn decode(&self, dat: &[u8], limit: Option<usize>) -> Vec<i64> {
+        let mut r = BitReader::new(dat);
+        let mut out = Output {
+            config: self,
+            samples: Vec::default(),
+            prev: 0,
+            limit: limit.unwrap_or(usize::MAX),
+        };
+        'rsi: while !out.full() {
+            let mut block = 0;
+            while block < self.rsi {
+                let checkpoint = out.samples.len();
+                match self.decode_block(&mut r, &mut out, block) {
+                    Some(blocks) => block += blocks,
+                    None => {
+                        out.samples.truncate(checkpoint);
+                        break 'rsi;
+                    }
+                }
+                if out.full() {
+                    break 'rsi;
+                }
+            }
+            if self.pad_rsi {
+                r.align();
+            }
+        }
+        out.samples.truncate(out.limit);
+        out.samples
+    }
+}
+
+/// Decompress CCSDS 121.0 (Rice) compressed data.
+///
+/// Parameters
+/// ----------
+/// data : bytes
+///     The compressed data.
+///
+/// bits_per_sample : int
+///     Bits per sample, 1..=32.
+///
+/// block_size : int
+///     Samples per block, one of 8, 16, 32 or 64.
+///
+/// rsi : int
+///     Reference sample interval in blocks.
+///
+/// preprocess : bool
+///     True if the unit-delay predictor and mapping were applied before coding.
+///
+/// signed : bool
+///     True if samples are two's complement signed values.
+///
+/// pad_rsi : bool
+///     True if each reference sample interval is padded to a byte boundary.
+///
+/// samples : int, optional
+///     Number of samples to decode. If not provided, decoding continues until the data
+///     runs out.
+///
+/// Returns
+/// -------
+/// list of int
+///     The decoded samples.
+#[pyfunction(signature=(data, bits_per_sample, block_size=16, rsi=128, preprocess=true, signed=false, pad_rsi=false, samples=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn rice_decompress(
+    data: &[u8],
+    bits_per_sample: u32,
+    block_size: usize,
+    rsi: usize,
+    preprocess: bool,
+    signed: bool,
+    pad_rsi: bool,
+    samples: Option<usize>,
+) -> PyResult<Vec<i64>> {
+    let config = RiceConfig::py_new(
+        bits_per_sample,
+        block_size,
+        rsi,
+        preprocess,
+        signed,
+        pad_rsi,
+        0,
+    )?;
+    Ok(config.decode(data, samples))
+}
@@ -0,0 +1,306 @@
+//! End-to-end check of a framing configuration by encoding packets into CADUs and
+//! decoding them back, e.g., to validate a mission configuration before a pass.
+use std::io::Cursor;
+
+use pyo3::{
+    exceptions::{PyAssertionError, PyValueError},
+    prelude::*,
+};
+
+use crate::{
+    idle::{FHP_NONE, FILL_APID, MPDU_HEADER_LEN},
+    message::with_packet,
+    packets::HEADER_LEN,
+    pn, rs,
+    services::VCDU_HEADER_LEN,
+    sync,
+};
+
+/// Frame counters are 24 bits.
+const COUNTER_MODULUS: u32 = 1 << 24;
+
+/// Outcome of a successful `roundtrip`.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default)]
+pub struct RoundtripReport {
+    /// Number of packets encoded and recovered.
+    #[pyo3(get)]
+    pub packets: usize,
+    /// Number of CADUs the packets were encoded into.
+    #[pyo3(get)]
+    pub frames: usize,
+    /// Length of the packet zone of each frame.
+    #[pyo3(get)]
+    pub packet_zone_len: usize,
+    /// Bytes of idle packet used to fill the last frames.
+    #[pyo3(get)]
+    pub fill_bytes: usize,
+}
+
+#[pymethods]
+impl RoundtripReport {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "RoundtripReport(packets={}, frames={}, packet_zone_len={}, fill_bytes={})",
+            self.packets, self.frames, self.packet_zone_len, self.fill_bytes,
+        )
+    }
+}
+
+/// The bytes of a `packets` item, either a Packet, DecodedPacket or the bytes of a
+/// single packet.
+fn packet_bytes(item: &PyAny) -> PyResult<Vec<u8>> {
+    if let Ok(dat) = with_packet(item, |p| p.packet.data.to_vec()) {
+        return Ok(dat);
+    }
+    let dat: Vec<u8> = item.extract()?;
+    let len = dat
+        .get(4..HEADER_LEN)
+        .map(|len| HEADER_LEN + usize::from(u16::from_be_bytes([len[0], len[1]])) + 1);
+    if len != Some(dat.len()) {
+        return Err(PyValueError::new_err(format!(
+            "invalid packet; expected a single packet of the length in its header, got {} bytes",
+            dat.len()
+        )));
+    }
+    Ok(dat)
+}
+
+/// An idle packet of `len` bytes, at least one more than the primary header.
+fn idle_packet(len: usize) -> Vec<u8> {
+    let len_minus1 = (len - HEADER_LEN - 1) as u16;
+    let mut dat = vec![0u8; len];
+    dat[..HEADER_LEN].copy_from_slice(&[
+        (FILL_APID >> 8) as u8,
+        FILL_APID as u8,
+        0xC0,
+        0,
+        (len_minus1 >> 8) as u8,
+        len_minus1 as u8,
+    ]);
+    dat
+}
+
+/// Multiplex the packet stream `dat` into the packet zones of consecutive frames.
+fn encode_frames(
+    dat: &[u8],
+    starts: &[usize],
+    scid: u16,
+    vcid: u16,
+    izone_len: usize,
+    zone_len: usize,
+    trailer_len: usize,
+) -> Vec<Vec<u8>> {
+    let mut starts = starts.iter().peekable();
+    let mut frames = Vec::default();
+    for (index, zone) in dat.chunks(zone_len).enumerate() {
+        let zone_start = index * zone_len;
+        while starts.next_if(|start| **start < zone_start).is_some() {}
+        let fhp = match starts.peek() {
+            Some(start) if **start < zone_start + zone_len => **start - zone_start,
+            _ => FHP_NONE,
+        };
+        let counter = (index as u32 % COUNTER_MODULUS).to_be_bytes();
+        let mut frame = vec![
+            0x40 | (scid >> 2) as u8,
+            (scid as u8 & 0x3) << 6 | vcid as u8,
+            counter[1],
+            counter[2],
+            counter[3],
+            0,
+        ];
+        frame.resize(VCDU_HEADER_LEN + izone_len, 0);
+        frame.extend_from_slice(&(fhp as u16).to_be_bytes());
+        frame.extend_from_slice(zone);
+        frame.resize(frame.len() + trailer_len, 0);
+        frames.push(frame);
+    }
+    frames
+}
+
+/// Encode packets into CADUs and decode them back, checking every packet is recovered
+/// bit-exact, to validate a framing configuration, e.g., the frame length, insert zone
+/// and Reed-Solomon settings of a mission, before a pass.
+///
+/// Packets are multiplexed into the frames of a single virtual channel, the last
+/// frame filled with an idle packet, and the frames encoded as by `encode_cadu`. The
+/// CADUs are decoded as by `decode_framed_packets`.
+///
+/// Parameters
+/// ----------
+/// packets : Iterable[Packet or DecodedPacket or bytes]
+///     The packets to encode, as Packets, DecodedPackets or the bytes of each packet.
+///     Idle packets, i.e., APID 2047, are not expected to be recovered.
+///
+/// scid : int
+///     The spacecraft identifier, 0 to 255.
+///
+/// cadu_len : int
+///     The length of each CADU, i.e., the ASM, frame and any Reed-Solomon parity.
+///
+/// vcid : int
+///     The virtual channel to encode the packets on, 0 to 62.
+///
+/// izone_len : int
+///     Length of the insert zone of each frame, which is zero filled.
+///
+/// trailer_len : int
+///     Length of the trailer of each frame, which is zero filled.
+///
+/// interleave : int, optional
+///     The Reed-Solomon interleave, 1 to 8. If not provided frames have no parity.
+///
+/// asm : bytes, optional
+///     Attached sync marker. Defaults to the standard CCSDS ASM.
+///
+/// Returns
+/// -------
+/// RoundtripReport
+///
+/// Raises
+/// ------
+/// ValueError
+///     If the framing parameters are invalid.
+///
+/// AssertionError
+///     If the packets are not all recovered bit-exact.
+#[pyfunction(signature=(packets, scid, cadu_len, vcid=0, izone_len=0, trailer_len=0, interleave=None, asm=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn roundtrip(
+    py: Python,
+    packets: &PyAny,
+    scid: u16,
+    cadu_len: usize,
+    vcid: u16,
+    izone_len: usize,
+    trailer_len: usize,
+    interleave: Option<usize>,
+    asm: Option<Vec<u8>>,
+) -> PyResult<RoundtripReport> {
+    if scid > 0xff {
+        return Err(PyValueError::new_err(format!(
+            "invalid scid; expected 0..=255, got {scid}"
+        )));
+    }
+    if vcid > 62 {
+        return Err(PyValueError::new_err(format!(
+            "invalid vcid; expected 0..=62, got {vcid}"
+        )));
+    }
+    if let Some(interleave) = interleave.filter(|i| !(1..=8).contains(i)) {
+        return Err(PyValueError::new_err(format!(
+            "invalid interleave value; expected 1..=8, got {interleave}"
+        )));
+    }
+    let asm = asm.unwrap_or_else(|| ccsds::ASM.to_vec());
+    let Some(frame_len) = cadu_len.checked_sub(asm.len()).filter(|len| *len > 0) else {
+        return Err(PyValueError::new_err(format!(
+            "invalid cadu_len; expected more than the {} byte ASM, got {cadu_len}",
+            asm.len()
+        )));
+    };
+    let data_len = match interleave {
+        Some(interleave) if frame_len != rs::N * interleave => {
+            return Err(PyValueError::new_err(format!(
+                "cadu_len {cadu_len} does not match interleave {interleave}; expected the {} \
+                 byte ASM plus interleave * {} = {} bytes",
+                asm.len(),
+                rs::N,
+                asm.len() + rs::N * interleave
+            )));
+        }
+        Some(interleave) => rs::K * interleave,
+        None => frame_len,
+    };
+    let overhead = VCDU_HEADER_LEN + izone_len + MPDU_HEADER_LEN + trailer_len;
+    let Some(zone_len) = data_len.checked_sub(overhead).filter(|len| *len > 0) else {
+        return Err(PyValueError::new_err(format!(
+            "frames of {data_len} bytes, excluding Reed-Solomon parity, leave no room for \
+             packet data after the {VCDU_HEADER_LEN} byte VCDU header, {izone_len} byte \
+             insert zone, {MPDU_HEADER_LEN} byte M_PDU header and {trailer_len} byte trailer"
+        )));
+    };
+
+    let mut expected = Vec::default();
+    let mut stream = Vec::default();
+    let mut starts = Vec::default();
+    for item in packets.iter()? {
+        let dat = packet_bytes(item?)?;
+        starts.push(stream.len());
+        stream.extend_from_slice(&dat);
+        if u16::from_be_bytes([dat[0], dat[1]]) & 0x7ff != FILL_APID {
+            expected.push(dat);
+        }
+    }
+    // fill the last frame with an idle packet, spilling into another frame if there is
+    // not room for the smallest idle packet
+    let mut fill_bytes = (zone_len - stream.len() % zone_len) % zone_len;
+    if fill_bytes > 0 && fill_bytes <= HEADER_LEN {
+        fill_bytes += zone_len;
+    }
+    if fill_bytes > 0 {
+        starts.push(stream.len());
+        stream.extend_from_slice(&idle_packet(fill_bytes));
+    }
+    let frames = encode_frames(
+        &stream,
+        &starts,
+        scid,
+        vcid,
+        izone_len,
+        zone_len,
+        trailer_len,
+    );
+    let num_frames = frames.len();
+
+    let mut cadus = Vec::with_capacity(num_frames * cadu_len);
+    for mut frame in frames {
+        if let Some(interleave) = interleave {
+            frame = rs::encode_interleaved(&frame, interleave);
+        }
+        pn::apply(&mut frame);
+        cadus.extend_from_slice(&asm);
+        cadus.extend_from_slice(&frame);
+    }
+
+    let recovered = py.allow_threads(|| {
+        let blocks = sync::Synchronizer::new(Cursor::new(cadus), &asm, frame_len)
+            .filter_map(|block| block.ok().map(|(_, block)| block));
+        let mut builder = ccsds::FrameDecoderBuilder::default();
+        if let Some(interleave) = interleave {
+            builder = builder.reed_solomon(interleave as u8);
+        }
+        let frames = builder.build().start(blocks).filter_map(Result::ok);
+        ccsds::decode_framed_packets(scid, frames, izone_len, trailer_len)
+            .filter(|decoded| decoded.vcid == vcid && decoded.packet.header.apid != FILL_APID)
+            .map(|decoded| decoded.packet.data)
+            .collect::<Vec<_>>()
+    });
+
+    if let Some(index) = (0..expected.len()).find(|i| recovered.get(*i) != Some(&expected[*i])) {
+        let found = match recovered.get(index) {
+            Some(dat) => format!("{} different bytes", dat.len()),
+            None => "nothing".to_string(),
+        };
+        return Err(PyAssertionError::new_err(format!(
+            "packet {index} of {} bytes not recovered bit-exact; decoded {found}",
+            expected[index].len()
+        )));
+    }
+    if recovered.len() != expected.len() {
+        return Err(PyAssertionError::new_err(format!(
+            "decoded {} packets, expected {}",
+            recovered.len(),
+            expected.len()
+        )));
+    }
+    Ok(RoundtripReport {
+        packets: expected.len(),
+        frames: num_frames,
+        packet_zone_len: zone_len,
+        fill_bytes,
+    })
+}
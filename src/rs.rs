@@ -0,0 +1,432 @@
+//! CCSDS Reed-Solomon (255,223) encoding and decoding.
+//!
+//! The code is defined over GF(2^8) with field polynomial x^8 + x^7 + x^2 + x + 1 and
+//! generator roots alpha^(11 * j) for j in 112..=143, using the dual basis
+//! representation for symbols on the wire (CCSDS 131.0-B).
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::Receiver,
+        OnceLock,
+    },
+    thread,
+};
+
+use crate::prefetch;
+
+/// Codeword length.
+pub const N: usize = 255;
+/// Message length.
+pub const K: usize = 223;
+/// Number of parity symbols.
+pub const PARITY_LEN: usize = N - K;
+
+const FIELD_POLY: u16 = 0x187;
+const FIRST_ROOT: usize = 112;
+const PRIM: usize = 11;
+/// Rows of the conventional to dual basis transformation matrix.
+const TAL: [u8; 8] = [0x8d, 0xef, 0xec, 0x86, 0xfa, 0x99, 0xaf, 0x7b];
+
+struct Tables {
+    alpha_to: [u8; 255],
+    index_of: [u8; 256],
+    /// Generator polynomial coefficients, highest degree first, excluding the leading 1.
+    genpoly: [u8; PARITY_LEN],
+    /// Conventional to dual basis.
+    to_dual: [u8; 256],
+    /// Dual to conventional basis.
+    from_dual: [u8; 256],
+}
+
+impl Tables {
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        let idx =
+            (usize::from(self.index_of[a as usize]) + usize::from(self.index_of[b as usize])) % 255;
+        self.alpha_to[idx]
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        assert_ne!(b, 0);
+        if a == 0 {
+            return 0;
+        }
+        let idx = (usize::from(self.index_of[a as usize]) + 255
+            - usize::from(self.index_of[b as usize]))
+            % 255;
+        self.alpha_to[idx]
+    }
+
+    /// alpha^(PRIM * exp), i.e., a power of the primitive element the code roots are
+    /// consecutive powers of.
+    fn beta_pow(&self, exp: usize) -> u8 {
+        self.alpha_to[(PRIM * exp) % 255]
+    }
+
+    /// Evaluate a polynomial, lowest degree first, at `x`.
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter().rev().fold(0, |acc, c| self.mul(acc, x) ^ c)
+    }
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let mut alpha_to = [0u8; 255];
+        let mut index_of = [0u8; 256];
+        let mut x: u16 = 1;
+        for (i, alpha) in alpha_to.iter_mut().enumerate() {
+            *alpha = x as u8;
+            index_of[x as usize] = i as u8;
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= FIELD_POLY;
+            }
+        }
+
+        let mut tables = Tables {
+            alpha_to,
+            index_of,
+            genpoly: [0u8; PARITY_LEN],
+            to_dual: [0u8; 256],
+            from_dual: [0u8; 256],
+        };
+
+        // multiply out (x - root) for each root, lowest degree first
+        let mut gen = vec![1u8];
+        for j in 0..PARITY_LEN {
+            let root = tables.alpha_to[((FIRST_ROOT + j) * PRIM) % 255];
+            let mut next = vec![0u8; gen.len() + 1];
+            for (i, coef) in gen.iter().enumerate() {
+                next[i + 1] ^= coef;
+                next[i] ^= tables.mul(*coef, root);
+            }
+            gen = next;
+        }
+        // gen[PARITY_LEN] is the leading 1
+        for (i, coef) in tables.genpoly.iter_mut().enumerate() {
+            *coef = gen[PARITY_LEN - 1 - i];
+        }
+
+        for i in 0..256usize {
+            let mut dual = 0u8;
+            for (k, row) in TAL.iter().rev().enumerate() {
+                if i & (1 << k) != 0 {
+                    dual ^= row;
+                }
+            }
+            tables.to_dual[i] = dual;
+            tables.from_dual[dual as usize] = i as u8;
+        }
+        tables
+    })
+}
+
+/// Compute the parity symbols for a message of up to [K] dual basis symbols. Shorter
+/// messages are treated as virtually zero-filled at the start.
+pub fn parity(msg: &[u8]) -> [u8; PARITY_LEN] {
+    assert!(msg.len() <= K);
+    let t = tables();
+    let mut parity = [0u8; PARITY_LEN];
+    for sym in msg {
+        let feedback = t.from_dual[*sym as usize] ^ parity[0];
+        parity.copy_within(1.., 0);
+        parity[PARITY_LEN - 1] = 0;
+        if feedback != 0 {
+            for (p, g) in parity.iter_mut().zip(t.genpoly.iter()) {
+                *p ^= t.mul(feedback, *g);
+            }
+        }
+    }
+    for p in parity.iter_mut() {
+        *p = t.to_dual[*p as usize];
+    }
+    parity
+}
+
+/// Append interleaved parity to `data`, which must be `K * interleave` bytes.
+pub fn encode_interleaved(data: &[u8], interleave: usize) -> Vec<u8> {
+    assert_eq!(data.len(), K * interleave);
+    let mut out = Vec::with_capacity(N * interleave);
+    out.extend_from_slice(data);
+    out.resize(N * interleave, 0);
+    for i in 0..interleave {
+        let msg: Vec<u8> = data.iter().skip(i).step_by(interleave).copied().collect();
+        for (j, p) in parity(&msg).iter().enumerate() {
+            out[K * interleave + i + j * interleave] = *p;
+        }
+    }
+    out
+}
+
+/// Result of decoding a codeword or interleaved block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Outcome {
+    /// Number of symbols found to be in error and corrected.
+    pub corrected: usize,
+    /// Number of codewords that had more errors than can be corrected.
+    pub uncorrectable: usize,
+    /// Number of symbols corrected in each codeword, in interleave order, or `None` for
+    /// uncorrectable codewords.
+    pub codewords: Vec<Option<usize>>,
+}
+
+/// Decode a codeword of up to [N] dual basis symbols in place, returning the number of
+/// symbols corrected, or `None` if the codeword is uncorrectable in which case it is
+/// not modified. Shorter codewords are treated as virtually zero-filled at the start.
+pub fn decode(codeword: &mut [u8]) -> Option<usize> {
+    assert!(codeword.len() > PARITY_LEN && codeword.len() <= N);
+    let t = tables();
+    let n = codeword.len();
+    // coefficients by degree, the first symbol being the highest degree
+    let conv: Vec<u8> = codeword
+        .iter()
+        .rev()
+        .map(|sym| t.from_dual[*sym as usize])
+        .collect();
+
+    let mut syndromes = [0u8; PARITY_LEN];
+    for (j, syn) in syndromes.iter_mut().enumerate() {
+        *syn = t.eval(&conv, t.beta_pow(FIRST_ROOT + j));
+    }
+    if syndromes.iter().all(|s| *s == 0) {
+        return Some(0);
+    }
+
+    // Berlekamp-Massey for the error locator, lowest degree first
+    let mut lambda = vec![0u8; PARITY_LEN + 1];
+    lambda[0] = 1;
+    let mut prev = lambda.clone();
+    let mut len = 0;
+    let mut shift = 1;
+    let mut prev_disc = 1u8;
+    for k in 0..PARITY_LEN {
+        let disc = (0..=len).fold(0, |acc, i| acc ^ t.mul(lambda[i], syndromes[k - i]));
+        if disc == 0 {
+            shift += 1;
+            continue;
+        }
+        let scale = t.div(disc, prev_disc);
+        let last = lambda.clone();
+        for i in shift..=PARITY_LEN {
+            lambda[i] ^= t.mul(scale, prev[i - shift]);
+        }
+        if 2 * len <= k {
+            len = k + 1 - len;
+            prev = last;
+            prev_disc = disc;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+    }
+    let lambda = &lambda[..=len];
+    if len > PARITY_LEN / 2 {
+        return None;
+    }
+
+    // error evaluator, syndromes * lambda mod x^PARITY_LEN
+    let mut omega = vec![0u8; PARITY_LEN];
+    for (i, l) in lambda.iter().enumerate() {
+        for (j, s) in syndromes.iter().enumerate().take(PARITY_LEN - i) {
+            omega[i + j] ^= t.mul(*l, *s);
+        }
+    }
+    // formal derivative of lambda, i.e., the odd degree terms
+    let deriv: Vec<u8> = lambda
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, l)| if i % 2 == 1 { *l } else { 0 })
+        .collect();
+
+    // Chien search over the degrees present in the codeword, then Forney
+    let mut errors = Vec::with_capacity(len);
+    for degree in 0..N {
+        let x_inv = t.beta_pow(N - degree % N);
+        if t.eval(lambda, x_inv) != 0 {
+            continue;
+        }
+        if degree >= n {
+            return None;
+        }
+        let num = t.mul(
+            t.eval(&omega, x_inv),
+            t.beta_pow(degree * (N - (FIRST_ROOT - 1))),
+        );
+        let den = t.eval(&deriv, x_inv);
+        if den == 0 {
+            return None;
+        }
+        errors.push((degree, t.div(num, den)));
+    }
+    if errors.len() != len {
+        return None;
+    }
+    for (degree, magnitude) in &errors {
+        let idx = n - 1 - degree;
+        let sym = t.from_dual[codeword[idx] as usize] ^ magnitude;
+        codeword[idx] = t.to_dual[sym as usize];
+    }
+    Some(errors.len())
+}
+
+/// True if a block of `len` bytes can hold `interleave` codewords, possibly shortened.
+pub fn valid_block_len(len: usize, interleave: usize) -> bool {
+    let codeword_len = len / interleave;
+    interleave > 0
+        && len.is_multiple_of(interleave)
+        && codeword_len > PARITY_LEN
+        && codeword_len <= N
+}
+
+/// Decode each of the `interleave` codewords in `block` in place. Uncorrectable
+/// codewords are left unmodified.
+pub fn decode_interleaved(block: &mut [u8], interleave: usize) -> Outcome {
+    let mut outcome = Outcome::default();
+    for i in 0..interleave {
+        let mut codeword = deinterleave(block, i, interleave);
+        let result = decode(&mut codeword);
+        outcome.record(block, i, interleave, codeword, result);
+    }
+    outcome
+}
+
+/// Codeword `i` of the `interleave` codewords in `block`.
+fn deinterleave(block: &[u8], i: usize, interleave: usize) -> Vec<u8> {
+    block.iter().skip(i).step_by(interleave).copied().collect()
+}
+
+impl Outcome {
+    /// Record the `result` of decoding codeword `i` of `block`, which must be the next
+    /// in interleave order, writing the decoded `codeword` back if it was corrected.
+    fn record(
+        &mut self,
+        block: &mut [u8],
+        i: usize,
+        interleave: usize,
+        codeword: Vec<u8>,
+        result: Option<usize>,
+    ) {
+        self.codewords.push(result);
+        match result {
+            Some(0) => {}
+            Some(n) => {
+                self.corrected += n;
+                for (j, sym) in codeword.into_iter().enumerate() {
+                    block[i + j * interleave] = sym;
+                }
+            }
+            None => self.uncorrectable += 1,
+        }
+    }
+}
+
+/// Number of blocks taken per thread for each batch decoded by [ParallelDecoder].
+const BLOCKS_PER_THREAD: usize = 16;
+
+/// Decodes the interleaved codewords of a stream of blocks on a number of threads,
+/// yielding each block decoded in place with its outcome, in stream order.
+///
+/// Blocks are read ahead on a dedicated thread and taken in batches of those already
+/// read, so a live source that stalls mid-batch does not hold back the blocks received
+/// before it. The codewords of all the blocks of a batch are handed out one at a time to
+/// whichever thread is free, so the codewords of a block are decoded in parallel and
+/// slow, error-laden codewords do not hold up the rest of the batch. Blocks that cannot
+/// hold `interleave` codewords have no outcome.
+pub struct ParallelDecoder {
+    blocks: Receiver<Vec<u8>>,
+    interleave: usize,
+    threads: usize,
+    ready: VecDeque<(Vec<u8>, Option<Outcome>)>,
+}
+
+impl ParallelDecoder {
+    pub fn new(
+        blocks: Box<dyn Iterator<Item = Vec<u8>> + Send>,
+        interleave: usize,
+        threads: usize,
+    ) -> Self {
+        let threads = threads.max(1);
+        Self {
+            blocks: prefetch::spawn(blocks, threads * BLOCKS_PER_THREAD),
+            interleave,
+            threads,
+            ready: VecDeque::default(),
+        }
+    }
+}
+
+impl Iterator for ParallelDecoder {
+    type Item = (Vec<u8>, Option<Outcome>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ready.is_empty() {
+            // wait for one block, then take only those already read
+            let mut batch = vec![self.blocks.recv().ok()?];
+            while batch.len() < self.threads * BLOCKS_PER_THREAD {
+                match self.blocks.try_recv() {
+                    Ok(block) => batch.push(block),
+                    Err(_) => break,
+                }
+            }
+            let outcomes = decode_batch(&mut batch, self.interleave, self.threads);
+            self.ready.extend(batch.into_iter().zip(outcomes));
+        }
+        self.ready.pop_front()
+    }
+}
+
+/// Decode the codewords of `blocks` in place on up to `threads` threads.
+fn decode_batch(blocks: &mut [Vec<u8>], interleave: usize, threads: usize) -> Vec<Option<Outcome>> {
+    let valid: Vec<bool> = blocks
+        .iter()
+        .map(|block| valid_block_len(block.len(), interleave))
+        .collect();
+    let jobs = blocks.len() * interleave;
+    let next = AtomicUsize::new(0);
+    let shared: &[Vec<u8>] = blocks;
+    let mut decoded: Vec<(usize, Vec<u8>, Option<usize>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.min(jobs))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut decoded = Vec::default();
+                    loop {
+                        let job = next.fetch_add(1, Ordering::Relaxed);
+                        if job >= jobs {
+                            break;
+                        }
+                        let (idx, i) = (job / interleave, job % interleave);
+                        if !valid[idx] {
+                            continue;
+                        }
+                        let mut codeword = deinterleave(&shared[idx], i, interleave);
+                        let result = decode(&mut codeword);
+                        decoded.push((job, codeword, result));
+                    }
+                    decoded
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+    decoded.sort_unstable_by_key(|(job, ..)| *job);
+
+    let mut outcomes: Vec<Option<Outcome>> = valid
+        .iter()
+        .map(|valid| valid.then(Outcome::default))
+        .collect();
+    for (job, codeword, result) in decoded {
+        let (idx, i) = (job / interleave, job % interleave);
+        if let Some(outcome) = outcomes[idx].as_mut() {
+            outcome.record(&mut blocks[idx], i, interleave, codeword, result);
+        }
+    }
+    outcomes
+}
@@ -0,0 +1,71 @@
+//! Sampling of decoded items for quick-look analysis of large sources.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// Selects which decoded items are yielded.
+#[derive(Clone, Debug)]
+pub enum Sampler {
+    /// Every `n`th item, starting with the first.
+    Every { n: u64, seen: u64 },
+    /// Each item independently with probability `p`.
+    Probability { p: f64, state: u64 },
+}
+
+impl Sampler {
+    /// Create a sampler from the `sample`, `probability` and `seed` arguments of the
+    /// decode functions, or `None` if every item is yielded.
+    pub fn from_args(
+        sample: Option<u64>,
+        probability: Option<f64>,
+        seed: Option<u64>,
+    ) -> PyResult<Option<Self>> {
+        match (sample, probability) {
+            (Some(_), Some(_)) => Err(PyValueError::new_err(
+                "sample and probability cannot both be provided",
+            )),
+            (Some(0), None) => Err(PyValueError::new_err(
+                "invalid sample; expected >= 1, got 0",
+            )),
+            (Some(n), None) => Ok(Some(Self::Every { n, seen: 0 })),
+            (None, Some(p)) if !(p > 0.0 && p <= 1.0) => Err(PyValueError::new_err(format!(
+                "invalid probability; expected 0 < p <= 1, got {p}"
+            ))),
+            (None, Some(p)) => {
+                let state = seed.unwrap_or_else(|| {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_nanos() as u64)
+                        .unwrap_or_default()
+                });
+                Ok(Some(Self::Probability { p, state }))
+            }
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Whether to yield the next item.
+    pub fn keep(&mut self) -> bool {
+        match self {
+            Self::Every { n, seen } => {
+                let keep = (*seen).is_multiple_of(*n);
+                *seen += 1;
+                keep
+            }
+            Self::Probability { p, state } => {
+                // SplitMix64, using the top 53 bits for a uniform value in [0, 1).
+                *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut z = *state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^= z >> 31;
+                ((z >> 11) as f64 / (1u64 << 53) as f64) < *p
+            }
+        }
+    }
+}
+
+/// Whether to yield the next item, if sampling.
+pub fn keep(sampler: &mut Option<Sampler>) -> bool {
+    sampler.as_mut().is_none_or(Sampler::keep)
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    idle::{FHP_IDLE, FILL_APID, MPDU_HEADER_LEN},
+    packets::HEADER_LEN,
+    timecode::TimecodeRegistry,
+};
+
+/// Length of an AOS VCDU primary header in bytes.
+pub const VCDU_HEADER_LEN: usize = 6;
+
+/// How the data carried by a virtual channel is handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Service {
+    /// Multiplexed space packets (M_PDU).
+    Packets,
+    /// Bitstream data (B_PDU), yielded as the frame data zone.
+    Bitstream,
+    /// Fill data, dropped.
+    Idle,
+    /// Dropped.
+    Ignore,
+}
+
+impl Service {
+    /// Lookup a service by the name used in the Python API.
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_lowercase().as_str() {
+            "packets" => Ok(Self::Packets),
+            "bitstream" => Ok(Self::Bitstream),
+            "idle" => Ok(Self::Idle),
+            "ignore" => Ok(Self::Ignore),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported service; expected one of packets, bitstream, idle, ignore, got {name}"
+            ))),
+        }
+    }
+}
+
+/// Convert a mapping of VCID to service name into services.
+pub fn services_arg(services: Option<HashMap<u16, &str>>) -> PyResult<HashMap<u16, Service>> {
+    let mut map = HashMap::default();
+    for (vcid, name) in services.unwrap_or_default() {
+        if vcid > 63 {
+            return Err(PyValueError::new_err(format!(
+                "invalid vcid; expected 0..64, got {vcid}"
+            )));
+        }
+        map.insert(vcid, Service::from_name(name)?);
+    }
+    Ok(map)
+}
+
+/// Time in UTC milliseconds of the first packet whose primary header starts in the
+/// M_PDU packet zone of a frame, where `frame` is the frame data including the VCDU
+/// header. `None` if no packet header starts in the frame, the packet has no secondary
+/// header, or its timecode does not fit in the frame.
+pub fn first_packet_time(
+    frame: &[u8],
+    izone_len: usize,
+    timecode: &TimecodeRegistry,
+) -> Option<i64> {
+    let mpdu = frame.get(VCDU_HEADER_LEN + izone_len..)?;
+    let fhp = usize::from(u16::from_be_bytes([*mpdu.first()?, *mpdu.get(1)?]) & 0x7ff);
+    if fhp >= FHP_IDLE {
+        return None;
+    }
+    let packet = mpdu.get(MPDU_HEADER_LEN + fhp..)?;
+    let header = packet.get(..HEADER_LEN)?;
+    let apid = u16::from_be_bytes([header[0], header[1]]) & 0x7ff;
+    if header[0] & 0x08 == 0 || apid == FILL_APID {
+        return None;
+    }
+    timecode.decode(apid, &packet[HEADER_LEN..])
+}
+
+/// The data zone of a frame on a virtual channel configured for bitstream service.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct BitstreamData {
+    #[pyo3(get)]
+    pub scid: u16,
+    #[pyo3(get)]
+    pub vcid: u16,
+    /// The frame's VCDU counter.
+    #[pyo3(get)]
+    pub counter: u32,
+    /// The frame data following the VCDU header and insert zone, excluding any trailer.
+    /// This is the B_PDU, including its 2-byte bitstream data pointer header.
+    #[pyo3(get)]
+    pub data: Vec<u8>,
+}
+
+#[pymethods]
+impl BitstreamData {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "BitstreamData(scid={}, vcid={}, counter={}, data_len={})",
+            self.scid,
+            self.vcid,
+            self.counter,
+            self.data.len()
+        )
+    }
+}
+
+impl BitstreamData {
+    fn new(frame: &ccsds::Frame, izone_len: usize, trailer_len: usize) -> Self {
+        let start = (VCDU_HEADER_LEN + izone_len).min(frame.data.len());
+        let end = frame.data.len().saturating_sub(trailer_len).max(start);
+        BitstreamData {
+            scid: frame.header.scid,
+            vcid: frame.header.vcid,
+            counter: frame.header.counter,
+            data: frame.data[start..end].to_vec(),
+        }
+    }
+}
+
+/// An item produced by decoding framed data with a service map.
+pub enum FramedItem {
+    /// A packet and the SCID, VCID and counter of the most recent frame decoded for
+    /// packets when it was produced, i.e., usually the frame the packet ends in.
+    Packet(ccsds::DecodedPacket, Option<(u16, u16, u32)>),
+    Bitstream(BitstreamData),
+}
+
+/// Routes frames to packet decoding or bitstream output according to the service
+/// configured for their VCID, producing items in approximately stream order.
+struct Router<P> {
+    packets: P,
+    bitstream: Arc<Mutex<VecDeque<BitstreamData>>>,
+    /// SCID, VCID and counter of the most recent frame handed to packet decoding.
+    last_frame: Arc<Mutex<Option<(u16, u16, u32)>>>,
+    /// A decoded packet held back until bitstream data from earlier frames is yielded.
+    stash: Option<FramedItem>,
+}
+
+impl<P: Iterator<Item = ccsds::DecodedPacket>> Iterator for Router<P> {
+    type Item = FramedItem;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(data) = self.bitstream.lock().unwrap().pop_front() {
+            return Some(FramedItem::Bitstream(data));
+        }
+        if let Some(item) = self.stash.take() {
+            return Some(item);
+        }
+        let item = self
+            .packets
+            .next()
+            .map(|packet| FramedItem::Packet(packet, *self.last_frame.lock().unwrap()));
+        if let Some(data) = self.bitstream.lock().unwrap().pop_front() {
+            self.stash = item;
+            return Some(FramedItem::Bitstream(data));
+        }
+        item
+    }
+}
+
+/// Decode `frames` according to `services`. Virtual channels without a configured
+/// service are decoded as packets.
+pub fn decode<I>(
+    scid: ccsds::SCID,
+    frames: I,
+    izone_len: usize,
+    trailer_len: usize,
+    services: HashMap<u16, Service>,
+) -> impl Iterator<Item = FramedItem> + Send + 'static
+where
+    I: Iterator<Item = ccsds::DecodedFrame> + Send + 'static,
+{
+    let bitstream = Arc::new(Mutex::new(VecDeque::default()));
+    let queue = bitstream.clone();
+    let last_frame = Arc::new(Mutex::new(None));
+    let frame_ids = last_frame.clone();
+    let frames = frames.filter(move |frame| {
+        let hdr = &frame.frame.header;
+        match services.get(&hdr.vcid).unwrap_or(&Service::Packets) {
+            Service::Packets => {
+                *frame_ids.lock().unwrap() = Some((hdr.scid, hdr.vcid, hdr.counter));
+                true
+            }
+            Service::Bitstream => {
+                let data = BitstreamData::new(&frame.frame, izone_len, trailer_len);
+                queue.lock().unwrap().push_back(data);
+                false
+            }
+            Service::Idle | Service::Ignore => false,
+        }
+    });
+    Router {
+        packets: ccsds::decode_framed_packets(scid, frames, izone_len, trailer_len).fuse(),
+        bitstream,
+        last_frame,
+        stash: None,
+    }
+}
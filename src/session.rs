@@ -0,0 +1,249 @@
+//! Reusable decode configuration.
+
+// pyo3 0.19 generates the constructor text signature as an impl nested in a function
+#![allow(non_local_definitions)]
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+    types::{PyDict, PyString},
+};
+
+use crate::stage::{BatchStage, DEFAULT_BATCH_SIZE};
+
+/// Options accepted by `decode_packets`, other than the source.
+pub(crate) const PACKET_OPTIONS: &[&str] = &[
+    "prefetch",
+    "timeout",
+    "resync",
+    "max_packet_len",
+    "versions",
+    "apids",
+    "truncated",
+    "rice",
+    "filter",
+    "secondary_headers",
+    "secondary_header_policy",
+    "secondary_header_lens",
+    "apid_lengths",
+    "length_policy",
+    "fill",
+    "fill_policy",
+    "sample",
+    "probability",
+    "seed",
+    "quicklook",
+    "anomalies",
+    "headers_only",
+    "check_sequence_flags",
+    "clock",
+];
+/// Options accepted by `decode_frames`, other than the source.
+pub(crate) const FRAME_OPTIONS: &[&str] = &[
+    "frame_len",
+    "interleave",
+    "prefetch",
+    "timeout",
+    "asm",
+    "rs_check_only",
+    "uncorrectable_dest",
+    "corrected_dest",
+    "izone_timecodes",
+    "izone_crcs",
+    "izone_crc_policy",
+    "sample",
+    "probability",
+    "seed",
+    "anomalies",
+    "frame_cache",
+    "headers_only",
+    "rs_threads",
+    "report",
+    "asm_errors",
+    "packet_timecodes",
+];
+/// Options accepted by `decode_framed_packets`, other than the source.
+pub(crate) const FRAMED_PACKET_OPTIONS: &[&str] = &[
+    "scid",
+    "cadu_len",
+    "izone_len",
+    "trailer_len",
+    "interleave",
+    "prefetch",
+    "timeout",
+    "asm",
+    "services",
+    "filter",
+    "secondary_headers",
+    "secondary_header_policy",
+    "secondary_header_lens",
+    "apid_lengths",
+    "length_policy",
+    "fill",
+    "fill_policy",
+    "sample",
+    "probability",
+    "seed",
+    "quicklook",
+    "anomalies",
+    "frame_cache",
+    "report",
+    "asm_errors",
+    "check_sequence_flags",
+    "mission",
+    "clock",
+];
+
+/// The subset of `options` whose keys are in `accepted`.
+pub(crate) fn accepted_options<'py>(
+    py: Python<'py>,
+    options: &PyDict,
+    accepted: &[&str],
+) -> PyResult<&'py PyDict> {
+    let kwargs = PyDict::new(py);
+    for (key, value) in options {
+        if accepted.contains(&key.extract::<&str>()?) {
+            kwargs.set_item(key, value)?;
+        }
+    }
+    Ok(kwargs)
+}
+
+/// Decode options configured once and applied to each source decoded with them.
+///
+/// Options are the keyword arguments of `decode_packets`, `decode_frames` and
+/// `decode_framed_packets`; each decode method passes the options its function
+/// accepts. If `asm` or `interleave` is `auto`, the values detected from the first
+/// source are used for all later sources.
+///
+/// Python processing stages added with `add_stage` are applied, in order, to the items
+/// decoded from each source, as by `map_batches`.
+///
+/// Parameters
+/// ----------
+/// **options
+///     Decode options, e.g., `scid`, `cadu_len` and `interleave`.
+#[pyclass(name = "Pipeline")]
+pub struct DecodePipeline {
+    options: Py<PyDict>,
+    /// Processing stages and their batch sizes.
+    stages: Vec<(PyObject, usize)>,
+}
+
+impl DecodePipeline {
+    /// The configured options, including any values detected from the first source.
+    pub(crate) fn options_dict<'py>(&self, py: Python<'py>) -> &'py PyDict {
+        self.options.clone_ref(py).into_ref(py)
+    }
+
+    /// True if Python processing stages have been added.
+    pub(crate) fn has_stages(&self) -> bool {
+        !self.stages.is_empty()
+    }
+
+    /// Call the module function `name` on `source` with the options it accepts.
+    fn decode(
+        &self,
+        py: Python,
+        name: &str,
+        accepted: &[&str],
+        source: &PyAny,
+    ) -> PyResult<PyObject> {
+        let options = self.options.as_ref(py);
+        let kwargs = accepted_options(py, options, accepted)?;
+        let iter = py
+            .import("ccsds")?
+            .getattr(name)?
+            .call((source,), Some(kwargs))?;
+        // pin detected values so every source is decoded the same way
+        for key in ["asm", "interleave"] {
+            let auto = match kwargs.get_item(key) {
+                Some(value) => {
+                    value.is_instance_of::<PyString>() && value.extract::<&str>()? == "auto"
+                }
+                None => false,
+            };
+            if auto && iter.hasattr(key)? {
+                options.set_item(key, iter.getattr(key)?)?;
+            }
+        }
+        let mut iter: PyObject = iter.into();
+        for (func, batch_size) in &self.stages {
+            let stage = BatchStage::new(iter.as_ref(py), func.clone_ref(py), *batch_size)?;
+            iter = Py::new(py, stage)?.into_py(py);
+        }
+        Ok(iter)
+    }
+}
+
+#[pymethods]
+impl DecodePipeline {
+    #[new]
+    #[pyo3(signature=(**options))]
+    fn py_new(py: Python, options: Option<&PyDict>) -> PyResult<Self> {
+        let options = match options {
+            Some(options) => options.copy()?,
+            None => PyDict::new(py),
+        };
+        for key in options.keys() {
+            let key: &str = key.extract()?;
+            let known = [PACKET_OPTIONS, FRAME_OPTIONS, FRAMED_PACKET_OPTIONS]
+                .iter()
+                .any(|accepted| accepted.contains(&key));
+            if !known {
+                return Err(PyTypeError::new_err(format!(
+                    "unexpected decode option {key}"
+                )));
+            }
+        }
+        Ok(Self {
+            options: options.into(),
+            stages: Vec::default(),
+        })
+    }
+
+    /// Add a Python processing stage applied to batches of decoded items, as by
+    /// `map_batches`. Decode methods return a BatchStage iterator once a stage is added.
+    #[pyo3(signature=(func, batch_size=DEFAULT_BATCH_SIZE))]
+    fn add_stage(&mut self, func: PyObject, batch_size: usize) -> PyResult<()> {
+        if batch_size == 0 {
+            return Err(PyValueError::new_err(
+                "invalid batch_size; expected > 0, got 0",
+            ));
+        }
+        self.stages.push((func, batch_size));
+        Ok(())
+    }
+
+    /// A copy of the configured options, including any values detected from the first
+    /// source.
+    #[getter]
+    fn options<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        self.options.clone_ref(py).into_ref(py).copy()
+    }
+
+    /// Decode space packets from `source` as by `decode_packets`.
+    fn decode_packets(&self, py: Python, source: &PyAny) -> PyResult<PyObject> {
+        self.decode(py, "decode_packets", PACKET_OPTIONS, source)
+    }
+
+    /// Decode frames from `source` as by `decode_frames`.
+    fn decode_frames(&self, py: Python, source: &PyAny) -> PyResult<PyObject> {
+        self.decode(py, "decode_frames", FRAME_OPTIONS, source)
+    }
+
+    /// Decode framed space packets from `source` as by `decode_framed_packets`.
+    fn decode_framed_packets(&self, py: Python, source: &PyAny) -> PyResult<PyObject> {
+        self.decode(py, "decode_framed_packets", FRAMED_PACKET_OPTIONS, source)
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        self.__str__(py)
+    }
+    fn __str__(&self, py: Python) -> PyResult<String> {
+        Ok(format!(
+            "Pipeline({}, stages={})",
+            self.options.as_ref(py).repr()?,
+            self.stages.len()
+        ))
+    }
+}
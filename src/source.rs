@@ -0,0 +1,65 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, ErrorKind, Read},
+    net::TcpStream,
+    path::Path,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    eps::EpsReader,
+    pcap::{ports_arg, PcapReader},
+    udp::{Annotation, UdpReader},
+};
+
+/// Open a byte stream source.
+///
+/// Sources of the form `tcp://host:port` connect to a TCP server and read the stream it
+/// sends. Sources of the form `pcap://path[?port=port[,port...]]` read the UDP payloads
+/// of a pcap or pcapng capture, optionally only those to or from the given ports.
+/// Sources of the form `udp://host:port` bind to the address and read the datagrams
+/// received. Both UDP sources accept the annotation parameters of [Annotation], e.g.,
+/// `udp://0.0.0.0:5000?header_len=16`, to strip a ground station's header from each
+/// datagram. Sources of the form `eps://path` read the packets of an EPS Level 0
+/// product.
+/// Anything else is treated as a local path, which may also be a named pipe.
+pub fn open_source(source: &Path) -> io::Result<Box<dyn Read + Send>> {
+    let Some(source_str) = source.to_str() else {
+        return Ok(Box::new(File::open(source)?));
+    };
+    if let Some(addr) = source_str.strip_prefix("tcp://") {
+        return Ok(Box::new(TcpStream::connect(addr)?));
+    }
+    if let Some(capture) = source_str.strip_prefix("pcap://") {
+        let (path, query) = capture.rsplit_once('?').unwrap_or((capture, ""));
+        let (annotation, query) = Annotation::from_query(query)?;
+        let ports = ports_arg(&query)?;
+        let file = BufReader::new(File::open(path)?);
+        return Ok(Box::new(
+            PcapReader::new(file, ports).annotation(annotation),
+        ));
+    }
+    if let Some(socket) = source_str.strip_prefix("udp://") {
+        let (addr, query) = socket.split_once('?').unwrap_or((socket, ""));
+        let (annotation, rest) = Annotation::from_query(query)?;
+        if !rest.is_empty() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid udp source parameter; expected annotation parameters, got {rest}"),
+            ));
+        }
+        return Ok(Box::new(UdpReader::bind(addr, annotation)?));
+    }
+    if let Some(path) = source_str.strip_prefix("eps://") {
+        return Ok(Box::new(EpsReader::new(BufReader::new(File::open(path)?))));
+    }
+    Ok(Box::new(File::open(source)?))
+}
+
+/// Convert a path to a string for APIs that require one.
+pub fn path_str(path: &Path) -> PyResult<&str> {
+    path.to_str().ok_or_else(|| {
+        PyValueError::new_err(format!("path is not valid UTF-8: {}", path.display()))
+    })
+}
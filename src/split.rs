@@ -0,0 +1,104 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fs,
+    io::{BufReader, Write},
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    compress::{self, Compression},
+    pn,
+    services::VCDU_HEADER_LEN,
+    source::open_source,
+    sync::asm_arg,
+};
+
+/// Split a stream of CADUs into a file per VCID.
+///
+/// CADUs are written unmodified, including the ASM, to `vcidNN.dat` in `out_dir`. The
+/// frames are not Reed-Solomon decoded, so the VCID of a CADU with bit errors in its
+/// header may be wrong.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of CADUs.
+///
+/// out_dir : str or os.PathLike
+///     Directory to write output files to. It is created if it does not exist and
+///     existing output files are overwritten.
+///
+/// cadu_len : int
+///     The length of the CADU, including the ASM.
+///
+/// randomized : bool
+///     True if the frames are pseudo-randomized.
+///
+/// asm : bytes or str, optional
+///     Attached sync marker, as accepted by `decode_frames`. Defaults to the standard
+///     CCSDS ASM.
+///
+/// vcids : Iterable[int], optional
+///     Only write these VCIDs. All VCIDs are written if not provided.
+///
+/// compression : str, optional
+///     Compress the output files, either `gzip` or `zstd`, adding `.gz` or `.zst` to
+///     their names.
+///
+/// Returns
+/// -------
+/// dict of int to int
+///     Number of CADUs written for each VCID.
+#[pyfunction(signature=(source, out_dir, cadu_len, randomized=true, asm=None, vcids=None, compression=None))]
+pub fn split_cadus_by_vcid(
+    source: PathBuf,
+    out_dir: PathBuf,
+    cadu_len: usize,
+    randomized: bool,
+    asm: Option<&PyAny>,
+    vcids: Option<&PyAny>,
+    compression: Option<&str>,
+) -> PyResult<HashMap<u16, u64>> {
+    let vcids: Option<HashSet<u16>> = crate::set_arg(vcids)?;
+    let compression = Compression::from_arg(compression, None)?;
+    let asm = asm_arg(&source, asm)?;
+    if cadu_len < asm.len() + VCDU_HEADER_LEN {
+        return Err(PyValueError::new_err(format!(
+            "cadu_len too short; expected at least {}, got {cadu_len}",
+            asm.len() + VCDU_HEADER_LEN
+        )));
+    }
+    fs::create_dir_all(&out_dir)?;
+
+    let reader = BufReader::new(open_source(&source)?);
+    let blocks = ccsds::Synchronizer::new(reader, &asm, cadu_len - asm.len()).into_iter();
+    let mut writers: HashMap<u16, compress::Writer> = HashMap::default();
+    let mut counts: HashMap<u16, u64> = HashMap::default();
+    for block in blocks.filter_map(Result::ok) {
+        let mut header = [block[0], block[1]];
+        if randomized {
+            pn::apply(&mut header);
+        }
+        let vcid = u16::from(header[1] & 0x3f);
+        if vcids.as_ref().is_some_and(|v| !v.contains(&vcid)) {
+            continue;
+        }
+        let writer = match writers.entry(vcid) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let path = out_dir.join(format!("vcid{vcid:02}.dat"));
+                let path = compress::with_extension(path, compression);
+                entry.insert(compress::Writer::create(&path, compression)?)
+            }
+        };
+        writer.write_all(&asm)?;
+        writer.write_all(&block)?;
+        *counts.entry(vcid).or_default() += 1;
+    }
+    for writer in writers.values_mut() {
+        writer.flush()?;
+    }
+    Ok(counts)
+}
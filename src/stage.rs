@@ -0,0 +1,117 @@
+//! User-supplied Python processing stages applied to batches of items.
+use std::collections::VecDeque;
+
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyIterator, PyList},
+};
+
+/// Default number of items passed to a stage per call.
+pub const DEFAULT_BATCH_SIZE: usize = 256;
+
+/// Iterator applying a Python function to batches of the items of another iterator.
+#[pyclass]
+pub struct BatchStage {
+    iter: Py<PyIterator>,
+    func: PyObject,
+    batch_size: usize,
+    /// Output of the last call, not yet yielded.
+    output: VecDeque<PyObject>,
+    done: bool,
+}
+
+impl BatchStage {
+    pub fn new(items: &PyAny, func: PyObject, batch_size: usize) -> PyResult<Self> {
+        if batch_size == 0 {
+            return Err(PyValueError::new_err(
+                "invalid batch_size; expected > 0, got 0",
+            ));
+        }
+        Ok(Self {
+            iter: items.iter()?.into(),
+            func,
+            batch_size,
+            output: VecDeque::default(),
+            done: false,
+        })
+    }
+
+    /// Pass the next batch to the function, queuing its output. Returns false once the
+    /// input is exhausted.
+    fn process_batch(&mut self, py: Python) -> PyResult<bool> {
+        let mut batch = Vec::with_capacity(self.batch_size);
+        for item in self.iter.as_ref(py) {
+            batch.push(item?);
+            if batch.len() == self.batch_size {
+                break;
+            }
+        }
+        if batch.len() < self.batch_size {
+            self.done = true;
+        }
+        if batch.is_empty() {
+            return Ok(false);
+        }
+        let batch = PyList::new(py, batch);
+        let result = self.func.call1(py, (batch,))?;
+        // a stage returning None passes its batch through, e.g., one that only observes
+        let result = if result.is_none(py) {
+            batch
+        } else {
+            result.as_ref(py)
+        };
+        for item in result.iter()? {
+            self.output.push_back(item?.into_py(py));
+        }
+        Ok(true)
+    }
+}
+
+#[pymethods]
+impl BatchStage {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        loop {
+            if let Some(item) = self.output.pop_front() {
+                return Ok(Some(item));
+            }
+            if self.done || !self.process_batch(py)? {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// Apply a Python function to batches of items, e.g., frames or packets from one of the
+/// decode functions.
+///
+/// Items are collected into lists of up to `batch_size` and each list is passed to
+/// `func`, so per-item transforms written in Python only cross between Rust and Python
+/// once per batch. The final batch may be smaller. A batch is only passed once it is
+/// full, so for live sources items are delayed until `batch_size` items are received.
+///
+/// Parameters
+/// ----------
+/// items : Iterable
+///     Items to process, e.g., from `decode_frames` or `decode_packets`.
+///
+/// func : Callable[[list], Iterable | None]
+///     Function called with each batch returning the items to yield in its place, which
+///     may be more or fewer items than the batch, or `None` to yield the batch
+///     unchanged.
+///
+/// batch_size : int
+///     Maximum number of items per batch.
+///
+/// Returns
+/// -------
+/// BatchStage
+///     Iterator of the items returned by `func`.
+#[pyfunction(signature=(items, func, batch_size=DEFAULT_BATCH_SIZE))]
+pub fn map_batches(items: &PyAny, func: PyObject, batch_size: usize) -> PyResult<BatchStage> {
+    BatchStage::new(items, func, batch_size)
+}
@@ -0,0 +1,223 @@
+//! Extraction of a subset of the packets in a source into a new file.
+use std::{
+    collections::HashSet,
+    io::{BufReader, Write},
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    compress::{self, Compression},
+    filter::{filter_arg, PacketFilter, Values},
+    packets::PacketReader,
+    source::open_source,
+    sync,
+    timecode::{registry_or_cds, TimecodeRegistry},
+    Packet,
+};
+
+/// Criteria a packet must meet to be included in a subset.
+struct Selection {
+    apids: Option<HashSet<u16>>,
+    vcids: Option<HashSet<u16>>,
+    start: Option<i64>,
+    end: Option<i64>,
+    timecode: TimecodeRegistry,
+    filter: Option<PacketFilter>,
+}
+
+impl Selection {
+    fn selected(&self, packet: &Packet, ids: Option<(u16, u16)>) -> bool {
+        let hdr = &packet.header;
+        if self.apids.as_ref().is_some_and(|a| !a.contains(&hdr.apid)) {
+            return false;
+        }
+        if let (Some(vcids), Some((_, vcid))) = (&self.vcids, ids) {
+            if !vcids.contains(&vcid) {
+                return false;
+            }
+        }
+        if self.start.is_some() || self.end.is_some() {
+            let time = hdr
+                .has_secondary_header
+                .then(|| self.timecode.decode(hdr.apid, packet.payload()))
+                .flatten();
+            let Some(time) = time else {
+                return false;
+            };
+            if self.start.is_some_and(|start| time < start)
+                || self.end.is_some_and(|end| time >= end)
+            {
+                return false;
+            }
+        }
+        self.filter.as_ref().is_none_or(|filter| {
+            filter.eval(&Values {
+                header: hdr,
+                data_len: packet.payload().len(),
+                ids,
+            })
+        })
+    }
+}
+
+/// Write the packets in a source that match the given criteria to a new file, reading,
+/// selecting and writing in a single pass.
+///
+/// The source is a stream of space packets unless `cadu_len` is provided, in which case
+/// it is a stream of CADUs the packets are decoded from as by `decode_framed_packets`.
+/// In either case the packets are written to `dest` as a stream of space packets.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of space packets or CADUs.
+///
+/// dest : str or os.PathLike
+///     Path of the file to write the selected packets to.
+///
+/// apids : Iterable[int], optional
+///     Only include packets with these APIDs.
+///
+/// start : int, optional
+///     Only include packets with a time at or after this time, in UTC milliseconds.
+///
+/// end : int, optional
+///     Only include packets with a time before this time, in UTC milliseconds.
+///
+/// timecode : str or TimecodeRegistry
+///     Timecode at the start of the secondary header used to select packets by time,
+///     either "cds" or "eoscuc", or the timecode of each APID. Defaults to "cds".
+///     Packets without a time are excluded if `start` or `end` is provided.
+///
+/// vcids : Iterable[int], optional
+///     Only include packets decoded from frames on these VCIDs. Requires `cadu_len`.
+///
+/// filter : str, optional
+///     Only include packets matching this filter expression. See `PacketFilter`.
+///
+/// scid : int, optional
+///     Spacecraft identifier of the frames to decode packets from. Requires `cadu_len`.
+///
+/// cadu_len : int, optional
+///     The length of the CADU, if the source is a stream of CADUs.
+///
+/// izone_len : int
+///     Frame insert-zone number of bytes used by the spacecraft, if any.
+///
+/// trailer_len : int
+///     Frame trailer number of bytes used by the spacecraft, if any.
+///
+/// interleave : int or str, optional
+///     The Reed-Solomon interleave, or `auto`, as accepted by `decode_frames`.
+///
+/// compression : str, optional
+///     Compress the output, either `gzip`, `zstd` or `none`. Defaults to `gzip` or
+///     `zstd` if `dest` ends with `.gz` or `.zst`, otherwise `none`.
+///
+/// Returns
+/// -------
+/// int
+///     The number of packets written.
+#[pyfunction(signature=(source, dest, apids=None, start=None, end=None, timecode=None, vcids=None, filter=None, scid=None, cadu_len=None, izone_len=0, trailer_len=0, interleave=None, compression=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn subset(
+    py: Python,
+    source: PathBuf,
+    dest: PathBuf,
+    apids: Option<&PyAny>,
+    start: Option<i64>,
+    end: Option<i64>,
+    timecode: Option<&PyAny>,
+    vcids: Option<&PyAny>,
+    filter: Option<&str>,
+    scid: Option<u16>,
+    cadu_len: Option<usize>,
+    izone_len: usize,
+    trailer_len: usize,
+    interleave: Option<&PyAny>,
+    compression: Option<&str>,
+) -> PyResult<usize> {
+    let compression = Compression::from_arg(compression, Some(&dest))?;
+    let selection = Selection {
+        apids: crate::set_arg(apids)?,
+        vcids: crate::set_arg(vcids)?,
+        start,
+        end,
+        timecode: registry_or_cds(timecode)?,
+        filter: filter_arg(filter)?,
+    };
+    let Some(cadu_len) = cadu_len else {
+        if selection.vcids.is_some() || scid.is_some() {
+            return Err(PyValueError::new_err("vcids and scid require cadu_len"));
+        }
+        let reader = PacketReader::new(BufReader::new(open_source(&source)?));
+        let mut writer = compress::Writer::create(&dest, compression)?;
+        let written = py.allow_threads(|| -> PyResult<usize> {
+            let mut written = 0;
+            for packet in reader {
+                let packet = Packet::from(packet?);
+                if selection.selected(&packet, None) {
+                    writer.write_all(&packet.data)?;
+                    written += 1;
+                }
+            }
+            writer.flush()?;
+            Ok(written)
+        })?;
+        return Ok(written);
+    };
+
+    let Some(scid) = scid else {
+        return Err(PyValueError::new_err("cadu_len requires scid"));
+    };
+    crate::check_scid(scid)?;
+    if izone_len >= 16 || trailer_len >= 16 {
+        return Err(PyValueError::new_err(format!(
+            "invalid izone_len or trailer_len; expected 0..16, got {izone_len} and {trailer_len}"
+        )));
+    }
+    let asm = sync::asm_arg(&source, None)?;
+    if cadu_len <= asm.len() {
+        return Err(PyValueError::new_err(format!(
+            "cadu_len cannot be less than the ASM size ({})",
+            asm.len()
+        )));
+    }
+    let block_len = cadu_len - asm.len();
+    let interleave = sync::interleave_arg(&source, &asm, block_len, interleave)?;
+    let blocks = sync::Synchronizer::new(BufReader::new(open_source(&source)?), &asm, block_len)
+        .filter_map(|block| block.ok().map(|(_, block)| block));
+    let mut builder = ccsds::FrameDecoderBuilder::default();
+    if let Some(interleave) = interleave {
+        builder = builder.reed_solomon(interleave);
+    }
+    // drop frames on unselected VCIDs before decoding packets from them
+    let frame_vcids = selection.vcids.clone();
+    let frames = builder
+        .build()
+        .start(blocks)
+        .filter_map(Result::ok)
+        .filter(move |frame| {
+            let vcid = frame.frame.header.vcid;
+            frame_vcids
+                .as_ref()
+                .is_none_or(|vcids| vcids.contains(&vcid))
+        });
+    let packets = ccsds::decode_framed_packets(scid, frames, izone_len, trailer_len);
+    let mut writer = compress::Writer::create(&dest, compression)?;
+    py.allow_threads(|| -> PyResult<usize> {
+        let mut written = 0;
+        for decoded in packets {
+            let ids = Some((decoded.scid, decoded.vcid));
+            let packet = Packet::new(decoded.packet);
+            if selection.selected(&packet, ids) {
+                writer.write_all(&packet.data)?;
+                written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(written)
+    })
+}
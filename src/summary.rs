@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use pyo3::prelude::*;
+use serde::Serialize;
+
+use crate::{repr::repr, RSState};
+
+/// Totals accumulated while iterating over decoded frames or packets.
+#[pyclass]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Summary {
+    /// True once the iterator has been exhausted.
+    #[pyo3(get)]
+    pub complete: bool,
+    /// True if iteration was cancelled before the iterator was exhausted.
+    #[pyo3(get)]
+    pub cancelled: bool,
+    /// Number of items yielded.
+    #[pyo3(get)]
+    pub items: u64,
+    /// Number of bytes read from the source.
+    #[pyo3(get)]
+    pub bytes_read: u64,
+    /// Number of errors encountered and skipped.
+    #[pyo3(get)]
+    pub errors: u64,
+    /// Number of missing frames or packets based on VCID or APID sequence counters.
+    #[pyo3(get)]
+    pub missing: u64,
+    /// Number of corrupt bytes skipped while resynchronizing a packet stream.
+    #[pyo3(get)]
+    pub skipped_bytes: u64,
+    #[pyo3(get)]
+    pub rs_ok: u64,
+    #[pyo3(get)]
+    pub rs_corrected: u64,
+    #[pyo3(get)]
+    pub rs_uncorrectable: u64,
+    #[pyo3(get)]
+    pub rs_not_performed: u64,
+    /// Number of frames with correctable errors that were not corrected because
+    /// Reed-Solomon was in check-only mode.
+    #[pyo3(get)]
+    pub rs_detected: u64,
+}
+
+#[pymethods]
+impl Summary {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "Summary(complete={}, cancelled={}, items={}, bytes_read={}, errors={}, missing={}, skipped_bytes={}, rs_ok={}, rs_corrected={}, rs_uncorrectable={}, rs_not_performed={}, rs_detected={})",
+            repr(&self.complete), repr(&self.cancelled), self.items, self.bytes_read, self.errors, self.missing, self.skipped_bytes, self.rs_ok, self.rs_corrected, self.rs_uncorrectable, self.rs_not_performed, self.rs_detected,
+        )
+    }
+}
+
+impl Summary {
+    pub(crate) fn add_rsstate(&mut self, rsstate: &RSState) {
+        match rsstate {
+            RSState::Ok => self.rs_ok += 1,
+            RSState::Corrected => self.rs_corrected += 1,
+            RSState::Uncorrectable => self.rs_uncorrectable += 1,
+            RSState::NotPerformed => self.rs_not_performed += 1,
+            RSState::Detected => self.rs_detected += 1,
+        }
+    }
+}
+
+/// Tracks per-VCID frame counters to count missing frames.
+#[derive(Default)]
+pub struct FrameCounters {
+    last: HashMap<u16, u32>,
+}
+
+impl FrameCounters {
+    /// Add the next frame counter for `vcid`, returning the number of frames missing
+    /// before it.
+    pub fn add(&mut self, vcid: u16, counter: u32) -> u32 {
+        match self.last.insert(vcid, counter) {
+            Some(last) => ccsds::missing_frames(counter, last),
+            None => 0,
+        }
+    }
+}
+
+/// Reader wrapper that counts the bytes read through it.
+pub struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            count: Arc::default(),
+        }
+    }
+
+    /// Handle to the byte count that remains valid after the reader is moved.
+    pub fn counter(&self) -> Arc<AtomicU64> {
+        self.count.clone()
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
@@ -0,0 +1,1040 @@
+use std::{
+    collections::HashMap,
+    io::{self, Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyString};
+
+use crate::{
+    packets::read_full,
+    pn,
+    repr::{repr, Hex},
+    rs,
+    source::open_source,
+};
+
+/// Default number of bytes scanned when detecting stream characteristics.
+pub const DEFAULT_SCAN_LEN: usize = 4 * 1024 * 1024;
+
+/// Minimum number of equally spaced sync markers required to consider a marker locked.
+const MIN_LOCK_COUNT: usize = 3;
+
+/// Maximum number of frames inspected when detecting randomization.
+const MAX_SAMPLE_FRAMES: usize = 64;
+
+/// Reed-Solomon (255,223) codeblock length.
+const RS_BLOCK_LEN: usize = 255;
+
+/// Reed-Solomon interleaves tried when detecting the interleave, most common first.
+const INTERLEAVE_CANDIDATES: [usize; 8] = [4, 5, 1, 2, 3, 6, 7, 8];
+
+/// Number of bytes read from the source at a time when synchronizing.
+const READ_CHUNK_LEN: usize = 64 * 1024;
+
+/// Default number of frames decoded when detecting the Reed-Solomon interleave.
+pub const DEFAULT_INTERLEAVE_FRAMES: usize = 16;
+
+/// A known attached sync marker.
+pub struct Candidate {
+    pub name: &'static str,
+    pub pattern: &'static [u8],
+}
+
+/// Built-in attached sync markers tried when detecting the ASM of a stream.
+pub const CANDIDATES: &[Candidate] = &[
+    Candidate {
+        name: "ccsds",
+        pattern: &[0x1a, 0xcf, 0xfc, 0x1d],
+    },
+    Candidate {
+        name: "ccsds-inverted",
+        pattern: &[0xe5, 0x30, 0x03, 0xe2],
+    },
+    Candidate {
+        name: "turbo-1/2",
+        pattern: &[0x03, 0x47, 0x76, 0xc7, 0x27, 0x28, 0x95, 0xb0],
+    },
+    Candidate {
+        name: "turbo-1/3",
+        pattern: &[
+            0x25, 0xd5, 0xc0, 0xce, 0x89, 0x90, 0xf6, 0xc9, 0x46, 0x1b, 0xf7, 0x9c,
+        ],
+    },
+    Candidate {
+        name: "turbo-1/4",
+        pattern: &[
+            0x03, 0x47, 0x76, 0xc7, 0x27, 0x28, 0x95, 0xb0, 0xfc, 0xb8, 0x89, 0x38, 0xd8, 0xd7,
+            0x6a, 0x4f,
+        ],
+    },
+];
+
+/// Iterator over the bit offsets in a buffer where a pattern occurs.
+///
+/// Rather than shifting through the buffer a bit at a time, the byte following each
+/// possible start byte is looked up in a table of the bytes the pattern produces there
+/// at each of the 8 bit shifts. Most bytes match none, so the scan is a tight loop over
+/// the buffer, and only the shifts that match are compared against the whole pattern.
+pub struct PatternHits<'a> {
+    dat: &'a [u8],
+    pattern: &'a [u8],
+    /// Bitmask of the bit shifts at which the pattern produces each byte value in the
+    /// byte following the one it starts in. Every shift for single byte patterns.
+    anchors: [u8; 256],
+    /// Byte the pending candidate hits start in.
+    byte: usize,
+    /// Bit shifts yet to be compared at `byte`.
+    shifts: u8,
+    /// Next byte to look for candidate hits starting in.
+    next: usize,
+}
+
+impl<'a> PatternHits<'a> {
+    pub fn new(dat: &'a [u8], pattern: &'a [u8]) -> Self {
+        assert!(!pattern.is_empty() && pattern.len() <= 16);
+        let mut anchors = [0u8; 256];
+        if pattern.len() == 1 {
+            anchors = [0xff; 256];
+        } else {
+            anchors[usize::from(pattern[1])] |= 1;
+            for shift in 1..8 {
+                let anchor = pattern[0] << (8 - shift) | pattern[1] >> shift;
+                anchors[usize::from(anchor)] |= 1 << shift;
+            }
+        }
+        Self {
+            dat,
+            pattern,
+            anchors,
+            byte: 0,
+            shifts: 0,
+            next: 0,
+        }
+    }
+
+    /// True if the pattern occurs starting `shift` bits into `byte`.
+    fn matches(&self, byte: usize, shift: usize) -> bool {
+        let len = self.pattern.len();
+        if shift == 0 {
+            return self.dat.get(byte..byte + len) == Some(self.pattern);
+        }
+        let Some(dat) = self.dat.get(byte..byte + len + 1) else {
+            return false;
+        };
+        dat.windows(2)
+            .zip(self.pattern)
+            .all(|(pair, b)| pair[0] << shift | pair[1] >> (8 - shift) == *b)
+    }
+}
+
+impl Iterator for PatternHits<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            while self.shifts != 0 {
+                let shift = self.shifts.trailing_zeros() as usize;
+                self.shifts &= self.shifts - 1;
+                if self.matches(self.byte, shift) {
+                    return Some((self.byte * 8 + shift) as u64);
+                }
+            }
+            let len = self.dat.len();
+            if self.next >= len {
+                return None;
+            }
+            let anchors = &self.anchors;
+            let found = self.dat[self.next + 1..]
+                .iter()
+                .position(|b| anchors[usize::from(*b)] != 0);
+            match found {
+                Some(n) => {
+                    self.byte = self.next + n;
+                    self.shifts = anchors[usize::from(self.dat[self.byte + 1])];
+                }
+                // only a byte aligned single byte pattern fits in the last byte
+                None if self.pattern.len() == 1 => {
+                    self.byte = len - 1;
+                    self.shifts = 1;
+                }
+                None => {
+                    self.next = len;
+                    return None;
+                }
+            }
+            self.next = self.byte + 1;
+        }
+    }
+}
+
+/// Find the bit offsets in `dat` where `pattern` occurs. The pattern may start at any
+/// bit, not just on byte boundaries. Patterns are limited to 16 bytes.
+pub fn find_pattern_bits(dat: &[u8], pattern: &[u8]) -> Vec<u64> {
+    PatternHits::new(dat, pattern).collect()
+}
+
+/// Most common distance between consecutive hits along with how many times it occurs.
+pub fn dominant_spacing(hits: &[u64]) -> Option<(u64, usize)> {
+    let mut counts: HashMap<u64, usize> = HashMap::default();
+    for pair in hits.windows(2) {
+        *counts.entry(pair[1] - pair[0]).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(spacing, count)| (*count, std::cmp::Reverse(*spacing)))
+}
+
+/// Read up to `len` bytes from the start of `source`.
+pub fn read_head(source: &Path, len: usize) -> PyResult<Vec<u8>> {
+    let mut reader = open_source(source)?;
+    let mut buf = vec![0u8; len];
+    let n = read_full(&mut reader, &mut buf)?;
+    buf.truncate(n);
+    Ok(buf)
+}
+
+/// Find the first bit offset in `dat` where `pattern` occurs with at most `max_errors`
+/// bits in error, along with the number of bits in error. Patterns are limited to 16
+/// bytes.
+pub fn find_pattern_tolerant(dat: &[u8], pattern: &[u8], max_errors: u32) -> Option<(u64, u32)> {
+    if max_errors == 0 {
+        return PatternHits::new(dat, pattern).next().map(|bit| (bit, 0));
+    }
+    assert!(!pattern.is_empty() && pattern.len() <= 16);
+    let nbits = pattern.len() as u64 * 8;
+    let mask = if nbits == 128 {
+        u128::MAX
+    } else {
+        (1u128 << nbits) - 1
+    };
+    let target = pattern
+        .iter()
+        .fold(0u128, |acc, b| acc << 8 | u128::from(*b));
+    let mut window: u128 = 0;
+    for pos in 0..dat.len() as u64 * 8 {
+        let bit = (dat[(pos / 8) as usize] >> (7 - pos % 8)) & 1;
+        window = window << 1 | u128::from(bit);
+        if pos + 1 >= nbits {
+            let errors = ((window & mask) ^ target).count_ones();
+            if errors <= max_errors {
+                return Some((pos + 1 - nbits, errors));
+            }
+        }
+    }
+    None
+}
+
+/// Largest number of bits in error accepted in an attached sync marker.
+const MAX_ASM_ERRORS: u32 = 3;
+
+/// Check the `asm_errors` argument of the decode functions.
+pub fn check_asm_errors(asm_errors: u32) -> PyResult<()> {
+    if asm_errors > MAX_ASM_ERRORS {
+        return Err(PyValueError::new_err(format!(
+            "invalid asm_errors; expected 0..={MAX_ASM_ERRORS}, got {asm_errors}"
+        )));
+    }
+    Ok(())
+}
+
+/// Number of bits of `pattern` in error at bit offset `bit` of `dat`, or `None` if `dat`
+/// ends first.
+fn bit_errors(dat: &[u8], bit: u64, pattern: &[u8]) -> Option<u32> {
+    let start = usize::try_from(bit / 8).ok()?;
+    let shift = (bit % 8) as u32;
+    let dat = dat.get(start..start + pattern.len() + usize::from(shift > 0))?;
+    let errors = pattern
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            let byte = if shift == 0 {
+                dat[i]
+            } else {
+                dat[i] << shift | dat[i + 1] >> (8 - shift)
+            };
+            (byte ^ b).count_ones()
+        })
+        .sum();
+    Some(errors)
+}
+
+/// A block synchronized by [Synchronizer].
+#[derive(Clone, Debug)]
+pub struct SyncBlock {
+    /// Byte offset of the marker in the source.
+    pub offset: u64,
+    pub block: Vec<u8>,
+    /// Number of bits in error in the marker.
+    pub asm_errors: u32,
+}
+
+/// Synchronizes on an attached sync marker, yielding the block following each marker
+/// along with the byte offset of the marker in the source. The marker may start at any
+/// bit; for streams that are not byte aligned the offset is that of the byte holding
+/// the first bit of the marker.
+pub struct Synchronizer<R> {
+    reader: R,
+    asm: Vec<u8>,
+    block_len: usize,
+    /// Number of bytes returned from the start of each block, if not the whole block.
+    prefix: Option<usize>,
+    /// Number of bits of the marker that may be in error.
+    max_errors: u32,
+    /// Bit of `buf[start]` the next marker is expected at if lock is held, i.e., the
+    /// bit offset of the previous marker within its byte.
+    locked_bit: Option<u64>,
+    buf: Vec<u8>,
+    start: usize,
+    /// Stream offset of `buf[0]`.
+    offset: u64,
+    eof: bool,
+}
+
+impl<R: Read> Synchronizer<R> {
+    pub fn new(reader: R, asm: &[u8], block_len: usize) -> Self {
+        Self {
+            reader,
+            asm: asm.to_vec(),
+            block_len,
+            prefix: None,
+            max_errors: 0,
+            locked_bit: None,
+            buf: Vec::default(),
+            start: 0,
+            offset: 0,
+            eof: false,
+        }
+    }
+
+    /// Only return the first `len` bytes of each block, e.g., to scan frame headers
+    /// without copying the rest of the frame.
+    pub fn prefix(mut self, len: usize) -> Self {
+        self.prefix = Some(len.min(self.block_len));
+        self
+    }
+
+    /// Accept markers with up to `max` bits in error, so lock is acquired and held on
+    /// noisy streams. A marker is first looked for where it is expected following the
+    /// previous block, then by searching for the first marker within the tolerance.
+    pub fn asm_errors(mut self, max: u32) -> Self {
+        self.max_errors = max;
+        self
+    }
+
+    /// Yield each block along with the number of bits in error in its marker.
+    pub fn blocks(mut self) -> impl Iterator<Item = io::Result<SyncBlock>> {
+        std::iter::from_fn(move || self.next_block())
+    }
+
+    /// Read until at least `len` bytes are available after `start`, or end of stream.
+    /// Reads return as soon as any data arrives, so a live source is not waited on for
+    /// more than is needed.
+    fn fill(&mut self, len: usize) -> io::Result<()> {
+        if self.start > self.buf.len() / 2 {
+            self.buf.drain(..self.start);
+            self.offset += self.start as u64;
+            self.start = 0;
+        }
+        while !self.eof && self.buf.len() - self.start < len {
+            let filled = self.buf.len();
+            self.buf.resize(filled + READ_CHUNK_LEN.max(len), 0);
+            let n = loop {
+                match self.reader.read(&mut self.buf[filled..]) {
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    n => break n,
+                }
+            };
+            let n = match n {
+                Ok(n) => n,
+                Err(err) => {
+                    self.buf.truncate(filled);
+                    self.eof = true;
+                    return Err(err);
+                }
+            };
+            self.buf.truncate(filled + n);
+            if n == 0 {
+                self.eof = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bit offset in `avail` of the next marker, and the number of bits in error.
+    fn find_marker(&self, avail: &[u8]) -> Option<(u64, u32)> {
+        let expected = self
+            .locked_bit
+            .filter(|_| self.max_errors > 0)
+            .and_then(|bit| Some((bit, bit_errors(avail, bit, &self.asm)?)))
+            .filter(|(_, errors)| *errors <= self.max_errors);
+        expected.or_else(|| find_pattern_tolerant(avail, &self.asm, self.max_errors))
+    }
+
+    fn next_block(&mut self) -> Option<io::Result<SyncBlock>> {
+        let asm_len = self.asm.len();
+        let mut need = asm_len + self.block_len;
+        loop {
+            if let Err(err) = self.fill(need) {
+                return Some(Err(err));
+            }
+            let avail = &self.buf[self.start..];
+            if avail.len() < asm_len + self.block_len {
+                return None;
+            }
+            let Some((bit, asm_errors)) = self.find_marker(avail) else {
+                // keep enough to find a marker spanning the end of the buffer
+                self.start = self.buf.len() - asm_len - 1;
+                self.locked_bit = None;
+                if self.eof {
+                    return None;
+                }
+                continue;
+            };
+            let byte = (bit / 8) as usize;
+            let data_bit = bit + asm_len as u64 * 8;
+            let block_end =
+                (data_bit / 8) as usize + self.block_len + usize::from(!data_bit.is_multiple_of(8));
+            let len = self.prefix.unwrap_or(self.block_len);
+            match (block_end <= avail.len())
+                .then(|| bytes_at_bit(avail, data_bit, len))
+                .flatten()
+            {
+                Some(block) => {
+                    let offset = self.offset + (self.start + byte) as u64;
+                    self.start += byte + asm_len + self.block_len;
+                    self.locked_bit = Some(bit % 8);
+                    return Some(Ok(SyncBlock {
+                        offset,
+                        block,
+                        asm_errors,
+                    }));
+                }
+                None if self.eof => return None,
+                None => {
+                    self.start += byte;
+                    // one extra byte for blocks that are not byte aligned
+                    need = asm_len + self.block_len + usize::from(!data_bit.is_multiple_of(8));
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for Synchronizer<R> {
+    type Item = io::Result<(u64, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block = self.next_block()?;
+        Some(block.map(|block| (block.offset, block.block)))
+    }
+}
+
+/// Synchronizes CADUs in arbitrarily sized chunks of a stream, for use where the
+/// stream is pushed to us rather than read. Blocks are returned as for [Synchronizer].
+pub struct BlockAssembler {
+    asm: Vec<u8>,
+    block_len: usize,
+    buf: Vec<u8>,
+    start: usize,
+    /// Stream offset of `buf[0]`.
+    offset: u64,
+}
+
+impl BlockAssembler {
+    pub fn new(asm: &[u8], block_len: usize) -> Self {
+        Self {
+            asm: asm.to_vec(),
+            block_len,
+            buf: Vec::default(),
+            start: 0,
+            offset: 0,
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        if self.start > 0 {
+            self.buf.drain(..self.start);
+            self.offset += self.start as u64;
+            self.start = 0;
+        }
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Number of bytes pushed that have not yet been returned or skipped.
+    pub fn buffered(&self) -> usize {
+        self.buf.len() - self.start
+    }
+
+    /// Return the next complete block and the offset of its marker, if enough bytes
+    /// have been pushed.
+    pub fn next_block(&mut self) -> Option<(u64, Vec<u8>)> {
+        let asm_len = self.asm.len();
+        let avail = &self.buf[self.start..];
+        if avail.len() < asm_len + self.block_len {
+            return None;
+        }
+        let Some(bit) = PatternHits::new(avail, &self.asm).next() else {
+            // keep enough to find a marker spanning the end of the buffer
+            self.start = self.buf.len() - asm_len - 1;
+            return None;
+        };
+        let byte = (bit / 8) as usize;
+        let data_bit = bit + asm_len as u64 * 8;
+        let block_end =
+            (data_bit / 8) as usize + self.block_len + usize::from(!data_bit.is_multiple_of(8));
+        if block_end > avail.len() {
+            self.start += byte;
+            return None;
+        }
+        let block = bytes_at_bit(avail, data_bit, self.block_len)?;
+        let offset = self.offset + (self.start + byte) as u64;
+        self.start += byte + asm_len + self.block_len;
+        Some((offset, block))
+    }
+}
+
+/// Result of scanning a stream for an attached sync marker.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct AsmDetection {
+    /// Name of the built-in marker, or `custom` for user provided markers.
+    #[pyo3(get)]
+    pub name: String,
+    /// The marker bytes.
+    #[pyo3(get)]
+    pub asm: Vec<u8>,
+    /// Number of times the marker was found.
+    #[pyo3(get)]
+    pub count: usize,
+    /// Most common distance between markers in bytes, i.e., the CADU length.
+    #[pyo3(get)]
+    pub spacing: Option<u64>,
+    /// Bit offset of marker within a byte; non-zero if the stream is not byte aligned.
+    #[pyo3(get)]
+    pub bit_offset: u8,
+    /// True if the marker recurs at a consistent spacing.
+    #[pyo3(get)]
+    pub locked: bool,
+}
+
+#[pymethods]
+impl AsmDetection {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "AsmDetection(name={}, asm={}, count={}, spacing={}, bit_offset={}, locked={})",
+            repr(&self.name),
+            Hex(&self.asm),
+            self.count,
+            repr(&self.spacing),
+            self.bit_offset,
+            repr(&self.locked),
+        )
+    }
+}
+
+impl AsmDetection {
+    pub fn scan(dat: &[u8], name: &str, pattern: &[u8]) -> Self {
+        let hits = find_pattern_bits(dat, pattern);
+        let spacing = dominant_spacing(&hits);
+        let locked = match spacing {
+            Some((spacing, count)) => {
+                spacing % 8 == 0 && count + 1 >= MIN_LOCK_COUNT && count * 2 >= hits.len() - 1
+            }
+            None => false,
+        };
+        Self {
+            name: name.to_string(),
+            asm: pattern.to_vec(),
+            count: hits.len(),
+            spacing: spacing.map(|(spacing, _)| spacing / 8),
+            bit_offset: hits.first().map(|h| (h % 8) as u8).unwrap_or_default(),
+            locked,
+        }
+    }
+}
+
+/// Scan `dat` for each of `candidates`, returning detections ordered best first.
+pub fn detect(dat: &[u8], candidates: &[(String, Vec<u8>)]) -> Vec<AsmDetection> {
+    let mut detections: Vec<AsmDetection> = candidates
+        .iter()
+        .map(|(name, pattern)| AsmDetection::scan(dat, name, pattern))
+        .collect();
+    // prefer locked, then most hits, then longest marker since longer markers are less
+    // likely to occur by chance
+    detections.sort_by_key(|d| {
+        (
+            std::cmp::Reverse(d.locked),
+            std::cmp::Reverse(d.count),
+            std::cmp::Reverse(d.asm.len()),
+        )
+    });
+    detections
+}
+
+fn builtin(name: &str) -> PyResult<Vec<u8>> {
+    CANDIDATES
+        .iter()
+        .find(|c| c.name == name)
+        .map(|c| c.pattern.to_vec())
+        .ok_or_else(|| {
+            let names: Vec<&str> = CANDIDATES.iter().map(|c| c.name).collect();
+            PyValueError::new_err(format!(
+                "unknown asm {name}; expected bytes, auto, or one of {}",
+                names.join(", ")
+            ))
+        })
+}
+
+fn builtin_candidates() -> Vec<(String, Vec<u8>)> {
+    CANDIDATES
+        .iter()
+        .map(|c| (c.name.to_string(), c.pattern.to_vec()))
+        .collect()
+}
+
+fn validate(asm: Vec<u8>) -> PyResult<Vec<u8>> {
+    if asm.is_empty() || asm.len() > 16 {
+        return Err(PyValueError::new_err(format!(
+            "invalid asm length; expected 1..=16 bytes, got {}",
+            asm.len()
+        )));
+    }
+    Ok(asm)
+}
+
+/// Resolve an `asm` argument that is the marker bytes or the name of a built-in
+/// marker, for decoding that cannot scan the stream ahead to detect the marker.
+pub fn fixed_asm_arg(asm: Option<&PyAny>) -> PyResult<Vec<u8>> {
+    let Some(asm) = asm else {
+        return Ok(ccsds::ASM.to_vec());
+    };
+    if !asm.is_instance_of::<PyString>() {
+        return validate(asm.extract()?);
+    }
+    match asm.extract()? {
+        "auto" => Err(PyValueError::new_err(
+            "asm cannot be detected here; expected bytes or the name of a built-in asm",
+        )),
+        name => builtin(name),
+    }
+}
+
+/// Resolve the `asm` argument of the decode functions into the marker to synchronize on.
+///
+/// The argument may be the marker bytes, the name of a built-in marker, or `auto` to
+/// detect the marker from the start of `source`. The standard CCSDS ASM is used if not
+/// provided.
+pub fn asm_arg(source: &Path, asm: Option<&PyAny>) -> PyResult<Vec<u8>> {
+    let Some(asm) = asm else {
+        return Ok(ccsds::ASM.to_vec());
+    };
+    if !asm.is_instance_of::<PyString>() {
+        return validate(asm.extract()?);
+    }
+    let name: &str = asm.extract()?;
+    if name != "auto" {
+        return builtin(name);
+    }
+    let dat = read_head(source, DEFAULT_SCAN_LEN)?;
+    match detect(&dat, &builtin_candidates()).into_iter().next() {
+        Some(detection) if detection.locked => Ok(detection.asm),
+        _ => Err(PyValueError::new_err(
+            "failed to detect asm; no known sync marker recurs at a consistent spacing",
+        )),
+    }
+}
+
+/// Scan the start of a stream for attached sync markers.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of CADUs.
+///
+/// candidates : list of bytes, optional
+///     Markers to look for. If not provided a built-in set of CCSDS markers is used,
+///     including the standard and inverted ASMs and the turbo code ASMs.
+///
+/// scan_len : int
+///     Number of bytes from the start of the stream to scan.
+///
+/// Returns
+/// -------
+/// list of AsmDetection
+///     A detection for each candidate, ordered best first. The first detection is the
+///     marker that locked if its `locked` attribute is true.
+#[pyfunction(signature=(source, candidates=None, scan_len=DEFAULT_SCAN_LEN))]
+pub fn detect_asm(
+    source: PathBuf,
+    candidates: Option<Vec<Vec<u8>>>,
+    scan_len: usize,
+) -> PyResult<Vec<AsmDetection>> {
+    let candidates = match candidates {
+        Some(candidates) => candidates
+            .into_iter()
+            .map(|asm| Ok(("custom".to_string(), validate(asm)?)))
+            .collect::<PyResult<Vec<_>>>()?,
+        None => builtin_candidates(),
+    };
+    let dat = read_head(&source, scan_len)?;
+    Ok(detect(&dat, &candidates))
+}
+
+/// Characteristics of a CADU stream determined by [detect_framing].
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct FramingDetection {
+    /// Name of the built-in marker, or `custom` for user provided markers.
+    #[pyo3(get)]
+    pub asm_name: String,
+    /// The marker bytes.
+    #[pyo3(get)]
+    pub asm: Vec<u8>,
+    /// Length of each CADU, including the ASM.
+    #[pyo3(get)]
+    pub cadu_len: usize,
+    /// Length of each frame, i.e., the CADU length minus the ASM length.
+    #[pyo3(get)]
+    pub frame_len: usize,
+    /// Number of markers found in the scanned data.
+    #[pyo3(get)]
+    pub count: usize,
+    /// Bit offset of the marker within a byte; non-zero if the stream is not byte aligned.
+    #[pyo3(get)]
+    pub bit_offset: u8,
+    /// True if frame headers only make sense after removing the CCSDS pseudo-noise.
+    #[pyo3(get)]
+    pub randomized: bool,
+    /// Spacecraft id from the frame headers, if they are consistent.
+    #[pyo3(get)]
+    pub scid: Option<u16>,
+    /// Reed-Solomon interleave consistent with the frame length, if any. Note a stream
+    /// with this frame length may also simply not use Reed-Solomon.
+    #[pyo3(get)]
+    pub interleave: Option<u8>,
+}
+
+#[pymethods]
+impl FramingDetection {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "FramingDetection(asm_name={}, asm={}, cadu_len={}, frame_len={}, count={}, bit_offset={}, randomized={}, scid={}, interleave={})",
+            repr(&self.asm_name), Hex(&self.asm), self.cadu_len, self.frame_len, self.count, self.bit_offset, repr(&self.randomized), repr(&self.scid), repr(&self.interleave),
+        )
+    }
+}
+
+/// Copy `len` bytes of `dat` starting at bit offset `bit`.
+fn bytes_at_bit(dat: &[u8], bit: u64, len: usize) -> Option<Vec<u8>> {
+    let start = usize::try_from(bit / 8).ok()?;
+    let shift = (bit % 8) as u32;
+    let end = start + len + usize::from(shift > 0);
+    if end > dat.len() {
+        return None;
+    }
+    if shift == 0 {
+        return Some(dat[start..start + len].to_vec());
+    }
+    Some(
+        dat[start..end]
+            .windows(2)
+            .map(|w| w[0] << shift | w[1] >> (8 - shift))
+            .collect(),
+    )
+}
+
+/// Most common spacecraft id among version 2 (AOS) frame headers, and the number of
+/// frames having it.
+fn consistent_scid(frames: &[Vec<u8>]) -> Option<(u16, usize)> {
+    let mut counts: HashMap<u16, usize> = HashMap::default();
+    for frame in frames {
+        if frame.len() < 2 || frame[0] >> 6 != 1 {
+            continue;
+        }
+        let scid = (u16::from(frame[0]) << 8 | u16::from(frame[1])) >> 6 & 0xff;
+        *counts.entry(scid).or_default() += 1;
+    }
+    counts.into_iter().max_by_key(|(_, count)| *count)
+}
+
+fn framing(dat: &[u8], detection: AsmDetection) -> PyResult<FramingDetection> {
+    let spacing = match detection.spacing {
+        Some(spacing) if detection.locked && spacing as usize > detection.asm.len() => {
+            spacing as usize
+        }
+        _ => {
+            return Err(PyValueError::new_err(
+                "failed to detect framing; no sync marker recurs at a consistent spacing",
+            ))
+        }
+    };
+    let frame_len = spacing - detection.asm.len();
+    let asm_bits = detection.asm.len() as u64 * 8;
+
+    let hits = find_pattern_bits(dat, &detection.asm);
+    let frames: Vec<Vec<u8>> = hits
+        .windows(2)
+        .filter(|w| w[1] - w[0] == spacing as u64 * 8)
+        .filter_map(|w| bytes_at_bit(dat, w[0] + asm_bits, frame_len))
+        .take(MAX_SAMPLE_FRAMES)
+        .collect();
+    let derandomized: Vec<Vec<u8>> = frames
+        .iter()
+        .map(|frame| {
+            let mut frame = frame.clone();
+            pn::apply(&mut frame);
+            frame
+        })
+        .collect();
+
+    let raw = consistent_scid(&frames);
+    let pn = consistent_scid(&derandomized);
+    let randomized =
+        pn.map(|(_, n)| n).unwrap_or_default() > raw.map(|(_, n)| n).unwrap_or_default();
+    let scid = if randomized { pn } else { raw }
+        .filter(|(_, n)| n * 2 > frames.len())
+        .map(|(scid, _)| scid);
+
+    let interleave = match frame_len / RS_BLOCK_LEN {
+        n @ 1..=8 if frame_len.is_multiple_of(RS_BLOCK_LEN) => Some(n as u8),
+        _ => None,
+    };
+
+    Ok(FramingDetection {
+        asm_name: detection.name,
+        asm: detection.asm,
+        cadu_len: spacing,
+        frame_len,
+        count: detection.count,
+        bit_offset: detection.bit_offset,
+        randomized,
+        scid,
+        interleave,
+    })
+}
+
+/// Determine the framing parameters of a CADU stream from the start of the stream.
+///
+/// The CADU length is determined from the spacing of the attached sync markers, the
+/// presence of pseudo-noise randomization by checking which of the raw or derandomized
+/// frame headers are consistent, and a plausible Reed-Solomon interleave from the frame
+/// length.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of CADUs.
+///
+/// asm : bytes or str, optional
+///     Attached sync marker, as accepted by `decode_frames`. If not provided the built-in
+///     markers are tried.
+///
+/// scan_len : int
+///     Number of bytes from the start of the stream to scan.
+///
+/// Returns
+/// -------
+/// FramingDetection
+///     The detected framing. A ValueError is raised if no marker recurs at a consistent
+///     spacing.
+#[pyfunction(signature=(source, asm=None, scan_len=DEFAULT_SCAN_LEN))]
+pub fn detect_framing(
+    source: PathBuf,
+    asm: Option<&PyAny>,
+    scan_len: usize,
+) -> PyResult<FramingDetection> {
+    let candidates = match asm {
+        Some(asm) => vec![("custom".to_string(), asm_arg(&source, Some(asm))?)],
+        None => builtin_candidates(),
+    };
+    let dat = read_head(&source, scan_len)?;
+    let best = detect(&dat, &candidates).into_iter().next();
+    match best {
+        Some(detection) => framing(&dat, detection),
+        None => Err(PyValueError::new_err("failed to detect framing")),
+    }
+}
+
+/// Result of trying Reed-Solomon interleaves on the first frames of a stream.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct InterleaveDetection {
+    /// The interleave with the most successfully decoded frames.
+    #[pyo3(get)]
+    pub interleave: u8,
+    /// Number of frames decoded.
+    #[pyo3(get)]
+    pub frames: usize,
+    /// Number of frames without errors.
+    #[pyo3(get)]
+    pub ok: usize,
+    /// Number of frames with errors that were corrected.
+    #[pyo3(get)]
+    pub corrected: usize,
+    /// Number of frames with at least one uncorrectable codeword.
+    #[pyo3(get)]
+    pub uncorrectable: usize,
+}
+
+#[pymethods]
+impl InterleaveDetection {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "InterleaveDetection(interleave={}, frames={}, ok={}, corrected={}, uncorrectable={})",
+            self.interleave, self.frames, self.ok, self.corrected, self.uncorrectable,
+        )
+    }
+}
+
+/// Try each plausible interleave for frames of this length on `frames`, which must be
+/// derandomized, returning the interleave with the most successfully decoded frames if
+/// more than half of them decode.
+fn detect_interleave_frames(frames: &[Vec<u8>]) -> Option<InterleaveDetection> {
+    let frame_len = frames.first()?.len();
+    let mut best: Option<InterleaveDetection> = None;
+    for interleave in INTERLEAVE_CANDIDATES {
+        if !rs::valid_block_len(frame_len, interleave) {
+            continue;
+        }
+        let mut detection = InterleaveDetection {
+            interleave: interleave as u8,
+            frames: frames.len(),
+            ok: 0,
+            corrected: 0,
+            uncorrectable: 0,
+        };
+        for frame in frames {
+            let outcome = rs::decode_interleaved(&mut frame.clone(), interleave);
+            if outcome.uncorrectable > 0 {
+                detection.uncorrectable += 1;
+            } else if outcome.corrected > 0 {
+                detection.corrected += 1;
+            } else {
+                detection.ok += 1;
+            }
+        }
+        let decoded = detection.ok + detection.corrected;
+        if decoded * 2 > frames.len() && best.as_ref().is_none_or(|b| decoded > b.ok + b.corrected)
+        {
+            best = Some(detection);
+        }
+    }
+    best
+}
+
+/// Detect the interleave of the Reed-Solomon encoded CADUs at the start of `source`.
+fn detect_interleave_head(
+    source: &Path,
+    asm: &[u8],
+    frame_len: usize,
+    randomized: bool,
+    frames: usize,
+) -> PyResult<Option<InterleaveDetection>> {
+    let dat = read_head(source, (frames + 2) * (asm.len() + frame_len))?;
+    let blocks: Vec<Vec<u8>> = ccsds::Synchronizer::new(Cursor::new(dat), &asm.to_vec(), frame_len)
+        .into_iter()
+        .filter_map(Result::ok)
+        .take(frames)
+        .map(|mut block| {
+            if randomized {
+                pn::apply(&mut block);
+            }
+            block
+        })
+        .collect();
+    Ok(detect_interleave_frames(&blocks))
+}
+
+/// Resolve the `interleave` argument of the decode functions, either an interleave or
+/// `auto` to detect the interleave from the start of the stream.
+pub fn interleave_arg(
+    source: &Path,
+    asm: &[u8],
+    frame_len: usize,
+    interleave: Option<&PyAny>,
+) -> PyResult<Option<u8>> {
+    let Some(interleave) = interleave else {
+        return Ok(None);
+    };
+    if interleave.is_instance_of::<PyString>() {
+        let name: &str = interleave.extract()?;
+        if name != "auto" {
+            return Err(PyValueError::new_err(format!(
+                "invalid interleave; expected an int or auto, got {name}"
+            )));
+        }
+        return match detect_interleave_head(
+            source,
+            asm,
+            frame_len,
+            true,
+            DEFAULT_INTERLEAVE_FRAMES,
+        )? {
+            Some(detection) => Ok(Some(detection.interleave)),
+            None => Err(PyValueError::new_err(
+                "failed to detect interleave; no interleave decodes most of the first frames",
+            )),
+        };
+    }
+    let interleave: i32 = interleave.extract()?;
+    if !(2..=10).contains(&interleave) {
+        return Err(PyValueError::new_err(format!(
+            "improbable interleave value; expected 2..10: got {interleave}"
+        )));
+    }
+    Ok(Some(interleave as u8))
+}
+
+/// Detect the Reed-Solomon interleave of a CADU stream by decoding the first frames
+/// with each plausible interleave, trying 4 and 5 then 1 through 8.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of CADUs.
+///
+/// cadu_len : int
+///     The length of the CADU, including the ASM.
+///
+/// asm : bytes or str, optional
+///     Attached sync marker, as accepted by `decode_frames`. Defaults to the standard
+///     CCSDS ASM.
+///
+/// randomized : bool
+///     True if the frames are pseudo-randomized.
+///
+/// frames : int
+///     Number of frames to decode.
+///
+/// Returns
+/// -------
+/// InterleaveDetection or None
+///     The interleave that decodes the most frames, or None if no interleave decodes
+///     more than half of them, e.g., because the stream does not use Reed-Solomon.
+#[pyfunction(signature=(source, cadu_len, asm=None, randomized=true, frames=DEFAULT_INTERLEAVE_FRAMES))]
+pub fn detect_interleave(
+    source: PathBuf,
+    cadu_len: usize,
+    asm: Option<&PyAny>,
+    randomized: bool,
+    frames: usize,
+) -> PyResult<Option<InterleaveDetection>> {
+    let asm = asm_arg(&source, asm)?;
+    if cadu_len <= asm.len() {
+        return Err(PyValueError::new_err(format!(
+            "cadu_len cannot be less than the ASM size ({})",
+            asm.len()
+        )));
+    }
+    detect_interleave_head(&source, &asm, cadu_len - asm.len(), randomized, frames)
+}
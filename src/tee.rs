@@ -0,0 +1,55 @@
+//! Copies of the CADUs of frames with selected Reed-Solomon states, written during
+//! decode.
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use pyo3::prelude::*;
+
+/// Writes the CADUs of uncorrectable, and optionally corrected, frames to separate
+/// files.
+pub struct RsTee {
+    asm: Vec<u8>,
+    uncorrectable: Option<BufWriter<File>>,
+    corrected: Option<BufWriter<File>>,
+}
+
+impl RsTee {
+    /// Create a tee writing to the given paths, or `None` if neither is provided.
+    pub fn new(
+        asm: &[u8],
+        uncorrectable: Option<PathBuf>,
+        corrected: Option<PathBuf>,
+    ) -> PyResult<Option<Self>> {
+        if uncorrectable.is_none() && corrected.is_none() {
+            return Ok(None);
+        }
+        let create = |path: Option<PathBuf>| -> io::Result<Option<BufWriter<File>>> {
+            path.map(|p| File::create(p).map(BufWriter::new))
+                .transpose()
+        };
+        Ok(Some(Self {
+            asm: asm.to_vec(),
+            uncorrectable: create(uncorrectable)?,
+            corrected: create(corrected)?,
+        }))
+    }
+
+    /// Write `block`, as received, with its ASM depending on `rsstate`, the state its
+    /// frame was decoded with. `None` means decoding failed, as it does for
+    /// uncorrectable frames, so the block is written as uncorrectable.
+    pub fn write(&mut self, block: &[u8], rsstate: Option<&ccsds::RSState>) -> io::Result<()> {
+        let writer = match rsstate {
+            Some(ccsds::RSState::Uncorrectable(_)) | None => self.uncorrectable.as_mut(),
+            Some(ccsds::RSState::Corrected(_)) => self.corrected.as_mut(),
+            _ => None,
+        };
+        if let Some(writer) = writer {
+            writer.write_all(&self.asm)?;
+            writer.write_all(block)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+//! Pacing of streamed packets to a bit rate or to the packet timestamps.
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyString};
+
+use crate::{timecode::Timecode, Packet};
+
+#[derive(Clone, Copy, Debug)]
+enum Pace {
+    /// Bits per second.
+    Bits(f64),
+    /// Replay in real time according to the packet times.
+    Realtime(Timecode),
+}
+
+/// Computes the delay needed before sending each packet to keep to a pace.
+#[derive(Debug)]
+pub struct Throttle {
+    pace: Pace,
+    /// When pacing started and, for real time pacing, the time of the first packet.
+    start: Option<(Instant, i64)>,
+    /// Bits sent since pacing started.
+    bits: f64,
+}
+
+impl Throttle {
+    /// Parse the `rate` argument of the streaming sinks: a bit rate in bits per second,
+    /// or `realtime` to pace by the CDS packet times, or `realtime:<timecode>` to pace by
+    /// packet times in another timecode, e.g., `realtime:eoscuc`.
+    pub fn from_arg(rate: Option<&PyAny>) -> PyResult<Option<Self>> {
+        let Some(rate) = rate else {
+            return Ok(None);
+        };
+        let pace = if rate.is_instance_of::<PyString>() {
+            let name: &str = rate.extract()?;
+            match name.split_once(':') {
+                Some(("realtime", timecode)) => Pace::Realtime(Timecode::from_name(timecode)?),
+                None if name == "realtime" => Pace::Realtime(Timecode::Cds),
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "invalid rate; expected bits per second or realtime, got {name}"
+                    )))
+                }
+            }
+        } else {
+            let bps: f64 = rate.extract()?;
+            if !(bps.is_finite() && bps > 0.0) {
+                return Err(PyValueError::new_err(format!(
+                    "invalid rate; expected bits per second > 0, got {bps}"
+                )));
+            }
+            Pace::Bits(bps)
+        };
+        Ok(Some(Self {
+            pace,
+            start: None,
+            bits: 0.0,
+        }))
+    }
+
+    /// Time to wait before sending `packet`, if any. Packets without a time are not
+    /// delayed when pacing in real time, nor are packets whose time precedes that of the
+    /// first packet.
+    pub fn delay(&mut self, packet: &Packet) -> Option<Duration> {
+        let (target, elapsed) = match self.pace {
+            Pace::Bits(bps) => {
+                let (start, _) = *self.start.get_or_insert((Instant::now(), 0));
+                let target = self.bits / bps;
+                self.bits += (packet.data.len() * 8) as f64;
+                (target, start.elapsed().as_secs_f64())
+            }
+            Pace::Realtime(timecode) => {
+                let time = packet
+                    .header
+                    .has_secondary_header
+                    .then(|| timecode.decode(packet.payload()))
+                    .flatten()?;
+                let (start, first) = *self.start.get_or_insert((Instant::now(), time));
+                let target = (time - first) as f64 / 1000.0;
+                (target, start.elapsed().as_secs_f64())
+            }
+        };
+        (target > elapsed).then(|| Duration::from_secs_f64(target - elapsed))
+    }
+}
+
+/// Sleep for `delay`, if any, without holding the GIL.
+pub fn sleep(py: Python, delay: Option<Duration>) -> PyResult<()> {
+    if let Some(delay) = delay {
+        py.allow_threads(|| thread::sleep(delay));
+        py.check_signals()?;
+    }
+    Ok(())
+}
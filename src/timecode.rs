@@ -0,0 +1,414 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use pyo3::{
+    exceptions::{PyFileNotFoundError, PyValueError},
+    prelude::*,
+    types::PyString,
+};
+use serde_json::Value;
+
+use crate::{epoch::Epoch, message::with_packet, repr::repr};
+
+/// Timecode formats that may be found at the start of a packet secondary header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timecode {
+    /// CCSDS Day-Segmented timecode as used by JPSS/SNPP.
+    Cds,
+    /// CCSDS Unsegmented timecode as used by NASA EOS (Aqua & Terra).
+    EosCuc,
+    /// CCSDS Unsegmented timecode of any length counting from a named epoch.
+    Cuc(CucFormat),
+}
+
+/// Layout and epoch of a CCSDS Unsegmented timecode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CucFormat {
+    /// Octets of whole seconds, 1 to 4.
+    pub coarse: usize,
+    /// Octets of binary fractions of a second, 0 to 3.
+    pub fine: usize,
+    pub epoch: Epoch,
+}
+
+impl CucFormat {
+    pub fn new(coarse: usize, fine: usize, epoch: &str) -> PyResult<Self> {
+        if !(1..=4).contains(&coarse) {
+            return Err(PyValueError::new_err(format!(
+                "invalid coarse octets; expected 1..=4, got {coarse}"
+            )));
+        }
+        if fine > 3 {
+            return Err(PyValueError::new_err(format!(
+                "invalid fine octets; expected 0..=3, got {fine}"
+            )));
+        }
+        Ok(Self {
+            coarse,
+            fine,
+            epoch: Epoch::from_name(epoch)?,
+        })
+    }
+
+    /// Parse the name of a CUC timecode, `cuc`, optionally followed by the coarse and
+    /// fine octets, e.g., `cuc4.3`, and optionally by the epoch, e.g., `cuc:gps`,
+    /// defaulting to 4 coarse and 2 fine octets from the TAI 1958 epoch.
+    fn from_name(name: &str) -> Option<PyResult<Self>> {
+        let rest = name.strip_prefix("cuc")?;
+        let (octets, epoch) = rest.split_once(':').unwrap_or((rest, "tai1958"));
+        let (coarse, fine) = match octets {
+            "" => (4, 2),
+            _ => match octets.split_once('.').map(|(c, f)| (c.parse(), f.parse())) {
+                Some((Ok(coarse), Ok(fine))) => (coarse, fine),
+                _ => return None,
+            },
+        };
+        Some(Self::new(coarse, fine, epoch))
+    }
+
+    /// Decode the timecode from `dat` into UTC nanoseconds.
+    pub fn decode_ns(&self, dat: &[u8]) -> Option<i64> {
+        let dat = dat.get(..self.coarse + self.fine)?;
+        let (coarse, fine) = dat.split_at(self.coarse);
+        let value = |octets: &[u8]| {
+            octets
+                .iter()
+                .fold(0i64, |value, octet| value << 8 | i64::from(*octet))
+        };
+        let count_ns =
+            value(coarse) * 1_000_000_000 + ((value(fine) * 1_000_000_000) >> (8 * self.fine));
+        Some(self.epoch.to_utc_ns(count_ns))
+    }
+}
+
+impl Timecode {
+    /// Lookup a timecode format by the name used in the Python API.
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name.to_lowercase().as_str() {
+            "cds" => Ok(Self::Cds),
+            "eoscuc" => Ok(Self::EosCuc),
+            lower => match CucFormat::from_name(lower) {
+                Some(format) => Ok(Self::Cuc(format?)),
+                None => Err(PyValueError::new_err(format!(
+                    "unsupported timecode; expected one of cds, eoscuc, cuc, got {name}"
+                ))),
+            },
+        }
+    }
+
+    /// The name used in the Python API.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Cds => "cds".to_string(),
+            Self::EosCuc => "eoscuc".to_string(),
+            Self::Cuc(format) => {
+                format!("cuc{}.{}:{}", format.coarse, format.fine, format.epoch.name)
+            }
+        }
+    }
+
+    /// Decode the timecode from `dat` into UTC milliseconds, where `dat` is the packet
+    /// data following the primary header.
+    pub fn decode(&self, dat: &[u8]) -> Option<i64> {
+        match self {
+            Self::Cds => ccsds::timecode::decode_cds(dat).ok(),
+            Self::EosCuc => ccsds::timecode::decode_eoscuc(dat).ok(),
+            Self::Cuc(format) => return Some(format.decode_ns(dat)?.div_euclid(1_000_000)),
+        }
+        .map(|tc| tc.timestamp_millis())
+    }
+
+    /// Decode the timecode from `dat` into UTC nanoseconds, keeping any sub-millisecond
+    /// precision, e.g., the microsecond field of an 8-byte CDS timecode.
+    pub fn decode_ns(&self, dat: &[u8]) -> Option<i64> {
+        match self {
+            Self::Cds => {
+                let millis = self.decode(dat)?;
+                // microsecond of the millisecond, following the day and millisecond of day
+                let micros = u16::from_be_bytes([*dat.get(6)?, *dat.get(7)?]);
+                Some(millis * 1_000_000 + i64::from(micros) * 1_000)
+            }
+            Self::EosCuc => ccsds::timecode::decode_eoscuc(dat)
+                .ok()?
+                .timestamp_nanos_opt(),
+            Self::Cuc(format) => format.decode_ns(dat),
+        }
+    }
+}
+
+/// A timecode format and the byte offset of the timecode in the packet data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimecodeSpec {
+    pub timecode: Timecode,
+    pub offset: usize,
+}
+
+impl TimecodeSpec {
+    /// Parse a spec from the Python API, either a format name or a (name, offset) tuple.
+    fn from_arg(arg: &PyAny) -> PyResult<Self> {
+        let (name, offset) = match arg.extract::<&str>() {
+            Ok(name) => (name, 0),
+            Err(_) => arg.extract::<(&str, usize)>().map_err(|_| {
+                PyValueError::new_err(format!(
+                    "invalid timecode; expected a name or (name, offset), got {arg}"
+                ))
+            })?,
+        };
+        Ok(Self {
+            timecode: Timecode::from_name(name)?,
+            offset,
+        })
+    }
+
+    /// Parse a spec from a spacecraft database entry, either a format name or an object
+    /// with `format` and optional `offset` members.
+    fn from_json(value: &Value) -> PyResult<Self> {
+        let (name, offset) = match value {
+            Value::String(name) => (name.as_str(), 0),
+            Value::Object(obj) => (
+                obj.get("format")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default(),
+                obj.get("offset").and_then(Value::as_u64).unwrap_or(0) as usize,
+            ),
+            _ => ("", 0),
+        };
+        Ok(Self {
+            timecode: Timecode::from_name(name)?,
+            offset,
+        })
+    }
+}
+
+/// Timecode format and offset of packets by APID, for missions where APIDs carry
+/// different timecode layouts.
+///
+/// Accepted wherever packet timecodes are decoded in place of a single format name.
+///
+/// Parameters
+/// ----------
+/// apids : dict of int to str or (str, int), optional
+///     Timecode format, one of "cds", "eoscuc" or "cuc", for each APID, optionally with
+///     the byte offset of the timecode in the packet data, i.e., following the primary
+///     header. The offset defaults to 0, the start of the secondary header.
+///
+///     A "cuc" timecode may be followed by its coarse and fine octets and its epoch,
+///     e.g., "cuc4.3:gps", defaulting to "cuc4.2:tai1958". See `epochs` for the epoch
+///     names.
+///
+/// default : str or (str, int), optional
+///     Timecode format, and optionally offset, of APIDs not in `apids`. If not provided
+///     packets with other APIDs have no time.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default)]
+pub struct TimecodeRegistry {
+    default: Option<TimecodeSpec>,
+    apids: Arc<HashMap<u16, TimecodeSpec>>,
+}
+
+impl TimecodeRegistry {
+    /// A registry using the same format for all APIDs.
+    pub fn single(timecode: Timecode) -> Self {
+        Self {
+            default: Some(TimecodeSpec {
+                timecode,
+                offset: 0,
+            }),
+            apids: Arc::default(),
+        }
+    }
+
+    /// The timecodes of a spacecraft entry of a spacecraftsdb database.
+    pub fn from_spacecraft(spacecraft: &Value) -> PyResult<Self> {
+        let default = spacecraft
+            .get("timecode")
+            .map(TimecodeSpec::from_json)
+            .transpose()?;
+        let mut apids = HashMap::default();
+        let vcids = spacecraft.get("vcids").and_then(Value::as_array);
+        for vcid in vcids.into_iter().flatten() {
+            let entries = vcid.get("apids").and_then(Value::as_array);
+            for entry in entries.into_iter().flatten() {
+                let (Some(apid), Some(timecode)) = (
+                    entry.get("apid").and_then(Value::as_u64),
+                    entry.get("timecode"),
+                ) else {
+                    continue;
+                };
+                apids.insert(apid as u16, TimecodeSpec::from_json(timecode)?);
+            }
+        }
+        Ok(Self {
+            default,
+            apids: Arc::new(apids),
+        })
+    }
+
+    pub fn get(&self, apid: u16) -> Option<TimecodeSpec> {
+        self.apids.get(&apid).copied().or(self.default)
+    }
+
+    /// Decode the timecode of a packet with `apid` into UTC milliseconds, where `dat` is
+    /// the packet data following the primary header.
+    pub fn decode(&self, apid: u16, dat: &[u8]) -> Option<i64> {
+        let spec = self.get(apid)?;
+        spec.timecode.decode(dat.get(spec.offset..)?)
+    }
+
+    /// As [Self::decode], but into UTC nanoseconds.
+    pub fn decode_ns(&self, apid: u16, dat: &[u8]) -> Option<i64> {
+        let spec = self.get(apid)?;
+        spec.timecode.decode_ns(dat.get(spec.offset..)?)
+    }
+}
+
+#[pymethods]
+impl TimecodeRegistry {
+    #[new]
+    #[pyo3(signature=(apids=None, default=None))]
+    fn new(apids: Option<HashMap<u16, &PyAny>>, default: Option<&PyAny>) -> PyResult<Self> {
+        let apids = apids
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(apid, spec)| Ok((apid, TimecodeSpec::from_arg(spec)?)))
+            .collect::<PyResult<_>>()?;
+        Ok(Self {
+            default: default.map(TimecodeSpec::from_arg).transpose()?,
+            apids: Arc::new(apids),
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "TimecodeRegistry(apids={}, default={})",
+            self.apids.len(),
+            repr(&self.default.map(|spec| (spec.timecode.name(), spec.offset))),
+        )
+    }
+
+    /// Load the timecodes of a spacecraft from a spacecraftsdb formatted database file.
+    ///
+    /// The timecode of each APID is read from the `timecode` member of its entry in the
+    /// `apids` of each of the spacecraft's `vcids`, and the default from the `timecode`
+    /// member of the spacecraft. Each is either a format name or an object with `format`
+    /// and optional `offset` members.
+    ///
+    /// Parameters
+    /// ----------
+    /// scid : int
+    ///     The spacecraft identifier.
+    ///
+    /// path : str or os.PathLike, optional
+    ///     Local path to the database file, otherwise found as for `framing_config`.
+    ///
+    /// Returns
+    /// -------
+    /// TimecodeRegistry
+    #[staticmethod]
+    #[pyo3(signature=(scid, path=None))]
+    fn from_spacecraftsdb(scid: u16, path: Option<PathBuf>) -> PyResult<Self> {
+        let (path, spacecrafts) = read_spacecraftsdb(path)?;
+        let Some(spacecraft) = spacecrafts
+            .iter()
+            .find(|sc| sc.get("scid").and_then(Value::as_u64) == Some(u64::from(scid)))
+        else {
+            return Err(PyValueError::new_err(format!(
+                "spacecraft {scid} not found in {}",
+                path.display()
+            )));
+        };
+        Self::from_spacecraft(spacecraft)
+    }
+
+    /// The timecode format and offset of packets with `apid`, or `None` if they have no
+    /// time.
+    fn timecode(&self, apid: u16) -> Option<(String, usize)> {
+        self.get(apid)
+            .map(|spec| (spec.timecode.name(), spec.offset))
+    }
+
+    /// Decode the time of a Packet or DecodedPacket in UTC milliseconds, or `None` if
+    /// its APID has no timecode or it could not be decoded.
+    #[pyo3(name = "decode")]
+    fn decode_packet(&self, packet: &PyAny) -> PyResult<Option<i64>> {
+        with_packet(packet, |p| {
+            self.decode(p.packet.header.apid, p.packet.payload())
+        })
+    }
+
+    /// Decode the time of a Packet or DecodedPacket in UTC nanoseconds, keeping any
+    /// sub-millisecond precision of the timecode, or `None` as for `decode`.
+    #[pyo3(name = "decode_ns")]
+    fn decode_packet_ns(&self, packet: &PyAny) -> PyResult<Option<i64>> {
+        with_packet(packet, |p| {
+            self.decode_ns(p.packet.header.apid, p.packet.payload())
+        })
+    }
+}
+
+/// Read the spacecraft entries of a spacecraftsdb database, the one at `path` or
+/// otherwise the one found as by `framing_config`, returning the path read.
+pub fn read_spacecraftsdb(path: Option<PathBuf>) -> PyResult<(PathBuf, Vec<Value>)> {
+    let Some(path) = path.or_else(default_db_path) else {
+        return Err(PyFileNotFoundError::new_err(
+            "no spacecraftsdb database found",
+        ));
+    };
+    let db = fs::read(&path)
+        .map_err(|err| PyFileNotFoundError::new_err(format!("{}: {err}", path.display())))?;
+    let db: Value = serde_json::from_slice(&db)
+        .map_err(|err| PyValueError::new_err(format!("invalid spacecraftsdb database: {err}")))?;
+    let spacecrafts = match db.get("spacecrafts").unwrap_or(&db) {
+        Value::Array(spacecrafts) => spacecrafts.clone(),
+        _ => Vec::default(),
+    };
+    Ok((path, spacecrafts))
+}
+
+/// The spacecraftsdb database found in the same locations as by `framing_config`.
+fn default_db_path() -> Option<PathBuf> {
+    let mut candidates = vec![PathBuf::from("spacecraftsdb.json")];
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".local/share")));
+    if let Some(data_home) = data_home {
+        candidates.push(data_home.join("spacecraftsdb/spacecraftsdb.json"));
+    }
+    if let Some(home) = home {
+        candidates.push(home.join(".spacecraftsdb.json"));
+    }
+    candidates.into_iter().find(|path| Path::is_file(path))
+}
+
+/// Convert a `timecode` argument, either a format name or a [TimecodeRegistry], into a
+/// registry.
+pub fn registry_arg(arg: &PyAny) -> PyResult<TimecodeRegistry> {
+    if arg.is_instance_of::<PyString>() {
+        return Ok(TimecodeRegistry::single(Timecode::from_name(
+            arg.extract()?,
+        )?));
+    }
+    arg.extract::<TimecodeRegistry>().map_err(|_| {
+        PyValueError::new_err(format!(
+            "invalid timecode; expected a name or TimecodeRegistry, got {arg}"
+        ))
+    })
+}
+
+/// Convert a `timecode` argument that defaults to CDS into a [TimecodeRegistry].
+pub fn registry_or_cds(arg: Option<&PyAny>) -> PyResult<TimecodeRegistry> {
+    Ok(optional_registry_arg(arg)?.unwrap_or_else(|| TimecodeRegistry::single(Timecode::Cds)))
+}
+
+/// Convert an optional `timecode` argument into a [TimecodeRegistry].
+pub fn optional_registry_arg(arg: Option<&PyAny>) -> PyResult<Option<TimecodeRegistry>> {
+    arg.map(registry_arg).transpose()
+}
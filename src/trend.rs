@@ -0,0 +1,185 @@
+//! Rolling Reed-Solomon correction statistics for link margin trending.
+use std::{
+    io::{self, BufReader, Write},
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    compress::{self, Compression},
+    pn,
+    repr::repr,
+    rs,
+    source::open_source,
+    sync,
+};
+
+/// Reed-Solomon statistics for a run of consecutive CADUs.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct RsTrendRecord {
+    /// Index of the first CADU of the record in the stream.
+    #[pyo3(get)]
+    pub start_frame: u64,
+    /// Number of CADUs in the record.
+    #[pyo3(get)]
+    pub frames: u64,
+    /// Byte offset of the first CADU of the record in the source.
+    #[pyo3(get)]
+    pub start_offset: u64,
+    /// Byte offset of the last CADU of the record in the source.
+    #[pyo3(get)]
+    pub end_offset: u64,
+    /// Symbols corrected in each interleave channel, i.e., each codeword position.
+    #[pyo3(get)]
+    pub corrected: Vec<u64>,
+    /// Uncorrectable codewords in each interleave channel.
+    #[pyo3(get)]
+    pub uncorrectable: Vec<u64>,
+}
+
+#[pymethods]
+impl RsTrendRecord {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "RsTrendRecord(start_frame={}, frames={}, start_offset={}, end_offset={}, corrected={}, uncorrectable={})",
+            self.start_frame, self.frames, self.start_offset, self.end_offset, repr(&self.corrected), repr(&self.uncorrectable),
+        )
+    }
+}
+
+impl RsTrendRecord {
+    fn new(start_frame: u64, start_offset: u64, interleave: usize) -> Self {
+        Self {
+            start_frame,
+            frames: 0,
+            start_offset,
+            end_offset: start_offset,
+            corrected: vec![0; interleave],
+            uncorrectable: vec![0; interleave],
+        }
+    }
+
+    fn add(&mut self, offset: u64, outcome: &rs::Outcome) {
+        self.frames += 1;
+        self.end_offset = offset;
+        for (i, codeword) in outcome.codewords.iter().enumerate() {
+            match codeword {
+                Some(n) => self.corrected[i] += *n as u64,
+                None => self.uncorrectable[i] += 1,
+            }
+        }
+    }
+}
+
+fn write_csv<W: Write>(mut w: W, records: &[RsTrendRecord], interleave: usize) -> io::Result<()> {
+    write!(w, "start_frame,frames,start_offset,end_offset")?;
+    for i in 0..interleave {
+        write!(w, ",corrected_{i}")?;
+    }
+    for i in 0..interleave {
+        write!(w, ",uncorrectable_{i}")?;
+    }
+    writeln!(w)?;
+    for rec in records {
+        write!(
+            w,
+            "{},{},{},{}",
+            rec.start_frame, rec.frames, rec.start_offset, rec.end_offset
+        )?;
+        for n in rec.corrected.iter().chain(&rec.uncorrectable) {
+            write!(w, ",{n}")?;
+        }
+        writeln!(w)?;
+    }
+    w.flush()
+}
+
+/// Compute Reed-Solomon correction statistics per interleave channel for consecutive
+/// runs of CADUs, e.g., to trend link margin over a long playback.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of pseudo-randomized CADUs with Reed-Solomon parity.
+///
+/// cadu_len : int
+///     The length of the CADU, i.e., the ASM length plus the frame and parity length.
+///
+/// interleave : int
+///     The Reed-Solomon interleave.
+///
+/// cadence : int
+///     Number of CADUs per record. The final record may have fewer.
+///
+/// asm : bytes or str, optional
+///     Attached sync marker, as accepted by `decode_frames`. Defaults to the standard
+///     CCSDS ASM.
+///
+/// dest : str or os.PathLike, optional
+///     If provided, the records are also written to this path as CSV with a
+///     `corrected_<i>` and an `uncorrectable_<i>` column per interleave channel.
+///
+/// compression : str, optional
+///     Compress the CSV, either `gzip`, `zstd` or `none`. Defaults to `gzip` or `zstd`
+///     if `dest` ends with `.gz` or `.zst`, otherwise `none`.
+///
+/// Returns
+/// -------
+/// list of RsTrendRecord
+#[pyfunction(signature=(source, cadu_len, interleave, cadence=1000, asm=None, dest=None, compression=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn rs_trend(
+    py: Python,
+    source: PathBuf,
+    cadu_len: usize,
+    interleave: usize,
+    cadence: u64,
+    asm: Option<&PyAny>,
+    dest: Option<PathBuf>,
+    compression: Option<&str>,
+) -> PyResult<Vec<RsTrendRecord>> {
+    let asm = sync::asm_arg(&source, asm)?;
+    let compression = Compression::from_arg(compression, dest.as_deref())?;
+    if cadence == 0 {
+        return Err(PyValueError::new_err(
+            "invalid cadence; expected > 0, got 0",
+        ));
+    }
+    let block_len = cadu_len.saturating_sub(asm.len());
+    if !rs::valid_block_len(block_len, interleave) {
+        return Err(PyValueError::new_err(format!(
+            "invalid cadu_len {cadu_len} for interleave {interleave}"
+        )));
+    }
+    let blocks = sync::Synchronizer::new(BufReader::new(open_source(&source)?), &asm, block_len)
+        .filter_map(Result::ok);
+
+    let records = py.allow_threads(|| {
+        let mut records: Vec<RsTrendRecord> = Vec::default();
+        let mut cur: Option<RsTrendRecord> = None;
+        for (index, (offset, mut block)) in (0u64..).zip(blocks) {
+            pn::apply(&mut block);
+            let outcome = rs::decode_interleaved(&mut block, interleave);
+            let rec = cur.get_or_insert_with(|| RsTrendRecord::new(index, offset, interleave));
+            rec.add(offset, &outcome);
+            if rec.frames == cadence {
+                records.extend(cur.take());
+            }
+        }
+        records.extend(cur);
+        records
+    });
+    if let Some(dest) = dest {
+        write_csv(
+            compress::Writer::create(&dest, compression)?,
+            &records,
+            interleave,
+        )?;
+    }
+    Ok(records)
+}
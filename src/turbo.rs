@@ -0,0 +1,499 @@
+//! CCSDS 131.0 turbo code decoding, run ahead of frame synchronization to turn a stream
+//! of turbo codewords into CADUs with the standard ASM for the frame decoders.
+//!
+//! Codewords are decoded from hard decisions with an iterative max-log-MAP decoder, so
+//! the coding gain is less than that of a decoder operating on soft symbols.
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+
+use crate::{packets::read_full, pn, source::open_source, sync::PatternHits};
+
+/// Number of states of each component encoder.
+const STATES: usize = 16;
+
+/// Number of trellis termination steps of each component encoder.
+const TERMINATION_STEPS: usize = 4;
+
+/// Frame lengths in bytes supported by the turbo code, i.e., information block lengths
+/// of 1784, 3568, 7136 and 8920 bits.
+const FRAME_LENS: [usize; 4] = [223, 446, 892, 1115];
+
+/// Primes used by the turbo code permutation.
+const PRIMES: [usize; 8] = [31, 37, 43, 47, 53, 59, 61, 67];
+
+/// Scaling applied to the extrinsic information exchanged between the component
+/// decoders, which compensates for the optimism of the max-log approximation.
+const EXTRINSIC_SCALE: f32 = 0.75;
+
+/// Largest number of sync marker bytes searched for; longer markers are synchronized on
+/// this many of their leading bytes.
+const MAX_SYNC_LEN: usize = 16;
+
+/// Number of bytes read from the source at a time.
+const READ_LEN: usize = 64 * 1024;
+
+/// Component encoder output, either of the two encoders, `a` encoding the information
+/// block in order and `b` encoding it permuted, and output 0 (systematic) to 3.
+type Symbol = (usize, usize);
+
+/// Turbo code rates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rate {
+    Half,
+    Third,
+    Quarter,
+    Sixth,
+}
+
+impl Rate {
+    /// Lookup a rate by the name used in the Python API.
+    pub fn from_name(name: &str) -> PyResult<Self> {
+        match name {
+            "1/2" => Ok(Self::Half),
+            "1/3" => Ok(Self::Third),
+            "1/4" => Ok(Self::Quarter),
+            "1/6" => Ok(Self::Sixth),
+            _ => Err(PyValueError::new_err(format!(
+                "unsupported turbo rate; expected one of 1/2, 1/3, 1/4, 1/6, got {name}"
+            ))),
+        }
+    }
+
+    /// Number of codeword symbols per information bit.
+    fn symbols_per_bit(&self) -> usize {
+        match self {
+            Self::Half => 2,
+            Self::Third => 3,
+            Self::Quarter => 4,
+            Self::Sixth => 6,
+        }
+    }
+
+    /// The attached sync marker of codewords of this rate.
+    fn asm(&self) -> &'static [u8] {
+        match self {
+            Self::Half => &[0x03, 0x47, 0x76, 0xc7, 0x27, 0x28, 0x95, 0xb0],
+            Self::Third => &[
+                0x25, 0xd5, 0xc0, 0xce, 0x89, 0x90, 0xf6, 0xc9, 0x46, 0x1b, 0xf7, 0x9c,
+            ],
+            Self::Quarter => &[
+                0x03, 0x47, 0x76, 0xc7, 0x27, 0x28, 0x95, 0xb0, 0xfc, 0xb8, 0x89, 0x38, 0xd8, 0xd7,
+                0x6a, 0x4f,
+            ],
+            Self::Sixth => &[
+                0x25, 0xd5, 0xc0, 0xce, 0x89, 0x90, 0xf6, 0xc9, 0x46, 0x1b, 0xf7, 0x9c, 0xda, 0x2a,
+                0x3f, 0x31, 0x76, 0x6f, 0x09, 0x36, 0xb9, 0xe4, 0x08, 0x63,
+            ],
+        }
+    }
+
+    /// The encoder outputs transmitted at step `t`, in order. Rate 1/2 alternates
+    /// between the parity of encoder `a` and encoder `b`.
+    fn symbols(&self, t: usize) -> &'static [Symbol] {
+        match self {
+            Self::Half if t.is_multiple_of(2) => &[(0, 0), (0, 1)],
+            Self::Half => &[(0, 0), (1, 1)],
+            Self::Third => &[(0, 0), (0, 1), (1, 1)],
+            Self::Quarter => &[(0, 0), (0, 2), (0, 3), (1, 1)],
+            Self::Sixth => &[(0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 3)],
+        }
+    }
+}
+
+/// Next state and outputs of a component encoder in `state` for input bit `u`. The
+/// encoder is recursive with feedback 1 + D^3 + D^4, and the outputs are the input
+/// followed by the parity for 1 + D + D^3 + D^4, 1 + D^2 + D^4 and
+/// 1 + D + D^2 + D^3 + D^4.
+fn step(state: usize, u: u8) -> (usize, [u8; 4]) {
+    let s = |i: usize| (state >> (4 - i)) as u8 & 1;
+    let w = u ^ s(3) ^ s(4);
+    let outputs = [
+        u,
+        w ^ s(1) ^ s(3) ^ s(4),
+        w ^ s(2) ^ s(4),
+        w ^ s(1) ^ s(2) ^ s(3) ^ s(4),
+    ];
+    (usize::from(w) << 3 | state >> 1, outputs)
+}
+
+/// The input that moves a component encoder towards the zero state.
+fn terminating_input(state: usize) -> u8 {
+    (state >> 1 ^ state) as u8 & 1
+}
+
+/// A turbo code for a rate and information block length.
+pub struct TurboCode {
+    rate: Rate,
+    /// Information block length in bits.
+    k: usize,
+    /// Index of the information bit encoded by encoder `b` at each step.
+    perm: Vec<usize>,
+}
+
+impl TurboCode {
+    pub fn new(rate: Rate, frame_len: usize) -> PyResult<Self> {
+        if !FRAME_LENS.contains(&frame_len) {
+            return Err(PyValueError::new_err(format!(
+                "invalid frame_len for turbo code; expected one of 223, 446, 892, 1115, got {frame_len}"
+            )));
+        }
+        let k = frame_len * 8;
+        let (k1, k2) = (8, k / 8);
+        let perm = (0..k)
+            .map(|s| {
+                let m = s % 2;
+                let i = s / (2 * k2);
+                let j = s / 2 - i * k2;
+                let t = (19 * i + 1) % (k1 / 2);
+                let q = t % 8;
+                let c = (PRIMES[q] * j + 21 * m) % k2;
+                2 * (t + c * k1 / 2 + 1) - m - 1
+            })
+            .collect();
+        Ok(Self { rate, k, perm })
+    }
+
+    /// Codeword length in bits.
+    pub fn codeword_len(&self) -> usize {
+        (self.k + TERMINATION_STEPS) * self.rate.symbols_per_bit()
+    }
+
+    /// Encode an information block of `k` bits, one per byte, into codeword bits.
+    fn encode(&self, bits: &[u8]) -> Vec<u8> {
+        let mut states = [0usize; 2];
+        let mut codeword = Vec::with_capacity(self.codeword_len());
+        for t in 0..self.k + TERMINATION_STEPS {
+            let mut outputs = [[0u8; 4]; 2];
+            for (enc, state) in states.iter_mut().enumerate() {
+                let u = match (t < self.k, enc) {
+                    (true, 0) => bits[t],
+                    (true, _) => bits[self.perm[t]],
+                    (false, _) => terminating_input(*state),
+                };
+                (*state, outputs[enc]) = step(*state, u);
+            }
+            for (enc, out) in self.rate.symbols(t) {
+                codeword.push(outputs[*enc][*out]);
+            }
+        }
+        codeword
+    }
+
+    /// Decode a codeword from the log-likelihood ratio of each of its bits, positive for
+    /// a 0, into the information bits, one per byte.
+    fn decode(&self, llrs: &[f32], iterations: usize) -> Vec<u8> {
+        let n = self.k + TERMINATION_STEPS;
+        // channel values of each encoder output at each step, 0 where not transmitted
+        let mut channel = [vec![[0f32; 4]; n], vec![[0f32; 4]; n]];
+        let mut llrs = llrs.iter();
+        let [a, b] = &mut channel;
+        for (t, steps) in a.iter_mut().zip(b.iter_mut()).enumerate() {
+            for (enc, out) in self.rate.symbols(t) {
+                let step = if *enc == 0 {
+                    &mut *steps.0
+                } else {
+                    &mut *steps.1
+                };
+                step[*out] = *llrs.next().unwrap_or(&0.0);
+            }
+        }
+        // encoder b does not transmit its systematic output, which is the permuted
+        // systematic output of encoder a
+        for t in 0..self.k {
+            channel[1][t][0] = channel[0][self.perm[t]][0];
+        }
+
+        let mut apriori_a = vec![0f32; self.k];
+        let mut apriori_b = vec![0f32; self.k];
+        let mut bits: Vec<u8> = Vec::default();
+        for _ in 0..iterations.max(1) {
+            let extrinsic_a = self.component(&channel[0], &apriori_a);
+            for t in 0..self.k {
+                apriori_b[t] = extrinsic_a[self.perm[t]];
+            }
+            let extrinsic_b = self.component(&channel[1], &apriori_b);
+            for t in 0..self.k {
+                apriori_a[self.perm[t]] = extrinsic_b[t];
+            }
+            let decided: Vec<u8> = (0..self.k)
+                .map(|t| u8::from(channel[0][t][0] + extrinsic_a[t] + apriori_a[t] < 0.0))
+                .collect();
+            if decided == bits {
+                break;
+            }
+            bits = decided;
+        }
+        bits
+    }
+
+    /// Max-log-MAP decode of a component code, returning the scaled extrinsic
+    /// information for each information bit.
+    fn component(&self, channel: &[[f32; 4]], apriori: &[f32]) -> Vec<f32> {
+        let n = channel.len();
+        let metric = |t: usize, state: usize, u: u8| -> (usize, f32) {
+            let (next, outputs) = step(state, u);
+            let mut m = 0.0;
+            for (j, bit) in outputs.iter().enumerate() {
+                let mut llr = channel[t][j];
+                if j == 0 && t < self.k {
+                    llr += apriori[t];
+                }
+                m += if *bit == 0 { llr } else { -llr };
+            }
+            (next, m / 2.0)
+        };
+        let inputs = |t: usize, state: usize| -> &'static [u8] {
+            match (t < self.k, terminating_input(state)) {
+                (true, _) => &[0, 1],
+                (false, 0) => &[0],
+                (false, _) => &[1],
+            }
+        };
+
+        let mut alpha = vec![[f32::NEG_INFINITY; STATES]; n + 1];
+        alpha[0][0] = 0.0;
+        for t in 0..n {
+            for state in 0..STATES {
+                if alpha[t][state] == f32::NEG_INFINITY {
+                    continue;
+                }
+                for u in inputs(t, state) {
+                    let (next, m) = metric(t, state, *u);
+                    let m = alpha[t][state] + m;
+                    if m > alpha[t + 1][next] {
+                        alpha[t + 1][next] = m;
+                    }
+                }
+            }
+            normalize(&mut alpha[t + 1]);
+        }
+
+        let mut beta = [f32::NEG_INFINITY; STATES];
+        beta[0] = 0.0;
+        let mut extrinsic = vec![0f32; self.k];
+        for t in (0..n).rev() {
+            let mut prev = [f32::NEG_INFINITY; STATES];
+            let mut best = [f32::NEG_INFINITY; 2];
+            for state in 0..STATES {
+                for u in inputs(t, state) {
+                    let (next, m) = metric(t, state, *u);
+                    let m = m + beta[next];
+                    if m > prev[state] {
+                        prev[state] = m;
+                    }
+                    let total = alpha[t][state] + m;
+                    if total > best[usize::from(*u)] {
+                        best[usize::from(*u)] = total;
+                    }
+                }
+            }
+            if t < self.k {
+                let llr = best[0] - best[1];
+                extrinsic[t] = EXTRINSIC_SCALE * (llr - channel[t][0] - apriori[t]);
+            }
+            normalize(&mut prev);
+            beta = prev;
+        }
+        extrinsic
+    }
+}
+
+/// Subtract the largest metric from all so metrics do not grow without bound.
+fn normalize(metrics: &mut [f32; STATES]) {
+    let max = metrics.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    if max.is_finite() {
+        metrics.iter_mut().for_each(|m| *m -= max);
+    }
+}
+
+/// Bit `i` of `dat`, most significant bit first.
+fn bit_at(dat: &[u8], i: usize) -> u8 {
+    dat[i / 8] >> (7 - i % 8) & 1
+}
+
+/// Bit `i` of the pseudo-noise sequence.
+fn pn_bit(i: usize) -> u8 {
+    bit_at(pn::sequence(), i % (pn::PN_LEN * 8))
+}
+
+/// Read into `buf` until it holds at least `len` bytes, returning false if the source
+/// ended first.
+fn fill<R: Read>(reader: &mut R, buf: &mut Vec<u8>, len: usize) -> PyResult<bool> {
+    while buf.len() < len {
+        let filled = buf.len();
+        buf.resize(filled + READ_LEN.max(len - filled), 0);
+        let n = read_full(reader, &mut buf[filled..])?;
+        buf.truncate(filled + n);
+        if n == 0 {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Decode a stream of turbo codewords into CADUs for the frame decoders.
+///
+/// Codewords are located by the attached sync marker for the rate, which need not be
+/// byte aligned, and decoded from hard decisions. Each decoded frame is written with
+/// the standard CCSDS ASM and pseudo-randomized, so the output can be decoded with
+/// `decode_frames` without an interleave. Turbo decoding does not detect residual
+/// errors, so frames should be checked with their frame error control field, if any.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of turbo codewords, each preceded by its attached sync
+///     marker.
+///
+/// dest : str or os.PathLike
+///     Path of the file to write CADUs to. It is created or truncated.
+///
+/// frame_len : int
+///     The frame length in bytes, i.e., the information block length, one of 223, 446,
+///     892 or 1115.
+///
+/// rate : str
+///     The code rate, one of "1/2", "1/3", "1/4" or "1/6".
+///
+/// iterations : int
+///     Maximum number of decoder iterations per codeword. Decoding stops early once
+///     an iteration does not change the decoded frame.
+///
+/// randomized : bool
+///     True if the codewords are pseudo-randomized.
+///
+/// Returns
+/// -------
+/// int
+///     Number of codewords decoded.
+#[pyfunction(signature=(source, dest, frame_len, rate, iterations=8, randomized=true))]
+pub fn decode_turbo(
+    source: PathBuf,
+    dest: PathBuf,
+    frame_len: usize,
+    rate: &str,
+    iterations: usize,
+    randomized: bool,
+) -> PyResult<u64> {
+    if iterations == 0 {
+        return Err(PyValueError::new_err(
+            "invalid iterations; expected > 0, got 0",
+        ));
+    }
+    let rate = Rate::from_name(rate)?;
+    let code = TurboCode::new(rate, frame_len)?;
+    let asm_bits = rate.asm().len() * 8;
+    let sync = &rate.asm()[..rate.asm().len().min(MAX_SYNC_LEN)];
+    let codeword_len = code.codeword_len();
+
+    let mut reader = open_source(&source)?;
+    let mut writer = BufWriter::new(File::create(dest)?);
+    let mut buf: Vec<u8> = Vec::default();
+    // bit position in buf to search for the next sync marker from
+    let mut pos = 0usize;
+    let mut count = 0;
+    loop {
+        if pos / 8 > READ_LEN {
+            buf.drain(..pos / 8);
+            pos %= 8;
+        }
+        let needed = (pos + asm_bits + codeword_len).div_ceil(8);
+        let more = fill(&mut reader, &mut buf, needed)?;
+        let start = pos / 8;
+        let hit = PatternHits::new(&buf[start..], sync)
+            .map(|bit| start * 8 + bit as usize)
+            .find(|bit| *bit >= pos);
+        let Some(hit) = hit else {
+            if !more {
+                break;
+            }
+            // keep enough to find a marker spanning the end of the buffer
+            pos = pos.max((buf.len() - sync.len()) * 8);
+            let len = buf.len() + READ_LEN;
+            fill(&mut reader, &mut buf, len)?;
+            continue;
+        };
+        let first = hit + asm_bits;
+        if (first + codeword_len).div_ceil(8) > buf.len() {
+            if !more {
+                break;
+            }
+            pos = hit;
+            continue;
+        }
+        let llrs: Vec<f32> = (0..codeword_len)
+            .map(|i| {
+                let mut bit = bit_at(&buf, first + i);
+                if randomized {
+                    bit ^= pn_bit(i);
+                }
+                if bit == 0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            })
+            .collect();
+        let bits = code.decode(&llrs, iterations);
+        let mut frame: Vec<u8> = bits
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, b| acc << 1 | b))
+            .collect();
+        pn::apply(&mut frame);
+        writer.write_all(&ccsds::ASM)?;
+        writer.write_all(&frame)?;
+        count += 1;
+        pos = first + codeword_len;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Turbo encode a frame, e.g., to produce test data for `decode_turbo`.
+///
+/// Parameters
+/// ----------
+/// frame : bytes
+///     The frame, of one of the lengths supported by `decode_turbo`.
+///
+/// rate : str
+///     The code rate, one of "1/2", "1/3", "1/4" or "1/6".
+///
+/// randomize : bool
+///     Apply the CCSDS pseudo-noise sequence to the codeword.
+///
+/// Returns
+/// -------
+/// bytes
+///     The attached sync marker for the rate followed by the codeword. Codewords that
+///     are not a whole number of bytes are padded with zero bits.
+#[pyfunction(signature=(frame, rate, randomize=true))]
+pub fn encode_turbo<'py>(
+    py: Python<'py>,
+    frame: &[u8],
+    rate: &str,
+    randomize: bool,
+) -> PyResult<&'py PyBytes> {
+    let rate = Rate::from_name(rate)?;
+    let code = TurboCode::new(rate, frame.len())?;
+    let bits: Vec<u8> = (0..frame.len() * 8).map(|i| bit_at(frame, i)).collect();
+    let mut codeword = code.encode(&bits);
+    if randomize {
+        codeword
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, bit)| *bit ^= pn_bit(i));
+    }
+    let mut out = rate.asm().to_vec();
+    out.extend(
+        codeword
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, b| acc << 1 | b) << (8 - byte.len())),
+    );
+    Ok(PyBytes::new(py, &out))
+}
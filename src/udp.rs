@@ -0,0 +1,184 @@
+//! Reception of CADUs or frames sent over UDP by ground stations, each datagram
+//! carrying one CADU or frame, optionally preceded by an annotation header added by the
+//! station, e.g., with the receive time and link quality, that is stripped.
+use std::{
+    io::{self, ErrorKind, Read},
+    net::UdpSocket,
+};
+
+use crate::pcap::{u16_at, u32_at};
+
+/// Largest UDP payload.
+const MAX_DATAGRAM_LEN: usize = 65535;
+
+fn invalid_param(msg: String) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid annotation parameter; {msg}"),
+    )
+}
+
+/// Names of the query parameters of an [Annotation].
+const PARAMS: [&str; 5] = [
+    "header_len",
+    "length_offset",
+    "length_size",
+    "byte_order",
+    "add_asm",
+];
+
+/// Layout of the annotation header preceding the CADU or frame of each datagram.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Annotation {
+    /// Length of the annotation header.
+    header_len: usize,
+    /// Offset in the header and size, 1, 2 or 4 bytes, of a field giving the length of
+    /// the CADU or frame following the header, used to drop any padding after it.
+    length_field: Option<(usize, usize)>,
+    /// Byte order of the fields of the header.
+    big_endian: bool,
+    /// Prefix each CADU or frame with the standard CCSDS ASM, for feeds of frames
+    /// without sync markers.
+    add_asm: bool,
+}
+
+impl Annotation {
+    /// Parse the annotation parameters of the query of a `udp://` or `pcap://` source,
+    /// `header_len`, `length_offset`, `length_size`, `byte_order` and `add_asm`,
+    /// returning the annotation, if any parameters were given, and the other
+    /// parameters.
+    pub fn from_query(query: &str) -> io::Result<(Option<Self>, String)> {
+        let mut annotation = None::<Self>;
+        let mut length_offset = None;
+        let mut length_size = 4;
+        let mut rest = Vec::default();
+        for param in query.split('&').filter(|p| !p.is_empty()) {
+            let Some((name, value)) = param
+                .split_once('=')
+                .filter(|(name, _)| PARAMS.contains(name))
+            else {
+                rest.push(param);
+                continue;
+            };
+            let number = || {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| invalid_param(format!("expected a number, got {param}")))
+            };
+            let ann = annotation.get_or_insert_with(|| Self {
+                big_endian: true,
+                ..Self::default()
+            });
+            match name {
+                "header_len" => ann.header_len = number()?,
+                "length_offset" => length_offset = Some(number()?),
+                "length_size" => length_size = number()?,
+                "byte_order" => {
+                    ann.big_endian = match value {
+                        "big" => true,
+                        "little" => false,
+                        _ => {
+                            return Err(invalid_param(format!(
+                                "expected byte_order=big or little, got {param}"
+                            )))
+                        }
+                    }
+                }
+                _ => {
+                    ann.add_asm = match value {
+                        "1" | "true" => true,
+                        "0" | "false" => false,
+                        _ => {
+                            return Err(invalid_param(format!(
+                                "expected add_asm=true or false, got {param}"
+                            )))
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(ann) = &mut annotation {
+            if !matches!(length_size, 1 | 2 | 4) {
+                return Err(invalid_param(format!(
+                    "expected length_size of 1, 2 or 4, got {length_size}"
+                )));
+            }
+            if let Some(offset) = length_offset {
+                if offset + length_size > ann.header_len {
+                    return Err(invalid_param(format!(
+                        "length field at {offset} of {length_size} bytes is not within the \
+                         {} byte header",
+                        ann.header_len
+                    )));
+                }
+                ann.length_field = Some((offset, length_size));
+            }
+        }
+        Ok((annotation, rest.join("&")))
+    }
+
+    /// Append the CADU or frame of a datagram to `out`. Datagrams too short for the
+    /// annotation header are dropped.
+    pub fn extract(&self, datagram: &[u8], out: &mut Vec<u8>) {
+        let Some(mut payload) = datagram.get(self.header_len..) else {
+            return;
+        };
+        if let Some((offset, size)) = self.length_field {
+            let len = match size {
+                1 => Some(usize::from(datagram[offset])),
+                2 => u16_at(datagram, offset, self.big_endian).map(usize::from),
+                _ => u32_at(datagram, offset, self.big_endian).map(|len| len as usize),
+            };
+            if let Some(len) = len {
+                payload = &payload[..len.min(payload.len())];
+            }
+        }
+        if self.add_asm {
+            out.extend_from_slice(&ccsds::ASM);
+        }
+        out.extend_from_slice(payload);
+    }
+}
+
+/// A reader of the datagrams received on a UDP socket as a contiguous byte stream,
+/// stripping any annotation header from each.
+pub struct UdpReader {
+    socket: UdpSocket,
+    annotation: Option<Annotation>,
+    datagram: Vec<u8>,
+    payload: Vec<u8>,
+    /// Position in `payload` of the next byte to read.
+    pos: usize,
+}
+
+impl UdpReader {
+    /// Bind a socket to `addr`, e.g., `0.0.0.0:5000`, to receive datagrams on.
+    pub fn bind(addr: &str, annotation: Option<Annotation>) -> io::Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr)?,
+            annotation,
+            datagram: vec![0u8; MAX_DATAGRAM_LEN],
+            payload: Vec::default(),
+            pos: 0,
+        })
+    }
+}
+
+impl Read for UdpReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.payload.len() {
+            let len = self.socket.recv(&mut self.datagram)?;
+            let datagram = &self.datagram[..len];
+            self.payload.clear();
+            self.pos = 0;
+            match &self.annotation {
+                Some(annotation) => annotation.extract(datagram, &mut self.payload),
+                None => self.payload.extend_from_slice(datagram),
+            }
+        }
+        let n = buf.len().min(self.payload.len() - self.pos);
+        buf[..n].copy_from_slice(&self.payload[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
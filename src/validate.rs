@@ -0,0 +1,348 @@
+//! Validation of a packet stream against the expectations of a mission, for automated
+//! acceptance of files into an archive.
+
+// pyo3 0.19 generates the constructor text signature as an impl nested in a function
+#![allow(non_local_definitions)]
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::BufReader,
+    path::PathBuf,
+};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use serde::Serialize;
+
+use crate::{
+    gaps::{packet_time, GapTracker},
+    idle::FILL_APID,
+    packets::{PacketReader, Truncated, HEADER_LEN},
+    repr::repr,
+    source::open_source,
+    timecode::{optional_registry_arg, TimecodeRegistry},
+};
+
+/// Expectations a packet stream is validated against by `validate`.
+///
+/// Fill packets, i.e., APID 2047, are not validated.
+///
+/// Parameters
+/// ----------
+/// apids : Iterable[int], optional
+///     APIDs allowed in the stream. All APIDs are allowed if not provided.
+///
+/// lengths : dict of int to (int, int), optional
+///     Inclusive range of the length of packets of each APID in bytes, including the
+///     primary header. Lengths of other APIDs are not checked.
+///
+/// secondary_header : bool or dict of int to bool, optional
+///     Whether packets must have a secondary header, either for all APIDs or for each
+///     APID. Not checked if not provided.
+///
+/// timecode : str or TimecodeRegistry, optional
+///     Format of the timecode at the start of the secondary header, one of `cds` or
+///     `eoscuc`, or the timecode of each APID. Required by `monotonic`.
+///
+/// monotonic : bool
+///     Require packet times not to decrease within each APID. Packets whose time cannot
+///     be decoded are not checked.
+///
+/// max_gap : int, optional
+///     Largest number of packets that may be missing from a single gap in the sequence
+///     counter of an APID.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default)]
+pub struct ValidationProfile {
+    #[pyo3(get)]
+    apids: Option<HashSet<u16>>,
+    #[pyo3(get)]
+    lengths: HashMap<u16, (usize, usize)>,
+    /// Whether secondary headers are required for APIDs not in `secondary_headers`.
+    secondary_header: Option<bool>,
+    secondary_headers: HashMap<u16, bool>,
+    timecode: Option<TimecodeRegistry>,
+    #[pyo3(get)]
+    monotonic: bool,
+    #[pyo3(get)]
+    max_gap: Option<u16>,
+}
+
+/// Convert a `secondary_header` argument that is not a bool into the requirement for
+/// each APID.
+fn secondary_header_arg(arg: &PyAny) -> PyResult<HashMap<u16, bool>> {
+    arg.extract().map_err(|_| {
+        PyValueError::new_err(format!(
+            "invalid secondary_header; expected a bool or dict of APID to bool, got {arg}"
+        ))
+    })
+}
+
+impl ValidationProfile {
+    fn secondary_header(&self, apid: u16) -> Option<bool> {
+        self.secondary_headers
+            .get(&apid)
+            .copied()
+            .or(self.secondary_header)
+    }
+}
+
+#[pymethods]
+impl ValidationProfile {
+    #[new]
+    #[pyo3(signature=(apids=None, lengths=None, secondary_header=None, timecode=None, monotonic=false, max_gap=None))]
+    fn py_new(
+        apids: Option<&PyAny>,
+        lengths: Option<HashMap<u16, (usize, usize)>>,
+        secondary_header: Option<&PyAny>,
+        timecode: Option<&PyAny>,
+        monotonic: bool,
+        max_gap: Option<u16>,
+    ) -> PyResult<Self> {
+        let apids: Option<HashSet<u16>> = crate::set_arg(apids)?;
+        let lengths = lengths.unwrap_or_default();
+        if let Some((apid, (min, max))) = lengths.iter().find(|(_, (min, max))| min > max) {
+            return Err(PyValueError::new_err(format!(
+                "invalid lengths for apid {apid}; expected min <= max, got ({min}, {max})"
+            )));
+        }
+        let (default, by_apid) = match secondary_header {
+            None => (None, HashMap::default()),
+            Some(arg) => match arg.extract::<bool>() {
+                Ok(required) => (Some(required), HashMap::default()),
+                Err(_) => (None, secondary_header_arg(arg)?),
+            },
+        };
+        let timecode = optional_registry_arg(timecode)?;
+        if monotonic && timecode.is_none() {
+            return Err(PyValueError::new_err("monotonic requires a timecode"));
+        }
+        Ok(Self {
+            apids,
+            lengths,
+            secondary_header: default,
+            secondary_headers: by_apid,
+            timecode,
+            monotonic,
+            max_gap,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "ValidationProfile(apids={}, lengths={}, secondary_header={}, monotonic={}, max_gap={})",
+            repr(&self.apids),
+            self.lengths.len(),
+            repr(&self.secondary_header),
+            repr(&self.monotonic),
+            repr(&self.max_gap),
+        )
+    }
+}
+
+/// A packet that failed a check of a `ValidationProfile`.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Serialize)]
+pub struct ValidationViolation {
+    /// The check failed, one of `apid`, `length`, `secondary_header`, `monotonic`,
+    /// `gap` or `truncated`.
+    #[pyo3(get)]
+    pub check: &'static str,
+    #[pyo3(get)]
+    pub apid: u16,
+    #[pyo3(get)]
+    pub sequence_id: u16,
+    /// Byte offset of the packet in the source.
+    #[pyo3(get)]
+    pub offset: u64,
+    #[pyo3(get)]
+    pub message: String,
+}
+
+#[pymethods]
+impl ValidationViolation {
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "ValidationViolation(check={}, apid={}, sequence_id={}, offset={}, message={})",
+            repr(self.check),
+            self.apid,
+            self.sequence_id,
+            self.offset,
+            repr(&self.message)
+        )
+    }
+}
+
+/// Outcome of validating a packet stream, as generated by `validate`.
+#[pyclass(frozen)]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ValidationReport {
+    /// True if no packet failed a check.
+    #[pyo3(get)]
+    pub passed: bool,
+    /// Number of packets validated, excluding fill packets.
+    #[pyo3(get)]
+    pub packets: u64,
+    /// Number of violations of each check, including any not in `violations`.
+    #[pyo3(get)]
+    pub counts: BTreeMap<&'static str, u64>,
+    /// Violations in the order they were found, up to the `max_violations` of
+    /// `validate`.
+    #[pyo3(get)]
+    pub violations: Vec<ValidationViolation>,
+}
+
+#[pymethods]
+impl ValidationReport {
+    /// The report as a JSON document.
+    #[pyo3(signature=(indent=true))]
+    fn to_json(&self, indent: bool) -> PyResult<String> {
+        let result = if indent {
+            serde_json::to_string_pretty(self)
+        } else {
+            serde_json::to_string(self)
+        };
+        result.map_err(|err| PyValueError::new_err(format!("{err}")))
+    }
+
+    fn __repr__(&self) -> String {
+        self.__str__()
+    }
+    fn __str__(&self) -> String {
+        format!(
+            "ValidationReport(passed={}, packets={}, counts={})",
+            repr(&self.passed),
+            self.packets,
+            repr(&self.counts)
+        )
+    }
+}
+
+impl ValidationReport {
+    fn add(&mut self, violation: ValidationViolation, max_violations: usize) {
+        *self.counts.entry(violation.check).or_default() += 1;
+        if self.violations.len() < max_violations {
+            self.violations.push(violation);
+        }
+    }
+}
+
+/// Validate a stream of space packets against a profile of the expected APIDs, packet
+/// lengths, secondary headers, time ordering and sequence gaps, e.g., to certify a file
+/// before accepting it into an archive.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of space packets.
+///
+/// profile : ValidationProfile
+///     The checks to perform.
+///
+/// max_violations : int
+///     Largest number of violations included in the report. All violations are
+///     counted.
+///
+/// Returns
+/// -------
+/// ValidationReport
+///     The report, which passes if no packet failed a check. A packet cut short by the
+///     end of the stream fails the `truncated` check.
+#[pyfunction(signature=(source, profile, max_violations=1000))]
+pub fn validate(
+    source: PathBuf,
+    profile: &ValidationProfile,
+    max_violations: usize,
+) -> PyResult<ValidationReport> {
+    let reader =
+        PacketReader::new(BufReader::new(open_source(&source)?)).truncated(Truncated::Partial);
+    let mut report = ValidationReport::default();
+    let mut gaps = GapTracker::default();
+    let mut last_times: HashMap<u16, i64> = HashMap::default();
+    for packet in reader {
+        let packet = packet?;
+        let hdr = &packet.header;
+        if hdr.apid == FILL_APID {
+            continue;
+        }
+        report.packets += 1;
+        let violation = |check, message| ValidationViolation {
+            check,
+            apid: hdr.apid,
+            sequence_id: hdr.sequence_id,
+            offset: packet.offset,
+            message,
+        };
+        let mut found = Vec::default();
+        if packet.truncated {
+            found.push(violation(
+                "truncated",
+                format!(
+                    "stream ended after {} of {} packet bytes",
+                    HEADER_LEN + packet.data.len(),
+                    HEADER_LEN + usize::from(hdr.len_minus1) + 1
+                ),
+            ));
+        }
+        if let Some(apids) = &profile.apids {
+            if !apids.contains(&hdr.apid) {
+                found.push(violation("apid", format!("unexpected apid {}", hdr.apid)));
+            }
+        }
+        if let Some((min, max)) = profile.lengths.get(&hdr.apid) {
+            let len = HEADER_LEN + usize::from(hdr.len_minus1) + 1;
+            if !(min..=max).contains(&&len) {
+                found.push(violation(
+                    "length",
+                    format!("length {len} outside {min} to {max} bytes"),
+                ));
+            }
+        }
+        match profile.secondary_header(hdr.apid) {
+            Some(true) if !hdr.has_secondary_header => {
+                found.push(violation(
+                    "secondary_header",
+                    "missing secondary header".into(),
+                ));
+            }
+            Some(false) if hdr.has_secondary_header => {
+                found.push(violation(
+                    "secondary_header",
+                    "unexpected secondary header".into(),
+                ));
+            }
+            _ => {}
+        }
+        let time = packet_time(&packet, profile.timecode.as_ref());
+        if let (true, Some(time)) = (profile.monotonic, time) {
+            match last_times.insert(hdr.apid, time) {
+                Some(last) if time < last => found.push(violation(
+                    "monotonic",
+                    format!("time {time} precedes time {last} of the previous packet"),
+                )),
+                _ => {}
+            }
+        }
+        let gap = gaps.add(&packet, time);
+        if let (Some(max_gap), Some(gap)) = (profile.max_gap, gap) {
+            if gap.count > max_gap {
+                found.push(violation(
+                    "gap",
+                    format!(
+                        "{} packets missing after sequence id {}, more than {max_gap}",
+                        gap.count, gap.start_sequence_id
+                    ),
+                ));
+            }
+        }
+        for violation in found {
+            report.add(violation, max_violations);
+        }
+    }
+    report.passed = report.counts.is_empty();
+    Ok(report)
+}
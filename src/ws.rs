@@ -0,0 +1,227 @@
+//! Minimal websocket server streaming decoded packets to connected clients.
+//!
+//! Only what is needed to push messages to clients is implemented, i.e., the opening
+//! handshake and unfragmented server-to-client frames. Messages from clients are ignored.
+use std::{
+    collections::HashSet,
+    io::{self, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use pyo3::prelude::*;
+use sha1::{Digest, Sha1};
+
+use crate::{
+    message::{PacketRef, Serialization},
+    packets::PacketReader,
+    source::open_source,
+    throttle::{self, Throttle},
+    Packet,
+};
+
+/// GUID appended to the client key to compute the accept key, per RFC 6455.
+const WS_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Largest handshake request accepted.
+const MAX_REQUEST_LEN: usize = 8192;
+/// Time allowed for a client to complete the handshake or accept a message.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Interval at which the listener checks whether serving is done, and serving checks
+/// for the first client and interrupts.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+
+/// The `Sec-WebSocket-Accept` value for a client's `Sec-WebSocket-Key`.
+fn accept_key(key: &str) -> String {
+    STANDARD.encode(Sha1::digest(format!("{key}{WS_GUID}")))
+}
+
+/// Read the client's opening handshake and respond, completing the upgrade to a
+/// websocket.
+fn handshake(stream: &mut TcpStream) -> io::Result<()> {
+    let mut request = Vec::default();
+    let mut reader = BufReader::new(stream.try_clone()?).take(MAX_REQUEST_LEN as u64);
+    let mut buf = [0u8; 1024];
+    while !request.ends_with(b"\r\n\r\n") {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "incomplete handshake",
+            ));
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let key = request.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("sec-websocket-key")
+            .then(|| value.trim().to_string())
+    });
+    let Some(key) = key else {
+        stream.write_all(b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n")?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a websocket request",
+        ));
+    };
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    )
+}
+
+/// Encode an unfragmented, unmasked websocket frame.
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(payload.len() + 10);
+    buf.push(0x80 | opcode);
+    match payload.len() {
+        len if len < 126 => buf.push(len as u8),
+        len if len <= 0xffff => {
+            buf.push(126);
+            buf.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            buf.push(127);
+            buf.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    buf.extend_from_slice(payload);
+    buf
+}
+
+type Clients = Arc<Mutex<Vec<TcpStream>>>;
+
+/// Accept clients until `done`, adding them to `clients` once their handshake
+/// completes.
+fn accept(listener: TcpListener, clients: Clients, done: Arc<AtomicBool>) {
+    while !done.load(Ordering::Relaxed) {
+        let mut stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_INTERVAL);
+                continue;
+            }
+            Err(_) => continue,
+        };
+        let clients = clients.clone();
+        thread::spawn(move || {
+            let ready = stream.set_nonblocking(false).is_ok()
+                && stream.set_read_timeout(Some(CLIENT_TIMEOUT)).is_ok()
+                && stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_ok()
+                && handshake(&mut stream).is_ok();
+            if ready {
+                clients.lock().unwrap().push(stream);
+            }
+        });
+    }
+}
+
+/// Send `msg` to each client, dropping clients that fail to receive it.
+fn broadcast(clients: &Clients, msg: &[u8]) {
+    clients
+        .lock()
+        .unwrap()
+        .retain_mut(|client| client.write_all(msg).is_ok());
+}
+
+/// Decode packets from a source and stream them to websocket clients.
+///
+/// A websocket server is started on `bind` and each decoded packet is sent as a message
+/// to every connected client. Decoding starts once the first client has connected, so
+/// it receives all packets, after which packets decoded while no clients are connected
+/// are dropped. Serving ends, closing client connections, once the source is exhausted.
+///
+/// Parameters
+/// ----------
+/// source : str or os.PathLike
+///     Source providing a stream of space packets, as accepted by `decode_packets`.
+///
+/// bind : str
+///     Address to listen on, as `host:port`.
+///
+/// serialization : str
+///     Either `json` to send a text message of the packet header fields with the data
+///     hex encoded, or `raw` to send a binary message of the packet bytes.
+///
+/// apids : Iterable[int], optional
+///     Only stream packets with these APIDs.
+///
+/// rate : float or str, optional
+///     Pace streaming to this many bits per second, or `realtime` to replay at the rate
+///     given by the packet CDS times, or `realtime:eoscuc` for EOS CUC times. Packets
+///     are streamed as fast as they are decoded if not provided.
+///
+/// Returns
+/// -------
+/// int
+///     The number of packets decoded and streamed.
+#[pyfunction(signature=(source, bind="0.0.0.0:8765", serialization="json", apids=None, rate=None))]
+pub fn serve_packets(
+    py: Python,
+    source: PathBuf,
+    bind: &str,
+    serialization: &str,
+    apids: Option<&PyAny>,
+    rate: Option<&PyAny>,
+) -> PyResult<usize> {
+    let apids: Option<HashSet<u16>> = crate::set_arg(apids)?;
+    let serialization = Serialization::from_name(serialization)?;
+    let mut throttle = Throttle::from_arg(rate)?;
+    let opcode = match serialization {
+        Serialization::Json => OPCODE_TEXT,
+        Serialization::Raw => OPCODE_BINARY,
+    };
+    let listener = TcpListener::bind(bind)?;
+    listener.set_nonblocking(true)?;
+    let mut reader = PacketReader::new(BufReader::new(open_source(&source)?));
+
+    let clients: Clients = Arc::default();
+    let done = Arc::new(AtomicBool::new(false));
+    let acceptor = {
+        let (clients, done) = (clients.clone(), done.clone());
+        thread::spawn(move || accept(listener, clients, done))
+    };
+    let result = (|| -> PyResult<usize> {
+        while clients.lock().unwrap().is_empty() {
+            py.allow_threads(|| thread::sleep(ACCEPT_INTERVAL));
+            py.check_signals()?;
+        }
+        let mut count = 0;
+        while let Some(packet) = py.allow_threads(|| reader.next()) {
+            let packet = Packet::from(packet?);
+            if apids
+                .as_ref()
+                .is_some_and(|apids| !apids.contains(&packet.header.apid))
+            {
+                continue;
+            }
+            throttle::sleep(py, throttle.as_mut().and_then(|t| t.delay(&packet)))?;
+            let msg = PacketRef {
+                ids: None,
+                packet: &packet,
+            }
+            .encode(serialization);
+            py.allow_threads(|| broadcast(&clients, &frame(opcode, &msg)));
+            py.check_signals()?;
+            count += 1;
+        }
+        Ok(count)
+    })();
+    done.store(true, Ordering::Relaxed);
+    let _ = acceptor.join();
+    broadcast(&clients, &frame(OPCODE_CLOSE, &[]));
+    result
+}
@@ -1,21 +1,1920 @@
+import asyncio
+import binascii
+import gc
+import gzip
 import hashlib
+import json
+import operator
+import random
+import socket
+import struct
+import threading
+import time
 from pathlib import Path
 
-import ccsdspy
+import pytest
+
+import ccsds as ccsdspy
+
+LFS_POINTER = b"version https://git-lfs.github.com/spec/"
 
 
 def fixture_path(name: str) -> str:
-    return str(Path(__file__).parent / "fixtures" / name)
+    path = Path(__file__).parent / "fixtures" / name
+    # fixtures are stored with Git LFS, so are only pointers if LFS is not installed
+    with open(path, "rb") as f:
+        if f.read(len(LFS_POINTER)) == LFS_POINTER:
+            pytest.skip(f"{name} is a Git LFS pointer; run git lfs pull")
+    return str(path)
 
 
 def test_read_framed_packets():
-    packet_iter = ccsdspy.read_framed_packets(
-        fixture_path("snpp_synchronized_cadus.dat"), 157, 4
-    )
+    src = fixture_path("snpp_synchronized_cadus.dat")
+    packet_iter = ccsdspy.decode_framed_packets(src, 157, 1024, interleave=4)
 
     csum = hashlib.md5()
     for p in packet_iter:
-        csum.update(bytes(p.data))
+        csum.update(bytes(p.packet.data))
     assert (
         csum.hexdigest() == "5e11051d86c46ddc3500904c99bbe978"
     ), "packet output file does not match fixture checksum"
+
+
+def make_packet(apid: int, seq: int, data: bytes) -> bytes:
+    hdr = bytes(
+        [
+            (apid >> 8) & 0x7,
+            apid & 0xFF,
+            0xC0 | ((seq >> 8) & 0x3F),
+            seq & 0xFF,
+            ((len(data) - 1) >> 8) & 0xFF,
+            (len(data) - 1) & 0xFF,
+        ]
+    )
+    return hdr + data
+
+
+def test_packet_data_same_for_all_decoders(tmp_path):
+    dat = make_packet(1, 0, b"\x01\x02\x03")
+    src = tmp_path / "packets.dat"
+    src.write_bytes(dat)
+    frame = bytes([0x40, 0x41, 0, 0, 0, 0, 0, 0]) + dat
+    cadus = tmp_path / "cadus.dat"
+    cadus.write_bytes(ccsdspy.encode_cadu(frame))
+
+    (streamed,) = ccsdspy.decode_packets(str(src))
+    (framed,) = ccsdspy.decode_framed_packets(str(cadus), 1, len(frame) + 4)
+
+    assert bytes(streamed.data) == dat
+    assert bytes(ccsdspy.Packet.decode(dat).data) == dat
+    assert bytes(framed.packet.data) == dat
+
+
+def test_dedup_packets(tmp_path):
+    src = tmp_path / "packets.dat"
+    dest = tmp_path / "dedup.dat"
+    packets = [make_packet(1, i, bytes([i] * 8)) for i in range(4)]
+    src.write_bytes(b"".join(packets + packets[2:]))
+
+    written, removed = ccsdspy.dedup_packets(str(src), str(dest), window=10)
+
+    assert (written, removed) == (4, 2)
+    assert dest.read_bytes() == b"".join(packets)
+
+
+def test_compare_packets(tmp_path):
+    a = tmp_path / "a.dat"
+    b = tmp_path / "b.dat"
+    packets = [make_packet(1, i, bytes([i] * 4)) for i in range(4)]
+    flagged = bytearray(packets[1])
+    flagged[2] &= 0x3F  # continuation segment, same data
+    a.write_bytes(b"".join(packets[:3]))
+    b.write_bytes(packets[0] + bytes(flagged) + packets[3])
+
+    cmp = ccsdspy.compare_packets(str(a), str(b))
+
+    assert cmp.matched == 1
+    assert [(x.sequence_id, y.sequence_id) for x, y in cmp.mismatched] == [(1, 1)]
+    assert [p.sequence_id for p in cmp.only_a] == [2]
+    assert [p.sequence_id for p in cmp.only_b] == [3]
+    assert not cmp.identical()
+    assert ccsdspy.compare_packets(str(a), str(a)).identical()
+
+
+def rice_bits(*fields: str) -> bytes:
+    """Pack CCSDS 121.0 codewords given as bit strings, zero padding the last byte."""
+    bits = "".join(fields).replace(" ", "")
+    bits += "0" * (-len(bits) % 8)
+    return int(bits, 2).to_bytes(len(bits) // 8, "big")
+
+
+def test_rice_decompress_options():
+    def decompress(data, n, **kwargs):
+        kwargs.setdefault("block_size", 8)
+        kwargs.setdefault("preprocess", False)
+        return ccsdspy.rice_decompress(data, n, **kwargs)
+
+    # k=2 split: ID, FS codes of the MSBs, then the 2 LSBs of each sample
+    split = rice_bits("011", "01 1 1 001 1 1 01 01", "01 00 11 01 01 10 11 00")
+    assert decompress(split, 8) == [5, 0, 3, 9, 1, 2, 7, 4]
+    # fundamental sequence, i.e., k=0
+    assert decompress(rice_bits("001", "1 01 001 1 1 1 1 0001"), 8) == [0, 1, 2, 0, 0, 0, 0, 3]
+    # no compression
+    raw = [7, 255, 0, 128, 1, 2, 3, 4]
+    assert decompress(rice_bits("111", *("{:08b}".format(v) for v in raw)), 8) == raw
+    # second extension pairs (0, 0), (1, 0), (0, 1), (2, 1)
+    se = rice_bits("000", "1", "1 01 001 00000001")
+    assert decompress(se, 8) == [0, 0, 1, 0, 0, 1, 2, 1]
+    # two zero blocks, then remainder of segment ending the 4 block RSI after 2 more
+    zero = rice_bits("000", "0", "01", "000", "0", "00001")
+    assert decompress(zero, 8, rsi=4) == [0] * 32
+    # 4-bit IDs, k=4, and 5-bit IDs, no compression of signed samples
+    wide = rice_bits(
+        "0101",
+        "01 1 001 01 1 1 001 00001",
+        "0001 0011 1000 0000 0000 1111 0001 0000",
+    )
+    assert decompress(wide, 16) == [17, 3, 40, 16, 0, 15, 33, 64]
+    signed = [-1, 2**31 - 1, -(2**31), 0, 1, -2, 3, -4]
+    words = ("{:032b}".format(v & 0xFFFFFFFF) for v in signed)
+    assert decompress(rice_bits("11111", *words), 32, signed=True) == signed
+
+
+def test_rice_decompress_preprocessed():
+    # reference sample 100 then mapped prediction residuals 2, 3, 0, 6, 3, 0, 3 with k=1
+    block = rice_bits("010", "01100100", "01 01 1 0001 01 1 01", "0 1 0 0 1 0 1")
+    samples = [100, 101, 99, 99, 102, 100, 100, 98]
+    assert ccsdspy.rice_decompress(block, 8, block_size=8) == samples
+    # a residual past the nearer bound, from 254 to 0, maps to 255
+    jump = rice_bits("111", "11111110", "11111111", "00000000" * 6)
+    assert ccsdspy.rice_decompress(jump, 8, block_size=8) == [254] + [0] * 7
+    # second extension with a reference, whose first pair is (0, 1) for residual 1
+    se = rice_bits("000", "1", "01100100", "001 1 1 1")
+    assert ccsdspy.rice_decompress(se, 8, block_size=8) == [100] + [99] * 7
+
+    # incomplete blocks at the end of the data are discarded
+    assert ccsdspy.rice_decompress(block[:-1], 8, block_size=8) == []
+    assert ccsdspy.rice_decompress(block + block[:3], 8, block_size=8, rsi=1) == samples
+    assert ccsdspy.rice_decompress(block, 8, block_size=8, samples=3) == samples[:3]
+
+
+def test_decode_packets_resync(tmp_path):
+    src = tmp_path / "packets.dat"
+    packets = [make_packet(1, i, bytes([i] * 8)) for i in range(6)]
+    corrupt = bytearray(packets[2])
+    corrupt[4:6] = b"\xff\x00"
+    src.write_bytes(b"".join(packets[:2] + [bytes(corrupt)] + packets[3:]))
+
+    packet_iter = ccsdspy.decode_packets(str(src), resync=True)
+    seqs = [p.header.sequence_id for p in packet_iter]
+
+    assert seqs == [0, 1, 3, 4, 5]
+    assert packet_iter.skipped() == [(28, 14)]
+
+
+def test_decode_packets_limits(tmp_path):
+    src = tmp_path / "packets.dat"
+    packets = [make_packet(apid, i, bytes(4)) for i, apid in enumerate([6, 7, 6, 6])]
+    src.write_bytes(b"".join(packets))
+
+    packet_iter = ccsdspy.decode_packets(str(src), apids=[6])
+    assert next(packet_iter).header.sequence_id == 0
+    with pytest.raises(ValueError, match="offset 10: apid 7 not in allowed apids 6"):
+        next(packet_iter)
+    packet_iter = ccsdspy.decode_packets(str(src), apids=[6], resync=True)
+    assert [p.header.sequence_id for p in packet_iter] == [0, 2, 3]
+    assert packet_iter.skipped() == [(10, 10)]
+
+    src.write_bytes(packets[0] + make_packet(6, 1, bytes(40)) + packets[2])
+    with pytest.raises(ValueError, match="offset 10: length 46 exceeds maximum 20"):
+        list(ccsdspy.decode_packets(str(src), max_packet_len=20))
+    packet_iter = ccsdspy.decode_packets(str(src), max_packet_len=20, resync=True)
+    assert [p.header.sequence_id for p in packet_iter] == [0, 2]
+
+    version1 = bytearray(packets[1])
+    version1[0] |= 0x20
+    src.write_bytes(packets[0] + bytes(version1) + packets[2])
+    with pytest.raises(ValueError, match="offset 10: version 1 not in allowed versions 0"):
+        list(ccsdspy.decode_packets(str(src), versions=[0]))
+    assert [p.header.version for p in ccsdspy.decode_packets(str(src), versions=[0, 1])] == [0, 1, 0]
+
+
+def prefetch_threads() -> int:
+    """Number of running background decode threads, which are named on Linux."""
+    tasks = Path("/proc/self/task")
+    if not tasks.exists():
+        pytest.skip("thread names are only available on Linux")
+    count = 0
+    for task in tasks.iterdir():
+        try:
+            count += (task / "comm").read_text().strip() == "ccsds-prefetch"
+        except FileNotFoundError:
+            pass  # thread exited
+    return count
+
+
+def test_decode_packets_prefetch(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(b"".join(make_packet(i % 3, i, bytes([i % 256]) * 16) for i in range(1000)))
+
+    with pytest.raises(ValueError, match="prefetch must be > 0"):
+        ccsdspy.decode_packets(str(src), prefetch=0)
+
+    direct = [(p.offset, bytes(p.data)) for p in ccsdspy.decode_packets(str(src))]
+    for prefetch in [1, 7, 4096]:
+        packets = ccsdspy.decode_packets(str(src), prefetch=prefetch)
+        assert [(p.offset, bytes(p.data)) for p in packets] == direct
+        assert packets.summary().complete
+
+    # dropping the iterator mid-stream stops its thread, which is blocked on a full queue
+    before = prefetch_threads()
+    packets = ccsdspy.decode_packets(str(src), prefetch=1)
+    next(packets)
+    assert prefetch_threads() == before + 1
+    del packets
+    gc.collect()
+    deadline = time.monotonic() + 5
+    while prefetch_threads() > before and time.monotonic() < deadline:
+        time.sleep(0.01)
+    assert prefetch_threads() == before
+
+
+def test_decode_packets_truncated(tmp_path):
+    src = tmp_path / "packets.dat"
+    packets = [make_packet(1, i, bytes([i] * 8)) for i in range(3)]
+    src.write_bytes(b"".join(packets)[:-3])
+
+    partial = list(ccsdspy.decode_packets(str(src), truncated="partial"))
+    assert [p.truncated for p in partial] == [False, False, True]
+    assert bytes(partial[-1].data) == packets[2][:-3]
+
+    with pytest.raises(EOFError):
+        list(ccsdspy.decode_packets(str(src), truncated="raise"))
+
+
+def test_decode_packets_headers_only(tmp_path):
+    src = tmp_path / "packets.dat"
+    packets = [make_packet(1, i, bytes([i] * (8 + i))) for i in range(4)]
+    src.write_bytes(b"".join(packets)[:-3])
+
+    full = [(p.header.sequence_id, p.offset) for p in ccsdspy.decode_packets(str(src))]
+    headers = list(ccsdspy.decode_packets(str(src), headers_only=True))
+
+    assert [(p.header.sequence_id, p.offset) for p in headers] == full
+    assert [bytes(p.data) for p in headers] == [p[:6] for p in packets[:3]]
+    with pytest.raises(EOFError):
+        list(ccsdspy.decode_packets(str(src), headers_only=True, truncated="raise"))
+    with pytest.raises(ValueError):
+        ccsdspy.decode_packets(str(src), headers_only=True, fill=ccsdspy.FillDetector())
+
+
+def test_eps_product(tmp_path):
+    def record(cls, group, body):
+        return struct.pack(">BBBBI6s6s", cls, group, 0, 0, 20 + len(body), bytes(6), bytes(6)) + body
+
+    mphr = b"PRODUCT_NAME                  = AVHR_00_M01\nSPACECRAFT_ID                 = M01\n"
+    packets = [make_packet(103, seq, b"\x01\x02\x03") for seq in range(3)]
+    product = record(1, 0, mphr) + record(2, 4, b"")
+    product += b"".join(record(8, 4, packet) for packet in packets[:2])
+    product += record(8, 13, b"\x00")
+    product += record(8, 4, packets[2])
+    src = tmp_path / "AVHR_00_M01.nat"
+    src.write_bytes(product)
+
+    eps = ccsdspy.read_eps_product(str(src))
+    assert eps.mphr["SPACECRAFT_ID"] == "M01"
+    assert (eps.mdrs, eps.dummy_mdrs) == (3, 1)
+    decoded = list(ccsdspy.decode_packets(f"eps://{src}"))
+    assert [p.header.sequence_id for p in decoded] == [0, 1, 2]
+    assert bytes(decoded[0].data) == packets[0]
+
+
+def test_decode_packets_pcap(tmp_path):
+    def frame(payload, port):
+        udp = struct.pack(">HHHH", 40000, port, 8 + len(payload), 0) + payload
+        addr = bytes([127, 0, 0, 1])
+        ip = struct.pack(
+            ">BBHHHBBH4s4s", 0x45, 0, 20 + len(udp), 0, 0, 64, 17, 0, addr, addr
+        )
+        return b"\0" * 12 + b"\x08\x00" + ip + udp
+
+    stream = b"".join(make_packet(1, i, bytes([i] * 8)) for i in range(3))
+    frames = [frame(stream[:10], 5000), frame(b"noise", 6000), frame(stream[10:], 5000)]
+    capture = struct.pack("<IHHiIII", 0xA1B2C3D4, 2, 4, 0, 0, 65535, 1)
+    for f in frames:
+        capture += struct.pack("<IIII", 0, 0, len(f), len(f)) + f
+    src = tmp_path / "feed.pcap"
+    src.write_bytes(capture)
+
+    packets = list(ccsdspy.decode_packets(f"pcap://{src}?port=5000"))
+
+    assert [p.header.sequence_id for p in packets] == [0, 1, 2]
+    with pytest.raises(OSError):
+        ccsdspy.decode_packets(f"pcap://{src}?port=x")
+
+
+def test_decode_packets_udp_annotation(tmp_path):
+    def frame(payload):
+        udp = struct.pack(">HHHH", 40000, 5000, 8 + len(payload), 0) + payload
+        addr = bytes([127, 0, 0, 1])
+        ip = struct.pack(">BBHHHBBH4s4s", 0x45, 0, 20 + len(udp), 0, 0, 64, 17, 0, addr, addr)
+        return b"\0" * 12 + b"\x08\x00" + ip + udp
+
+    # 8 byte annotation header with the little-endian length of the packet at offset 4,
+    # followed by the packet and padding
+    packets = [make_packet(1, i, bytes([i] * 8)) for i in range(3)]
+    frames = [frame(struct.pack("<IHH", 0, len(p), 0) + p + bytes(4)) for p in packets]
+    capture = struct.pack("<IHHiIII", 0xA1B2C3D4, 2, 4, 0, 0, 65535, 1)
+    for f in frames:
+        capture += struct.pack("<IIII", 0, 0, len(f), len(f)) + f
+    src = tmp_path / "feed.pcap"
+    src.write_bytes(capture)
+
+    query = "port=5000&header_len=8&length_offset=4&length_size=2&byte_order=little"
+    decoded = list(ccsdspy.decode_packets(f"pcap://{src}?{query}"))
+
+    assert [p.header.sequence_id for p in decoded] == [0, 1, 2]
+    with pytest.raises(OSError):
+        ccsdspy.decode_packets(f"pcap://{src}?header_len=8&length_offset=8")
+
+
+def test_layout_calibration(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(make_packet(1, 0, bytes([0x12, 0x34, 0x80, 0x00])))
+    layout = ccsdspy.Layout(
+        [
+            ccsdspy.Field("raw", 0, 16),
+            ccsdspy.Field(
+                "poly", 0, 8, calibration=ccsdspy.Calibration.polynomial([1.0, 2.0])
+            ),
+            ccsdspy.Field(
+                "interp",
+                16,
+                8,
+                calibration=ccsdspy.Calibration.point_pair([(0, 0.0), (256, 1.0)]),
+            ),
+        ]
+    )
+
+    (packet,) = ccsdspy.decode_packets(str(src))
+
+    assert layout.extract(packet) == {"raw": 0x1234, "poly": 37.0, "interp": 0.5}
+
+
+def test_layout_states(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(b"".join(make_packet(1, i, bytes([i])) for i in range(3)))
+    layout = ccsdspy.Layout([ccsdspy.Field("mode", 0, 8, states={0: "OFF", 1: "ON"})])
+
+    columns = layout.extract_all(ccsdspy.decode_packets(str(src)))
+
+    assert columns == {"mode": ["OFF", "ON", None]}
+
+
+def test_layout_derived():
+    packet = ccsdspy.Packet.decode(make_packet(1, 0, bytes([0x03, 0xE8, 0x05])))
+    layout = ccsdspy.Layout(
+        [ccsdspy.Field("raw", 0, 16), ccsdspy.Field("status", 16, 8)],
+        derived=[
+            ccsdspy.Derived("volts", "raw * 0.0025 + 1.2"),
+            ccsdspy.Derived("enabled", "bit(status, 0) and volts > 2"),
+            ccsdspy.Derived("ratio", "raw / (status - 5)"),
+        ],
+    )
+
+    values = layout.extract(packet)
+
+    assert values["volts"] == pytest.approx(3.7)
+    assert values["enabled"] == 1
+    assert values["ratio"] is None
+    with pytest.raises(ValueError):
+        ccsdspy.Layout([ccsdspy.Field("raw", 0, 16)], [ccsdspy.Derived("x", "y + 1")])
+    # deeply nested expressions are rejected rather than overflowing the stack
+    for expr in ["(" * 100000 + "1" + ")" * 100000, "-" * 200000 + "1", "+".join("1" * 100000)]:
+        with pytest.raises(ValueError, match="nested"):
+            ccsdspy.Derived("x", expr)
+
+
+def test_layout_array_field():
+    packet = ccsdspy.Packet.decode(make_packet(1, 0, bytes([1, 0, 2, 0, 3, 0])))
+    layout = ccsdspy.Layout([ccsdspy.Field("samples", 0, 8, count=3, stride=16)])
+
+    assert layout.extract(packet) == {"samples": [1, 2, 3]}
+
+
+def test_layout_pack_roundtrip():
+    layout = ccsdspy.Layout(
+        [
+            ccsdspy.Field("flag", 0, 3),
+            ccsdspy.Field("count", 3, 13, byte_order="big"),
+            ccsdspy.Field("le", 16, 16, byte_order="little"),
+            ccsdspy.Field("fixed", 32, 16, kind="int", fraction_bits=8),
+            ccsdspy.Field("mode", 48, 8, states={0: "OFF", 1: "ON"}),
+            ccsdspy.Field(
+                "volts", 56, 8, calibration=ccsdspy.Calibration.polynomial([1.0, 0.5])
+            ),
+            ccsdspy.Field("f", 64, 32, kind="mil1750a"),
+        ]
+    )
+    values = {
+        "flag": 5,
+        "count": 0x1234,
+        "le": 0xABCD,
+        "fixed": -1.5,
+        "mode": "ON",
+        "volts": 11.0,
+        "f": 0.5,
+    }
+
+    data = layout.pack(values)
+    packet = ccsdspy.Packet.decode(make_packet(1, 0, data))
+
+    assert data[2:4] == bytes([0xCD, 0xAB])
+    assert data[8:12] == bytes([0x40, 0, 0, 0])
+    assert layout.extract(packet) == values
+    assert layout.pack({"mode": "ON"}, bytes(packet.data[6:])) == data
+    data = layout.fields[0].pack(data, 2)
+    assert data[0] >> 5 == 2
+    with pytest.raises(ValueError):
+        layout.pack({"flag": 8})
+
+
+def test_layout_check(tmp_path):
+    src = tmp_path / "packets.dat"
+    values = [5, 50, 95]
+    src.write_bytes(b"".join(make_packet(1, i, bytes([v])) for i, v in enumerate(values)))
+    limits = ccsdspy.AlarmLimits(red_low=0, yellow_low=10, yellow_high=80, red_high=90)
+    layout = ccsdspy.Layout([ccsdspy.Field("temp", 0, 8, limits=limits)])
+
+    violations = layout.check_all(ccsdspy.decode_packets(str(src)))
+
+    assert [(v.sequence_id, v.level, v.bound) for v in violations] == [
+        (0, "yellow", "low"),
+        (2, "red", "high"),
+    ]
+
+
+def test_load_mib(tmp_path):
+    tables = {
+        "pid": ["3\t25\t100\t0\t0\t1001\tHK report"],
+        "pcf": [
+            "TEMP\tTemperature\t\tdegC\t3\t4\t\t\t\tN\tR\tCAL1",
+            "MODE\tMode\t\t\t2\t8\t\t\t\tS\tR\tTXT1",
+        ],
+        "plf": ["TEMP\t1001\t16\t0", "MODE\t1001\t18\t0"],
+        "mcf": ["CAL1\tdeg\t-10\t0.5"],
+        "txp": ["TXT1\t0\t0\tOFF", "TXT1\t1\t1\tON"],
+    }
+    for name, rows in tables.items():
+        (tmp_path / f"{name}.dat").write_text("\n".join(rows) + "\n")
+
+    (packet,) = ccsdspy.load_mib(tmp_path)
+
+    assert (packet.spid, packet.apid, packet.service_type) == (1001, 100, 3)
+    assert [f.name for f in packet.layout.fields] == ["TEMP", "MODE"]
+    assert [f.bit_offset for f in packet.layout.fields] == [80, 96]
+    assert packet.layout.fields[1].states == {0: "OFF", 1: "ON"}
+
+
+def test_detect_interleave(tmp_path):
+    src = tmp_path / "cadus.dat"
+    frames = [bytes([0x40, i]) + bytes((i + j) % 256 for j in range(890)) for i in range(8)]
+    src.write_bytes(b"".join(ccsdspy.encode_cadu(f, interleave=4) for f in frames))
+
+    detection = ccsdspy.detect_interleave(str(src), 1024)
+
+    assert detection is not None
+    assert (detection.interleave, detection.ok) == (4, 8)
+
+
+def test_detect_framing(tmp_path):
+    src = tmp_path / "cadus.dat"
+    # scid 157, vcid 5
+    header = bytes([0x67, 0x45, 0, 0])
+    frames = [header + bytes([i, 0]) + bytes((i + j) % 256 for j in range(886)) for i in range(8)]
+    src.write_bytes(b"".join(ccsdspy.encode_cadu(f, interleave=4) for f in frames))
+
+    detection = ccsdspy.detect_framing(str(src))
+
+    assert (detection.asm_name, bytes(detection.asm)) == ("ccsds", b"\x1a\xcf\xfc\x1d")
+    assert (detection.cadu_len, detection.frame_len, detection.count) == (1024, 1020, 8)
+    assert (detection.randomized, detection.scid, detection.interleave) == (True, 157, 4)
+    assert repr(detection) == (
+        "FramingDetection(asm_name='ccsds', asm=0x1acffc1d, cadu_len=1024, frame_len=1020, "
+        "count=8, bit_offset=0, randomized=True, scid=157, interleave=4)"
+    )
+
+    src.write_bytes(b"".join(ccsdspy.encode_cadu(f, randomize=False) for f in frames))
+
+    detection = ccsdspy.detect_framing(str(src))
+
+    assert (detection.cadu_len, detection.frame_len) == (896, 892)
+    assert (detection.randomized, detection.scid, detection.interleave) == (False, 157, None)
+    assert repr(detection).endswith("randomized=False, scid=157, interleave=None)")
+
+
+def test_detect_asm_matches_bitwise_search(tmp_path):
+    def bitwise_hits(dat, pattern):
+        bits = "".join(f"{b:08b}" for b in dat)
+        target = "".join(f"{b:08b}" for b in pattern)
+        return [i for i in range(len(bits) - len(target) + 1) if bits.startswith(target, i)]
+
+    rng = random.Random(195)
+    patterns = [b"\x1a", b"\xaa\xaa", b"\xf0\xf0\xf0", b"\x1a\xcf\xfc\x1d", rng.randbytes(16)]
+    src = tmp_path / "dat"
+    for _ in range(300):
+        pattern = rng.choice(patterns)
+        target = "".join(f"{b:08b}" for b in pattern)
+        bits = "".join(rng.choice("01") for _ in range(rng.randrange(600)))
+        # plant markers at any bit, including back to back so they overlap for periodic
+        # patterns, at either end of the buffer and truncated by its end
+        for _ in range(rng.randrange(5)):
+            at = rng.randrange(len(bits) + 1)
+            bits = bits[:at] + target * rng.randint(1, 3) + bits[at:]
+        if rng.random() < 0.3:
+            bits = target + bits
+        bits += rng.choice([target, target[: rng.randrange(len(target))], ""])
+        bits += "0" * (-len(bits) % 8)
+        dat = int(bits, 2).to_bytes(len(bits) // 8, "big") if bits else b""
+        src.write_bytes(dat)
+
+        (detection,) = ccsdspy.detect_asm(str(src), candidates=[pattern])
+
+        hits = bitwise_hits(dat, pattern)
+        assert detection.count == len(hits), (dat.hex(), pattern.hex())
+        assert detection.bit_offset == (hits[0] % 8 if hits else 0)
+
+
+def test_detect_asm_repr(tmp_path):
+    src = tmp_path / "cadus.dat"
+    src.write_bytes((b"\x1a\xcf\xfc\x1d" + bytes(60)) * 8)
+
+    detection = ccsdspy.detect_asm(str(src))[0]
+
+    assert detection.asm == list(b"\x1a\xcf\xfc\x1d")
+    assert repr(detection) == (
+        "AsmDetection(name='ccsds', asm=0x1acffc1d, count=8, spacing=64, bit_offset=0, "
+        "locked=True)"
+    )
+
+
+def test_packet_offsets(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(b"".join(make_packet(1, i, bytes([i] * (i + 1))) for i in range(3)))
+
+    offsets = [p.offset for p in ccsdspy.decode_packets(str(src))]
+
+    assert offsets == [0, 7, 15]
+
+
+def test_packet_hexdump():
+    packet = ccsdspy.Packet.decode(make_packet(1, 0, b"AB\x00" * 6))
+
+    lines = packet.hexdump(width=16).splitlines()
+
+    assert lines[0] == "00000000  " + "41 42 00 " * 5 + "41  |AB.AB.AB.AB.AB.A|"
+    assert lines[1] == "00000010  42 00" + " " * 42 + "  |B.|"
+    assert "apid                  1" in packet.describe()
+
+
+def test_decoded_packet_to_dict():
+    packet = ccsdspy.Packet.decode(make_packet(5, 3, b"\x01\x02"))
+
+    decoded = ccsdspy.DecodedPacket(42, 7, packet)
+    dct = decoded.to_dict()
+
+    assert (decoded.vcid, decoded.packet.header.apid) == (7, 5)
+    assert (dct["scid"], dct["vcid"], dct["apid"], dct["sequence_id"]) == (42, 7, 5, 3)
+    assert dct["data"] == b"\x01\x02"
+    # AOS spacecraft ids are 14 bits
+    assert ccsdspy.DecodedPacket(16383, 63, packet).scid == 16383
+    with pytest.raises(ValueError, match="invalid scid value; expected 0..16384, got 16384"):
+        ccsdspy.DecodedPacket(16384, 0, packet)
+    with pytest.raises(ValueError, match="invalid vcid"):
+        ccsdspy.DecodedPacket(0, 64, packet)
+
+
+def test_packet_iterator_peek(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(b"".join(make_packet(1, i, bytes([i])) for i in range(2)))
+    packets = ccsdspy.decode_packets(str(src))
+
+    assert packets.peek().header.sequence_id == 0
+    assert next(packets).header.sequence_id == 0
+    assert next(packets).header.sequence_id == 1
+    assert packets.peek() is None
+
+
+def test_packet_field_accessors():
+    payload = (
+        b"\x12\x34\x56\x78"
+        + b"\xff\x80\x00"
+        + struct.pack(">f", 1.5)
+        + struct.pack(">d", -2.25)
+        + b"\x40\x00\x00\x01"
+    )
+    packet = ccsdspy.Packet.decode(make_packet(1, 0, payload))
+
+    # big-endian, most significant bit first, at any bit offset
+    assert packet.u(0, 16) == 0x1234
+    assert packet.u(0, 32) == 0x12345678
+    assert packet.u(4, 8) == 0x23
+    assert packet.u(12, 1) == 0
+    assert packet.u(11, 1) == 1
+    assert packet.u(0, 64) == int.from_bytes(payload[:8], "big")
+
+    assert packet.i(32, 8) == -1
+    assert packet.i(40, 16) == -32768
+    assert packet.i(36, 4) == -1
+    assert packet.i(0, 16) == 0x1234
+    assert packet.i(40, 1) == -1
+
+    assert packet.f32(56) == 1.5
+    assert packet.f64(88) == -2.25
+    assert packet.f32(152, mil1750a=True) == 1.0
+
+    bits = len(payload) * 8
+    assert packet.u(bits - 8, 8) == 0x01
+    for offset, bit_len in [(bits - 7, 8), (bits, 1), (2**63, 8), (2**64 - 1, 64)]:
+        with pytest.raises(ValueError, match="exceeds data length"):
+            packet.u(offset, bit_len)
+        with pytest.raises(ValueError, match="exceeds data length"):
+            packet.i(offset, bit_len)
+    for bit_len in [0, 65]:
+        with pytest.raises(ValueError, match="invalid bit_len"):
+            packet.u(0, bit_len)
+    with pytest.raises(ValueError, match="exceeds data length"):
+        packet.f64(bits - 32)
+    with pytest.raises(OverflowError):
+        packet.u(-1, 8)
+
+
+def test_reprs_use_python_values(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(make_packet(1, 0, b"\x01\x02\x03"))
+
+    (packet,) = ccsdspy.decode_packets(str(src))
+
+    assert str(packet).endswith(
+        "has_secondary_header=False, apid=1, sequence_flags=3, sequence_id=0, len_minus1=2), "
+        "data_len=9, offset=0)"
+    )
+    assert repr(ccsdspy.AlarmLimits(red_high=10)) == (
+        "AlarmLimits(red_low=None, yellow_low=None, yellow_high=None, red_high=10.0)"
+    )
+    field = ccsdspy.Field("mode", 0, 8, states={1: "it's on"})
+    assert repr(field) == (
+        "Field(name='mode', bit_offset=0, bit_len=8, kind='uint', calibration=None, "
+        "states={1: \"it's on\"}, limits=None, count=None, stride=8, byte_order='big', "
+        "fraction_bits=0)"
+    )
+    registry = ccsdspy.TimecodeRegistry(default="cds")
+    assert repr(registry) == "TimecodeRegistry(apids=0, default=('cds', 0))"
+
+
+def test_group_packets_by_apid(tmp_path):
+    src = tmp_path / "packets.dat"
+    apids = [1, 1, 2, 1]
+    src.write_bytes(b"".join(make_packet(a, i, bytes([i])) for i, a in enumerate(apids)))
+
+    groups = ccsdspy.group_packets_by_apid(ccsdspy.decode_packets(str(src)))
+
+    assert [[p.header.sequence_id for p in g] for g in groups] == [[0, 1], [2], [3]]
+    assert list(ccsdspy.group_packets_by_apid([])) == []
+
+
+def test_window_packets_by_time(tmp_path):
+    def packet(seq, millis, secondary_header=True):
+        cds = struct.pack(">HIH", 21915, millis, 0)
+        dat = bytearray(make_packet(1, seq, cds + bytes(4)))
+        if secondary_header:
+            dat[0] |= 0x08
+        return bytes(dat)
+
+    src = tmp_path / "packets.dat"
+    src.write_bytes(
+        packet(0, 10_000)
+        + packet(1, 10_999)  # last millisecond of the window
+        + packet(2, 50_000, secondary_header=False)  # no time, stays in the window
+        + packet(3, 11_000)  # first millisecond of the next window
+        + b"\x08\x01\xc0\x04\x00\x00\x00"  # secondary header too short for a time
+        + packet(5, 9_000)  # out of order, starts a new window
+    )
+
+    groups = ccsdspy.window_packets_by_time(ccsdspy.decode_packets(str(src)), 1.0)
+
+    assert [[p.header.sequence_id for p in g] for g in groups] == [[0, 1, 2], [3, 4], [5]]
+    with pytest.raises(ValueError, match="invalid window"):
+        ccsdspy.window_packets_by_time([], 0)
+
+
+def test_packet_writer_segments(tmp_path):
+    dest = tmp_path / "packets.dat"
+    with ccsdspy.PacketWriter(str(dest), max_data_len=4) as writer:
+        assert writer.write(5, b"abc") == 1
+        assert writer.write(5, b"0123456789") == 3
+
+    packets = list(ccsdspy.decode_packets(str(dest)))
+
+    assert [p.header.sequence_flags for p in packets] == [3, 1, 0, 2]
+    assert [p.header.sequence_id for p in packets] == [0, 1, 2, 3]
+    assert b"".join(bytes(p.data[6:]) for p in packets[1:]) == b"0123456789"
+
+
+def test_packet_archive_roundtrip(tmp_path):
+    dest = tmp_path / "packets.ccsdsza"
+    packets = [make_packet(1 + i // 50, i, bytes(100)) for i in range(100)]
+    with ccsdspy.PacketArchiveWriter(str(dest), chunk_size=1024) as writer:
+        assert writer.write_all(packets) == 100
+
+    archive = ccsdspy.PacketArchive(str(dest))
+
+    assert len(archive) == 100
+    assert dest.stat().st_size < sum(len(p) for p in packets) / 3
+    assert [p.encode() for p in archive.read()] == packets
+    assert [c.apids for c in archive.chunks][0] == [1]
+    assert [c.apids for c in archive.chunks][-1] == [2]
+    assert [p.header.sequence_id for p in archive.read(apids=[2])] == list(range(50, 100))
+
+    # packets are archived as their bytes whichever way they were decoded
+    with ccsdspy.PacketArchiveWriter(str(dest)) as writer:
+        writer.write_all(ccsdspy.Packet.decode(p) for p in packets[:3])
+    assert [p.encode() for p in ccsdspy.PacketArchive(str(dest)).read()] == packets[:3]
+
+
+def test_decode_packets_fill(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(
+        make_packet(1, 0, bytes(range(64)))
+        + make_packet(1, 1, b"\x01\x02" + b"\xaa" * 62)
+        + make_packet(1, 2, bytes(range(32)) + bytes(32))
+    )
+    fill = ccsdspy.FillDetector(threshold=0.9)
+
+    packets = ccsdspy.decode_packets(str(src), fill=fill, fill_policy="drop")
+
+    assert [p.header.sequence_id for p in packets] == [0, 2]
+    ((apid, pattern, fraction, offset),) = packets.fill_violations()
+    assert (apid, pattern, offset) == (1, 0xAA, 70)
+    assert fraction == pytest.approx(62 / 64)
+
+
+def test_rewrite_packets(tmp_path):
+    src = tmp_path / "packets.dat"
+    dest = tmp_path / "rewritten.dat"
+    src.write_bytes(b"".join(make_packet(a, 0, b"data") for a in [1, 2]))
+
+    assert ccsdspy.rewrite_packets(str(src), str(dest), apid_map={1: 100}) == (2, 1)
+
+    packets = list(ccsdspy.decode_packets(str(dest)))
+    assert [p.header.apid for p in packets] == [100, 2]
+    assert [bytes(p.data[6:]) for p in packets] == [b"data", b"data"]
+
+
+def test_compressed_output(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(b"".join(make_packet(1, seq, b"data" * 64) for seq in range(100)))
+
+    dest = tmp_path / "rewritten.dat.gz"
+    assert ccsdspy.rewrite_packets(str(src), str(dest)) == (100, 0)
+    assert gzip.decompress(dest.read_bytes()) == src.read_bytes()
+    assert dest.stat().st_size < src.stat().st_size
+
+    dest = tmp_path / "rewritten.dat"
+    ccsdspy.rewrite_packets(str(src), str(dest), compression="gzip")
+    assert gzip.decompress(dest.read_bytes()) == src.read_bytes()
+
+    dest = tmp_path / "rewritten.dat.zst"
+    ccsdspy.rewrite_packets(str(src), str(dest))
+    assert dest.read_bytes()[:4] == b"\x28\xb5\x2f\xfd"  # zstd frame magic
+    assert dest.stat().st_size < src.stat().st_size
+
+    with pytest.raises(ValueError):
+        ccsdspy.rewrite_packets(str(src), str(dest), compression="bzip2")
+
+
+def test_validate(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(
+        make_packet(1, 0, b"data") + make_packet(1, 5, b"data") + make_packet(2, 0, b"longer data")
+    )
+
+    profile = ccsdspy.ValidationProfile(apids={1, 2}, lengths={1: (10, 10)})
+    report = ccsdspy.validate(str(src), profile)
+    assert report.passed
+    assert report.packets == 3
+
+    profile = ccsdspy.ValidationProfile(
+        apids={1}, lengths={1: (6, 8)}, secondary_header=False, max_gap=2
+    )
+    report = ccsdspy.validate(str(src), profile)
+    assert not report.passed
+    assert report.counts == {"apid": 1, "length": 2, "gap": 1}
+    assert [(v.check, v.apid, v.offset) for v in report.violations] == [
+        ("length", 1, 0),
+        ("length", 1, 10),
+        ("gap", 1, 10),
+        ("apid", 2, 20),
+    ]
+    assert type(report.violations[0]) is ccsdspy.ValidationViolation
+
+    with pytest.raises(ValueError):
+        ccsdspy.ValidationProfile(monotonic=True)
+
+
+def test_decode_grb(tmp_path):
+    def grb_packet(flags, seq, data):
+        # day 1 and 500 ms since J2000, GRB version 2, system environment 5
+        secondary = struct.pack(">HIBB", 1, 500, 2 << 3, 0x05)
+        hdr = struct.pack(">HHH", 0x0800 | 0x101, flags << 14 | seq, len(secondary) + len(data) - 1)
+        return hdr + secondary + data
+
+    packets = [
+        grb_packet(1, 0, b"a" * 8),
+        grb_packet(0, 1, b"b" * 8),
+        grb_packet(2, 2, b"c" * 8),
+        grb_packet(3, 3, b"d" * 8),
+    ]
+    frames = [bytes([0x40, 0x41, 0, 0, i, 0, 0, 0]) + p for i, p in enumerate(packets)]
+    src = tmp_path / "rhcp.dat"
+    src.write_bytes(b"".join(ccsdspy.encode_cadu(f) for f in frames))
+
+    payloads = list(ccsdspy.decode_grb(1, rhcp=str(src), cadu_len=len(frames[0]) + 4))
+    assert [(p.channel, p.apid, p.sequence_id, p.packets, p.complete) for p in payloads] == [
+        ("rhcp", 0x101, 0, 3, True),
+        ("rhcp", 0x101, 3, 1, True),
+    ]
+    assert payloads[0].data == b"a" * 8 + b"b" * 8 + b"c" * 8
+    assert payloads[0].time == 946728000000 + 86400000 + 500
+    assert (payloads[0].version, payloads[0].system_environment) == (2, 5)
+    with pytest.raises(ValueError):
+        ccsdspy.decode_grb(1)
+
+    # a first header pointer beyond the end of the frame data
+    src.write_bytes(ccsdspy.encode_cadu(bytes([0x40, 0x41, 0, 0, 0, 0, 0x07, 0x00]) + bytes(32)))
+    with pytest.raises(RuntimeError, match="rhcp channel"):
+        list(ccsdspy.decode_grb(1, rhcp=str(src), cadu_len=44))
+
+
+def test_idle_accounting(tmp_path):
+    src = tmp_path / "cadus.dat"
+    zone = make_packet(1, 0, bytes(14)) + make_packet(0x7FF, 0, bytes(14))
+    frames = [
+        bytes([0x40, 0x41, 0, 0, 0, 0, 0x00, 0x00]) + zone,
+        bytes([0x40, 0x7F, 0, 0, 0, 0]) + bytes(42),
+        bytes([0x40, 0x41, 0, 0, 1, 0, 0x07, 0xFE]) + bytes(40),
+    ]
+    src.write_bytes(b"".join(ccsdspy.encode_cadu(f) for f in frames))
+
+    acct = ccsdspy.idle_accounting(str(src), 52)
+
+    assert (acct.frames, acct.fill_frames, acct.packets, acct.fill_packets) == (3, 1, 1, 1)
+    assert (acct.packet_bytes, acct.fill_packet_bytes, acct.pad_bytes) == (20, 20, 40)
+    assert (acct.fill_frame_bytes, acct.overhead_bytes) == (52, 24)
+    assert acct.efficiency == pytest.approx(20 / 156)
+
+
+def test_kafka_frames_and_packets():
+    frame = bytes([0x40, 0x41, 0, 0, 5, 0, 0, 0]) + make_packet(1, 0, bytes(8))
+    frames = list(ccsdspy.decode_kafka_frames([ccsdspy.encode_cadu(frame), b"\x00"]))
+
+    (decoded,) = frames
+    assert (decoded.header.vcid, decoded.header.counter) == (1, 5)
+    assert bytes(decoded.data) == frame
+    assert decoded.data is decoded.data
+    assert bytes(decoded) == frame
+
+    class Producer:
+        def __init__(self):
+            self.sent = []
+
+        def send(self, topic, value=None, key=None):
+            self.sent.append((topic, key, value))
+
+        def flush(self):
+            pass
+
+    producer = Producer()
+    packets = [ccsdspy.Packet.decode(make_packet(1, 0, b"\x01\x02"))]
+    assert ccsdspy.publish_kafka_packets(packets, producer, "packets", "json") == 1
+
+    ((topic, key, value),) = producer.sent
+    assert (topic, key) == ("packets", b"1")
+    assert json.loads(value)["data"] == "0102"
+    assert ccsdspy.publish_kafka_packets(packets, producer, "packets", "raw") == 1
+    assert producer.sent[1][2] == make_packet(1, 0, b"\x01\x02")
+
+
+def test_publish_redis_packets():
+    class Redis:
+        def __init__(self):
+            self.streams = {}
+
+        def xadd(self, name, fields, maxlen=None, approximate=True):
+            self.streams.setdefault(name, []).append(fields["packet"])
+
+    client = Redis()
+    raw = [make_packet(apid, 0, b"\x01") for apid in [1, 2, 1]]
+    packets = [ccsdspy.Packet.decode(p) for p in raw]
+
+    assert ccsdspy.publish_redis_packets(packets, client, apids=[1]) == 2
+    assert client.streams == {"ccsds:apid:1": [raw[0], raw[2]]}
+
+
+def test_publish_rate():
+    class Redis:
+        def xadd(self, name, fields, maxlen=None, approximate=True):
+            pass
+
+    # 56 bits per packet at 560 bits/s paces packets 0.1s apart
+    packets = [ccsdspy.Packet.decode(make_packet(1, i, b"\x01")) for i in range(3)]
+    start = time.monotonic()
+    assert ccsdspy.publish_redis_packets(packets, Redis(), rate=560) == 3
+    assert time.monotonic() - start >= 0.18
+    with pytest.raises(ValueError):
+        ccsdspy.publish_redis_packets(packets, Redis(), rate="later")
+
+
+def test_decode_packets_tcp_timeout():
+    packets = [make_packet(apid, i, bytes([i]) * 4) for i, apid in enumerate([1, 2, 1])]
+    with socket.socket() as server:
+        server.bind(("127.0.0.1", 0))
+        server.listen()
+        port = server.getsockname()[1]
+
+        decoded = ccsdspy.decode_packets(f"tcp://127.0.0.1:{port}", timeout=0.2)
+        conn, _ = server.accept()
+        with conn:
+            # nothing sent yet
+            with pytest.raises(TimeoutError):
+                next(decoded)
+            assert decoded.poll() is None
+
+            conn.sendall(packets[0])
+            assert bytes(next(decoded).data) == packets[0]
+            assert decoded.poll() is None
+
+            conn.sendall(b"".join(packets[1:]))
+        # the stream ends when the server closes the connection
+        assert [bytes(p.data) for p in decoded] == packets[1:]
+        assert decoded.summary().complete
+
+
+def test_aio_decode_packets(tmp_path):
+    src = tmp_path / "packets.dat"
+    packets = [make_packet(1, i, bytes([i]) * 4) for i in range(20)]
+    src.write_bytes(b"".join(packets))
+
+    async def iterate():
+        return [bytes(p.data) async for p in ccsdspy.aio.decode_packets(str(src))]
+
+    async def gather():
+        # concurrent __anext__ calls each take the next packet
+        decoded = ccsdspy.aio.decode_packets(str(src), prefetch=1)
+        return await asyncio.gather(*(decoded.__anext__() for _ in range(len(packets))))
+
+    async def stream():
+        reader = asyncio.StreamReader()
+        for packet in packets[:3]:
+            reader.feed_data(packet[:5])
+            reader.feed_data(packet[5:])
+        reader.feed_eof()
+        return [bytes(p.data) async for p in ccsdspy.aio.decode_packets(reader)]
+
+    assert asyncio.run(iterate()) == packets
+    assert sorted(bytes(p.data) for p in asyncio.run(gather())) == packets
+    assert asyncio.run(stream()) == packets[:3]
+
+
+def test_serve_packets(tmp_path):
+    src = tmp_path / "packets.dat"
+    packets = [make_packet(apid, i, bytes([i])) for i, apid in enumerate([1, 2, 1])]
+    src.write_bytes(b"".join(packets))
+    with socket.socket() as sock:
+        sock.bind(("127.0.0.1", 0))
+        port = sock.getsockname()[1]
+
+    served = []
+    server = threading.Thread(
+        target=lambda: served.append(
+            ccsdspy.serve_packets(
+                str(src), f"127.0.0.1:{port}", serialization="raw", apids=[1]
+            )
+        )
+    )
+    server.start()
+    for _ in range(50):
+        try:
+            client = socket.create_connection(("127.0.0.1", port))
+            break
+        except ConnectionRefusedError:
+            time.sleep(0.1)
+    with client:
+        # the example key and accept value from RFC 6455
+        client.sendall(
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n"
+            b"Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n"
+            b"Sec-WebSocket-Version: 13\r\n\r\n"
+        )
+        received = b""
+        while not received.endswith(b"\x88\x00"):
+            chunk = client.recv(4096)
+            if not chunk:
+                break
+            received += chunk
+    server.join()
+
+    response, messages = received.split(b"\r\n\r\n", 1)
+    assert response.startswith(b"HTTP/1.1 101")
+    assert b"Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=" in response
+    # unmasked binary frames of each APID 1 packet, then a close frame
+    assert messages == b"".join(
+        bytes([0x82, len(p)]) + p for p in [packets[0], packets[2]]
+    ) + b"\x88\x00"
+    assert served == [2]
+
+
+def test_packets_to_dataframe(tmp_path):
+    pytest.importorskip("pandas")
+    src = tmp_path / "packets.dat"
+    apids = [1, 2, 1]
+    src.write_bytes(b"".join(make_packet(a, i, bytes([i, 0])) for i, a in enumerate(apids)))
+    layout = ccsdspy.Layout([ccsdspy.Field("first", 0, 8)])
+
+    df = ccsdspy.packets_to_dataframe(str(src), fields=layout, apids=[1])
+
+    assert list(df["sequence_id"]) == [0, 2]
+    assert list(df["offset"]) == [0, 16]
+    assert list(df["first"]) == [0, 2]
+
+
+def test_pipeline(tmp_path):
+    srcs = [tmp_path / f"packets{i}.dat" for i in range(2)]
+    for src in srcs:
+        src.write_bytes(b"".join(make_packet(a, 0, b"\x00") for a in [1, 2]))
+    pipeline = ccsdspy.Pipeline(apids={1, 2}, frame_len=1020)
+
+    for src in srcs:
+        assert [p.header.apid for p in pipeline.decode_packets(str(src))] == [1, 2]
+    with pytest.raises(TypeError):
+        ccsdspy.Pipeline(bogus=1)
+
+
+def test_process_files(tmp_path):
+    srcs = [tmp_path / f"packets{i}.dat" for i in range(3)]
+    for src in srcs:
+        src.write_bytes(b"".join(make_packet(a, i, b"\x00") for i, a in enumerate([1, 2, 1])))
+    dest_dir = tmp_path / "out"
+    dest_dir.mkdir()
+    pipeline = ccsdspy.Pipeline(filter="apid == 1")
+
+    result = ccsdspy.process_files(srcs + [tmp_path / "missing.dat"], pipeline, input_format="packets", dest_dir=dest_dir, jobs=2)
+
+    assert (result.total.items, result.failed) == (6, 1)
+    assert [f.summary.items for f in result.files] == [2, 2, 2, 0]
+    assert (dest_dir / "packets0.dat.packets").stat().st_size == 14
+    assert json.loads(result.to_json())["failed"] == 1
+
+
+def test_packet_filter(tmp_path):
+    src = tmp_path / "packets.dat"
+    apids = [1289, 1290, 7, 1289]
+    src.write_bytes(b"".join(make_packet(a, i, bytes([i])) for i, a in enumerate(apids)))
+
+    packets = ccsdspy.decode_packets(str(src), filter="apid in (1289, 1290) and seq != 0x3")
+
+    assert [p.header.sequence_id for p in packets] == [0, 1]
+    assert ccsdspy.PacketFilter("not apid == 7").matches(ccsdspy.Packet.decode(make_packet(1, 0, b"\x00")))
+    with pytest.raises(ValueError):
+        ccsdspy.PacketFilter("apid in (1,")
+    # deeply nested expressions are rejected rather than overflowing the stack
+    for expr in [
+        "(" * 100000 + "apid" + ")" * 100000,
+        "not " * 200000 + "apid == 1",
+        " or ".join(["apid == 1"] * 100000),
+    ]:
+        with pytest.raises(ValueError, match="nested"):
+            ccsdspy.PacketFilter(expr)
+    assert ccsdspy.PacketFilter("(" * 50 + "apid == 1" + ")" * 50).expr
+
+
+def test_map_batches(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(b"".join(make_packet(1, i, bytes([i])) for i in range(5)))
+    sizes = []
+
+    def stage(batch):
+        sizes.append(len(batch))
+        return [p for p in batch if p.header.sequence_id % 2 == 0]
+
+    packets = ccsdspy.map_batches(ccsdspy.decode_packets(str(src)), stage, batch_size=2)
+
+    assert [p.header.sequence_id for p in packets] == [0, 2, 4]
+    assert sizes == [2, 2, 1]
+
+
+def test_pass_report():
+    report = ccsdspy.pass_report(
+        fixture_path("snpp_synchronized_cadus.dat"), 157, 1024, interleave=4, timecode="cds"
+    )
+
+    assert report.cadus == report.frames > 0
+    assert report.frames == report.fill_frames + sum(v.frames for v in report.vcids)
+    assert report.packets == sum(a.packets for a in report.apids) > 0
+    assert report.start_time <= report.end_time
+    assert json.loads(report.to_json())["scid"] == 157
+
+
+def test_write_gap_report_csv_json(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(b"".join(make_packet(1, seq, b"\x00") for seq in [0, 3]))
+    empty = tmp_path / "empty.dat"
+    empty.write_bytes(b"")
+
+    assert ccsdspy.write_gap_report(str(src), str(tmp_path / "gaps.csv")) == 1
+    assert (tmp_path / "gaps.csv").read_text().splitlines() == [
+        "apid,start_sequence_id,end_sequence_id,count,start_offset,end_offset,start_time,end_time",
+        "1,0,3,2,0,7,,",
+    ]
+    assert ccsdspy.write_gap_report(str(src), str(tmp_path / "gaps.json"), format="json") == 1
+    (gap,) = json.loads((tmp_path / "gaps.json").read_text())
+    assert (gap["apid"], gap["count"], gap["end_offset"], gap["start_time"]) == (1, 2, 7, None)
+
+    assert ccsdspy.write_gap_report(str(empty), str(tmp_path / "empty.csv")) == 0
+    assert (tmp_path / "empty.csv").read_text().count("\n") == 1
+    assert ccsdspy.write_gap_report(str(empty), str(tmp_path / "empty.json"), format="json") == 0
+    assert json.loads((tmp_path / "empty.json").read_text()) == []
+
+
+def test_write_gap_report_edos(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(b"".join(make_packet(1, seq, b"\x00") for seq in [0, 3, 4, 6]))
+    dest = tmp_path / "gaps.edos"
+
+    assert ccsdspy.write_gap_report(str(src), str(dest), format="edos") == 2
+
+    dat = dest.read_bytes()
+    assert len(dat) == 8 + 2 * 32
+    assert dat[:8] == bytes([0, 0, 0, 1, 0, 0, 0, 2])
+    assert dat[8:24] == bytes([0, 0, 0, 1]) + (7).to_bytes(8, "big") + bytes([0, 0, 0, 2])
+
+
+def test_frame_gaps(tmp_path):
+    src = tmp_path / "cadus.dat"
+    frames = [bytes([0x40, 0x41, 0, 0, c, 0]) + bytes(42) for c in [0, 1, 4]]
+    src.write_bytes(b"".join(ccsdspy.encode_cadu(f) for f in frames))
+
+    frames = ccsdspy.decode_frames(str(src), 48)
+    assert len(list(frames)) == 3
+
+    (gap,) = frames.gaps()
+    assert (gap.scid, gap.vcid, gap.start_counter, gap.end_counter, gap.count) == (1, 1, 1, 4, 2)
+    assert (gap.start_offset, gap.end_offset) == (52, 104)
+    assert frames.summary().missing == 2
+
+
+def test_decode_frames_frame_cache(tmp_path):
+    src = tmp_path / "cadus.dat"
+    frames = [bytes([0x40, 0x41, 0, 0, c, 0]) + bytes(42) for c in range(4)]
+    src.write_bytes(b"".join(ccsdspy.encode_cadu(f) for f in frames))
+
+    frames = ccsdspy.decode_frames(str(src), 48, frame_cache=2)
+    list(frames)
+
+    assert [f.header.counter for f in frames.cached_frames()] == [2, 3]
+    assert frames.cached_frame(1, 3).offset == 156
+    assert frames.cached_frame(1, 0) is None
+    with pytest.raises(ValueError):
+        ccsdspy.decode_frames(str(src), 48).cached_frames()
+
+
+def test_cancel(tmp_path):
+    packet_src = tmp_path / "packets.dat"
+    packet_src.write_bytes(b"".join(make_packet(1, i, bytes([i])) for i in range(8)))
+    frame_src = tmp_path / "cadus.dat"
+    frames = [bytes([0x40, 0x41, 0, 0, c, 0]) + bytes(42) for c in range(8)]
+    frame_src.write_bytes(b"".join(ccsdspy.encode_cadu(f) for f in frames))
+
+    for prefetch in [None, 2]:
+        iterators = [
+            ccsdspy.decode_packets(str(packet_src), prefetch=prefetch),
+            ccsdspy.decode_frames(str(frame_src), 48, prefetch=prefetch),
+        ]
+        for items in iterators:
+            next(items)
+            assert items.peek() is not None
+            assert not items.summary().cancelled
+
+            items.cancel()
+
+            # the peeked item is discarded and iteration ends
+            assert items.summary().cancelled
+            assert items.peek() is None
+            assert list(items) == []
+            with pytest.raises(StopIteration):
+                next(items)
+            assert not items.summary().complete
+
+
+def test_split_cadus_by_vcid(tmp_path):
+    src = tmp_path / "cadus.dat"
+    vcids = [1, 2, 1, 63, 2, 1]
+    cadus = [
+        ccsdspy.encode_cadu(bytes([0x40, 0x40 | v, 0, 0, i, 0]) + bytes([i]) * 42)
+        for i, v in enumerate(vcids)
+    ]
+    # garbage before the first ASM is skipped
+    src.write_bytes(b"\x00\x01\x02" + b"".join(cadus))
+
+    counts = ccsdspy.split_cadus_by_vcid(str(src), tmp_path / "out", 52)
+
+    assert counts == {1: 3, 2: 2, 63: 1}
+    assert sorted(p.name for p in (tmp_path / "out").iterdir()) == [
+        "vcid01.dat",
+        "vcid02.dat",
+        "vcid63.dat",
+    ]
+    for vcid in counts:
+        expected = b"".join(c for c, v in zip(cadus, vcids) if v == vcid)
+        assert (tmp_path / "out" / f"vcid{vcid:02}.dat").read_bytes() == expected
+
+    counts = ccsdspy.split_cadus_by_vcid(
+        str(src), tmp_path / "gz", 52, vcids=[2], compression="gzip"
+    )
+
+    assert counts == {2: 2}
+    assert [p.name for p in (tmp_path / "gz").iterdir()] == ["vcid02.dat.gz"]
+    written = gzip.decompress((tmp_path / "gz" / "vcid02.dat.gz").read_bytes())
+    assert written == cadus[1] + cadus[4]
+
+
+def test_decode_frames_headers_only():
+    src = fixture_path("snpp_synchronized_cadus.dat")
+
+    full = [(f.header.vcid, f.header.counter, f.offset) for f in ccsdspy.decode_frames(src, 1020)]
+    frames = list(ccsdspy.decode_frames(src, 1020, headers_only=True))
+
+    assert [(f.header.vcid, f.header.counter, f.offset) for f in frames] == full
+    assert all(len(f.data) == 6 for f in frames)
+    with pytest.raises(ValueError):
+        ccsdspy.decode_frames(src, 1020, headers_only=True, rs_check_only=True)
+
+
+def test_missing_frames_counter_bits():
+    assert ccsdspy.missing_frames(0, 0xFFFFFF) == 0
+    assert ccsdspy.missing_frames(1, 0xFF, counter_bits=8) == 1
+    assert ccsdspy.missing_frames(0x1000000, 0xFFFFFF, counter_bits=28) == 0
+    with pytest.raises(ValueError):
+        ccsdspy.missing_frames(1, 0, counter_bits=33)
+
+
+def test_secondary_header_policy(tmp_path):
+    src = tmp_path / "packets.dat"
+    # make_packet never sets the secondary header flag
+    src.write_bytes(b"".join(make_packet(a, 0, b"\x00") for a in [1, 2, 1]))
+
+    packets = ccsdspy.decode_packets(
+        str(src), secondary_headers={1: True, 2: False}, secondary_header_policy="drop"
+    )
+
+    assert [p.header.apid for p in packets] == [2]
+    assert packets.secondary_header_violations() == [(1, 0), (1, 14)]
+    with pytest.raises(ValueError):
+        list(ccsdspy.decode_packets(str(src), secondary_headers={1: True}, secondary_header_policy="raise"))
+
+
+def test_decode_error_position(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(make_packet(1, 0, bytes(4)) + make_packet(1, 1, bytes(8)))
+
+    packets = ccsdspy.decode_packets(
+        str(src), apid_lengths={1: (None, 10)}, length_policy="raise"
+    )
+
+    assert next(packets).header.sequence_id == 0
+    with pytest.raises(ValueError, match="near offset 10, apid 1, sequence id 1"):
+        next(packets)
+
+
+def test_decode_packets_progress(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(b"".join(make_packet(1, i, bytes(4)) for i in range(4)))
+
+    packets = ccsdspy.decode_packets(str(src))
+    assert packets.progress() == 0.0
+    next(packets)
+    assert packets.progress() == 0.25
+    assert operator.length_hint(packets) == 3
+    list(packets)
+    assert packets.progress() == 1.0
+
+
+def test_decode_packets_sample(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(b"".join(make_packet(1, i, bytes(4)) for i in range(10)))
+
+    packets = ccsdspy.decode_packets(str(src), sample=3)
+    assert [p.header.sequence_id for p in packets] == [0, 3, 6, 9]
+
+    first = [p.header.sequence_id for p in ccsdspy.decode_packets(str(src), probability=0.5, seed=7)]
+    second = [p.header.sequence_id for p in ccsdspy.decode_packets(str(src), probability=0.5, seed=7)]
+    assert first == second
+    with pytest.raises(ValueError):
+        ccsdspy.decode_packets(str(src), sample=2, probability=0.5)
+
+
+def test_decode_packets_quicklook(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(b"".join(make_packet(5, i, bytes([i] * 8)) for i in range(4)))
+
+    config = ccsdspy.QuickLookConfig(offset=2, width=6, bit_depth=8, decimate=2)
+    packets = ccsdspy.decode_packets(str(src), quicklook={5: config}, filter="apid == 1")
+    list(packets)
+    image = packets.quicklook()[5]
+
+    assert (image.packets, image.width, image.height) == (4, 3, 2)
+    assert image.lines == [[0, 0, 0], [2, 2, 2]]
+
+
+def test_decode_packets_anomalies(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(make_packet(1, 0, bytes(4)) + make_packet(1, 3, bytes(4)) + make_packet(2, 0, bytes(4)))
+
+    collector = ccsdspy.AnomalyCollector()
+    list(ccsdspy.decode_packets(str(src), apid_lengths={2: (12, None)}, anomalies=collector))
+
+    assert len(collector) == 2
+    gap, length = collector.drain()
+    assert (gap.kind, gap.severity, gap.apid, gap.offset) == ("packet_gap", "warning", 1, 10)
+    assert (length.kind, length.apid, length.offset) == ("length", 2, 20)
+    assert len(collector) == 0
+
+
+def test_decode_packets_apid_lengths(tmp_path):
+    src = tmp_path / "packets.dat"
+    src.write_bytes(make_packet(1, 0, bytes(2)) + make_packet(1, 1, bytes(4)) + make_packet(2, 2, bytes(8)))
+
+    packets = ccsdspy.decode_packets(str(src), apid_lengths={1: (None, 8)})
+    assert len(list(packets)) == 3
+    assert packets.length_violations() == [(1, 10, 8)]
+
+    packets = ccsdspy.decode_packets(str(src), apid_lengths={1: (None, 8)}, length_policy="drop")
+    assert [p.header.sequence_id for p in packets] == [0, 2]
+
+
+def test_packet_user_data(tmp_path):
+    dat = bytearray(make_packet(1, 0, bytes(range(12))))
+    dat[0] |= 0x08  # secondary header flag
+    src = tmp_path / "packets.dat"
+    src.write_bytes(bytes(dat))
+
+    (packet,) = ccsdspy.decode_packets(str(src), secondary_header_lens={1: 4})
+
+    assert packet.secondary_header_bytes == bytes(range(4))
+    assert packet.user_data() == bytes(range(4, 12))
+    assert packet.user_data(secondary_header_len=8) == bytes(range(8, 12))
+    assert ccsdspy.Packet.decode(bytes(dat)).user_data() == bytes(range(8, 12))
+    assert ccsdspy.Packet.decode(make_packet(1, 0, b"\x01")).user_data() == b"\x01"
+
+
+def test_packet_encode():
+    dat = make_packet(1289, 42, b"\x01\x02\x03")
+    packet = ccsdspy.Packet.decode(dat)
+
+    assert packet.encode() == dat
+    assert bytes(packet) == dat
+
+
+def test_subset(tmp_path):
+    src = tmp_path / "packets.dat"
+    packets = [make_packet(a, i, bytes([i])) for i, a in enumerate([1, 2, 1, 3])]
+    src.write_bytes(b"".join(packets))
+    dest = tmp_path / "subset.dat"
+
+    assert ccsdspy.subset(str(src), str(dest), apids=[1, 3], filter="seq > 0") == 2
+    assert dest.read_bytes() == packets[2] + packets[3]
+    with pytest.raises(ValueError):
+        ccsdspy.subset(str(src), str(dest), vcids=[1])
+
+
+def test_cli(tmp_path, capsys):
+    src = tmp_path / "packets.dat"
+    packets = [make_packet(a, i, bytes([i])) for i, a in enumerate([1, 2, 1, 3])]
+    src.write_bytes(b"".join(packets))
+    dest = tmp_path / "subset.dat"
+
+    ccsdspy.filter_main([str(src), str(dest), "--apid", "1", "--apid", "3"])
+    assert capsys.readouterr().out == "wrote 3 packets\n"
+    assert dest.read_bytes() == packets[0] + packets[2] + packets[3]
+
+    ccsdspy.info_main([str(dest)])
+    assert json.loads(capsys.readouterr().out)["packets"] == 3
+
+    with pytest.raises(SystemExit) as exc:
+        ccsdspy.filter_main([str(tmp_path / "missing.dat"), str(dest)])
+    assert exc.value.code == 1
+
+
+def test_merge_realtime_playback(tmp_path):
+    src = fixture_path("snpp_synchronized_cadus.dat")
+    dest = tmp_path / "merged.dat"
+
+    summary = ccsdspy.merge_realtime_playback(src, src, str(dest), 157, 1024, interleave=4)
+
+    assert summary.realtime > 0
+    assert summary.playback == 0
+    assert summary.duplicates == summary.realtime
+    packets = list(ccsdspy.decode_packets(str(dest)))
+    assert len(packets) == summary.realtime
+
+
+def test_manifest(tmp_path):
+    src = tmp_path / "packets.dat"
+    packets = [make_packet(a, i, bytes([i] * 100)) for i, a in enumerate([1, 2, 1])]
+    src.write_bytes(b"".join(packets))
+
+    manifest = ccsdspy.manifest(str(src))
+
+    assert manifest.digest == hashlib.sha256(src.read_bytes()).hexdigest()
+    assert (manifest.bytes, manifest.packets) == (len(src.read_bytes()), 3)
+    apid1, apid2 = manifest.apids
+    assert (apid1.apid, apid1.packets) == (1, 2)
+    assert apid1.digest == hashlib.sha256(packets[0] + packets[2]).hexdigest()
+    assert apid2.digest == hashlib.sha256(packets[1]).hexdigest()
+    assert json.loads(manifest.to_json())["packets"] == 3
+
+
+def test_convert(tmp_path):
+    src = tmp_path / "packets.dat"
+    packets = [make_packet(a, i, bytes([i])) for i, a in enumerate([1, 2, 1])]
+    src.write_bytes(b"".join(packets))
+    dest = tmp_path / "converted.dat"
+
+    n = ccsdspy.convert(str(src), str(dest), input_format="packets", filter="apid == 1")
+
+    assert n == 2
+    assert dest.read_bytes() == packets[0] + packets[2]
+    with pytest.raises(TypeError):
+        ccsdspy.convert(str(src), str(dest), input_format="packets", scid=1)
+    with pytest.raises(ValueError):
+        ccsdspy.convert(str(src), str(dest), input_format="packets", output_format="frames")
+
+
+def test_decode_frames_rs_tee(tmp_path):
+    frames = [bytes([0x40, 0x41, 0, 0, c, 0]) + bytes(886) for c in range(3)]
+    cadus = [bytearray(ccsdspy.encode_cadu(f, interleave=4)) for f in frames]
+    cadus[1][10:110] = bytes(100)
+    src = tmp_path / "cadus.dat"
+    src.write_bytes(b"".join(cadus))
+    dest = tmp_path / "uncorrectable.dat"
+
+    for rs_threads in [None, 2]:
+        frames = list(
+            ccsdspy.decode_frames(
+                str(src), 1020, interleave=4, rs_threads=rs_threads, uncorrectable_dest=str(dest)
+            )
+        )
+
+        assert len(frames) == 3
+        assert str(frames[1].rsstate) == "uncorrectable"
+        assert dest.read_bytes() == bytes(cadus[1])
+
+
+def test_decode_frames_rs_threads_live():
+    frames = [bytes([0x40, 0x41, 0, 0, c, 0]) + bytes([c]) * 886 for c in range(3)]
+    cadus = [ccsdspy.encode_cadu(f, interleave=4) for f in frames]
+    with socket.socket() as server:
+        server.bind(("127.0.0.1", 0))
+        server.listen()
+        port = server.getsockname()[1]
+
+        decoded = ccsdspy.decode_frames(
+            f"tcp://127.0.0.1:{port}", 1020, interleave=4, rs_threads=2, timeout=1
+        )
+        conn, _ = server.accept()
+        with conn:
+            # frames received are released while the source waits for more data
+            conn.sendall(cadus[0] + cadus[1])
+            assert [next(decoded).header.counter for _ in range(2)] == [0, 1]
+            with pytest.raises(TimeoutError):
+                next(decoded)
+
+            conn.sendall(cadus[2])
+            frame = next(decoded)
+        assert (frame.header.counter, str(frame.rsstate)) == (2, "ok")
+        assert bytes(frame.data) == frames[2]
+        assert list(decoded) == []
+
+
+def test_rs_trend(tmp_path):
+    frames = [bytes([0x40, 0x41, 0, 0, c, 0]) + bytes(886) for c in range(3)]
+    cadus = [bytearray(ccsdspy.encode_cadu(f, interleave=4)) for f in frames]
+    cadus[2][4] ^= 0xFF  # one symbol error in the first codeword
+    src = tmp_path / "cadus.dat"
+    src.write_bytes(b"".join(cadus))
+    dest = tmp_path / "trend.csv"
+
+    records = ccsdspy.rs_trend(str(src), 1024, 4, cadence=2, dest=str(dest))
+
+    assert [(r.start_frame, r.frames) for r in records] == [(0, 2), (2, 1)]
+    assert records[1].corrected == [1, 0, 0, 0]
+    assert dest.read_text().splitlines()[2] == "2,1,2048,2048,1,0,0,0,0,0,0,0"
+
+
+def test_decode_frames_izone_timecode(tmp_path):
+    # CDS day 1 (1958-01-02), 1000 ms of day, no microseconds
+    cds = bytes([0, 1, 0, 0, 0x03, 0xE8, 0, 0])
+    frame = bytes([0x40, 0x41, 0, 0, 0, 0]) + cds + bytes(34)
+    src = tmp_path / "cadus.dat"
+    src.write_bytes(ccsdspy.encode_cadu(frame))
+
+    (frame,) = ccsdspy.decode_frames(str(src), 48, izone_timecodes={1: "cds"})
+
+    assert frame.time == ccsdspy.decode_cds_timecode(cds)
+
+
+def test_decode_turbo(tmp_path):
+    frames = [bytes([0x40, 0x41, 0, 0, i, 0]) + bytes(range(217)) for i in range(3)]
+    codewords = [bytearray(ccsdspy.encode_turbo(f, "1/3")) for f in frames]
+    codewords[1][100] ^= 0x24
+    src = tmp_path / "turbo.dat"
+    src.write_bytes(b"".join(codewords))
+    dest = tmp_path / "cadus.dat"
+
+    assert ccsdspy.decode_turbo(str(src), str(dest), 223, "1/3") == 3
+
+    decoded = ccsdspy.decode_frames(str(dest), 223)
+    assert [f.data for f in decoded] == frames
+
+
+def test_push_decoder():
+    stream = b"".join(make_packet(1, i, bytes([i] * (4 + i))) for i in range(5))
+    decoder = ccsdspy.PushDecoder()
+
+    packets = []
+    for i in range(0, len(stream), 7):
+        decoder.feed(stream[i : i + 7])
+        packets += decoder.take()
+
+    assert [p.header.sequence_id for p in packets] == [0, 1, 2, 3, 4]
+    assert [bytes(p.data) for p in packets] == [make_packet(1, i, bytes([i] * (4 + i))) for i in range(5)]
+    assert decoder.buffered == 0
+
+    src = fixture_path("snpp_synchronized_cadus.dat")
+    expected = [(f.header.counter, f.offset) for f in ccsdspy.decode_frames(src, 1020)]
+    decoder = ccsdspy.PushDecoder(frame_len=1020)
+    frames = []
+    dat = Path(src).read_bytes()
+    for i in range(0, len(dat), 333):
+        decoder.feed(dat[i : i + 333])
+        frames += decoder.take()
+
+    assert [(f.header.counter, f.offset) for f in frames] == expected
+
+
+def test_extract_dvbs2(tmp_path):
+    def crc8(dat):
+        crc = 0
+        for byte in dat:
+            crc ^= byte
+            for _ in range(8):
+                crc = ((crc << 1) ^ 0xD5) & 0xFF if crc & 0x80 else (crc << 1) & 0xFF
+        return crc
+
+    def crc32(dat):
+        crc = 0xFFFFFFFF
+        for byte in dat:
+            crc ^= byte << 24
+            for _ in range(8):
+                crc = (crc << 1) ^ 0x04C11DB7 if crc & 0x80000000 else crc << 1
+                crc &= 0xFFFFFFFF
+        return crc
+
+    def bbframe(data_field):
+        hdr = struct.pack(">BBHHBH", 0x72, 0, 0, len(data_field) * 8, 0, 0)
+        return (hdr + bytes([crc8(hdr)]) + data_field).ljust(64, b"\0")
+
+    def gse(start, end, label_type, body):
+        return struct.pack(">H", start << 15 | end << 14 | label_type << 12 | len(body)) + body
+
+    packets = [make_packet(1, 0, b"ABCD"), make_packet(1, 1, b"EFGHIJKLMNOP")]
+    whole = gse(1, 1, 2, b"\x88\xb5" + packets[0])
+    unit = struct.pack(">HH", 2 + len(packets[1]), 0x88B5) + packets[1]
+    first = gse(1, 0, 2, b"\x07" + unit[:10])
+    last = gse(0, 1, 3, b"\x07" + unit[10:] + struct.pack(">I", crc32(unit)))
+    other = gse(1, 1, 2, b"\x08\x00ip")
+    src = tmp_path / "bbframes.dat"
+    src.write_bytes(b"junk" + bbframe(whole + first) + bbframe(other + last))
+    dest = tmp_path / "packets.dat"
+
+    summary = ccsdspy.extract_dvbs2(str(src), str(dest), frame_len=64, protocol_type=0x88B5)
+
+    assert (summary.bbframes, summary.pdus, summary.filtered) == (2, 2, 1)
+    assert summary.skipped_bytes == 4
+    assert [p.header.sequence_id for p in ccsdspy.decode_packets(str(dest))] == [0, 1]
+
+
+def test_decode_frames_izone_crc(tmp_path):
+    izone = bytes([1, 2, 3, 4])
+    izone += binascii.crc_hqx(izone, 0xFFFF).to_bytes(2, "big")
+    good = bytes([0x40, 0x41, 0, 0, 0, 0]) + izone + bytes(36)
+    bad = bytes([0x40, 0x41, 0, 0, 1, 0]) + bytes(6) + bytes(36)
+    src = tmp_path / "cadus.dat"
+    src.write_bytes(ccsdspy.encode_cadu(good) + ccsdspy.encode_cadu(bad))
+
+    frames = list(ccsdspy.decode_frames(str(src), 48, izone_crcs={1: ("crc16", 6)}))
+
+    assert [f.izone_crc for f in frames] == [True, False]
+    frames = ccsdspy.decode_frames(
+        str(src), 48, izone_crcs={1: ("crc16", 6)}, izone_crc_policy="drop"
+    )
+    assert len(list(frames)) == 1
+
+
+def test_timecode_registry(tmp_path):
+    # CDS day 1 (1958-01-02), 1000 ms of day, no microseconds
+    cds = bytes([0, 1, 0, 0, 0x03, 0xE8, 0, 0])
+    src = tmp_path / "packets.dat"
+    src.write_bytes(make_packet(1, 0, cds) + make_packet(2, 0, bytes(2) + cds))
+    registry = ccsdspy.TimecodeRegistry({2: ("cds", 2)}, default="cds")
+
+    assert registry.timecode(1) == ("cds", 0)
+    assert registry.timecode(2) == ("cds", 2)
+    expected = ccsdspy.decode_cds_timecode(cds)
+    assert [registry.decode(p) for p in ccsdspy.decode_packets(str(src))] == [
+        expected,
+        expected,
+    ]
+
+    db = tmp_path / "spacecraftsdb.json"
+    db.write_text(
+        json.dumps(
+            [{"scid": 157, "vcids": [{"apids": [{"apid": 2, "timecode": "eoscuc"}]}]}]
+        )
+    )
+    registry = ccsdspy.TimecodeRegistry.from_spacecraftsdb(157, str(db))
+    assert registry.timecode(1) is None
+    assert registry.timecode(2) == ("eoscuc", 0)
+
+
+def test_decode_frames_config_mismatch():
+    src = fixture_path("snpp_synchronized_cadus.dat")
+
+    with pytest.raises(ValueError, match="include the 4 byte ASM"):
+        ccsdspy.decode_frames(src, 1024, interleave=4)
+    with pytest.raises(ValueError, match="matches interleave 4"):
+        ccsdspy.decode_frames(src, 1020, interleave=5)
+    with pytest.raises(ValueError, match="exclude the 4 byte ASM"):
+        ccsdspy.decode_framed_packets(src, 157, 1020, interleave=4)
+
+
+def test_decode_frames_rs_threads():
+    src = fixture_path("snpp_synchronized_cadus.dat")
+
+    expected = list(ccsdspy.decode_frames(src, 1020, interleave=4))
+    frames = list(ccsdspy.decode_frames(src, 1020, interleave=4, rs_threads=3))
+
+    assert [(f.header.counter, repr(f.rsstate), bytes(f.data)) for f in frames] == [
+        (f.header.counter, repr(f.rsstate), bytes(f.data)) for f in expected
+    ]
+    with pytest.raises(ValueError):
+        ccsdspy.decode_frames(src, 1020, rs_threads=2)
+
+
+def test_pass_reporter():
+    src = fixture_path("snpp_synchronized_cadus.dat")
+    expected = ccsdspy.pass_report(src, 157, 1024, interleave=4)
+    reporter = ccsdspy.PassReporter()
+
+    packets = ccsdspy.decode_framed_packets(
+        src, 157, 1024, interleave=4, filter="apid == 826", report=reporter
+    )
+    assert all(p.packet.header.apid == 826 for p in packets)
+
+    report = reporter.report()
+    assert json.loads(report.to_json()) == json.loads(expected.to_json())
+
+
+def test_decode_frames_asm_errors(tmp_path):
+    frame = bytes([0x40, 0x41, 0, 0, 0, 0]) + bytes(42)
+    noisy = bytearray(ccsdspy.encode_cadu(frame))
+    noisy[0] ^= 0x81
+    src = tmp_path / "cadus.dat"
+    src.write_bytes(ccsdspy.encode_cadu(frame) + bytes(noisy))
+
+    assert len(list(ccsdspy.decode_frames(str(src), 48))) == 1
+    frames = list(ccsdspy.decode_frames(str(src), 48, asm_errors=2))
+    assert [f.asm_errors for f in frames] == [0, 2]
+    with pytest.raises(ValueError):
+        ccsdspy.decode_frames(str(src), 48, asm_errors=4)
+
+
+def test_decode_framed_packets_requires_cadu_len_without_db(tmp_path, monkeypatch):
+    monkeypatch.chdir(tmp_path)
+    monkeypatch.setenv("HOME", str(tmp_path))
+    monkeypatch.setenv("XDG_DATA_HOME", str(tmp_path))
+    src = fixture_path("snpp_synchronized_cadus.dat")
+
+    with pytest.raises(ValueError, match="cadu_len"):
+        ccsdspy.decode_framed_packets(src, 157)
+
+    packets = ccsdspy.decode_framed_packets(src, 157, 1024, interleave=4)
+    assert len(list(packets)) > 0
+
+
+def test_demux_frames():
+    src = fixture_path("snpp_synchronized_cadus.dat")
+    expected = {}
+    for frame in ccsdspy.decode_frames(src, 1020, interleave=4):
+        expected.setdefault(frame.header.vcid, []).append(frame.header.counter)
+    vcids = sorted(expected)[:2]
+
+    iters = ccsdspy.demux_frames(src, 1020, vcids, interleave=4)
+    assert sorted(iters) == vcids
+    # consumed one after the other, since the queues are unbounded
+    for vcid in vcids:
+        assert iters[vcid].vcid == vcid
+        assert [f.header.counter for f in iters[vcid]] == expected[vcid]
+
+    with pytest.raises(ValueError):
+        ccsdspy.demux_frames(src, 1020, [64], interleave=4)
+
+
+def test_decode_packets_sequence_flags(tmp_path):
+    def packet(apid, seq, flags):
+        dat = bytearray(make_packet(apid, seq, bytes(4)))
+        dat[2] = (dat[2] & 0x3F) | (flags << 6)
+        return bytes(dat)
+
+    src = tmp_path / "packets.dat"
+    src.write_bytes(
+        packet(1, 0, 0)  # continuation without a first
+        + packet(1, 1, 1)
+        + packet(1, 2, 3)  # unsegmented inside the group
+        + packet(1, 3, 2)  # last without a first
+        + packet(2, 0, 1)  # never ended
+    )
+
+    collector = ccsdspy.AnomalyCollector()
+    packets = ccsdspy.decode_packets(str(src), check_sequence_flags=True, anomalies=collector)
+    assert len(list(packets)) == 5
+    assert packets.sequence_flag_violations() == [
+        (1, "orphan_continuation", 0),
+        (1, "unsegmented_in_group", 20),
+        (1, "orphan_last", 30),
+        (2, "unterminated_group", 40),
+    ]
+    assert [a.kind for a in collector.drain()] == ["sequence_flags"] * 4
+
+    packets = ccsdspy.decode_packets(str(src))
+    assert len(list(packets)) == 5
+    assert packets.sequence_flag_violations() == []
+
+
+def test_mission_presets(tmp_path, monkeypatch):
+    framing = {
+        "length": 892,
+        "insertZoneLength": 0,
+        "trailerLength": 0,
+        "pseudoNoise": {},
+        "reedSolomon": {"interleave": 4, "virtualFillLength": 0, "numCorrectable": 16},
+    }
+    spacecrafts = [
+        {
+            "scid": scid,
+            "name": name,
+            "aliases": aliases,
+            "catalogNumber": 0,
+            "framingConfig": framing,
+            "timecode": "cds",
+            "vcids": [],
+        }
+        for scid, name, aliases in [(157, "snpp", ["npp"]), (159, "jpss1", ["noaa20"])]
+    ]
+    (tmp_path / "spacecraftsdb.json").write_text(json.dumps({"spacecrafts": spacecrafts}))
+    monkeypatch.chdir(tmp_path)
+
+    assert ccsdspy.missions() == ["snpp", "jpss1"]
+    preset = ccsdspy.mission_preset("NOAA-20")
+    assert (preset.name, preset.scid, preset.cadu_len, preset.interleave) == ("jpss1", 159, 1024, 4)
+    assert preset.framing_config.codeblock_len() == 1020
+    assert preset.timecode.timecode(1) == ("cds", 0)
+    with pytest.raises(ValueError):
+        ccsdspy.mission_preset("voyager")
+
+    src = fixture_path("snpp_synchronized_cadus.dat")
+    expected = ccsdspy.decode_framed_packets(src, 157, 1024, interleave=4, services={63: "idle"})
+    packets = ccsdspy.decode_framed_packets(src, mission="snpp")
+    assert [bytes(p.packet.data) for p in packets] == [bytes(p.packet.data) for p in expected]
+
+
+def test_decode_frames_packet_timecodes(tmp_path):
+    def packet(seq, millis):
+        dat = bytearray(make_packet(1, seq, struct.pack(">HIH", 21915, millis, 0) + bytes(4)))
+        dat[0] |= 0x08  # secondary header flag
+        return bytes(dat)
+
+    def frame(vcid, counter, fhp, zone):
+        # version 2, scid 157
+        return bytes([0x67, 0x40 | vcid, 0, 0, counter, 0]) + struct.pack(">H", fhp) + zone
+
+    packets = [packet(i, (i + 1) * 1000) for i in range(5)]
+    frames = [
+        frame(1, 0, 0, packets[0] + packets[1] + packets[2][:4]),
+        # the first packet header in the frame follows the rest of the third packet
+        frame(1, 1, 14, packets[2][4:] + packets[3] + packets[4][:8]),
+        frame(63, 0, 0x7FE, bytes(40)),
+    ]
+    src = tmp_path / "cadus.dat"
+    src.write_bytes(b"".join(ccsdspy.encode_cadu(f) for f in frames))
+    times = [ccsdspy.decode_cds_timecode(p[6:14]) for p in packets]
+
+    frames = list(ccsdspy.decode_frames(str(src), 48, packet_timecodes={157: ("cds", 0)}))
+    assert [f.time for f in frames] == [times[0], times[3], None]
+
+    frames = ccsdspy.decode_frames(str(src), 48)
+    assert all(f.time is None for f in frames)
+
+
+def test_decode_packets_clock(tmp_path):
+    def packet(apid, seq, seconds):
+        cds = struct.pack(">HIH", 21915, seconds * 1000, 0)
+        dat = bytearray(make_packet(apid, seq, cds + bytes(4)))
+        dat[0] |= 0x08  # secondary header flag
+        return bytes(dat)
+
+    src = tmp_path / "packets.dat"
+    src.write_bytes(
+        packet(1, 0, 10)
+        + packet(1, 1, 11)
+        + packet(2, 0, 5)  # other APIDs are tracked separately
+        + packet(1, 2, 3)  # clock reset
+        + packet(1, 3, 4)
+        + packet(1, 4, 3600)  # jump
+    )
+
+    collector = ccsdspy.AnomalyCollector()
+    clock = ccsdspy.ClockCheck("cds", max_jump=60_000)
+    packets = ccsdspy.decode_packets(str(src), clock=clock, anomalies=collector)
+    assert len(list(packets)) == 6
+    base = ccsdspy.decode_cds_timecode(struct.pack(">HIH", 21915, 0, 0))
+    assert packets.clock_violations() == [
+        (1, "regression", base + 11_000, base + 3_000, 54),
+        (1, "jump", base + 4_000, base + 3_600_000, 90),
+    ]
+    assert [a.kind for a in collector.drain()] == ["clock"] * 2
+
+    with pytest.raises(ValueError):
+        ccsdspy.decode_packets(str(src), clock=clock, headers_only=True)
+
+
+def test_roundtrip():
+    packets = [
+        make_packet(1, 0, bytes(100)),
+        make_packet(2, 0, bytes(range(256)) * 3),
+        make_packet(1, 1, b"abc"),
+    ]
+
+    report = ccsdspy.roundtrip(packets, 157, 1024, vcid=16, interleave=4)
+    assert (report.packets, report.frames, report.packet_zone_len) == (3, 2, 884)
+
+    report = ccsdspy.roundtrip(packets, 1, 256, izone_len=2, trailer_len=4)
+    assert (report.packets, report.frames, report.packet_zone_len) == (3, 4, 238)
+
+    with pytest.raises(ValueError):
+        ccsdspy.roundtrip(packets, 157, 1020, interleave=4)
+
+
+def test_cuc_epochs():
+    # 2018-01-01T00:00:00 UTC, 37 leap seconds after TAI
+    assert ccsdspy.decode_cuc_timecode(struct.pack(">IH", 1893456037, 0)) == 1514764800000
+    assert ccsdspy.decode_cuc_timecode(bytes(6), epoch="gps") == 315964800000
+    assert ccsdspy.decode_cuc_timecode(bytes(6), epoch="J2000") == 946727935816
+    assert ccsdspy.decode_cuc_timecode(struct.pack(">IH", 1000, 0x8000), epoch="unix") == 1000500
+    assert ccsdspy.decode_cuc_timecode_ns(b"\x00\x01\x40", coarse=2, fine=1, epoch="unix") == 1_250_000_000
+
+    ccsdspy.register_epoch("mission", 1514764800000, timescale="tai")
+    assert "mission" in ccsdspy.epochs()
+    assert ccsdspy.decode_cuc_timecode(struct.pack(">IH", 10, 0), epoch="mission") == 1514764810000
+    registry = ccsdspy.TimecodeRegistry(default="cuc4.3:mission")
+    assert registry.timecode(1) == ("cuc4.3:mission", 0)
+    with pytest.raises(ValueError):
+        ccsdspy.register_epoch("gps", 0)
+    with pytest.raises(ValueError):
+        ccsdspy.decode_cuc_timecode(bytes(6), epoch="unknown")
+
+
+def test_decode_cds_timecode_ns(tmp_path):
+    # 1000 ms and 250 us of day 21915 (2018-01-01)
+    cds = struct.pack(">HIH", 21915, 1000, 250)
+    ms = ccsdspy.decode_cds_timecode(cds)
+    assert ccsdspy.decode_cds_timecode_ns(cds) == ms * 1_000_000 + 250_000
+    with pytest.raises(ValueError):
+        ccsdspy.decode_cds_timecode_ns(cds[:4])
+
+    packet = bytearray(make_packet(1, 0, cds + bytes(4)))
+    packet[0] |= 0x08  # secondary header flag
+    src = tmp_path / "packets.dat"
+    src.write_bytes(bytes(packet))
+    (packet,) = ccsdspy.decode_packets(str(src))
+    assert ccsdspy.TimecodeRegistry(default="cds").decode_ns(packet) == ms * 1_000_000 + 250_000